@@ -73,18 +73,19 @@ fn generate_ico(src_png: &Path, out_ico: &Path) -> io::Result<()> {
     for &s in sizes {
         let resized = image::imageops::resize(&img, s, s, FilterType::Lanczos3);
         let ii = IconImage::from_rgba_data(s, s, resized.into_raw());
-        let entry = IconDirEntry::encode(&ii).map_err(|e| io::Error::new(io::ErrorKind::Other, format!("encode ico {s}px: {e}")))?;
+        let entry = IconDirEntry::encode(&ii).map_err(|e| io::Error::other(format!("encode ico {s}px: {e}")))?;
         dir.add_entry(entry);
     }
 
     let mut f = fs::File::create(out_ico).map_err(|e| io::Error::new(e.kind(), format!("create {}: {e}", out_ico.display())))?;
     dir.write(&mut f)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, format!("write {}: {e}", out_ico.display())))?;
+        .map_err(|e| io::Error::other(format!("write {}: {e}", out_ico.display())))?;
     Ok(())
 }
 
 fn main() {
-    // Enable WinFsp delay-loading
+    // Enable WinFsp delay-loading (the viewer only mounts on Windows)
+    #[cfg(windows)]
     winfsp::build::winfsp_link_delayload();
     
     // Set build timestamp for embedding in EXE
@@ -93,14 +94,8 @@ fn main() {
         .unwrap_or_default()
         .as_secs();
     
-    // Format as readable datetime (simple UTC approximation)
-    let total_secs = timestamp;
-    let days = total_secs / 86400;
-    let epoch_date = 719468; // days from 0000-01-01 to 1970-01-01
-    let total_days = epoch_date + days as i32;
-    
-    // Simple date calculation from days since epoch
     // Proper date calculation from UNIX timestamp
+    let total_secs = timestamp;
     const SECONDS_PER_DAY: u64 = 86400;
     let days_since_epoch = total_secs / SECONDS_PER_DAY;
     
@@ -0,0 +1,125 @@
+//! Malformations of "protected" archives that tolerant loading accepts, as Storm and the game do.
+//!
+//! Map protectors corrupt the parts of an archive the game does not need: header sizes and format
+//! versions, table sizes running past the end of the file, archive sizes that lie, blocks that overlap
+//! or whose compressed size is smaller than the sectors they hold.
+
+use crate::archive::header::HEADER_SIZE_V1;
+use crate::archive::reader::{MpqArchive, sector_offsets};
+use crate::archive::tables::MPQ_FILE_SECTOR_CRC;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Anomaly {
+    /// Header size or format version is bogus; the header was read as v1.
+    MalformedHeader { header_size: u32, format_version: u16 },
+    /// Declared archive size is zero, past the end of the file or smaller than the tables it contains.
+    ArchiveSize { declared: u64, available: u64 },
+    /// A table runs past the end of the file; only the entries present were read.
+    TableCut { table: &'static str, declared: u64, available: u64 },
+    HashTableSizeNotPowerOfTwo(u32),
+    /// Live hash entries pointing past the block table.
+    InvalidBlockIndices(usize),
+    /// A block's data runs past the end of the file.
+    BlockPastEof { block: usize },
+    /// Two blocks share bytes.
+    OverlappingBlocks { first: usize, second: usize },
+    /// The sector offset table ends after the block's declared compressed size.
+    CompressedSizeTooSmall { block: usize, declared: u32, actual: u32 },
+}
+
+impl Display for Anomaly {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Anomaly::MalformedHeader { header_size, format_version } => write!(f, "malformed header (size 0x{:X}, format version {}), read as v1", header_size, format_version),
+            Anomaly::ArchiveSize { declared, available } => write!(f, "archive size {} does not match the data ({} bytes available)", declared, available),
+            Anomaly::TableCut { table, declared, available } => write!(f, "{} declares {} bytes but only {} are in the file", table, declared, available),
+            Anomaly::HashTableSizeNotPowerOfTwo(size) => write!(f, "hash table size {} is not a power of two", size),
+            Anomaly::InvalidBlockIndices(count) => write!(f, "{} hash entries point past the block table", count),
+            Anomaly::BlockPastEof { block } => write!(f, "block {} runs past the end of the file", block),
+            Anomaly::OverlappingBlocks { first, second } => write!(f, "blocks {} and {} overlap", first, second),
+            Anomaly::CompressedSizeTooSmall { block, declared, actual } => write!(f, "block {} declares {} compressed bytes but its sectors span {}", block, declared, actual),
+        }
+    }
+}
+
+/// True when a header cannot be trusted beyond its v1 fields. Warcraft III only reads v1 headers.
+pub(crate) fn header_is_malformed(header_size: u32, format_version: u16, is_map: bool) -> bool {
+    header_size < HEADER_SIZE_V1 || format_version > 3 || (is_map && format_version != 0)
+}
+
+impl MpqArchive {
+    /// Anomalies found in the loaded tables and blocks. Reads the sector offset table of every plain compressed block.
+    pub(crate) fn scan_anomalies(&self) -> Vec<Anomaly> {
+        let mut anomalies = Vec::new();
+        let header = self.header();
+        let available = self.source_len().saturating_sub(self.location().archive_offset);
+
+        let tables_end = (header.hash_table_offset() + header.hash_table_size as u64 * 16).max(header.block_table_offset() + header.block_table_size as u64 * 16);
+        let declared = if header.archive_size_64 != 0 { header.archive_size_64 } else { header.archive_size as u64 };
+        if declared == 0 || declared > available || declared < tables_end.min(available) {
+            anomalies.push(Anomaly::ArchiveSize { declared, available });
+        }
+        // HET/BET-only archives have no classic hash table at all.
+        if !header.hash_table_size.is_power_of_two() && (header.hash_table_size != 0 || self.het_index().is_none()) {
+            anomalies.push(Anomaly::HashTableSizeNotPowerOfTwo(header.hash_table_size));
+        }
+        let invalid = self
+            .hash_table()
+            .iter()
+            .filter(|hash| !hash.is_empty() && !hash.is_deleted() && hash.block_index as usize >= self.block_table().len())
+            .count();
+        if invalid > 0 {
+            anomalies.push(Anomaly::InvalidBlockIndices(invalid));
+        }
+
+        let mut extents = Vec::new();
+        for (index, block) in self.block_table().iter().enumerate() {
+            if !block.exists() {
+                continue;
+            }
+            let end = block.file_pos + block.compressed_size as u64;
+            if end > available {
+                anomalies.push(Anomaly::BlockPastEof { block: index });
+            }
+            if block.compressed_size > 0 {
+                extents.push((block.file_pos, end, index));
+            }
+            if let Some(actual) = self.sector_table_end(index)
+                && actual > block.compressed_size
+            {
+                anomalies.push(Anomaly::CompressedSizeTooSmall { block: index, declared: block.compressed_size, actual });
+            }
+        }
+
+        extents.sort_unstable();
+        let mut furthest: Option<(u64, usize)> = None;
+        for (start, end, index) in extents {
+            if let Some((previous_end, previous)) = furthest
+                && start < previous_end
+            {
+                anomalies.push(Anomaly::OverlappingBlocks { first: previous, second: index });
+            }
+            if furthest.is_none_or(|(previous_end, _)| end > previous_end) {
+                furthest = Some((end, index));
+            }
+        }
+        anomalies
+    }
+
+    /// End of the data a multi-sector compressed block's offset table points to. `None` for encrypted
+    /// blocks (their key needs the name) and blocks without an offset table.
+    pub(crate) fn sector_table_end(&self, index: usize) -> Option<u32> {
+        let block = self.block_table().get(index)?;
+        if !block.is_compressed() || block.is_single_unit() || block.is_encrypted() || block.file_size == 0 {
+            return None;
+        }
+        let sector_count = (block.file_size as usize).div_ceil(self.header().sector_size() as usize);
+        let entries = sector_count + 1 + usize::from(block.flags & MPQ_FILE_SECTOR_CRC != 0);
+        let raw = self.read_exact(block.file_pos, entries * 4).ok()?;
+        sector_offsets(&raw, sector_count, block.flags, None)
+            .ok()?
+            .last()
+            .copied()
+    }
+}
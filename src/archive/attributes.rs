@@ -0,0 +1,72 @@
+//! The `(attributes)` file: per-block CRC32, file time, MD5 and patch bit arrays maintained by Storm.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::header::{read_u32, read_u64};
+
+pub const ATTRIBUTES_FILE_NAME: &str = "(attributes)";
+
+pub const MPQ_ATTRIBUTE_CRC32: u32 = 0x01;
+pub const MPQ_ATTRIBUTE_FILETIME: u32 = 0x02;
+pub const MPQ_ATTRIBUTE_MD5: u32 = 0x04;
+pub const MPQ_ATTRIBUTE_PATCH_BIT: u32 = 0x08;
+
+const ATTRIBUTES_HEADER_SIZE: usize = 8;
+
+/// Parsed `(attributes)`. Arrays are indexed by block and empty when the attribute is absent.
+/// Storm often writes them before adding `(attributes)` itself, so they may be one entry short.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub version: u32,
+    pub flags: u32,
+    pub crc32: Vec<u32>,
+    /// Windows FILETIME (100 ns ticks since 1601).
+    pub file_time: Vec<u64>,
+    pub md5: Vec<[u8; 16]>,
+    pub patch_bits: Vec<bool>,
+}
+
+impl Attributes {
+    pub fn parse(bytes: &[u8], block_count: usize) -> Result<Self, MpqArchiveError> {
+        if bytes.len() < ATTRIBUTES_HEADER_SIZE {
+            return Err(MpqArchiveError::Corrupted("truncated (attributes) header".into()));
+        }
+        let attributes_flags = read_u32(bytes, 4);
+        let mut attributes = Self { version: read_u32(bytes, 0), flags: attributes_flags, ..Self::default() };
+        let mut pos = ATTRIBUTES_HEADER_SIZE;
+
+        // Each present array holds `count` items; take as many whole items as remain.
+        let mut take = |flag: u32, count: usize, item_size: usize| -> &[u8] {
+            if attributes_flags & flag == 0 {
+                return &[];
+            }
+            let len = count.min((bytes.len() - pos) / item_size) * item_size;
+            let slice = &bytes[pos..pos + len];
+            pos += len;
+            slice
+        };
+
+        let crc32 = take(MPQ_ATTRIBUTE_CRC32, block_count, 4);
+        attributes.crc32 = crc32.chunks_exact(4).map(|c| read_u32(c, 0)).collect();
+        let file_time = take(MPQ_ATTRIBUTE_FILETIME, block_count, 8);
+        attributes.file_time = file_time.chunks_exact(8).map(|c| read_u64(c, 0)).collect();
+        let md5 = take(MPQ_ATTRIBUTE_MD5, block_count, 16);
+        attributes.md5 = md5.chunks_exact(16).map(|c| c.try_into().unwrap()).collect();
+        // Patch bits are packed, most significant bit first.
+        let bits = take(MPQ_ATTRIBUTE_PATCH_BIT, block_count.div_ceil(8), 1);
+        attributes.patch_bits = (0..(bits.len() * 8).min(block_count))
+            .map(|i| bits[i / 8] & (0x80 >> (i % 8)) != 0)
+            .collect();
+
+        Ok(attributes)
+    }
+
+    /// Stored CRC32 of a block; zero means "not computed" and yields `None`.
+    pub fn crc32(&self, block_index: usize) -> Option<u32> {
+        self.crc32.get(block_index).copied().filter(|&crc| crc != 0)
+    }
+
+    /// Stored MD5 of a block; all zeroes means "not computed" and yields `None`.
+    pub fn md5(&self, block_index: usize) -> Option<[u8; 16]> {
+        self.md5.get(block_index).copied().filter(|md5| md5.iter().any(|&b| b != 0))
+    }
+}
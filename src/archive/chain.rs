@@ -0,0 +1,115 @@
+//! Patch chains: a base archive with ordered patch archives layered on top.
+//!
+//! A name resolves to the top-most archive that has it. Full files there win outright; `PTCH`
+//! entries are collected while descending and applied oldest-first onto the first full file
+//! found below them. A delete marker hides the file and everything beneath it.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::patch::PatchFile;
+use crate::archive::reader::MpqArchive;
+use crate::archive::tables::BlockEntry;
+use std::collections::HashSet;
+
+pub struct MpqArchiveChain {
+    /// Base archive first, newest patch last.
+    archives: Vec<MpqArchive>,
+}
+
+impl MpqArchiveChain {
+    pub fn new(base: MpqArchive) -> Self {
+        Self { archives: vec![base] }
+    }
+
+    /// Layers a patch archive over everything added so far.
+    pub fn add_patch(&mut self, patch: MpqArchive) {
+        self.archives.push(patch);
+    }
+
+    pub fn archives(&self) -> &[MpqArchive] {
+        &self.archives
+    }
+
+    /// The top-most entry for `name`: the archive index and its block, which may be a patch or delete marker.
+    pub fn find_file(&self, name: &str) -> Option<(usize, &BlockEntry)> {
+        self.archives
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, archive)| archive.find_file(name).map(|(_, block)| (index, block)))
+    }
+
+    /// True when `name` resolves to content (not hidden by a delete marker).
+    pub fn contains(&self, name: &str) -> bool {
+        self.find_file(name)
+            .is_some_and(|(_, block)| !block.is_delete_marker())
+    }
+
+    /// Reads `name` as the game would see it, with every applicable patch applied and verified.
+    pub fn read_file(&self, name: &str) -> Result<Vec<u8>, MpqArchiveError> {
+        self.read_file_with_compression(name)
+            .map(|(data, _)| data)
+    }
+
+    /// Like [`read_file`](Self::read_file), also returning the compression mask of the top-most entry.
+    pub fn read_file_with_compression(&self, name: &str) -> Result<(Vec<u8>, u8), MpqArchiveError> {
+        let mut patches: Vec<PatchFile> = Vec::new();
+        let mut top_mask = None;
+
+        for archive in self.archives.iter().rev() {
+            let Some((_, block)) = archive.find_file(name) else { continue };
+
+            if block.is_delete_marker() {
+                break;
+            }
+
+            let (data, mask) = archive.read_block_with_compression(block, Some(name))?;
+            let top_mask = *top_mask.get_or_insert(mask);
+            if block.is_patch_file() {
+                patches.push(PatchFile::parse(&data)?);
+                continue;
+            }
+
+            return Ok((apply_patches(data, &patches)?, top_mask));
+        }
+
+        if patches.is_empty() {
+            return Err(MpqArchiveError::FileNotFound(name.to_string()));
+        }
+        // Patches that introduce a new file are made against empty content.
+        Ok((apply_patches(Vec::new(), &patches)?, top_mask.unwrap_or(0)))
+    }
+
+    /// Compression mask of the top-most entry of `name`, read from its sector headers.
+    pub fn compression_mask(&self, name: &str) -> Result<u8, MpqArchiveError> {
+        self.archives
+            .iter()
+            .rev()
+            .find_map(|archive| archive.find_file(name).map(|(_, block)| (archive, block)))
+            .filter(|(_, block)| !block.is_delete_marker())
+            .ok_or_else(|| MpqArchiveError::FileNotFound(name.to_string()))
+            .and_then(|(archive, block)| archive.block_compression_mask(block, Some(name)))
+    }
+
+    /// Union of the file names of all archives, minus files hidden by delete markers.
+    pub fn file_names(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut names: Vec<String> = Vec::new();
+        for archive in &self.archives {
+            for name in archive.file_names() {
+                if seen.insert(name.to_ascii_lowercase()) {
+                    names.push(name);
+                }
+            }
+        }
+        names.retain(|name| self.contains(name));
+        names
+    }
+}
+
+/// Applies patches collected top-down, so the last one collected is applied first.
+fn apply_patches(mut data: Vec<u8>, patches: &[PatchFile]) -> Result<Vec<u8>, MpqArchiveError> {
+    for patch in patches.iter().rev() {
+        data = patch.apply(&data)?;
+    }
+    Ok(data)
+}
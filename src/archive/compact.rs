@@ -0,0 +1,205 @@
+//! Archive compaction: rewrites an archive keeping only blocks that are still referenced.
+//!
+//! Like Storm, block indices are kept stable so `(attributes)` stays valid; dead blocks become
+//! empty block table entries and their data is dropped. Everything before the MPQ header (the
+//! HM3W map header, the `MPQ\x1B` user-data block) is copied verbatim.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::crypto::{decrypt_bytes, encrypt_bytes, file_key};
+use crate::archive::header::MpqHeader;
+use crate::archive::reader::{MpqArchive, sector_offsets};
+use crate::archive::tables::{BlockEntry, MPQ_FILE_FIX_KEY, write_block_table, write_hash_table, write_hi_block_table};
+use crate::log::log;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Progress snapshot passed to the compaction callback after each copied block.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactProgress {
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+}
+
+/// Outcome of a compaction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactReport {
+    pub live_blocks: usize,
+    pub freed_blocks: usize,
+    pub original_size: u64,
+    pub compacted_size: u64,
+}
+
+impl CompactReport {
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.original_size.saturating_sub(self.compacted_size)
+    }
+}
+
+impl MpqArchive {
+    /// Block indices referenced by a live hash entry or HET slot.
+    pub fn live_blocks(&self) -> Vec<bool> {
+        let mut live = vec![false; self.block_table().len()];
+        for entry in self.hash_table().iter().chain(self.het_index().map_or(&[][..], |index| index.entries())) {
+            if entry.is_empty() || entry.is_deleted() {
+                continue;
+            }
+            if let Some(block) = self.block_table().get(entry.block_index as usize) {
+                live[entry.block_index as usize] = block.exists();
+            }
+        }
+        live
+    }
+
+    /// Writes a compacted copy of the archive to `out`.
+    pub fn compact_to(&self, out: &mut impl Write, mut progress: impl FnMut(&CompactProgress)) -> Result<CompactReport, MpqArchiveError> {
+        let header = self.header();
+        if header.format_version >= 2 {
+            return Err(MpqArchiveError::Unsupported("compaction of MPQ v3/v4 archives"));
+        }
+
+        let live = self.live_blocks();
+        let names = self.names_by_block();
+        let header_size = MpqHeader::size_for_version(header.format_version) as u64;
+
+        // Lay out the new archive: header, live file data, hash table, block table, hi-block table.
+        let mut blocks = self.block_table().to_vec();
+        let mut pos = header_size;
+        for (block, &is_live) in blocks.iter_mut().zip(&live) {
+            if is_live {
+                block.file_pos = pos;
+                pos += block.compressed_size as u64;
+            } else {
+                *block = BlockEntry::default();
+            }
+        }
+        let hash_table_pos = pos;
+        let block_table_pos = hash_table_pos + self.hash_table().len() as u64 * 16;
+        let mut archive_size = block_table_pos + blocks.len() as u64 * 16;
+
+        let needs_hi_block = blocks.iter().any(|b| b.file_pos > u32::MAX as u64) || archive_size > u32::MAX as u64;
+        if needs_hi_block && header.format_version == 0 {
+            return Err(MpqArchiveError::Unsupported("compacted archive exceeds 4 GiB, which MPQ v1 cannot address"));
+        }
+        let hi_block_table_pos = if needs_hi_block || header.hi_block_table_pos != 0 { archive_size } else { 0 };
+        if hi_block_table_pos != 0 {
+            archive_size += blocks.len() as u64 * 2;
+        }
+
+        let mut new_header = header.clone();
+        new_header.header_size = header_size as u32;
+        new_header.archive_size = archive_size.min(u32::MAX as u64) as u32;
+        new_header.hash_table_pos = hash_table_pos as u32;
+        new_header.hash_table_pos_hi = (hash_table_pos >> 32) as u16;
+        new_header.block_table_pos = block_table_pos as u32;
+        new_header.block_table_pos_hi = (block_table_pos >> 32) as u16;
+        new_header.hi_block_table_pos = hi_block_table_pos;
+
+        let prefix_len = self.location().archive_offset;
+        out.write_all(&self.read_absolute(0, prefix_len as usize)?)?;
+        out.write_all(&new_header.to_bytes())?;
+
+        let mut state = CompactProgress { files_total: live.iter().filter(|l| **l).count(), bytes_total: blocks.iter().map(|b| b.compressed_size as u64).sum(), ..Default::default() };
+        for (index, (old, new)) in self.block_table().iter().zip(&blocks).enumerate() {
+            if !live[index] {
+                continue;
+            }
+            let mut raw = self.read_exact(old.file_pos, old.compressed_size as usize)?;
+            if old.is_encrypted() && old.flags & MPQ_FILE_FIX_KEY != 0 && old.file_pos != new.file_pos {
+                let name = names
+                    .get(&index)
+                    .ok_or(MpqArchiveError::Unsupported("cannot relocate a FIX_KEY encrypted file whose name is unknown"))?;
+                self.rekey_block(&mut raw, old, file_key(name, old.file_pos, old.file_size, true), file_key(name, new.file_pos, new.file_size, true))?;
+            }
+            out.write_all(&raw)?;
+
+            state.files_done += 1;
+            state.bytes_done += raw.len() as u64;
+            progress(&state);
+        }
+
+        out.write_all(&write_hash_table(self.hash_table()))?;
+        out.write_all(&write_block_table(&blocks))?;
+        if hi_block_table_pos != 0 {
+            out.write_all(&write_hi_block_table(&blocks))?;
+        }
+        out.flush()?;
+
+        let report = CompactReport { live_blocks: state.files_total, freed_blocks: live.iter().filter(|l| !**l).count(), original_size: self.source_len(), compacted_size: prefix_len + archive_size };
+        log(format!("compact: {} live blocks, {} freed, {} -> {} bytes", report.live_blocks, report.freed_blocks, report.original_size, report.compacted_size));
+        Ok(report)
+    }
+
+    /// The name of every block a known name leads to, in any locale.
+    fn names_by_block(&self) -> HashMap<usize, String> {
+        let mut names = HashMap::new();
+        for name in self.file_names() {
+            for entry in self.file_variants(&name) {
+                names.insert(entry.block_index as usize, name.clone());
+            }
+        }
+        names
+    }
+
+    /// Re-encrypts a block's sector table and sectors from `old_key` to `new_key`.
+    fn rekey_block(&self, raw: &mut [u8], block: &BlockEntry, old_key: u32, new_key: u32) -> Result<(), MpqArchiveError> {
+        let recrypt = |data: &mut [u8], old: u32, new: u32| {
+            decrypt_bytes(data, old);
+            encrypt_bytes(data, new);
+        };
+
+        if block.is_single_unit() {
+            recrypt(raw, old_key, new_key);
+            return Ok(());
+        }
+
+        let sector_size = self.header().sector_size() as usize;
+        if !block.is_compressed() {
+            for (i, sector) in raw.chunks_mut(sector_size).enumerate() {
+                recrypt(sector, old_key.wrapping_add(i as u32), new_key.wrapping_add(i as u32));
+            }
+            return Ok(());
+        }
+
+        let sector_count = (block.file_size as usize).div_ceil(sector_size);
+        let offsets = sector_offsets(raw, sector_count, block.flags, Some(old_key))?;
+        recrypt(&mut raw[..offsets.len() * 4], old_key.wrapping_sub(1), new_key.wrapping_sub(1));
+        for i in 0..sector_count {
+            let (start, end) = (offsets[i] as usize, offsets[i + 1] as usize);
+            if start > end || end > raw.len() {
+                return Err(MpqArchiveError::Corrupted(format!("sector {i} out of bounds ({start}..{end})")));
+            }
+            recrypt(&mut raw[start..end], old_key.wrapping_add(i as u32), new_key.wrapping_add(i as u32));
+        }
+        Ok(())
+    }
+}
+
+/// Compacts the archive at `path` into `output`, or in place when `output` is `None`.
+pub fn compact_archive(path: &Path, output: Option<&Path>, progress: impl FnMut(&CompactProgress)) -> Result<CompactReport, MpqArchiveError> {
+    let target = output.unwrap_or(path);
+    let mut temp_name = OsString::from(target.as_os_str());
+    temp_name.push(".compact-tmp");
+    let temp = PathBuf::from(temp_name);
+
+    let result = (|| {
+        let archive = MpqArchive::open(path)?;
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        archive.compact_to(&mut writer, progress)
+    })();
+
+    match result {
+        Ok(report) => {
+            fs::rename(&temp, target)?;
+            Ok(report)
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            Err(err)
+        }
+    }
+}
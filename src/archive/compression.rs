@@ -0,0 +1,116 @@
+//! Sector decompression. The first byte of a compressed sector is a mask of the methods applied.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::explode::explode;
+use std::io::Read;
+
+pub const COMPRESSION_HUFFMAN: u8 = 0x01;
+pub const COMPRESSION_ZLIB: u8 = 0x02;
+pub const COMPRESSION_PKWARE: u8 = 0x08;
+pub const COMPRESSION_BZIP2: u8 = 0x10;
+pub const COMPRESSION_SPARSE: u8 = 0x20;
+pub const COMPRESSION_ADPCM_MONO: u8 = 0x40;
+pub const COMPRESSION_ADPCM_STEREO: u8 = 0x80;
+/// LZMA is not a bit in the mask but a distinct value.
+pub const COMPRESSION_LZMA: u8 = 0x12;
+
+/// Methods in the order they are undone; compression applies them in reverse.
+const DECOMPRESSION_ORDER: [u8; 7] = [COMPRESSION_BZIP2, COMPRESSION_PKWARE, COMPRESSION_ZLIB, COMPRESSION_SPARSE, COMPRESSION_HUFFMAN, COMPRESSION_ADPCM_STEREO, COMPRESSION_ADPCM_MONO];
+
+/// Names of the methods in a compression mask, for diagnostics.
+pub fn describe_compression(mask: u8) -> Vec<&'static str> {
+    if mask == COMPRESSION_LZMA {
+        return vec!["lzma"];
+    }
+    const NAMES: &[(u8, &str)] = &[
+        (COMPRESSION_HUFFMAN, "huffman"),
+        (COMPRESSION_ZLIB, "zlib"),
+        (COMPRESSION_PKWARE, "pkware"),
+        (COMPRESSION_BZIP2, "bzip2"),
+        (COMPRESSION_SPARSE, "sparse"),
+        (COMPRESSION_ADPCM_MONO, "adpcm-mono"),
+        (COMPRESSION_ADPCM_STEREO, "adpcm-stereo"),
+    ];
+    NAMES
+        .iter()
+        .filter(|(bit, _)| mask & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Whether every method in `mask` can be undone; Huffman, ADPCM, bzip2 and LZMA cannot yet.
+pub fn is_supported(mask: u8) -> bool {
+    mask & (COMPRESSION_HUFFMAN | COMPRESSION_BZIP2 | COMPRESSION_ADPCM_MONO | COMPRESSION_ADPCM_STEREO) == 0
+}
+
+/// Decompresses a sector stored with `MPQ_FILE_COMPRESS` (mask byte followed by payload).
+pub fn decompress(data: &[u8], expected_size: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    let (&mask, payload) = data
+        .split_first()
+        .ok_or_else(|| MpqArchiveError::Corrupted("empty compressed sector".into()))?;
+
+    if mask == COMPRESSION_LZMA {
+        return Err(MpqArchiveError::Unsupported("LZMA compression"));
+    }
+
+    let known = DECOMPRESSION_ORDER.iter().fold(0u8, |acc, m| acc | m);
+    if mask & !known != 0 {
+        return Err(MpqArchiveError::Corrupted(format!("unknown compression mask 0x{mask:02X}")));
+    }
+
+    let mut buffer = payload.to_vec();
+    for method in DECOMPRESSION_ORDER {
+        if mask & method != 0 {
+            buffer = decompress_one(method, &buffer, expected_size)?;
+        }
+    }
+    Ok(buffer)
+}
+
+/// Decompresses a sector stored with the legacy `MPQ_FILE_IMPLODE` flag (no mask byte).
+pub fn decompress_imploded(data: &[u8], expected_size: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    explode(data, expected_size)
+}
+
+fn decompress_one(method: u8, input: &[u8], expected_size: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    match method {
+        COMPRESSION_ZLIB => inflate(input, expected_size),
+        COMPRESSION_PKWARE => explode(input, expected_size),
+        COMPRESSION_SPARSE => expand_sparse(input),
+        COMPRESSION_BZIP2 => Err(MpqArchiveError::Unsupported("bzip2 compression")),
+        COMPRESSION_HUFFMAN => Err(MpqArchiveError::Unsupported("Huffman compression")),
+        _ => Err(MpqArchiveError::Unsupported("ADPCM compression")),
+    }
+}
+
+fn inflate(input: &[u8], expected_size: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    let mut out = Vec::with_capacity(expected_size);
+    flate2::read::ZlibDecoder::new(input)
+        .read_to_end(&mut out)
+        .map_err(|e| MpqArchiveError::Corrupted(format!("zlib stream: {e}")))?;
+    Ok(out)
+}
+
+/// Storm's run-length "sparse" encoding: a big-endian output size, then copy/zero-fill runs.
+fn expand_sparse(input: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+    if input.len() < 4 {
+        return Err(MpqArchiveError::Corrupted("truncated sparse header".into()));
+    }
+    let size = u32::from_be_bytes([input[0], input[1], input[2], input[3]]) as usize;
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 4;
+    while pos < input.len() && out.len() < size {
+        let op = input[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            let run = ((op & 0x7F) as usize + 1).min(input.len() - pos);
+            out.extend_from_slice(&input[pos..pos + run]);
+            pos += run;
+        } else {
+            let run = (op & 0x7F) as usize + 3;
+            out.resize(out.len() + run, 0);
+        }
+    }
+    out.truncate(size);
+    Ok(out)
+}
@@ -0,0 +1,118 @@
+//! Storm encryption primitives: the crypt table, file name hashing and block (de)cryption.
+
+use std::sync::OnceLock;
+
+/// Hash type used to compute the hash table slot of a file name.
+pub const HASH_TABLE_OFFSET: u32 = 0;
+/// First verification hash stored in a hash table entry.
+pub const HASH_NAME_A: u32 = 1;
+/// Second verification hash stored in a hash table entry.
+pub const HASH_NAME_B: u32 = 2;
+/// Hash type used to derive encryption keys.
+pub const HASH_FILE_KEY: u32 = 3;
+
+static CRYPT_TABLE: OnceLock<[u32; 0x500]> = OnceLock::new();
+
+fn crypt_table() -> &'static [u32; 0x500] {
+    CRYPT_TABLE.get_or_init(|| {
+        let mut table = [0u32; 0x500];
+        let mut seed: u32 = 0x0010_0001;
+        for index1 in 0..0x100 {
+            let mut index2 = index1;
+            for _ in 0..5 {
+                seed = (seed * 125 + 3) % 0x2A_AAAB;
+                let temp1 = (seed & 0xFFFF) << 0x10;
+                seed = (seed * 125 + 3) % 0x2A_AAAB;
+                let temp2 = seed & 0xFFFF;
+                table[index2] = temp1 | temp2;
+                index2 += 0x100;
+            }
+        }
+        table
+    })
+}
+
+/// Hashes a file name the way Storm does: case-insensitive, with `/` treated as `\`.
+pub fn hash_string(name: &str, hash_type: u32) -> u32 {
+    let table = crypt_table();
+    let mut seed1: u32 = 0x7FED_7FED;
+    let mut seed2: u32 = 0xEEEE_EEEE;
+    for &byte in name.as_bytes() {
+        let ch = match byte {
+            b'/' => b'\\',
+            b => b.to_ascii_uppercase(),
+        } as u32;
+        seed1 = table[((hash_type << 8) + ch) as usize] ^ seed1.wrapping_add(seed2);
+        seed2 = ch
+            .wrapping_add(seed1)
+            .wrapping_add(seed2)
+            .wrapping_add(seed2 << 5)
+            .wrapping_add(3);
+    }
+    seed1
+}
+
+/// Decrypts a run of little-endian dwords in place.
+pub fn decrypt_block(data: &mut [u32], mut key: u32) {
+    let table = crypt_table();
+    let mut seed: u32 = 0xEEEE_EEEE;
+    for value in data.iter_mut() {
+        seed = seed.wrapping_add(table[0x400 + (key & 0xFF) as usize]);
+        let plain = *value ^ key.wrapping_add(seed);
+        key = ((!key) << 0x15).wrapping_add(0x1111_1111) | (key >> 0x0B);
+        seed = plain
+            .wrapping_add(seed)
+            .wrapping_add(seed << 5)
+            .wrapping_add(3);
+        *value = plain;
+    }
+}
+
+/// Encrypts a run of little-endian dwords in place.
+pub fn encrypt_block(data: &mut [u32], mut key: u32) {
+    let table = crypt_table();
+    let mut seed: u32 = 0xEEEE_EEEE;
+    for value in data.iter_mut() {
+        seed = seed.wrapping_add(table[0x400 + (key & 0xFF) as usize]);
+        let plain = *value;
+        *value = plain ^ key.wrapping_add(seed);
+        key = ((!key) << 0x15).wrapping_add(0x1111_1111) | (key >> 0x0B);
+        seed = plain
+            .wrapping_add(seed)
+            .wrapping_add(seed << 5)
+            .wrapping_add(3);
+    }
+}
+
+/// Decrypts a byte buffer in place. Trailing bytes that do not fill a dword stay as-is, like in Storm.
+pub fn decrypt_bytes(data: &mut [u8], key: u32) {
+    let mut dwords = bytes_to_dwords(data);
+    decrypt_block(&mut dwords, key);
+    dwords_to_bytes(&dwords, data);
+}
+
+/// Encrypts a byte buffer in place. Trailing bytes that do not fill a dword stay as-is, like in Storm.
+pub fn encrypt_bytes(data: &mut [u8], key: u32) {
+    let mut dwords = bytes_to_dwords(data);
+    encrypt_block(&mut dwords, key);
+    dwords_to_bytes(&dwords, data);
+}
+
+/// Derives the encryption key of a file from its name, applying the FIX_KEY adjustment when requested.
+pub fn file_key(name: &str, file_pos: u64, file_size: u32, fix_key: bool) -> u32 {
+    let plain_name = name.rsplit(['\\', '/']).next().unwrap_or(name);
+    let key = hash_string(plain_name, HASH_FILE_KEY);
+    if fix_key { key.wrapping_add(file_pos as u32) ^ file_size } else { key }
+}
+
+fn bytes_to_dwords(data: &[u8]) -> Vec<u32> {
+    data.chunks_exact(4)
+        .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+fn dwords_to_bytes(dwords: &[u32], data: &mut [u8]) {
+    for (chunk, value) in data.chunks_exact_mut(4).zip(dwords) {
+        chunk.copy_from_slice(&value.to_le_bytes());
+    }
+}
@@ -0,0 +1,135 @@
+//! Comparison of two archives by resolved path: added, removed and modified files.
+//!
+//! Content is compared by CRC32 and MD5 of the decompressed data, so recompressing or re-encrypting
+//! a file is reported as a storage change rather than a content change.
+
+use crate::archive::reader::MpqArchive;
+use crate::utils::md5::md5;
+use std::collections::HashMap;
+
+/// What a file looks like in one of the compared archives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSummary {
+    pub size: u64,
+    pub compressed_size: u64,
+    pub flags: u32,
+    /// Union of the compression masks of the file's sectors.
+    pub compression_mask: u8,
+    pub crc32: u32,
+    pub md5: [u8; 16],
+}
+
+impl FileSummary {
+    fn read(archive: &MpqArchive, name: &str) -> Result<Self, String> {
+        let (_, block) = archive.find_file(name).ok_or_else(|| format!("{name} not found"))?;
+        let (data, compression_mask) = archive
+            .read_block_with_compression(block, Some(name))
+            .map_err(|e| e.to_string())?;
+        Ok(Self { size: data.len() as u64, compressed_size: block.compressed_size as u64, flags: block.flags, compression_mask, crc32: crc32fast::hash(&data), md5: md5(&data) })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl ChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: ChangeKind,
+    pub old: Option<FileSummary>,
+    pub new: Option<FileSummary>,
+    /// Set when a side could not be read; a file unreadable on either side is reported as modified.
+    pub error: Option<String>,
+}
+
+impl FileChange {
+    pub fn content_changed(&self) -> bool {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => old.crc32 != new.crc32 || old.md5 != new.md5 || old.size != new.size,
+            _ => true,
+        }
+    }
+
+    pub fn flags_changed(&self) -> bool {
+        matches!((&self.old, &self.new), (Some(old), Some(new)) if old.flags != new.flags)
+    }
+
+    pub fn compression_changed(&self) -> bool {
+        matches!((&self.old, &self.new), (Some(old), Some(new)) if old.compression_mask != new.compression_mask)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveDiff {
+    /// Changed files, sorted by path.
+    pub changes: Vec<FileChange>,
+    pub unchanged: usize,
+}
+
+impl ArchiveDiff {
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|change| change.kind == kind).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Compares the named files of `old` and `new`. Names are matched case-insensitively.
+pub fn diff_archives(old: &MpqArchive, new: &MpqArchive) -> ArchiveDiff {
+    let old_names: HashMap<String, String> = old
+        .file_names()
+        .into_iter()
+        .map(|name| (name.to_ascii_lowercase(), name))
+        .collect();
+    let new_names: HashMap<String, String> = new
+        .file_names()
+        .into_iter()
+        .map(|name| (name.to_ascii_lowercase(), name))
+        .collect();
+
+    let mut diff = ArchiveDiff::default();
+    for (key, name) in &old_names {
+        if !new_names.contains_key(key) {
+            let summary = FileSummary::read(old, name);
+            diff.changes.push(FileChange { path: name.clone(), kind: ChangeKind::Removed, error: summary.as_ref().err().cloned(), old: summary.ok(), new: None });
+        }
+    }
+    for (key, name) in &new_names {
+        let Some(old_name) = old_names.get(key) else {
+            let summary = FileSummary::read(new, name);
+            diff.changes.push(FileChange { path: name.clone(), kind: ChangeKind::Added, error: summary.as_ref().err().cloned(), old: None, new: summary.ok() });
+            continue;
+        };
+
+        let (old_summary, new_summary) = (FileSummary::read(old, old_name), FileSummary::read(new, name));
+        let error = [&old_summary, &new_summary]
+            .into_iter()
+            .find_map(|summary| summary.as_ref().err().cloned());
+        let change = FileChange { path: name.clone(), kind: ChangeKind::Modified, old: old_summary.ok(), new: new_summary.ok(), error };
+        if change.error.is_some() || change.content_changed() || change.flags_changed() || change.compression_changed() {
+            diff.changes.push(change);
+        } else {
+            diff.unchanged += 1;
+        }
+    }
+
+    diff.changes
+        .sort_by_key(|change| change.path.to_ascii_lowercase());
+    diff
+}
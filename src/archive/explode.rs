@@ -0,0 +1,156 @@
+//! PKWARE Data Compression Library "explode" decoder (a port of Mark Adler's blast.c).
+
+use crate::archive::MpqArchiveError;
+
+const MAX_BITS: usize = 13;
+
+/// Bit lengths of the literal code, in blast.c's compact repeat-count form.
+const LIT_LEN: &[u8] = &[
+    11, 124, 8, 7, 28, 7, 188, 13, 76, 4, 10, 8, 12, 10, 12, 10, 8, 23, 8, 9, 7, 6, 7, 8, 7, 6, 55, 8, 23, 24, 12, 11, 7, 9, 11, 12, 6, 7, 22, 5, 7, 24, 6, 11, 9, 6, 7, 22, 7, 11, 38, 7, 9, 8, 25, 11, 8, 11, 9, 12, 8, 12, 5, 38, 5, 38, 5, 11, 7, 5, 6, 21, 6, 10, 53, 8, 7, 24, 10, 27, 44, 253, 253, 253, 252, 252, 252, 13, 12, 45, 12, 45, 12, 61, 12, 45, 44, 173,
+];
+const LEN_LEN: &[u8] = &[2, 35, 36, 53, 38, 23];
+const DIST_LEN: &[u8] = &[2, 20, 53, 230, 247, 151, 248];
+const LEN_BASE: [u16; 16] = [3, 2, 4, 5, 6, 7, 8, 9, 10, 12, 16, 24, 40, 72, 136, 264];
+const LEN_EXTRA: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8];
+
+/// Canonical Huffman decoding table.
+struct Huffman {
+    count: [u16; MAX_BITS + 1],
+    symbol: Vec<u16>,
+}
+
+impl Huffman {
+    fn construct(rep: &[u8]) -> Self {
+        let mut lengths = Vec::with_capacity(256);
+        for &r in rep {
+            let len = r & 15;
+            for _ in 0..(r >> 4) + 1 {
+                lengths.push(len as usize);
+            }
+        }
+
+        let mut count = [0u16; MAX_BITS + 1];
+        for &len in &lengths {
+            count[len] += 1;
+        }
+
+        let mut offs = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offs[len + 1] = offs[len] + count[len];
+        }
+
+        let mut symbol = vec![0u16; lengths.len()];
+        for (sym, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbol[offs[len] as usize] = sym as u16;
+                offs[len] += 1;
+            }
+        }
+
+        Self { count, symbol }
+    }
+}
+
+struct BitReader<'a> {
+    input: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, pos: 0, bit_buf: 0, bit_count: 0 }
+    }
+
+    fn bits(&mut self, need: u32) -> Option<u32> {
+        while self.bit_count < need {
+            let byte = *self.input.get(self.pos)?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u32 << need) - 1);
+        self.bit_buf >>= need;
+        self.bit_count -= need;
+        Some(value)
+    }
+
+    /// Decodes one symbol. PKWARE stores its Huffman codes bit-inverted.
+    fn decode(&mut self, h: &Huffman) -> Option<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..=MAX_BITS {
+            code |= (self.bits(1)? as i32) ^ 1;
+            let count = h.count[len] as i32;
+            if code < first + count {
+                return h.symbol.get((index + code - first) as usize).copied();
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+/// Decompresses a PKWARE DCL stream. Stops at the end code or once `expected_size` bytes are produced.
+pub fn explode(input: &[u8], expected_size: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    let corrupted = |what: &str| MpqArchiveError::Corrupted(format!("PKWARE stream: {what}"));
+
+    let lit_code = Huffman::construct(LIT_LEN);
+    let len_code = Huffman::construct(LEN_LEN);
+    let dist_code = Huffman::construct(DIST_LEN);
+
+    let mut reader = BitReader::new(input);
+    let coded_literals = reader
+        .bits(8)
+        .ok_or_else(|| corrupted("truncated header"))?;
+    let dict = reader
+        .bits(8)
+        .ok_or_else(|| corrupted("truncated header"))?;
+    if coded_literals > 1 {
+        return Err(corrupted("invalid literal mode"));
+    }
+    if !(4..=6).contains(&dict) {
+        return Err(corrupted("invalid dictionary size"));
+    }
+
+    let mut out = Vec::with_capacity(expected_size);
+    while out.len() < expected_size {
+        let Some(flag) = reader.bits(1) else { break };
+        if flag == 1 {
+            let symbol = reader
+                .decode(&len_code)
+                .ok_or_else(|| corrupted("bad length code"))? as usize;
+            let extra = reader
+                .bits(LEN_EXTRA[symbol] as u32)
+                .ok_or_else(|| corrupted("truncated length"))?;
+            let len = LEN_BASE[symbol] as usize + extra as usize;
+            if len == 519 {
+                break;
+            }
+            let shift = if len == 2 { 2 } else { dict };
+            let dist_hi = reader
+                .decode(&dist_code)
+                .ok_or_else(|| corrupted("bad distance code"))? as usize;
+            let dist_lo = reader
+                .bits(shift)
+                .ok_or_else(|| corrupted("truncated distance"))? as usize;
+            let dist = (dist_hi << shift) + dist_lo + 1;
+            if dist > out.len() {
+                return Err(corrupted("distance too far back"));
+            }
+            let start = out.len() - dist;
+            for i in 0..len {
+                let byte = out[start + i];
+                out.push(byte);
+            }
+        } else {
+            let symbol = if coded_literals == 1 { reader.decode(&lit_code).map(|s| s as u32) } else { reader.bits(8) };
+            out.push(symbol.ok_or_else(|| corrupted("truncated literal"))? as u8);
+        }
+    }
+
+    out.truncate(expected_size);
+    Ok(out)
+}
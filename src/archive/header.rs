@@ -0,0 +1,268 @@
+//! MPQ header, user-data header and archive location.
+
+use crate::archive::MpqArchiveError;
+
+/// `MPQ\x1A` — the archive header signature.
+pub const MPQ_HEADER_ID: u32 = 0x1A51_504D;
+/// `MPQ\x1B` — the user-data header signature (used by SC2 maps and replays).
+pub const MPQ_USER_DATA_ID: u32 = 0x1B51_504D;
+/// `HM3W` — the Warcraft III map header that precedes the MPQ in `.w3m`/`.w3x` files.
+pub const HM3W_ID: &[u8; 4] = b"HM3W";
+
+/// Headers are only searched at multiples of this alignment.
+pub const HEADER_ALIGNMENT: u64 = 0x200;
+
+pub const HEADER_SIZE_V1: u32 = 0x20;
+pub const HEADER_SIZE_V2: u32 = 0x2C;
+pub const HEADER_SIZE_V3: u32 = 0x44;
+pub const HEADER_SIZE_V4: u32 = 0xD0;
+/// The v4 header's own MD5 covers the bytes before it.
+pub const MD5_HEADER_OFFSET: usize = 0xC0;
+
+/// The `MPQ\x1B` block that may precede the archive header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserDataHeader {
+    /// Absolute offset of the user-data header in the file.
+    pub offset: u64,
+    pub user_data_size: u32,
+    /// Offset of the MPQ header relative to the user-data header.
+    pub header_offset: u32,
+    pub user_data_header_size: u32,
+}
+
+impl UserDataHeader {
+    pub fn parse(offset: u64, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 16 || read_u32(bytes, 0) != MPQ_USER_DATA_ID {
+            return None;
+        }
+        Some(Self { offset, user_data_size: read_u32(bytes, 4), header_offset: read_u32(bytes, 8), user_data_header_size: read_u32(bytes, 12) })
+    }
+}
+
+/// Parsed MPQ header. Fields of newer format versions are zero for older archives.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MpqHeader {
+    pub header_size: u32,
+    pub archive_size: u32,
+    /// 0 = v1 (original), 1 = v2 (Burning Crusade), 2 = v3, 3 = v4 (Cataclysm).
+    pub format_version: u16,
+    pub sector_size_shift: u16,
+    pub hash_table_pos: u32,
+    pub block_table_pos: u32,
+    pub hash_table_size: u32,
+    pub block_table_size: u32,
+
+    // v2
+    pub hi_block_table_pos: u64,
+    pub hash_table_pos_hi: u16,
+    pub block_table_pos_hi: u16,
+
+    // v3
+    pub archive_size_64: u64,
+    pub bet_table_pos: u64,
+    pub het_table_pos: u64,
+
+    // v4
+    pub hash_table_size_64: u64,
+    pub block_table_size_64: u64,
+    pub hi_block_table_size_64: u64,
+    pub het_table_size_64: u64,
+    pub bet_table_size_64: u64,
+    pub raw_chunk_size: u32,
+    pub md5_block_table: [u8; 16],
+    pub md5_hash_table: [u8; 16],
+    pub md5_hi_block_table: [u8; 16],
+    pub md5_bet_table: [u8; 16],
+    pub md5_het_table: [u8; 16],
+    pub md5_header: [u8; 16],
+}
+
+impl MpqHeader {
+    /// Parses the header at the start of `bytes`, which must begin with `MPQ\x1A`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        if bytes.len() < HEADER_SIZE_V1 as usize || read_u32(bytes, 0) != MPQ_HEADER_ID {
+            return Err(MpqArchiveError::Corrupted("missing MPQ header signature".into()));
+        }
+
+        let mut header = Self {
+            header_size: read_u32(bytes, 0x04),
+            archive_size: read_u32(bytes, 0x08),
+            format_version: read_u16(bytes, 0x0C),
+            sector_size_shift: read_u16(bytes, 0x0E),
+            hash_table_pos: read_u32(bytes, 0x10),
+            block_table_pos: read_u32(bytes, 0x14),
+            hash_table_size: read_u32(bytes, 0x18),
+            block_table_size: read_u32(bytes, 0x1C),
+            ..Self::default()
+        };
+
+        if header.format_version >= 1 && bytes.len() >= HEADER_SIZE_V2 as usize {
+            header.hi_block_table_pos = read_u64(bytes, 0x20);
+            header.hash_table_pos_hi = read_u16(bytes, 0x28);
+            header.block_table_pos_hi = read_u16(bytes, 0x2A);
+        }
+
+        if header.format_version >= 2 && bytes.len() >= HEADER_SIZE_V3 as usize {
+            header.archive_size_64 = read_u64(bytes, 0x2C);
+            header.bet_table_pos = read_u64(bytes, 0x34);
+            header.het_table_pos = read_u64(bytes, 0x3C);
+        }
+
+        if header.format_version >= 3 && bytes.len() >= HEADER_SIZE_V4 as usize {
+            header.hash_table_size_64 = read_u64(bytes, 0x44);
+            header.block_table_size_64 = read_u64(bytes, 0x4C);
+            header.hi_block_table_size_64 = read_u64(bytes, 0x54);
+            header.het_table_size_64 = read_u64(bytes, 0x5C);
+            header.bet_table_size_64 = read_u64(bytes, 0x64);
+            header.raw_chunk_size = read_u32(bytes, 0x6C);
+            header.md5_block_table = read_md5(bytes, 0x70);
+            header.md5_hash_table = read_md5(bytes, 0x80);
+            header.md5_hi_block_table = read_md5(bytes, 0x90);
+            header.md5_bet_table = read_md5(bytes, 0xA0);
+            header.md5_het_table = read_md5(bytes, 0xB0);
+            header.md5_header = read_md5(bytes, MD5_HEADER_OFFSET);
+        }
+
+        Ok(header)
+    }
+
+    /// Serializes the header using the size implied by `format_version`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let size = Self::size_for_version(self.format_version);
+        let mut out = Vec::with_capacity(size as usize);
+        out.extend_from_slice(&MPQ_HEADER_ID.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&self.archive_size.to_le_bytes());
+        out.extend_from_slice(&self.format_version.to_le_bytes());
+        out.extend_from_slice(&self.sector_size_shift.to_le_bytes());
+        out.extend_from_slice(&self.hash_table_pos.to_le_bytes());
+        out.extend_from_slice(&self.block_table_pos.to_le_bytes());
+        out.extend_from_slice(&self.hash_table_size.to_le_bytes());
+        out.extend_from_slice(&self.block_table_size.to_le_bytes());
+
+        if self.format_version >= 1 {
+            out.extend_from_slice(&self.hi_block_table_pos.to_le_bytes());
+            out.extend_from_slice(&self.hash_table_pos_hi.to_le_bytes());
+            out.extend_from_slice(&self.block_table_pos_hi.to_le_bytes());
+        }
+
+        if self.format_version >= 2 {
+            out.extend_from_slice(&self.archive_size_64.to_le_bytes());
+            out.extend_from_slice(&self.bet_table_pos.to_le_bytes());
+            out.extend_from_slice(&self.het_table_pos.to_le_bytes());
+        }
+
+        if self.format_version >= 3 {
+            out.extend_from_slice(&self.hash_table_size_64.to_le_bytes());
+            out.extend_from_slice(&self.block_table_size_64.to_le_bytes());
+            out.extend_from_slice(&self.hi_block_table_size_64.to_le_bytes());
+            out.extend_from_slice(&self.het_table_size_64.to_le_bytes());
+            out.extend_from_slice(&self.bet_table_size_64.to_le_bytes());
+            out.extend_from_slice(&self.raw_chunk_size.to_le_bytes());
+            for md5 in [&self.md5_block_table, &self.md5_hash_table, &self.md5_hi_block_table, &self.md5_bet_table, &self.md5_het_table, &self.md5_header] {
+                out.extend_from_slice(md5);
+            }
+        }
+
+        out
+    }
+
+    pub fn size_for_version(format_version: u16) -> u32 {
+        match format_version {
+            0 => HEADER_SIZE_V1,
+            1 => HEADER_SIZE_V2,
+            2 => HEADER_SIZE_V3,
+            _ => HEADER_SIZE_V4,
+        }
+    }
+
+    /// Size of one file sector in bytes.
+    pub fn sector_size(&self) -> u32 {
+        512u32 << self.sector_size_shift
+    }
+
+    /// Hash table offset relative to the archive start.
+    pub fn hash_table_offset(&self) -> u64 {
+        self.hash_table_pos as u64 | ((self.hash_table_pos_hi as u64) << 32)
+    }
+
+    /// Block table offset relative to the archive start.
+    pub fn block_table_offset(&self) -> u64 {
+        self.block_table_pos as u64 | ((self.block_table_pos_hi as u64) << 32)
+    }
+}
+
+/// The 512-byte `HM3W` header of Warcraft III maps, which precedes the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapHeader {
+    pub name: String,
+    pub flags: u32,
+    pub max_players: u32,
+}
+
+impl MapHeader {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 8 || &bytes[..4] != HM3W_ID {
+            return None;
+        }
+        let name_len = bytes[8..].iter().position(|&b| b == 0)?;
+        let name = String::from_utf8_lossy(&bytes[8..8 + name_len]).into_owned();
+        let fields = 8 + name_len + 1;
+        if bytes.len() < fields + 8 {
+            return None;
+        }
+        Some(Self { name, flags: read_u32(bytes, fields), max_players: read_u32(bytes, fields + 4) })
+    }
+}
+
+/// Where the archive lives inside its container file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveLocation {
+    /// Absolute offset of the `MPQ\x1A` header; all table offsets are relative to it.
+    pub archive_offset: u64,
+    pub user_data: Option<UserDataHeader>,
+}
+
+/// Scans `read_at` at every [`HEADER_ALIGNMENT`] boundary for the archive header, following `MPQ\x1B` redirects.
+pub fn locate_archive(file_len: u64, mut read_at: impl FnMut(u64, &mut [u8]) -> std::io::Result<usize>) -> Result<ArchiveLocation, MpqArchiveError> {
+    let mut probe = [0u8; 16];
+    let mut offset = 0u64;
+    while offset + 4 <= file_len {
+        let read = read_at(offset, &mut probe)?;
+        if read >= 4 {
+            match read_u32(&probe, 0) {
+                MPQ_HEADER_ID => return Ok(ArchiveLocation { archive_offset: offset, user_data: None }),
+                MPQ_USER_DATA_ID => {
+                    if let Some(user_data) = UserDataHeader::parse(offset, &probe[..read]) {
+                        let archive_offset = offset + user_data.header_offset as u64;
+                        let mut id = [0u8; 4];
+                        if archive_offset + 4 <= file_len && read_at(archive_offset, &mut id)? == 4 && u32::from_le_bytes(id) == MPQ_HEADER_ID {
+                            return Ok(ArchiveLocation { archive_offset, user_data: Some(user_data) });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        offset += HEADER_ALIGNMENT;
+    }
+    Err(MpqArchiveError::Unsupported("no MPQ header found"))
+}
+
+pub(crate) fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+pub(crate) fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+pub(crate) fn read_u64(bytes: &[u8], at: usize) -> u64 {
+    read_u32(bytes, at) as u64 | ((read_u32(bytes, at + 4) as u64) << 32)
+}
+
+fn read_md5(bytes: &[u8], at: usize) -> [u8; 16] {
+    let mut md5 = [0u8; 16];
+    md5.copy_from_slice(&bytes[at..at + 16]);
+    md5
+}
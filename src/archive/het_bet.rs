@@ -0,0 +1,352 @@
+//! HET and BET tables of format v3+ archives (StarCraft II, Heroes of the Storm): a Jenkins-hash index of file names
+//! and a bit-packed block table. Archives usually keep the classic tables next to them; lookups fall back to the
+//! HET/BET pair when those have no entry.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::crypto::{HASH_FILE_KEY, decrypt_bytes, encrypt_bytes, hash_string};
+use crate::archive::header::read_u32;
+use crate::archive::locale::LOCALE_NEUTRAL;
+use crate::archive::tables::{BlockEntry, HASH_ENTRY_EMPTY, HashEntry};
+
+/// `HET\x1A`
+pub const HET_TABLE_ID: u32 = 0x1A54_4548;
+/// `BET\x1A`
+pub const BET_TABLE_ID: u32 = 0x1A54_4542;
+/// Signature, version and data size; the data after it is encrypted.
+pub const EXT_TABLE_HEADER_SIZE: usize = 12;
+
+const HET_HEADER_SIZE: usize = 32;
+const BET_HEADER_SIZE: usize = 76;
+/// Name hash width Storm writes.
+const NAME_HASH_BITS: u32 = 64;
+
+/// Name index of a HET table: one slot per possible file, probed linearly from `hash % slots`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HetTable {
+    pub name_hash_bits: u32,
+    /// Top 8 bits of the name hash of each slot; 0 marks a free slot.
+    pub hashes: Vec<u8>,
+    /// BET entry of each slot; [`HASH_ENTRY_EMPTY`] for free slots.
+    pub file_indices: Vec<u32>,
+}
+
+impl HetTable {
+    /// Parses a HET table, `raw` starting at its `HET\x1A` header.
+    pub fn parse(raw: &[u8]) -> Result<Self, MpqArchiveError> {
+        let data = decrypt_ext_table(raw, HET_TABLE_ID, "HET")?;
+        let truncated = || MpqArchiveError::Corrupted("HET table is truncated".into());
+        if data.len() < HET_HEADER_SIZE {
+            return Err(truncated());
+        }
+        let slots = read_u32(&data, 8) as usize;
+        let name_hash_bits = read_u32(&data, 12);
+        let index_size_total = read_u32(&data, 16) as usize;
+        let index_size = read_u32(&data, 24);
+        let index_table_size = read_u32(&data, 28) as usize;
+        if !(8..=64).contains(&name_hash_bits) || index_size > 32 {
+            return Err(MpqArchiveError::Corrupted(format!("HET table has {}-bit name hashes and {}-bit indices", name_hash_bits, index_size)));
+        }
+        let hashes = data.get(HET_HEADER_SIZE..HET_HEADER_SIZE + slots).ok_or_else(truncated)?.to_vec();
+        let indices = data.get(HET_HEADER_SIZE + slots..HET_HEADER_SIZE + slots + index_table_size).ok_or_else(truncated)?;
+        let file_indices = hashes
+            .iter()
+            .enumerate()
+            .map(|(slot, &hash)| if hash == 0 { HASH_ENTRY_EMPTY } else { read_bits(indices, slot * index_size_total, index_size) as u32 })
+            .collect();
+        Ok(Self { name_hash_bits, hashes, file_indices })
+    }
+
+    /// The name hash as the table stores it: Jenkins hash cut to `name_hash_bits`, top bit set.
+    pub fn name_hash(&self, name: &str) -> u64 {
+        let mask = if self.name_hash_bits >= 64 { u64::MAX } else { (1u64 << self.name_hash_bits) - 1 };
+        (jenkins_hash(name) & mask) | (1u64 << (self.name_hash_bits - 1))
+    }
+}
+
+/// Packed block table of a BET table, with the low bits of each file's name hash.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BetTable {
+    pub blocks: Vec<BlockEntry>,
+    /// Name hash of each file without the top 8 bits kept in the HET table.
+    pub name_hashes: Vec<u64>,
+}
+
+impl BetTable {
+    /// Parses a BET table, `raw` starting at its `BET\x1A` header.
+    pub fn parse(raw: &[u8]) -> Result<Self, MpqArchiveError> {
+        let data = decrypt_ext_table(raw, BET_TABLE_ID, "BET")?;
+        let truncated = || MpqArchiveError::Corrupted("BET table is truncated".into());
+        if data.len() < BET_HEADER_SIZE {
+            return Err(truncated());
+        }
+        let field = |index: usize| read_u32(&data, index * 4);
+        let count = field(1) as usize;
+        let entry_size = field(3) as usize;
+        let (pos_at, size_at, compressed_at, flag_at) = (field(4) as usize, field(5) as usize, field(6) as usize, field(7) as usize);
+        let (pos_bits, size_bits, compressed_bits, flag_bits) = (field(9), field(10), field(11), field(12));
+        let (hash_total, hash_bits, hash_array_size) = (field(14) as usize, field(16), field(17) as usize);
+        let flag_count = field(18) as usize;
+        if [pos_bits, size_bits, compressed_bits, flag_bits, hash_bits].iter().any(|&bits| bits > 64) {
+            return Err(MpqArchiveError::Corrupted("BET table has fields wider than 64 bits".into()));
+        }
+
+        let flags_end = BET_HEADER_SIZE + flag_count * 4;
+        let flags: Vec<u32> = data.get(BET_HEADER_SIZE..flags_end).ok_or_else(truncated)?.chunks_exact(4).map(|flag| read_u32(flag, 0)).collect();
+        let table_end = flags_end + (count * entry_size).div_ceil(8);
+        let table = data.get(flags_end..table_end).ok_or_else(truncated)?;
+        let hashes = data.get(table_end..table_end + hash_array_size).ok_or_else(truncated)?;
+
+        let mut blocks = Vec::with_capacity(count);
+        for index in 0..count {
+            let at = index * entry_size;
+            let flag_index = read_bits(table, at + flag_at, flag_bits) as usize;
+            blocks.push(BlockEntry {
+                file_pos: read_bits(table, at + pos_at, pos_bits),
+                compressed_size: read_bits(table, at + compressed_at, compressed_bits) as u32,
+                file_size: read_bits(table, at + size_at, size_bits) as u32,
+                flags: flags.get(flag_index).copied().unwrap_or(0),
+            });
+        }
+        let name_hashes = (0..count).map(|index| read_bits(hashes, index * hash_total, hash_bits)).collect();
+        Ok(Self { blocks, name_hashes })
+    }
+}
+
+/// A HET/BET pair, with its occupied slots as hash entries so lookups can hand them out like classic ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HetIndex {
+    pub het: HetTable,
+    pub bet: BetTable,
+    /// One entry per HET slot: `hash_a`/`hash_b` are the high and low halves of the stored name hash, the locale is
+    /// neutral (HET tables have none) and `block_index` is the BET entry.
+    entries: Vec<HashEntry>,
+}
+
+impl HetIndex {
+    pub fn new(het: HetTable, bet: BetTable) -> Self {
+        let shift = het.name_hash_bits - 8;
+        let entries = het
+            .hashes
+            .iter()
+            .zip(&het.file_indices)
+            .map(|(&top, &file_index)| {
+                let low = bet.name_hashes.get(file_index as usize).copied().unwrap_or(0);
+                let hash = if top == 0 { 0 } else { ((top as u64) << shift) | low };
+                HashEntry { hash_a: (hash >> 32) as u32, hash_b: hash as u32, locale: LOCALE_NEUTRAL, platform: 0, block_index: file_index }
+            })
+            .collect();
+        Self { het, bet, entries }
+    }
+
+    pub fn entries(&self) -> &[HashEntry] {
+        &self.entries
+    }
+
+    /// The slot holding `name`, checking the BET half of the name hash as Storm does.
+    pub fn find(&self, name: &str) -> Option<(usize, &HashEntry)> {
+        let slots = self.het.hashes.len();
+        if slots == 0 {
+            return None;
+        }
+        let hash = self.het.name_hash(name);
+        let shift = self.het.name_hash_bits - 8;
+        let top = (hash >> shift) as u8;
+        let low = hash & ((1u64 << shift) - 1);
+        let start = (hash % slots as u64) as usize;
+        for step in 0..slots {
+            let slot = (start + step) % slots;
+            match self.het.hashes[slot] {
+                0 => break,
+                stored if stored == top => {
+                    let file_index = self.het.file_indices[slot] as usize;
+                    if self.bet.name_hashes.get(file_index) == Some(&low) {
+                        return Some((slot, &self.entries[slot]));
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Storm's 64-bit name hash for HET tables: Bob Jenkins' `hashlittle2` over the name lowercased, `/` as `\`.
+pub fn jenkins_hash(name: &str) -> u64 {
+    let normalized: Vec<u8> = name.bytes().map(|b| if b == b'/' { b'\\' } else { b.to_ascii_lowercase() }).collect();
+    let (secondary, primary) = hashlittle2(&normalized, 2, 1);
+    ((primary as u64) << 32) | secondary as u64
+}
+
+/// `lookup3.c` `hashlittle2`: returns the updated `(pc, pb)`.
+fn hashlittle2(key: &[u8], pc: u32, pb: u32) -> (u32, u32) {
+    let word = |bytes: &[u8], at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    let mut a = 0xDEAD_BEEFu32.wrapping_add(key.len() as u32).wrapping_add(pc);
+    let mut b = a;
+    let mut c = a.wrapping_add(pb);
+
+    let mut rest = key;
+    while rest.len() > 12 {
+        a = a.wrapping_add(word(rest, 0));
+        b = b.wrapping_add(word(rest, 4));
+        c = c.wrapping_add(word(rest, 8));
+        a = a.wrapping_sub(c); a ^= c.rotate_left(4); c = c.wrapping_add(b);
+        b = b.wrapping_sub(a); b ^= a.rotate_left(6); a = a.wrapping_add(c);
+        c = c.wrapping_sub(b); c ^= b.rotate_left(8); b = b.wrapping_add(a);
+        a = a.wrapping_sub(c); a ^= c.rotate_left(16); c = c.wrapping_add(b);
+        b = b.wrapping_sub(a); b ^= a.rotate_left(19); a = a.wrapping_add(c);
+        c = c.wrapping_sub(b); c ^= b.rotate_left(4); b = b.wrapping_add(a);
+        rest = &rest[12..];
+    }
+    if rest.is_empty() {
+        return (c, b);
+    }
+
+    let mut tail = [0u8; 12];
+    tail[..rest.len()].copy_from_slice(rest);
+    a = a.wrapping_add(word(&tail, 0));
+    b = b.wrapping_add(word(&tail, 4));
+    c = c.wrapping_add(word(&tail, 8));
+    c ^= b; c = c.wrapping_sub(b.rotate_left(14));
+    a ^= c; a = a.wrapping_sub(c.rotate_left(11));
+    b ^= a; b = b.wrapping_sub(a.rotate_left(25));
+    c ^= b; c = c.wrapping_sub(b.rotate_left(16));
+    a ^= c; a = a.wrapping_sub(c.rotate_left(4));
+    b ^= a; b = b.wrapping_sub(a.rotate_left(14));
+    c ^= b; c = c.wrapping_sub(b.rotate_left(24));
+    (c, b)
+}
+
+/// Length of a HET or BET table (header included) from its first [`EXT_TABLE_HEADER_SIZE`] bytes.
+pub fn ext_table_len(header: &[u8]) -> usize {
+    EXT_TABLE_HEADER_SIZE + read_u32(header, 8) as usize
+}
+
+/// Checks the signature of a HET or BET table and returns its data, decrypted.
+fn decrypt_ext_table(raw: &[u8], id: u32, table: &str) -> Result<Vec<u8>, MpqArchiveError> {
+    if raw.len() < EXT_TABLE_HEADER_SIZE || read_u32(raw, 0) != id {
+        return Err(MpqArchiveError::Corrupted(format!("missing {} table signature", table)));
+    }
+    let mut data = raw.get(EXT_TABLE_HEADER_SIZE..ext_table_len(raw)).ok_or_else(|| MpqArchiveError::Corrupted(format!("{} table is truncated", table)))?.to_vec();
+    decrypt_bytes(&mut data, ext_table_key(id));
+    Ok(data)
+}
+
+/// Writes a HET table indexing `names`, file `i` being BET entry `i`.
+pub fn write_het_table(names: &[&str]) -> Vec<u8> {
+    let slots = names.len() * 4 / 3 + 1;
+    let index_size = bit_count(names.len().saturating_sub(1) as u64).max(1);
+    let index_table_size = (slots * index_size as usize).div_ceil(8);
+    let het = HetTable { name_hash_bits: NAME_HASH_BITS, ..HetTable::default() };
+
+    let mut hashes = vec![0u8; slots];
+    let mut indices = vec![0u8; index_table_size];
+    for (file_index, name) in names.iter().enumerate() {
+        let hash = het.name_hash(name);
+        let mut slot = (hash % slots as u64) as usize;
+        while hashes[slot] != 0 {
+            slot = (slot + 1) % slots;
+        }
+        hashes[slot] = (hash >> (NAME_HASH_BITS - 8)) as u8;
+        write_bits(&mut indices, slot * index_size as usize, index_size, file_index as u64);
+    }
+
+    let mut data = Vec::new();
+    let table_size = (EXT_TABLE_HEADER_SIZE + HET_HEADER_SIZE + slots + index_table_size) as u32;
+    for field in [table_size, names.len() as u32, slots as u32, NAME_HASH_BITS, index_size, 0, index_size, index_table_size as u32] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(&hashes);
+    data.extend_from_slice(&indices);
+    encrypt_ext_table(HET_TABLE_ID, data)
+}
+
+/// Writes a BET table of `blocks`, `names[i]` naming `blocks[i]`.
+pub fn write_bet_table(blocks: &[BlockEntry], names: &[&str]) -> Vec<u8> {
+    let mut flags: Vec<u32> = Vec::new();
+    for block in blocks {
+        if !flags.contains(&block.flags) {
+            flags.push(block.flags);
+        }
+    }
+    let widest = |value: fn(&BlockEntry) -> u64| bit_count(blocks.iter().map(value).max().unwrap_or(0));
+    let pos_bits = widest(|block| block.file_pos);
+    let size_bits = widest(|block| block.file_size as u64);
+    let compressed_bits = widest(|block| block.compressed_size as u64);
+    let flag_bits = bit_count(flags.len().saturating_sub(1) as u64);
+    let entry_size = pos_bits + size_bits + compressed_bits + flag_bits;
+    let hash_bits = NAME_HASH_BITS - 8;
+
+    let mut table = vec![0u8; (blocks.len() * entry_size as usize).div_ceil(8)];
+    let mut hashes = vec![0u8; (blocks.len() * hash_bits as usize).div_ceil(8)];
+    let het = HetTable { name_hash_bits: NAME_HASH_BITS, ..HetTable::default() };
+    for (index, block) in blocks.iter().enumerate() {
+        let at = index * entry_size as usize;
+        let flag_index = flags.iter().position(|&flag| flag == block.flags).unwrap_or(0) as u64;
+        write_bits(&mut table, at, pos_bits, block.file_pos);
+        write_bits(&mut table, at + pos_bits as usize, size_bits, block.file_size as u64);
+        write_bits(&mut table, at + (pos_bits + size_bits) as usize, compressed_bits, block.compressed_size as u64);
+        write_bits(&mut table, at + (pos_bits + size_bits + compressed_bits) as usize, flag_bits, flag_index);
+        let name = names.get(index).copied().unwrap_or_default();
+        write_bits(&mut hashes, index * hash_bits as usize, hash_bits, het.name_hash(name) & (u64::MAX >> 8));
+    }
+
+    let table_size = (EXT_TABLE_HEADER_SIZE + BET_HEADER_SIZE + flags.len() * 4 + table.len() + hashes.len()) as u32;
+    let fields = [
+        table_size,
+        blocks.len() as u32,
+        0x10,
+        entry_size,
+        0,
+        pos_bits,
+        pos_bits + size_bits,
+        pos_bits + size_bits + compressed_bits,
+        entry_size,
+        pos_bits,
+        size_bits,
+        compressed_bits,
+        flag_bits,
+        0,
+        hash_bits,
+        0,
+        hash_bits,
+        hashes.len() as u32,
+        flags.len() as u32,
+    ];
+    let mut data: Vec<u8> = fields.iter().chain(&flags).flat_map(|field| field.to_le_bytes()).collect();
+    data.extend_from_slice(&table);
+    data.extend_from_slice(&hashes);
+    encrypt_ext_table(BET_TABLE_ID, data)
+}
+
+fn encrypt_ext_table(id: u32, mut data: Vec<u8>) -> Vec<u8> {
+    encrypt_bytes(&mut data, ext_table_key(id));
+    let mut out = Vec::with_capacity(EXT_TABLE_HEADER_SIZE + data.len());
+    for field in [id, 1, data.len() as u32] {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+    out.extend_from_slice(&data);
+    out
+}
+
+/// HET tables share the classic hash table's key, BET tables the block table's.
+fn ext_table_key(id: u32) -> u32 {
+    if id == HET_TABLE_ID { hash_string("(hash table)", HASH_FILE_KEY) } else { hash_string("(block table)", HASH_FILE_KEY) }
+}
+
+/// Bits needed to store `value`.
+fn bit_count(value: u64) -> u32 {
+    64 - value.leading_zeros()
+}
+
+/// Reads `count` bits starting at bit `at`, least significant first.
+fn read_bits(bytes: &[u8], at: usize, count: u32) -> u64 {
+    (0..count as usize).filter(|&bit| bytes.get((at + bit) / 8).is_some_and(|byte| byte >> ((at + bit) % 8) & 1 != 0)).fold(0, |value, bit| value | (1 << bit))
+}
+
+fn write_bits(bytes: &mut [u8], at: usize, count: u32, value: u64) {
+    for bit in 0..count as usize {
+        if value >> bit & 1 != 0 {
+            bytes[(at + bit) / 8] |= 1 << ((at + bit) % 8);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Hash table locales (Windows LCIDs) and their Blizzard short names.
+
+/// Locale of files that are not language-specific.
+pub const LOCALE_NEUTRAL: u16 = 0;
+
+const LOCALES: &[(u16, &str)] = &[
+    (0x0404, "zhTW"),
+    (0x0405, "csCZ"),
+    (0x0407, "deDE"),
+    (0x0409, "enUS"),
+    (0x040A, "esES"),
+    (0x040C, "frFR"),
+    (0x0410, "itIT"),
+    (0x0411, "jaJP"),
+    (0x0412, "koKR"),
+    (0x0415, "plPL"),
+    (0x0416, "ptBR"),
+    (0x0419, "ruRU"),
+    (0x0804, "zhCN"),
+    (0x0809, "enGB"),
+    (0x080A, "esMX"),
+    (0x0816, "ptPT"),
+];
+
+/// Short name of a locale (`ruRU`), `neutral` for 0, or the hex LCID when unknown.
+pub fn locale_name(locale: u16) -> String {
+    if locale == LOCALE_NEUTRAL {
+        return "neutral".to_string();
+    }
+    LOCALES
+        .iter()
+        .find(|(id, _)| *id == locale)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("0x{locale:04X}"))
+}
+
+/// Parses `ruRU`, `neutral`, `0x0419` or `1049` into an LCID.
+pub fn parse_locale(text: &str) -> Option<u16> {
+    if text.eq_ignore_ascii_case("neutral") {
+        return Some(LOCALE_NEUTRAL);
+    }
+    if let Some((id, _)) = LOCALES.iter().find(|(_, name)| name.eq_ignore_ascii_case(text)) {
+        return Some(*id);
+    }
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
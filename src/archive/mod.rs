@@ -0,0 +1,615 @@
+pub mod anomaly;
+pub mod attributes;
+pub mod chain;
+pub mod compact;
+pub mod compression;
+pub mod crypto;
+pub mod diff;
+mod explode;
+pub mod header;
+pub mod het_bet;
+pub mod locale;
+pub mod patch;
+pub mod reader;
+pub mod recovery;
+pub mod signature;
+pub mod source;
+pub mod space;
+pub mod tables;
+pub mod verify;
+
+use crate::log::log;
+use crate::war3::jass::{JASS_SCRIPT_PATHS, prettify, pretty_script_path};
+use crate::war3::objects::{ObjectData, ObjectKind};
+use crate::war3::replay::Replay;
+use crate::war3::w3i::{MapInfo, W3I_FILE_NAME};
+use crate::war3::wts::{StringTable, WTS_FILE_NAME, decode_text, escape_script_string, inline_strings};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, OnceLock};
+
+pub use chain::MpqArchiveChain;
+pub use compact::{CompactProgress, CompactReport, compact_archive};
+use anomaly::Anomaly;
+pub use reader::MpqArchive;
+use reader::OpenOptions;
+use signature::SignatureKeys;
+
+/// Virtual file listing which archive of an overlay each file came from.
+pub const OVERLAY_INFO_FILE_NAME: &str = "(overlay).txt";
+/// Virtual file reporting the weak and strong signature status of each mounted archive.
+pub const SIGNATURES_INFO_FILE_NAME: &str = "(signatures).txt";
+/// Virtual file listing the malformations tolerant loading accepted.
+pub const ANOMALIES_INFO_FILE_NAME: &str = "(anomalies).txt";
+/// Virtual file with the parsed `war3map.w3i` of a Warcraft III map, strings resolved.
+pub const MAP_INFO_FILE_NAME: &str = "map-info.json";
+/// Virtual folder holding a copy of each Warcraft III map file that references `war3map.wts`, strings inlined.
+pub const STRINGS_DIR_NAME: &str = "(strings)";
+/// Virtual folder holding every locale variant of multi-locale files, as `(locales)\ruRU\<path>`.
+pub const LOCALES_DIR_NAME: &str = "(locales)";
+/// Files a mounted `.w3g` replay is presented as: header and game settings, the players, and the raw actions.
+pub const REPLAY_HEADER_FILE_NAME: &str = "header.json";
+pub const REPLAY_PLAYERS_FILE_NAME: &str = "players.json";
+pub const REPLAY_ACTIONS_FILE_NAME: &str = "actions.bin";
+
+const PLACEHOLDER_FILE_NAME: &str = "TEST.txt";
+const PLACEHOLDER_HEADER: &str = "MPQ archive preview is not implemented yet.";
+
+#[derive(Debug, Clone)]
+pub struct MpqEntry {
+    pub path: String,
+    pub uncompressed_size: u64,
+    pub compressed_size: u64,
+    /// Read through [`read`](Self::read).
+    pub(crate) data: EntryData,
+    /// Archive the content was taken from (the top-most one in an overlay).
+    pub source: Option<Arc<str>>,
+    /// Block metadata of the entry in its source archive; `None` for virtual entries.
+    pub info: Option<MpqEntryInfo>,
+}
+
+/// Where the bytes of an entry are.
+#[derive(Debug, Clone)]
+pub(crate) enum EntryData {
+    /// In memory: virtual files, generated views and patched files.
+    Loaded(Arc<[u8]>),
+    /// Left in the mounted archives and decompressed each time it is read.
+    Stored(Arc<StoredFile>),
+}
+
+/// A file of a mounted chain, read on demand. The chain (and the archive sources behind it) stays open as long as
+/// an entry refers to it.
+pub(crate) struct StoredFile {
+    chain: Arc<MpqArchiveChain>,
+    name: String,
+    /// `(archive, block)` of a locale variant, read as stored; `None` resolves `name` through the chain.
+    variant: Option<(usize, usize)>,
+    /// Found from the sector headers on first use, or when the file is read.
+    compression_mask: OnceLock<u8>,
+}
+
+impl StoredFile {
+    fn read(&self) -> Result<Vec<u8>, MpqArchiveError> {
+        let (data, mask) = match self.variant {
+            Some((archive, block)) => {
+                let archive = &self.chain.archives()[archive];
+                archive.read_block_with_compression(&archive.block_table()[block], Some(&self.name))?
+            }
+            None => self.chain.read_file_with_compression(&self.name)?,
+        };
+        let _ = self.compression_mask.set(mask);
+        Ok(data)
+    }
+
+    fn compression_mask(&self) -> u8 {
+        *self.compression_mask.get_or_init(|| {
+            let mask = match self.variant {
+                Some((archive, block)) => {
+                    let archive = &self.chain.archives()[archive];
+                    archive.block_compression_mask(&archive.block_table()[block], Some(&self.name))
+                }
+                None => self.chain.compression_mask(&self.name),
+            };
+            mask.unwrap_or_else(|err| {
+                log(format!("MpqEntry: cannot read the sectors of {}: {}", self.name, err));
+                0
+            })
+        })
+    }
+}
+
+impl std::fmt::Debug for StoredFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredFile").field("name", &self.name).field("variant", &self.variant).finish()
+    }
+}
+
+/// Where and how an entry is stored in its source archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MpqEntryInfo {
+    pub block_index: usize,
+    pub file_pos: u64,
+    pub flags: u32,
+    /// Union of the compression masks of the entry's sectors (0 when stored raw); filled in for entries read at load
+    /// time only, see [`MpqEntry::compression_mask`].
+    pub compression_mask: u8,
+    pub locale: u16,
+    pub platform: u16,
+}
+
+impl MpqEntry {
+    pub fn from_bytes(path: String, bytes: Vec<u8>) -> Self {
+        let len = bytes.len() as u64;
+        Self { path, uncompressed_size: len, compressed_size: len, data: EntryData::Loaded(Arc::from(bytes.into_boxed_slice())), source: None, info: None }
+    }
+
+    fn extracted(path: String, bytes: Vec<u8>, compressed_size: u64, source: Arc<str>, info: MpqEntryInfo) -> Self {
+        Self { compressed_size, source: Some(source), info: Some(info), ..Self::from_bytes(path, bytes) }
+    }
+
+    fn stored(path: String, file: StoredFile, block: &tables::BlockEntry, source: Arc<str>, info: MpqEntryInfo) -> Self {
+        Self { path, uncompressed_size: block.file_size as u64, compressed_size: block.compressed_size as u64, data: EntryData::Stored(Arc::new(file)), source: Some(source), info: Some(info) }
+    }
+
+    /// The content. Files of mounted archives are decompressed from the archive on every call.
+    pub fn read(&self) -> Result<Arc<[u8]>, MpqArchiveError> {
+        match &self.data {
+            EntryData::Loaded(bytes) => Ok(Arc::clone(bytes)),
+            EntryData::Stored(file) => Ok(Arc::from(file.read()?)),
+        }
+    }
+
+    /// Union of the compression masks of the entry's sectors. Files still in their archive are not decompressed for
+    /// it: only the sector offset table and the first byte of each sector are read, once.
+    pub fn compression_mask(&self) -> u8 {
+        match &self.data {
+            EntryData::Stored(file) => file.compression_mask(),
+            EntryData::Loaded(_) => self.info.map_or(0, |info| info.compression_mask),
+        }
+    }
+
+    pub fn from_text(path: impl Into<String>, text: String) -> Self {
+        Self::from_bytes(path.into(), text.into_bytes())
+    }
+
+    /// Human-readable origin report, served as the `:mpq` stream of mounted files.
+    pub fn metadata_text(&self) -> String {
+        let mut text = format!("path: {}\r\n", self.path);
+        text.push_str(&format!("source: {}\r\n", self.source.as_deref().unwrap_or("(virtual)")));
+        if let Some(info) = &self.info {
+            text.push_str(&format!("block_index: {}\r\n", info.block_index));
+            text.push_str(&format!("file_pos: 0x{:X}\r\n", info.file_pos));
+            text.push_str(&format!("flags: 0x{:08X} ({})\r\n", info.flags, tables::describe_flags(info.flags).join(", ")));
+            let methods = compression::describe_compression(self.compression_mask());
+            text.push_str(&format!("compression: {}\r\n", if methods.is_empty() { "none".to_string() } else { methods.join(", ") }));
+            text.push_str(&format!("locale: 0x{:04X} ({})\r\n", info.locale, locale::locale_name(info.locale)));
+            text.push_str(&format!("platform: {}\r\n", info.platform));
+        }
+        text.push_str(&format!("compressed_size: {}\r\n", self.compressed_size));
+        text.push_str(&format!("file_size: {}\r\n", self.uncompressed_size));
+        text
+    }
+}
+
+/// Choices made when an archive is mounted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Locale served for files stored in several locales; neutral and then any variant are the fallbacks.
+    pub preferred_locale: u16,
+    /// Also expose every locale variant under [`LOCALES_DIR_NAME`].
+    pub locale_view: bool,
+    /// Custom keys signatures are checked against, after Blizzard's published ones.
+    pub signature_keys: SignatureKeys,
+    /// Whether v4 header, table and raw chunk MD5 mismatches reject the archive or are only logged.
+    pub integrity: verify::IntegrityMode,
+    /// Accept malformed ("protected") archives like Storm does; what was wrong is listed in
+    /// [`MpqArchiveDescriptor::anomalies`] and [`ANOMALIES_INFO_FILE_NAME`].
+    pub tolerant: bool,
+    /// Recover names of files missing from `(listfile)` so they are mounted too.
+    pub recover_names: bool,
+    /// Also expose files referencing `war3map.wts` strings with the strings inlined, under [`STRINGS_DIR_NAME`].
+    pub inline_strings: bool,
+    /// Converters whose virtual siblings are listed next to mounted files (`blp-png`, `slk-csv`, ... or `all`);
+    /// see [`ConverterRegistry`](crate::convert::ConverterRegistry).
+    pub converters: Vec<String>,
+    /// Whether archives are memory-mapped or read with positioned reads.
+    pub read_mode: source::ReadMode,
+}
+
+#[derive(Debug, Clone)]
+pub struct MpqArchiveDescriptor {
+    pub entries: Arc<[MpqEntry]>,
+    /// Malformations accepted by tolerant loading, with the archive each was found in.
+    pub anomalies: Vec<(Arc<str>, Anomaly)>,
+    /// The [`STRINGS_DIR_NAME`] view when it is enabled, built from `entries` the first time it is used.
+    strings: Option<Arc<OnceLock<Vec<MpqEntry>>>>,
+}
+
+impl MpqArchiveDescriptor {
+    pub fn new(entries: Vec<MpqEntry>) -> Self {
+        Self { entries: Arc::from(entries.into_boxed_slice()), anomalies: Vec::new(), strings: None }
+    }
+
+    pub fn placeholder(message: impl Into<String>) -> Self {
+        let body = format!("{header}\r\n{details}\r\n", header = PLACEHOLDER_HEADER, details = message.into());
+        Self::new(vec![MpqEntry::from_text(PLACEHOLDER_FILE_NAME, body)])
+    }
+
+    pub fn placeholder_from_path(path: &str) -> Self {
+        Self::placeholder(format!("Source archive path: {path}"))
+    }
+
+    pub fn placeholder_from_stream(len: usize) -> Self {
+        Self::placeholder(format!("Source archive provided via stream ({} bytes).", len))
+    }
+
+    pub fn load_from_path(path: &str) -> Result<Self, MpqArchiveError> {
+        log(format!("MpqArchiveDescriptor::load_from_path path={}", path));
+        Self::from_archive(MpqArchive::open(path)?)
+    }
+
+    pub fn load_from_bytes(bytes: Arc<[u8]>) -> Result<Self, MpqArchiveError> {
+        log(format!("MpqArchiveDescriptor::load_from_bytes size={}", bytes.len()));
+        Self::from_archive(MpqArchive::from_bytes(bytes)?)
+    }
+
+    /// Opens `base` with `patches` layered on top (oldest first) and extracts the patched view.
+    pub fn load_chain(base: &str, patches: &[String]) -> Result<Self, MpqArchiveError> {
+        Self::load_chain_with(base, patches, &LoadOptions::default())
+    }
+
+    /// Like [`load_chain`](Self::load_chain), honouring the mount `options`.
+    pub fn load_chain_with(base: &str, patches: &[String], options: &LoadOptions) -> Result<Self, MpqArchiveError> {
+        log(format!("MpqArchiveDescriptor::load_chain base={} patches={:?} locale=0x{:04X} locale_view={} integrity={:?} tolerant={} recover_names={} inline_strings={} read_mode={:?} custom_keys={}", base, patches, options.preferred_locale, options.locale_view, options.integrity, options.tolerant, options.recover_names, options.inline_strings, options.read_mode, options.signature_keys.weak.len() + options.signature_keys.strong.len()));
+        let open = |path: &str| -> Result<MpqArchive, MpqArchiveError> {
+            let mut archive = MpqArchive::open_with(path, OpenOptions { integrity: options.integrity, tolerant: options.tolerant, recover_names: options.recover_names, read_mode: options.read_mode })?;
+            archive.set_preferred_locale(options.preferred_locale);
+            Ok(archive)
+        };
+        let mut chain = MpqArchiveChain::new(open(base)?);
+        for patch in patches {
+            chain.add_patch(open(patch)?);
+        }
+        Self::from_chain_with(chain, options)
+    }
+
+    pub fn from_chain(chain: MpqArchiveChain) -> Result<Self, MpqArchiveError> {
+        Self::from_chain_with(chain, &LoadOptions::default())
+    }
+
+    /// Mounts every file of a patch chain as the game would see it. The chain is kept open and files are read from it
+    /// when they are opened, except patched files, which are resolved now (their size is only known once patched);
+    /// those that fail to resolve are logged and skipped. When more than one archive is layered, an
+    /// [`OVERLAY_INFO_FILE_NAME`] entry records where each file came from. Locale preference is a property of the
+    /// chain's archives; `options` only controls the extra views.
+    pub fn from_chain_with(chain: MpqArchiveChain, options: &LoadOptions) -> Result<Self, MpqArchiveError> {
+        let chain = Arc::new(chain);
+        let sources: Vec<Arc<str>> = chain
+            .archives()
+            .iter()
+            .map(|archive| Arc::from(archive.display_name()))
+            .collect();
+
+        let mut entries = Vec::new();
+        for name in chain.file_names() {
+            let Some((index, block)) = chain.find_file(&name) else { continue };
+            let mut info = entry_info(&chain.archives()[index], &name, block);
+            if options.locale_view {
+                entries.extend(locale_variants(&chain, index, &name, &sources[index]));
+            }
+            if !block.is_patch_file() {
+                let file = StoredFile { chain: Arc::clone(&chain), name: name.clone(), variant: None, compression_mask: OnceLock::new() };
+                entries.push(MpqEntry::stored(name, file, block, Arc::clone(&sources[index]), info));
+                continue;
+            }
+            match chain.read_file_with_compression(&name) {
+                Ok((data, mask)) => {
+                    info.compression_mask = mask;
+                    entries.push(MpqEntry::extracted(name, data, block.compressed_size as u64, Arc::clone(&sources[index]), info));
+                }
+                Err(err) => log(format!("MpqArchiveDescriptor: skipping {}: {}", name, err)),
+            }
+        }
+
+        if sources.len() > 1 {
+            let overlay = overlay_info(&sources, &entries);
+            push_generated(&mut entries, overlay);
+        }
+        if let Some(entry) = map_info(&entries) {
+            push_generated(&mut entries, entry);
+        }
+        let pretty = pretty_scripts(&entries);
+        entries.extend(pretty);
+        let objects = object_views(&entries);
+        entries.extend(objects);
+        let mut keys = SignatureKeys::blizzard();
+        keys.extend(&options.signature_keys);
+        if let Some(entry) = signatures_info(&chain, &sources, &keys) {
+            push_generated(&mut entries, entry);
+        }
+        let anomalies: Vec<(Arc<str>, Anomaly)> = chain
+            .archives()
+            .iter()
+            .zip(&sources)
+            .flat_map(|(archive, source)| archive.anomalies().iter().map(|anomaly| (Arc::clone(source), anomaly.clone())))
+            .collect();
+        if !anomalies.is_empty() {
+            push_generated(&mut entries, anomalies_info(&anomalies));
+        }
+        let strings = (options.inline_strings && entries.iter().any(|entry| same_path(&entry.path, WTS_FILE_NAME))).then(|| Arc::new(OnceLock::new()));
+        Ok(Self { anomalies, strings, ..Self::new(entries) })
+    }
+
+    /// Mounts every named file of an opened archive as stored (patch files are not applied). The archive is kept
+    /// open and files are read from it when they are opened.
+    pub fn from_archive(archive: MpqArchive) -> Result<Self, MpqArchiveError> {
+        let source: Arc<str> = Arc::from(archive.display_name());
+        let chain = Arc::new(MpqArchiveChain::new(archive));
+        let archive = &chain.archives()[0];
+        let mut entries = Vec::new();
+        for name in archive.file_names() {
+            let Some((index, block)) = archive.find_file(&name) else { continue };
+            let info = entry_info(archive, &name, block);
+            let file = StoredFile { chain: Arc::clone(&chain), name: name.clone(), variant: Some((0, index)), compression_mask: OnceLock::new() };
+            entries.push(MpqEntry::stored(name, file, block, Arc::clone(&source), info));
+        }
+        Ok(Self::new(entries))
+    }
+
+    /// Presents a `.w3g` replay (not an MPQ) as [`REPLAY_HEADER_FILE_NAME`], [`REPLAY_PLAYERS_FILE_NAME`] and
+    /// [`REPLAY_ACTIONS_FILE_NAME`].
+    pub fn load_replay(path: &str) -> Result<Self, MpqArchiveError> {
+        log(format!("MpqArchiveDescriptor::load_replay path={}", path));
+        let replay = Replay::parse(&std::fs::read(path)?)?;
+        Ok(Self::from_replay(&replay))
+    }
+
+    pub fn from_replay(replay: &Replay) -> Self {
+        Self::new(vec![
+            MpqEntry::from_text(REPLAY_HEADER_FILE_NAME, replay.header_json().to_pretty()),
+            MpqEntry::from_text(REPLAY_PLAYERS_FILE_NAME, replay.players_json().to_pretty()),
+            MpqEntry::from_bytes(REPLAY_ACTIONS_FILE_NAME.to_string(), replay.actions().to_vec()),
+        ])
+    }
+
+    pub fn entries(&self) -> &[MpqEntry] {
+        &self.entries
+    }
+
+    pub fn total_uncompressed_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.uncompressed_size).sum()
+    }
+
+    /// Entry at `name`, ignoring ASCII case and whether `/` or `\\` separates folders.
+    pub fn find_entry(&self, name: &str) -> Option<&MpqEntry> {
+        self.entries_for(name)
+            .iter()
+            .find(|entry| same_path(&entry.path, name))
+    }
+
+    /// Whether the [`STRINGS_DIR_NAME`] folder is mounted; true before its files are made.
+    pub fn has_strings_view(&self) -> bool {
+        self.strings.is_some()
+    }
+
+    /// Files referencing `war3map.wts` strings with the strings inlined, under [`STRINGS_DIR_NAME`]. They are made
+    /// the first time they are asked for, not when the archive is mounted.
+    pub fn strings_view(&self) -> &[MpqEntry] {
+        self.strings.as_ref().map_or(&[], |view| view.get_or_init(|| inlined_strings(&self.entries)))
+    }
+
+    /// Entries a lookup or listing of `path` draws from: the strings view for paths inside [`STRINGS_DIR_NAME`], the
+    /// mounted entries otherwise.
+    pub fn entries_for(&self, path: &str) -> &[MpqEntry] {
+        let inside = path
+            .get(..STRINGS_DIR_NAME.len())
+            .is_some_and(|dir| dir.eq_ignore_ascii_case(STRINGS_DIR_NAME) && matches!(path.as_bytes().get(dir.len()), Some(b'\\' | b'/')));
+        if inside { self.strings_view() } else { &self.entries }
+    }
+}
+
+/// Archive paths compared the way Storm hashes names: ASCII case and slash direction do not matter.
+fn same_path(a: &str, b: &str) -> bool {
+    let fold = |c: u8| if c == b'/' { b'\\' } else { c.to_ascii_lowercase() };
+    a.len() == b.len() && a.bytes().zip(b.bytes()).all(|(x, y)| fold(x) == fold(y))
+}
+
+/// Block metadata of `name` in `archive`; the compression mask is filled in once the data is read.
+fn entry_info(archive: &MpqArchive, name: &str, block: &tables::BlockEntry) -> MpqEntryInfo {
+    let hash = archive.find_hash_entry(name);
+    MpqEntryInfo {
+        block_index: hash.map(|h| h.block_index as usize).unwrap_or(0),
+        file_pos: block.file_pos,
+        flags: block.flags,
+        compression_mask: 0,
+        locale: hash.map(|h| h.locale).unwrap_or(0),
+        platform: hash.map(|h| h.platform).unwrap_or(0),
+    }
+}
+
+/// Every stored locale variant of a multi-locale file in archive `index` of the chain, as entries under
+/// [`LOCALES_DIR_NAME`]. Alternates are read as stored in that archive; patches and delete markers are not listed.
+fn locale_variants(chain: &Arc<MpqArchiveChain>, index: usize, name: &str, source: &Arc<str>) -> Vec<MpqEntry> {
+    let archive = &chain.archives()[index];
+    let variants = archive.file_variants(name);
+    let mut entries = Vec::new();
+    if variants.len() < 2 {
+        return entries;
+    }
+
+    let mut seen = HashSet::new();
+    for hash in variants {
+        let block_index = hash.block_index as usize;
+        let block = &archive.block_table()[block_index];
+        if block.is_patch_file() || block.is_delete_marker() || !seen.insert(hash.locale) {
+            continue;
+        }
+        let path = format!("{}\\{}\\{}", LOCALES_DIR_NAME, locale::locale_name(hash.locale), name);
+        let info = MpqEntryInfo { block_index, file_pos: block.file_pos, flags: block.flags, compression_mask: 0, locale: hash.locale, platform: hash.platform };
+        let file = StoredFile { chain: Arc::clone(chain), name: name.to_string(), variant: Some((index, block_index)), compression_mask: OnceLock::new() };
+        entries.push(MpqEntry::stored(path, file, block, Arc::clone(source), info));
+    }
+    entries
+}
+
+/// Content of an entry the views are built from; read failures are logged.
+fn view_source(entry: &MpqEntry) -> Option<Arc<[u8]>> {
+    entry
+        .read()
+        .map_err(|err| log(format!("MpqArchiveDescriptor: cannot read {}: {}", entry.path, err)))
+        .ok()
+}
+
+/// Renders the map's `war3map.w3i` as JSON; `None` when there is none or it cannot be parsed.
+fn map_info(entries: &[MpqEntry]) -> Option<MpqEntry> {
+    let find = |name: &str| entries.iter().find(|entry| entry.path.eq_ignore_ascii_case(name));
+    let w3i = view_source(find(W3I_FILE_NAME)?)?;
+    let wts = find(WTS_FILE_NAME).and_then(view_source);
+    match MapInfo::load(&w3i, wts.as_deref()) {
+        Ok(info) => Some(MpqEntry::from_text(MAP_INFO_FILE_NAME, info.to_json().to_pretty())),
+        Err(err) => {
+            log(format!("MpqArchiveDescriptor: cannot parse {}: {}", W3I_FILE_NAME, err));
+            None
+        }
+    }
+}
+
+/// Pretty-printed copies of the map's JASS scripts, next to them as `war3map.pretty.j`.
+fn pretty_scripts(entries: &[MpqEntry]) -> Vec<MpqEntry> {
+    entries
+        .iter()
+        .filter(|entry| entry.info.is_some() && JASS_SCRIPT_PATHS.iter().any(|path| entry.path.eq_ignore_ascii_case(path)))
+        .filter_map(|entry| {
+            let pretty = prettify(&decode_text(&view_source(entry)?));
+            Some(MpqEntry { source: entry.source.clone(), ..MpqEntry::from_text(pretty_script_path(&entry.path), pretty) })
+        })
+        .collect()
+}
+
+/// JSON and INI renderings of the object data files (`war3map.w3u.json`, `war3map.w3u.ini`...), strings resolved.
+fn object_views(entries: &[MpqEntry]) -> Vec<MpqEntry> {
+    let strings = entries
+        .iter()
+        .find(|entry| entry.path.eq_ignore_ascii_case(WTS_FILE_NAME))
+        .and_then(view_source)
+        .map(|wts| StringTable::from_bytes(&wts))
+        .unwrap_or_default();
+    let mut views = Vec::new();
+    for entry in entries.iter().filter(|entry| entry.info.is_some()) {
+        let Some(kind) = ObjectKind::from_path(&entry.path) else { continue };
+        let Some(bytes) = view_source(entry) else { continue };
+        match ObjectData::parse(kind, &bytes) {
+            Ok(data) => {
+                let json = MpqEntry::from_text(format!("{}.json", entry.path), data.to_json(&strings).to_pretty());
+                let ini = MpqEntry::from_text(format!("{}.ini", entry.path), data.to_ini(&strings));
+                views.push(MpqEntry { source: entry.source.clone(), ..json });
+                views.push(MpqEntry { source: entry.source.clone(), ..ini });
+            }
+            Err(err) => log(format!("MpqArchiveDescriptor: cannot parse {}: {}", entry.path, err)),
+        }
+    }
+    views
+}
+
+/// Copies of the entries referencing `war3map.wts` strings, with the strings inlined, under [`STRINGS_DIR_NAME`].
+/// Scripts get the strings escaped as literals; binary files keep their NUL-terminated layout.
+fn inlined_strings(entries: &[MpqEntry]) -> Vec<MpqEntry> {
+    let Some(wts) = entries.iter().find(|entry| entry.path.eq_ignore_ascii_case(WTS_FILE_NAME)).and_then(view_source) else { return Vec::new() };
+    let strings = StringTable::from_bytes(&wts);
+    let mut inlined = Vec::new();
+    for entry in entries {
+        if entry.info.is_none() || entry.path.eq_ignore_ascii_case(WTS_FILE_NAME) {
+            continue;
+        }
+        let extension = entry.path.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+        let escape: fn(&str) -> Cow<'_, str> = match extension.as_deref() {
+            Some("j" | "lua") => escape_script_string,
+            _ => |text| Cow::Borrowed(text),
+        };
+        let Some(bytes) = view_source(entry) else { continue };
+        if let Some(data) = inline_strings(&bytes, &strings, escape) {
+            let path = format!("{}\\{}", STRINGS_DIR_NAME, entry.path);
+            inlined.push(MpqEntry { source: entry.source.clone(), ..MpqEntry::from_bytes(path, data) });
+        }
+    }
+    inlined
+}
+
+/// Adds a generated info file unless an archive file of that name is mounted, which is kept instead.
+fn push_generated(entries: &mut Vec<MpqEntry>, entry: MpqEntry) {
+    if entries.iter().any(|existing| same_path(&existing.path, &entry.path)) {
+        log(format!("MpqArchiveDescriptor: an archive file is named {}; not listing the generated one", entry.path));
+        return;
+    }
+    entries.push(entry);
+}
+
+/// Renders the overlay origin table: the archive stack, then one `path<TAB>archive` line per file.
+fn overlay_info(sources: &[Arc<str>], entries: &[MpqEntry]) -> MpqEntry {
+    let mut text = format!("# Overlay of {} archives; later archives shadow earlier ones.\r\n", sources.len());
+    for (index, source) in sources.iter().enumerate() {
+        text.push_str(&format!("# {}: {}\r\n", index, source));
+    }
+    for entry in entries {
+        text.push_str(&format!("{}\t{}\r\n", entry.path, entry.source.as_deref().unwrap_or("")));
+    }
+    MpqEntry::from_text(OVERLAY_INFO_FILE_NAME, text)
+}
+
+/// Renders the anomalies tolerant loading accepted, one `archive<TAB>anomaly` line each.
+fn anomalies_info(anomalies: &[(Arc<str>, Anomaly)]) -> MpqEntry {
+    let mut text = format!("# {} anomalies accepted by tolerant loading.\r\n", anomalies.len());
+    for (source, anomaly) in anomalies {
+        text.push_str(&format!("{}\t{}\r\n", source, anomaly));
+    }
+    MpqEntry::from_text(ANOMALIES_INFO_FILE_NAME, text)
+}
+
+/// Renders the signature status of every archive of the chain; `None` when no archive is signed.
+fn signatures_info(chain: &MpqArchiveChain, sources: &[Arc<str>], keys: &SignatureKeys) -> Option<MpqEntry> {
+    let reports: Vec<_> = chain
+        .archives()
+        .iter()
+        .map(|archive| archive.verify_signatures(keys))
+        .collect();
+    if !reports.iter().any(|report| report.weak.is_present() || report.strong.is_present()) {
+        return None;
+    }
+
+    let mut text = String::new();
+    for (source, report) in sources.iter().zip(&reports) {
+        text.push_str(&format!("archive: {}\r\n", source));
+        text.push_str(&format!("weak: {}\r\n", report.weak.describe()));
+        text.push_str(&format!("strong: {}\r\n", report.strong.describe()));
+    }
+    Some(MpqEntry::from_text(SIGNATURES_INFO_FILE_NAME, text))
+}
+
+/// Errors encountered while preparing MPQ metadata for the shell provider.
+#[derive(Debug)]
+pub enum MpqArchiveError {
+    Io(std::io::Error),
+    Unsupported(&'static str),
+    Corrupted(String),
+    FileNotFound(String),
+}
+
+impl Display for MpqArchiveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MpqArchiveError::Io(err) => write!(f, "I/O error: {}", err),
+            MpqArchiveError::Unsupported(reason) => write!(f, "Unsupported archive: {}", reason),
+            MpqArchiveError::Corrupted(detail) => write!(f, "Corrupted archive: {}", detail),
+            MpqArchiveError::FileNotFound(name) => write!(f, "File not found in archive: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for MpqArchiveError {}
+
+impl From<std::io::Error> for MpqArchiveError {
+    fn from(err: std::io::Error) -> Self {
+        MpqArchiveError::Io(err)
+    }
+}
@@ -0,0 +1,193 @@
+//! Incremental `PTCH` patch files: parsing and application of `COPY` and `BSD0` (bsdiff) patches.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::header::{read_u32, read_u64};
+use crate::utils::md5::{md5, to_hex};
+
+const PTCH_ID: u32 = 0x4843_5450; // "PTCH"
+const MD5_ID: u32 = 0x5F35_444D; // "MD5_"
+const XFRM_ID: u32 = 0x4D52_4658; // "XFRM"
+const BSD0_ID: u32 = 0x3044_5342; // "BSD0"
+const COPY_ID: u32 = 0x5950_4F43; // "COPY"
+const BSDIFF40_ID: &[u8; 8] = b"BSDIFF40";
+
+const PATCH_HEADER_SIZE: usize = 0x44;
+const XFRM_HEADER_SIZE: usize = 12;
+const BSDIFF_HEADER_SIZE: usize = 32;
+/// Longest run one RLE control byte expands to.
+const RLE_MAX_RUN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    /// The payload is the complete new file.
+    Copy,
+    /// The payload is an RLE-packed Blizzard bsdiff stream.
+    Bsd0,
+}
+
+/// A parsed `PTCH` file.
+#[derive(Debug, Clone)]
+pub struct PatchFile {
+    pub size_before: u32,
+    pub size_after: u32,
+    pub md5_before: [u8; 16],
+    pub md5_after: [u8; 16],
+    pub kind: PatchKind,
+    /// Transform payload, already RLE-expanded for `BSD0`.
+    pub payload: Vec<u8>,
+}
+
+impl PatchFile {
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let corrupted = |what: &str| MpqArchiveError::Corrupted(format!("PTCH file: {what}"));
+
+        if bytes.len() < PATCH_HEADER_SIZE || read_u32(bytes, 0) != PTCH_ID {
+            return Err(corrupted("missing PTCH signature"));
+        }
+        if read_u32(bytes, 0x10) != MD5_ID || read_u32(bytes, 0x38) != XFRM_ID {
+            return Err(corrupted("missing MD5_ or XFRM block"));
+        }
+
+        let patch_data_size = read_u32(bytes, 0x04) as usize;
+        let size_before = read_u32(bytes, 0x08);
+        let size_after = read_u32(bytes, 0x0C);
+        let mut md5_before = [0u8; 16];
+        let mut md5_after = [0u8; 16];
+        md5_before.copy_from_slice(&bytes[0x18..0x28]);
+        md5_after.copy_from_slice(&bytes[0x28..0x38]);
+
+        let xfrm_size = read_u32(bytes, 0x3C) as usize;
+        let packed_len = xfrm_size.saturating_sub(XFRM_HEADER_SIZE);
+        let packed = PATCH_HEADER_SIZE
+            .checked_add(packed_len)
+            .and_then(|end| bytes.get(PATCH_HEADER_SIZE..end))
+            .ok_or_else(|| corrupted("XFRM block past end of file"))?;
+
+        let (kind, payload) = match read_u32(bytes, 0x40) {
+            COPY_ID => (PatchKind::Copy, packed.to_vec()),
+            BSD0_ID => {
+                let unpacked_len = patch_data_size.saturating_sub(PATCH_HEADER_SIZE);
+                let payload = if packed_len < unpacked_len { expand_rle(packed, unpacked_len)? } else { packed.to_vec() };
+                (PatchKind::Bsd0, payload)
+            }
+            _ => return Err(MpqArchiveError::Unsupported("unknown PTCH transform")),
+        };
+
+        Ok(Self { size_before, size_after, md5_before, md5_after, kind, payload })
+    }
+
+    /// Applies the patch to `old`, verifying the MD5 of the input and of the result.
+    pub fn apply(&self, old: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        let actual = md5(old);
+        if actual != self.md5_before {
+            return Err(MpqArchiveError::Corrupted(format!("patch base MD5 mismatch: expected {}, got {}", to_hex(&self.md5_before), to_hex(&actual))));
+        }
+
+        let new = match self.kind {
+            PatchKind::Copy => self.payload.clone(),
+            PatchKind::Bsd0 => apply_bsdiff(old, &self.payload, self.size_after as usize)?,
+        };
+
+        let actual = md5(&new);
+        if actual != self.md5_after {
+            return Err(MpqArchiveError::Corrupted(format!("patched file MD5 mismatch: expected {}, got {}", to_hex(&self.md5_after), to_hex(&actual))));
+        }
+        Ok(new)
+    }
+}
+
+/// Blizzard's RLE used on BSD0 payloads: the leading dword is skipped, then bit 7 of each
+/// control byte selects a literal run of `(n & 0x7F) + 1` bytes or a zero run of `n + 1` bytes.
+/// A length larger than the packets can expand to is rejected before anything is allocated.
+fn expand_rle(packed: &[u8], unpacked_len: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    if unpacked_len > packed.len().saturating_sub(4).saturating_mul(RLE_MAX_RUN) {
+        return Err(MpqArchiveError::Corrupted(format!("BSD0 patch: {unpacked_len} bytes cannot unpack from {} packed bytes", packed.len())));
+    }
+    let mut out = vec![0u8; unpacked_len];
+    let mut src = 4;
+    let mut dst = 0;
+    while src < packed.len() && dst < unpacked_len {
+        let op = packed[src];
+        src += 1;
+        if op & 0x80 != 0 {
+            let run = ((op & 0x7F) as usize + 1)
+                .min(packed.len() - src)
+                .min(unpacked_len - dst);
+            out[dst..dst + run].copy_from_slice(&packed[src..src + run]);
+            src += run;
+            dst += run;
+        } else {
+            dst += op as usize + 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Applies a `BSDIFF40` stream producing `size_after` bytes. Blizzard's variant stores control
+/// triples as 32-bit values with a sign bit rather than the 64-bit offsets of upstream bsdiff.
+fn apply_bsdiff(old: &[u8], patch: &[u8], size_after: usize) -> Result<Vec<u8>, MpqArchiveError> {
+    let corrupted = |what: &str| MpqArchiveError::Corrupted(format!("BSD0 patch: {what}"));
+
+    if patch.len() < BSDIFF_HEADER_SIZE || &patch[..8] != BSDIFF40_ID {
+        return Err(corrupted("missing BSDIFF40 signature"));
+    }
+    let size = |offset: usize| usize::try_from(read_u64(patch, offset)).map_err(|_| corrupted("block size out of range"));
+    let (ctrl_len, data_len, new_size) = (size(8)?, size(16)?, size(24)?);
+    if new_size != size_after {
+        return Err(corrupted(&format!("stream builds {new_size} bytes, the PTCH header expects {size_after}")));
+    }
+
+    let ctrl_start = BSDIFF_HEADER_SIZE;
+    let data_start = ctrl_start.checked_add(ctrl_len);
+    let extra_start = data_start.and_then(|start| start.checked_add(data_len));
+    let (Some(data_start), Some(extra_start)) = (data_start, extra_start.filter(|&start| start <= patch.len())) else {
+        return Err(corrupted("blocks past end of patch"));
+    };
+    let ctrl = &patch[ctrl_start..data_start];
+    let data = &patch[data_start..extra_start];
+    let extra = &patch[extra_start..];
+    // Every new byte comes from the data or the extra block.
+    if new_size > data.len() + extra.len() {
+        return Err(corrupted("new file larger than the patch can build"));
+    }
+
+    let mut new = vec![0u8; new_size];
+    let (mut new_pos, mut old_pos) = (0usize, 0i64);
+    let (mut ctrl_pos, mut data_pos, mut extra_pos) = (0usize, 0usize, 0usize);
+
+    while new_pos < new_size {
+        if ctrl_pos + 12 > ctrl.len() {
+            return Err(corrupted("control block exhausted"));
+        }
+        let add_len = read_u32(ctrl, ctrl_pos) as usize;
+        let copy_len = read_u32(ctrl, ctrl_pos + 4) as usize;
+        let seek = read_u32(ctrl, ctrl_pos + 8);
+        ctrl_pos += 12;
+
+        if new_pos + add_len > new_size || data_pos + add_len > data.len() {
+            return Err(corrupted("add run out of bounds"));
+        }
+        for i in 0..add_len {
+            let old_byte = usize::try_from(old_pos + i as i64)
+                .ok()
+                .and_then(|p| old.get(p))
+                .copied()
+                .unwrap_or(0);
+            new[new_pos + i] = data[data_pos + i].wrapping_add(old_byte);
+        }
+        new_pos += add_len;
+        data_pos += add_len;
+        old_pos += add_len as i64;
+
+        if new_pos + copy_len > new_size || extra_pos + copy_len > extra.len() {
+            return Err(corrupted("copy run out of bounds"));
+        }
+        new[new_pos..new_pos + copy_len].copy_from_slice(&extra[extra_pos..extra_pos + copy_len]);
+        new_pos += copy_len;
+        extra_pos += copy_len;
+
+        old_pos += if seek & 0x8000_0000 != 0 { -((seek & 0x7FFF_FFFF) as i64) } else { seek as i64 };
+    }
+
+    Ok(new)
+}
@@ -0,0 +1,516 @@
+//! Random-access MPQ reader: locates the archive, loads its tables and extracts files.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::anomaly::{Anomaly, header_is_malformed};
+use crate::archive::compression::{COMPRESSION_PKWARE, decompress, decompress_imploded};
+use crate::archive::crypto::{decrypt_bytes, file_key};
+use crate::archive::het_bet::{BetTable, EXT_TABLE_HEADER_SIZE, HetIndex, HetTable, ext_table_len};
+use crate::archive::header::{ArchiveLocation, HEADER_ALIGNMENT, HEADER_SIZE_V1, HEADER_SIZE_V4, HM3W_ID, MapHeader, MpqHeader, locate_archive, read_u32};
+use crate::archive::locale::LOCALE_NEUTRAL;
+use crate::archive::source::{ReadAt, ReadMode, open_file};
+use crate::archive::tables::{BlockEntry, HashEntry, MPQ_FILE_COMPRESS, MPQ_FILE_FIX_KEY, MPQ_FILE_PATCH_FILE, MPQ_FILE_SECTOR_CRC, find_hash_entries, parse_block_table, parse_hash_table};
+use crate::archive::verify::{Check, IntegrityMode};
+use crate::log::log;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Names of the internal files Storm maintains; they are never listed in `(listfile)`.
+pub const SPECIAL_FILES: &[&str] = &["(listfile)", "(attributes)", "(signature)"];
+
+/// Cut hash tables are padded back to their declared size only up to this many entries.
+const MAX_PADDED_HASH_ENTRIES: usize = 1 << 20;
+
+/// How an archive is opened.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// How v4 MD5 mismatches are treated.
+    pub integrity: IntegrityMode,
+    /// Accept malformed ("protected") archives the way Storm does, recording what was wrong in [`MpqArchive::anomalies`].
+    pub tolerant: bool,
+    /// Recover names missing from `(listfile)` by hashing candidates (see [`MpqArchive::recover_names`]).
+    pub recover_names: bool,
+    /// Whether files are memory-mapped or read with positioned reads.
+    pub read_mode: ReadMode,
+}
+
+/// An opened MPQ archive.
+pub struct MpqArchive {
+    source: Box<dyn ReadAt>,
+    path: Option<PathBuf>,
+    location: ArchiveLocation,
+    header: MpqHeader,
+    hash_table: Vec<HashEntry>,
+    block_table: Vec<BlockEntry>,
+    /// HET/BET tables of v3+ archives that have them.
+    het_index: Option<HetIndex>,
+    preferred_locale: u16,
+    integrity_mode: IntegrityMode,
+    tolerant: bool,
+    anomalies: Vec<Anomaly>,
+    /// Names known from outside `(listfile)`, such as recovered ones.
+    extra_names: Vec<String>,
+}
+
+impl MpqArchive {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MpqArchiveError> {
+        Self::open_with(path, OpenOptions::default())
+    }
+
+    pub fn open_with(path: impl AsRef<Path>, options: OpenOptions) -> Result<Self, MpqArchiveError> {
+        let source = open_file(path.as_ref(), options.read_mode)?;
+        let mut archive = Self::from_source(source, options)?;
+        archive.path = Some(path.as_ref().to_path_buf());
+        Ok(archive)
+    }
+
+    pub fn from_bytes(bytes: Arc<[u8]>) -> Result<Self, MpqArchiveError> {
+        Self::from_bytes_with(bytes, OpenOptions::default())
+    }
+
+    pub fn from_bytes_with(bytes: Arc<[u8]>, options: OpenOptions) -> Result<Self, MpqArchiveError> {
+        Self::from_reader(bytes, options)
+    }
+
+    /// Reads the archive from any [`ReadAt`] source.
+    pub fn from_reader(source: impl ReadAt + 'static, options: OpenOptions) -> Result<Self, MpqArchiveError> {
+        Self::from_source(Box::new(source), options)
+    }
+
+    fn from_source(source: Box<dyn ReadAt>, options: OpenOptions) -> Result<Self, MpqArchiveError> {
+        let location = locate_archive(source.len(), |offset, buf| source.read_at(offset, buf))?;
+
+        let mut header_bytes = vec![0u8; HEADER_SIZE_V4 as usize];
+        let read = source.read_at(location.archive_offset, &mut header_bytes)?;
+        let mut header = MpqHeader::parse(&header_bytes[..read])?;
+        let mut anomalies = Vec::new();
+        if options.tolerant {
+            let mut id = [0u8; 4];
+            let is_map = location.archive_offset > 0 && source.read_at(0, &mut id)? == 4 && &id == HM3W_ID;
+            if header_is_malformed(header.header_size, header.format_version, is_map) {
+                anomalies.push(Anomaly::MalformedHeader { header_size: header.header_size, format_version: header.format_version });
+                header = MpqHeader { header_size: HEADER_SIZE_V1, format_version: 0, ..MpqHeader::parse(&header_bytes[..HEADER_SIZE_V1 as usize])? };
+            }
+        }
+
+        let mut archive = Self { source, path: None, location, header, hash_table: Vec::new(), block_table: Vec::new(), het_index: None, preferred_locale: LOCALE_NEUTRAL, integrity_mode: options.integrity, tolerant: options.tolerant, anomalies: Vec::new(), extra_names: Vec::new() };
+
+        let hash_count = archive.header.hash_table_size as usize;
+        let raw_hash = archive.read_table("hash table", archive.header.hash_table_offset(), hash_count * 16, &mut anomalies)?;
+        archive.hash_table = parse_hash_table(&raw_hash, hash_count);
+        if archive.hash_table.len() < hash_count && hash_count <= MAX_PADDED_HASH_ENTRIES {
+            // Storm leaves the missing tail of a cut hash table zeroed (not empty), so probing runs through it.
+            archive.hash_table.resize(hash_count, HashEntry { hash_a: 0, hash_b: 0, locale: 0, platform: 0, block_index: 0 });
+        }
+
+        let block_count = archive.header.block_table_size as usize;
+        let raw_block = archive.read_table("block table", archive.header.block_table_offset(), block_count * 16, &mut anomalies)?;
+        let raw_hi = if archive.header.hi_block_table_pos != 0 { Some(archive.read_table("hi-block table", archive.header.hi_block_table_pos, block_count * 2, &mut anomalies)?) } else { None };
+        archive.block_table = parse_block_table(&raw_block, block_count, raw_hi.as_deref());
+
+        if archive.header.het_table_pos != 0 && archive.header.bet_table_pos != 0 {
+            match archive.read_het_index() {
+                Ok(index) => {
+                    if archive.block_table.is_empty() {
+                        archive.block_table = index.bet.blocks.clone();
+                    }
+                    archive.het_index = Some(index);
+                }
+                // The classic tables are enough to read the archive without them.
+                Err(err) if !archive.hash_table.is_empty() => log(format!("MpqArchive: ignoring HET/BET tables of {}: {}", archive.display_name(), err)),
+                Err(err) => return Err(err),
+            }
+        }
+
+        for piece in archive.verify_md5_pieces() {
+            if piece.check == Check::Mismatch {
+                archive.integrity_failure(piece.piece)?;
+            }
+        }
+        if archive.tolerant {
+            anomalies.extend(archive.scan_anomalies());
+            for anomaly in &anomalies {
+                log(format!("MpqArchive: {}: {}", archive.display_name(), anomaly));
+            }
+            archive.anomalies = anomalies;
+        }
+        if options.recover_names {
+            let recovery = archive.recover_names::<&str>(&[]);
+            log(format!("MpqArchive: recovered {} name(s) in {}, {} still unknown", recovery.recovered.len(), archive.display_name(), recovery.unresolved));
+            archive.add_names(recovery.recovered);
+        }
+        Ok(archive)
+    }
+
+    /// Reads a table. Tolerant archives get whatever part of it is in the file; the cut is recorded.
+    fn read_table(&self, table: &'static str, offset: u64, len: usize, anomalies: &mut Vec<Anomaly>) -> Result<Vec<u8>, MpqArchiveError> {
+        if !self.tolerant {
+            return self.read_exact(offset, len);
+        }
+        let bytes = self.read_available(offset, len)?;
+        if bytes.len() < len {
+            anomalies.push(Anomaly::TableCut { table, declared: len as u64, available: bytes.len() as u64 });
+        }
+        Ok(bytes)
+    }
+
+    /// Reads and parses the HET and BET tables the header points to.
+    fn read_het_index(&self) -> Result<HetIndex, MpqArchiveError> {
+        let read = |offset: u64| -> Result<Vec<u8>, MpqArchiveError> {
+            let header = self.read_exact(offset, EXT_TABLE_HEADER_SIZE)?;
+            self.read_exact(offset, ext_table_len(&header))
+        };
+        let het = HetTable::parse(&read(self.header.het_table_pos)?)?;
+        let bet = BetTable::parse(&read(self.header.bet_table_pos)?)?;
+        Ok(HetIndex::new(het, bet))
+    }
+
+    /// Reads up to `len` bytes at an offset relative to the archive start, stopping at the end of the file.
+    fn read_available(&self, offset: u64, len: usize) -> Result<Vec<u8>, MpqArchiveError> {
+        let start = self.location.archive_offset.saturating_add(offset);
+        let len = (len as u64).min(self.source.len().saturating_sub(start)) as usize;
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        self.read_absolute(start, len)
+    }
+
+    /// Malformations accepted while opening a tolerant archive; always empty otherwise.
+    pub fn anomalies(&self) -> &[Anomaly] {
+        &self.anomalies
+    }
+
+    /// Path the archive was opened from, if it came from a file.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Human-readable name of the archive for logs and reports.
+    pub fn display_name(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => format!("<memory, {} bytes>", self.source.len()),
+        }
+    }
+
+    /// The Warcraft III map header, when the archive is embedded in a `.w3m`/`.w3x` file.
+    pub fn map_header(&self) -> Option<MapHeader> {
+        let len = (HEADER_ALIGNMENT as usize).min(self.location.archive_offset as usize);
+        self.read_absolute(0, len)
+            .ok()
+            .and_then(|bytes| MapHeader::parse(&bytes))
+    }
+
+    pub fn header(&self) -> &MpqHeader {
+        &self.header
+    }
+
+    pub fn location(&self) -> ArchiveLocation {
+        self.location
+    }
+
+    pub fn hash_table(&self) -> &[HashEntry] {
+        &self.hash_table
+    }
+
+    /// The block table; the BET table's entries when the archive has no classic one.
+    pub fn block_table(&self) -> &[BlockEntry] {
+        &self.block_table
+    }
+
+    /// HET/BET tables, for v3+ archives that have them.
+    pub fn het_index(&self) -> Option<&HetIndex> {
+        self.het_index.as_ref()
+    }
+
+    /// Total length of the container file, including any prefix before the archive.
+    pub fn source_len(&self) -> u64 {
+        self.source.len()
+    }
+
+    /// Reads `len` bytes at an offset relative to the archive start.
+    pub fn read_exact(&self, offset: u64, len: usize) -> Result<Vec<u8>, MpqArchiveError> {
+        self.read_absolute(self.location.archive_offset.saturating_add(offset), len)
+    }
+
+    /// Reads `len` bytes at an absolute offset in the container file. Ranges past the end fail before anything is
+    /// allocated for them.
+    pub fn read_absolute(&self, offset: u64, len: usize) -> Result<Vec<u8>, MpqArchiveError> {
+        if offset.checked_add(len as u64).is_none_or(|end| end > self.source.len()) {
+            return Err(MpqArchiveError::Corrupted(format!("unexpected end of file reading {len} bytes at 0x{offset:X}")));
+        }
+        let mut buf = vec![0u8; len];
+        let read = self.source.read_at(offset, &mut buf)?;
+        if read != len {
+            return Err(MpqArchiveError::Corrupted(format!("unexpected end of file reading {len} bytes at 0x{offset:X}")));
+        }
+        Ok(buf)
+    }
+
+    /// Locale preferred by name lookups when a file exists in several locales.
+    pub fn preferred_locale(&self) -> u16 {
+        self.preferred_locale
+    }
+
+    pub fn set_preferred_locale(&mut self, locale: u16) {
+        self.preferred_locale = locale;
+    }
+
+    /// How v4 MD5 mismatches are treated when the archive and its files are read.
+    pub fn integrity_mode(&self) -> IntegrityMode {
+        self.integrity_mode
+    }
+
+    /// Every variant of `name` whose block exists, one per locale/platform. Names missing from the classic hash table
+    /// are looked up in the HET table, which has a single neutral variant.
+    pub fn file_variants(&self, name: &str) -> Vec<&HashEntry> {
+        let mut found = find_hash_entries(&self.hash_table, name);
+        if found.is_empty() {
+            found.extend(self.het_index.as_ref().and_then(|index| index.find(name)));
+        }
+        found
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .filter(|entry| self.block_table.get(entry.block_index as usize).is_some_and(|b| b.exists()))
+            .collect()
+    }
+
+    /// The hash entry name lookups resolve to: the preferred locale, then neutral, then the first variant found.
+    pub fn find_hash_entry(&self, name: &str) -> Option<&HashEntry> {
+        let variants = self.file_variants(name);
+        variants
+            .iter()
+            .find(|entry| entry.locale == self.preferred_locale)
+            .or_else(|| variants.iter().find(|entry| entry.locale == LOCALE_NEUTRAL))
+            .or_else(|| variants.first())
+            .copied()
+    }
+
+    /// Resolves a file name to its block index and block entry.
+    pub fn find_file(&self, name: &str) -> Option<(usize, &BlockEntry)> {
+        let entry = self.find_hash_entry(name)?;
+        let index = entry.block_index as usize;
+        self.block_table
+            .get(index)
+            .map(|block| (index, block))
+    }
+
+    /// Whether `name` is a file of this archive. Delete markers of patch archives are not files.
+    pub fn contains(&self, name: &str) -> bool {
+        self.find_file(name).is_some_and(|(_, block)| !block.is_delete_marker())
+    }
+
+    /// Extracts and decompresses a file by name. Delete markers read as missing files.
+    pub fn read_file(&self, name: &str) -> Result<Vec<u8>, MpqArchiveError> {
+        let (_, block) = self
+            .find_file(name)
+            .filter(|(_, block)| !block.is_delete_marker())
+            .ok_or_else(|| MpqArchiveError::FileNotFound(name.to_string()))?;
+        self.read_block(block, Some(name))
+    }
+
+    /// Extracts the data of a block. `name` is only needed for encrypted files.
+    /// For patch files (`MPQ_FILE_PATCH_FILE`) this returns the `PTCH` blob, not the patched content.
+    pub fn read_block(&self, block: &BlockEntry, name: Option<&str>) -> Result<Vec<u8>, MpqArchiveError> {
+        self.read_block_with_compression(block, name)
+            .map(|(data, _)| data)
+    }
+
+    /// Like [`read_block`](Self::read_block), also returning the union of the compression masks of its sectors.
+    pub fn read_block_with_compression(&self, block: &BlockEntry, name: Option<&str>) -> Result<(Vec<u8>, u8), MpqArchiveError> {
+        if block.file_size == 0 {
+            return Ok((Vec::new(), 0));
+        }
+
+        let key = block_key(block, name)?;
+        let mut raw = if self.tolerant { self.read_available(block.file_pos, block.compressed_size as usize)? } else { self.read_exact(block.file_pos, block.compressed_size as usize)? };
+        if self.verify_raw_chunks(block, &raw) == Check::Mismatch {
+            self.integrity_failure(&format!("raw chunk of block at 0x{:X}", block.file_pos))?;
+        }
+        let mut file_size = block.file_size as usize;
+
+        if block.flags & MPQ_FILE_PATCH_FILE != 0 {
+            // Patch files start with an unencrypted info block: length, flags, data size, MD5.
+            if raw.len() < 12 {
+                return Err(MpqArchiveError::Corrupted("truncated patch info".into()));
+            }
+            let info_len = (read_u32(&raw, 0) as usize).min(raw.len());
+            file_size = read_u32(&raw, 8) as usize;
+            raw.drain(..info_len);
+        }
+
+        if block.is_single_unit() {
+            if let Some(key) = key {
+                decrypt_bytes(&mut raw, key);
+            }
+            return self.unpack_sector(block, raw, file_size);
+        }
+
+        let sector_size = self.header.sector_size() as usize;
+        let sector_count = file_size.div_ceil(sector_size);
+
+        if !block.is_compressed() {
+            if let Some(key) = key {
+                for (i, sector) in raw.chunks_mut(sector_size).enumerate() {
+                    decrypt_bytes(sector, key.wrapping_add(i as u32));
+                }
+            }
+            raw.truncate(file_size);
+            return Ok((raw, 0));
+        }
+
+        let offsets = sector_offsets(&raw, sector_count, block.flags, key)?;
+        let sectors_end = offsets[sector_count] as usize;
+        if self.tolerant && sectors_end > raw.len() && block.flags & MPQ_FILE_PATCH_FILE == 0 {
+            // Protected maps understate compressed sizes; the sector offset table is what Storm trusts.
+            raw = self.read_available(block.file_pos, sectors_end)?;
+        }
+        let mut out = Vec::with_capacity(file_size);
+        let mut mask = 0u8;
+        for i in 0..sector_count {
+            let (start, end) = (offsets[i] as usize, offsets[i + 1] as usize);
+            if start > end || end > raw.len() {
+                return Err(MpqArchiveError::Corrupted(format!("sector {i} out of bounds ({start}..{end})")));
+            }
+            let mut sector = raw[start..end].to_vec();
+            if let Some(key) = key {
+                decrypt_bytes(&mut sector, key.wrapping_add(i as u32));
+            }
+            let expected = sector_size.min(file_size - i * sector_size);
+            let (unpacked, sector_mask) = self.unpack_sector(block, sector, expected)?;
+            out.extend_from_slice(&unpacked);
+            mask |= sector_mask;
+        }
+        Ok((out, mask))
+    }
+
+    /// Union of the compression masks of a block's sectors, found from the sector offset table and the first byte of
+    /// each compressed sector without decompressing anything.
+    pub fn block_compression_mask(&self, block: &BlockEntry, name: Option<&str>) -> Result<u8, MpqArchiveError> {
+        if block.file_size == 0 || !block.is_compressed() {
+            return Ok(0);
+        }
+        let key = block_key(block, name)?;
+        let (mut start, mut stored_size, mut file_size) = (block.file_pos, block.compressed_size as usize, block.file_size as usize);
+        if block.flags & MPQ_FILE_PATCH_FILE != 0 {
+            let info = self.read_exact(block.file_pos, 12)?;
+            let info_len = (read_u32(&info, 0) as usize).min(stored_size);
+            start += info_len as u64;
+            stored_size -= info_len;
+            file_size = read_u32(&info, 8) as usize;
+        }
+        // First byte of a sector stored at `offset`, decrypted with its dword.
+        let mask_at = |offset: u64, len: usize, key: Option<u32>| -> Result<u8, MpqArchiveError> {
+            if block.flags & MPQ_FILE_COMPRESS == 0 {
+                return Ok(COMPRESSION_PKWARE);
+            }
+            let mut head = self.read_exact(offset, len.min(4))?;
+            if let Some(key) = key {
+                decrypt_bytes(&mut head, key);
+            }
+            Ok(head[0])
+        };
+
+        if block.is_single_unit() {
+            return if stored_size == 0 || stored_size >= file_size { Ok(0) } else { mask_at(start, stored_size, key) };
+        }
+        let sector_size = self.header.sector_size() as usize;
+        let sector_count = file_size.div_ceil(sector_size);
+        let entries = sector_count + 1 + usize::from(block.flags & MPQ_FILE_SECTOR_CRC != 0);
+        let offsets = sector_offsets(&self.read_exact(start, entries * 4)?, sector_count, block.flags, key)?;
+        let mut mask = 0u8;
+        for i in 0..sector_count {
+            let (from, to) = (offsets[i] as usize, offsets[i + 1] as usize);
+            if from > to {
+                return Err(MpqArchiveError::Corrupted(format!("sector {i} out of bounds ({from}..{to})")));
+            }
+            let expected = sector_size.min(file_size - i * sector_size);
+            if from < to && to - from < expected {
+                mask |= mask_at(start + from as u64, to - from, key.map(|key| key.wrapping_add(i as u32)))?;
+            }
+        }
+        Ok(mask)
+    }
+
+    /// Decompresses a sector unless it was stored raw because compression did not help. Returns the mask applied.
+    fn unpack_sector(&self, block: &BlockEntry, data: Vec<u8>, expected: usize) -> Result<(Vec<u8>, u8), MpqArchiveError> {
+        if !block.is_compressed() || data.len() >= expected {
+            return Ok((data, 0));
+        }
+        let (mut unpacked, mask) = if block.flags & MPQ_FILE_COMPRESS != 0 { (decompress(&data, expected)?, data[0]) } else { (decompress_imploded(&data, expected)?, COMPRESSION_PKWARE) };
+        unpacked.truncate(expected);
+        Ok((unpacked, mask))
+    }
+
+    /// Makes names found outside `(listfile)` part of [`file_names`](Self::file_names).
+    pub fn add_names(&mut self, names: impl IntoIterator<Item = String>) {
+        self.extra_names.extend(names);
+    }
+
+    /// File names from `(listfile)` and [`add_names`](Self::add_names), plus the special files that are present.
+    /// Names hidden by delete markers are left out; see [`deleted_names`](Self::deleted_names).
+    pub fn file_names(&self) -> Vec<String> {
+        self.known_names(|name| self.contains(name))
+    }
+
+    /// Names from `(listfile)` and [`add_names`](Self::add_names) whose entry is a delete marker.
+    pub fn deleted_names(&self) -> Vec<String> {
+        self.known_names(|name| self.find_file(name).is_some_and(|(_, block)| block.is_delete_marker()))
+    }
+
+    /// Every name the archive knows of that passes `keep`, without case-insensitive duplicates.
+    fn known_names(&self, keep: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut seen = HashSet::new();
+        let mut names: Vec<String> = Vec::new();
+        let mut push = |name: &str| {
+            if keep(name) && seen.insert(name.to_ascii_lowercase()) {
+                names.push(name.to_string());
+            }
+        };
+
+        if let Ok(listfile) = self.read_file("(listfile)") {
+            for name in parse_listfile(&listfile) {
+                push(&name);
+            }
+        }
+        for name in &self.extra_names {
+            push(name);
+        }
+        for name in SPECIAL_FILES {
+            push(name);
+        }
+        names
+    }
+}
+
+/// Splits a listfile into names. Entries may be separated by CR, LF or `;`.
+pub fn parse_listfile(bytes: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(bytes)
+        .split(['\r', '\n', ';'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Decryption key of an encrypted block, derived from its name; `None` for plain blocks.
+pub(crate) fn block_key(block: &BlockEntry, name: Option<&str>) -> Result<Option<u32>, MpqArchiveError> {
+    if !block.is_encrypted() {
+        return Ok(None);
+    }
+    let name = name.ok_or(MpqArchiveError::Unsupported("encrypted file without a known name"))?;
+    Ok(Some(file_key(name, block.file_pos, block.file_size, block.flags & MPQ_FILE_FIX_KEY != 0)))
+}
+
+/// Reads the (optionally encrypted) sector offset table at the start of a compressed block.
+pub(crate) fn sector_offsets(raw: &[u8], sector_count: usize, flags: u32, key: Option<u32>) -> Result<Vec<u32>, MpqArchiveError> {
+    let entries = sector_count + 1 + usize::from(flags & MPQ_FILE_SECTOR_CRC != 0);
+    if raw.len() < entries * 4 {
+        return Err(MpqArchiveError::Corrupted("truncated sector offset table".into()));
+    }
+    let mut table = raw[..entries * 4].to_vec();
+    if let Some(key) = key {
+        decrypt_bytes(&mut table, key.wrapping_sub(1));
+    }
+    Ok((0..entries).map(|i| read_u32(&table, i * 4)).collect())
+}
@@ -0,0 +1,136 @@
+//! Recovery of file names for archives whose `(listfile)` was stripped.
+//!
+//! Candidate paths are hashed and matched against the hash table: the standard Warcraft III map
+//! files, then every path-like string found in files already resolved (`war3map.j`/`.lua`, `war3map.w3i`,
+//! texture references in models, `war3map.imp`, object data). Each newly resolved file is scanned in
+//! turn until nothing new turns up.
+
+use crate::archive::crypto::{HASH_NAME_A, HASH_NAME_B, hash_string};
+use crate::archive::reader::MpqArchive;
+use crate::archive::tables::find_hash_entries;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Files a Warcraft III map or campaign may contain, tried before anything else.
+pub const KNOWN_MAP_FILES: &[&str] = &[
+    "(listfile)", "(attributes)", "(signature)",
+    "war3map.j", "scripts\\war3map.j", "war3map.lua", "scripts\\war3map.lua",
+    "war3map.w3i", "war3map.w3e", "war3map.wpm", "war3map.doo", "war3mapUnits.doo", "war3map.shd", "war3map.mmp",
+    "war3map.w3r", "war3map.w3c", "war3map.w3s", "war3map.wts", "war3map.wtg", "war3map.wct", "war3map.imp",
+    "war3map.w3u", "war3map.w3t", "war3map.w3b", "war3map.w3d", "war3map.w3a", "war3map.w3h", "war3map.w3q",
+    "war3mapMap.blp", "war3mapMap.tga", "war3mapMap.b00", "war3mapPreview.tga", "war3mapPreview.blp", "war3mapPath.tga",
+    "war3mapMisc.txt", "war3mapSkin.txt", "war3mapExtra.txt", "war3mapSkin.w3u", "war3mapSkin.w3t", "war3mapSkin.w3b",
+    "war3mapSkin.w3d", "war3mapSkin.w3a", "war3mapSkin.w3h", "war3mapSkin.w3q",
+    "war3campaign.w3u", "war3campaign.w3t", "war3campaign.w3b", "war3campaign.w3d", "war3campaign.w3a", "war3campaign.w3h",
+    "war3campaign.w3q", "war3campaign.w3f", "war3campaign.wts", "war3campaign.imp",
+    "conversation.json",
+];
+
+/// Extensions that make a string found in a file worth hashing.
+const PATH_EXTENSIONS: &[&str] = &[
+    "mdx", "mdl", "blp", "tga", "dds", "wav", "mp3", "flac", "ogg", "mid", "txt", "slk", "j", "lua", "ai", "fdf", "toc",
+    "imp", "doo", "w3u", "w3t", "w3b", "w3d", "w3a", "w3h", "w3q", "wts", "pld", "json", "ttf",
+];
+/// Folder the World Editor imports files into by default.
+const IMPORTED_DIR: &str = "war3mapImported\\";
+const MAX_PATH_LEN: usize = 260;
+
+/// Result of a name recovery pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameRecovery {
+    /// Names resolved by hashing candidates, in discovery order; names already known are not repeated.
+    pub recovered: Vec<String>,
+    /// Live hash entries that are still nameless.
+    pub unresolved: usize,
+}
+
+impl MpqArchive {
+    /// Recovers names of files missing from `(listfile)`. `extra` candidates (e.g. an external listfile) are tried too.
+    pub fn recover_names<S: AsRef<str>>(&self, extra: &[S]) -> NameRecovery {
+        let known = self.file_names();
+        let mut named_slots = HashSet::new();
+        for name in known.iter().chain(&self.deleted_names()) {
+            named_slots.extend(find_hash_entries(self.hash_table(), name).into_iter().map(|(index, _)| index));
+        }
+        // Nameless live entries, keyed by the two name hashes a candidate must reproduce.
+        let mut unresolved: HashMap<(u32, u32), usize> = HashMap::new();
+        for (index, hash) in self.hash_table().iter().enumerate() {
+            let live = !hash.is_empty() && !hash.is_deleted() && self.block_table().get(hash.block_index as usize).is_some_and(|block| block.exists());
+            if live && !named_slots.contains(&index) {
+                *unresolved.entry((hash.hash_a, hash.hash_b)).or_default() += 1;
+            }
+        }
+
+        let mut recovery = NameRecovery::default();
+        let mut tried: HashSet<String> = HashSet::new();
+        let mut candidates: VecDeque<String> = KNOWN_MAP_FILES
+            .iter()
+            .map(|name| name.to_string())
+            .chain(extra.iter().map(|name| name.as_ref().to_string()))
+            .collect();
+        // Known files are scanned as well: their references can name the missing ones.
+        let mut to_scan: Vec<String> = known;
+
+        while !unresolved.is_empty() && (!candidates.is_empty() || !to_scan.is_empty()) {
+            for name in to_scan.drain(..) {
+                if let Ok(data) = self.read_file(&name) {
+                    candidates.extend(referenced_paths(&data));
+                }
+            }
+            while let Some(candidate) = candidates.pop_front() {
+                if !tried.insert(candidate.to_ascii_lowercase()) {
+                    continue;
+                }
+                let key = (hash_string(&candidate, HASH_NAME_A), hash_string(&candidate, HASH_NAME_B));
+                if unresolved.remove(&key).is_some() {
+                    to_scan.push(candidate.clone());
+                    recovery.recovered.push(candidate);
+                }
+            }
+        }
+        recovery.unresolved = unresolved.values().sum();
+        recovery
+    }
+}
+
+/// Path-like strings in `data` (quoted strings, NUL-terminated strings, SLK/INI values), with the
+/// variants the game would also look up: `.mdl` as `.mdx`, `.tga` as `.blp` and the imported folder.
+pub fn referenced_paths(data: &[u8]) -> Vec<String> {
+    let mut paths = Vec::new();
+    let is_separator = |b: u8| !(0x20..0x7F).contains(&b) || matches!(b, b'"' | b',' | b';' | b'=' | b'|' | b'(' | b')' | b'\'');
+    for token in data.split(|&b| is_separator(b)) {
+        let Ok(token) = std::str::from_utf8(token) else { continue };
+        let token = token.trim();
+        if token.is_empty() || token.len() > MAX_PATH_LEN || !has_path_extension(token) {
+            continue;
+        }
+        // JASS and Lua escape backslashes; both slashes mean the same to Storm.
+        let path = token.replace("\\\\", "\\").replace('/', "\\");
+        let path = path.trim_start_matches('\\');
+        paths.extend(path_variants(path));
+    }
+    paths
+}
+
+fn has_path_extension(token: &str) -> bool {
+    token
+        .rsplit_once('.')
+        .is_some_and(|(stem, ext)| !stem.is_empty() && PATH_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+fn path_variants(path: &str) -> Vec<String> {
+    let mut variants = vec![path.to_string()];
+    let (stem, ext) = path.rsplit_once('.').unwrap_or((path, ""));
+    match ext.to_ascii_lowercase().as_str() {
+        "mdl" => variants.push(format!("{stem}.mdx")),
+        "mdx" => variants.push(format!("{stem}_portrait.mdx")),
+        "tga" => variants.push(format!("{stem}.blp")),
+        _ => {}
+    }
+    let prefixed: Vec<String> = variants
+        .iter()
+        .filter(|variant| !variant.to_ascii_lowercase().starts_with(&IMPORTED_DIR.to_ascii_lowercase()))
+        .map(|variant| format!("{IMPORTED_DIR}{variant}"))
+        .collect();
+    variants.extend(prefixed);
+    variants
+}
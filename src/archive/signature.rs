@@ -0,0 +1,388 @@
+//! Weak and strong digital signatures.
+//!
+//! The weak signature is the `(signature)` file: an RSA-512 PKCS#1 v1.5 signature of the MD5 of the
+//! archive, hashed with the `(signature)` file's own bytes zeroed. The strong signature is `NGIS`
+//! plus an RSA-2048 block appended after the archive; it must decrypt to exactly `0x0B`, `0xBB` padding
+//! and the byte-reversed SHA-1 of the archive, optionally followed by one of Storm's "tails" (the
+//! upper-case file name or `ARCHIVE`). Both signatures are stored little-endian.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::reader::MpqArchive;
+use crate::utils::md5::Md5;
+use num_bigint::BigUint;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// Name of the weak signature file.
+pub const SIGNATURE_FILE_NAME: &str = "(signature)";
+/// `NGIS` — marker of the strong signature appended after the archive.
+pub const STRONG_SIGNATURE_ID: &[u8; 4] = b"NGIS";
+/// Marker plus the 256-byte signature.
+pub const STRONG_SIGNATURE_SIZE: usize = 4 + 256;
+
+/// The weak signature sits after 8 unused bytes of the `(signature)` file.
+const WEAK_SIGNATURE_OFFSET: usize = 8;
+const WEAK_SIGNATURE_SIZE: usize = 64;
+const SHA1_DIGEST_SIZE: usize = 20;
+/// DER `DigestInfo` prefix for MD5 used by PKCS#1 v1.5.
+const MD5_DIGEST_INFO: &[u8] = &[0x30, 0x20, 0x30, 0x0C, 0x06, 0x08, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x02, 0x05, 0x05, 0x00, 0x04, 0x10];
+const HASH_CHUNK_SIZE: usize = 1 << 20;
+
+/// Blizzard's weak signature key (RSA-512), as published with Storm.
+pub const BLIZZARD_WEAK_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAJJidwS/uILMBSO5DLGsBFknIXWWjQJe
+2kfdfEk3G/j66w4KkhZ1V61Rt4zLaMVCYpDun7FLwRjkMDSepO1q2DcCAwEAAQ==
+-----END PUBLIC KEY-----";
+
+/// Blizzard's strong signature key (RSA-2048) for game archives.
+pub const BLIZZARD_STRONG_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIDANBgkqhkiG9w0BAQEFAAOCAQ0AMIIBCAKCAQEAsQZ+ziT2h8h+J/iMQpgd
+tH1HaJzOBE3agjU4yMPcrixaPOZoA4t8bwfey7qczfWywocYo3pleytFF+IuD4HD
+Fl9OXN1SFyupSgMx1EGZlgbFAomnbq9MQJyMqQtMhRAjFgg4TndS7YNb+JMSAEKp
+kXNqY28n/EVBHD5TsMuVCL579gIenbr61dI92DDEdy790IzIG0VKWLh/KOTcTJfm
+Ds/7HQTkGouVW+WUsfekuqNQo7ND9DBnhLjLjptxeFE2AZqYcA1ao3S9LN3GL1tW
+lVXFIX9c7fWqaVTQlZ2oNsI/ARVApOK3grNgqvwH6YoVYVXjNJEo5sQJsPsdV/hk
+dwIBAw==
+-----END PUBLIC KEY-----";
+
+/// Blizzard's strong signature key (RSA-2048) for Warcraft III maps.
+pub const WARCRAFT3_MAP_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIDANBgkqhkiG9w0BAQEFAAOCAQ0AMIIBCAKCAQEA1BwklUUQ3UvjizOBRoF5
+yyOVc7KD+oGOQH5i/eUQ1Bg9ouzHV3Gh9GUEgHsNHqHijfxM3jLS2d2SmrBz7JEz
+kBHDOiHUP7+lQnBKd3AxJ7CkYgNSAn8YIpp9AFF0ZQ5tfd1QNw4eATfeGZGhQ14W
+xzNaopI9u+IthaJ0PR+9xIF6lY5JkUZrF9aK3yH1FJYrCXLDoAWR2JjtSc0LpXJ3
++RwS1xG2YoJLWq4DkD0jHbaxO1khH+xzA9fmwZs2b+2cmzPy/Y6lTmVfbhyEVVFZ
+hVbq1p1MUgLUZdh9kbhZx1P06amfc8xo4eFO8nsi5Wn4ewlnoE74oGdhqiS9lSl6
+6wIBAw==
+-----END PUBLIC KEY-----";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RsaPublicKey {
+    pub modulus: BigUint,
+    pub exponent: BigUint,
+}
+
+impl RsaPublicKey {
+    /// Parses a PEM `PUBLIC KEY` (SubjectPublicKeyInfo) or `RSA PUBLIC KEY` (PKCS#1).
+    pub fn from_pem(pem: &str) -> Result<Self, MpqArchiveError> {
+        let body: String = pem
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with("-----"))
+            .collect();
+        let der = decode_base64(&body).ok_or_else(|| MpqArchiveError::Corrupted("invalid base64 in PEM key".into()))?;
+        Self::from_der(&der)
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Self, MpqArchiveError> {
+        parse_rsa_key(der).ok_or_else(|| MpqArchiveError::Corrupted("not an RSA public key".into()))
+    }
+
+    /// Modulus size in bytes.
+    pub fn size(&self) -> usize {
+        self.modulus.bits().div_ceil(8) as usize
+    }
+
+    /// Raw RSA public operation on a little-endian signature, returning the big-endian result padded to the key size.
+    fn recover(&self, signature_le: &[u8]) -> Option<Vec<u8>> {
+        let signature = BigUint::from_bytes_le(signature_le);
+        if signature >= self.modulus {
+            return None;
+        }
+        let message = signature.modpow(&self.exponent, &self.modulus).to_bytes_be();
+        let mut padded = vec![0u8; self.size().checked_sub(message.len())?];
+        padded.extend_from_slice(&message);
+        Some(padded)
+    }
+}
+
+/// A public key with a name for reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedKey {
+    pub name: String,
+    pub key: RsaPublicKey,
+}
+
+/// Keys signatures are checked against, tried in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SignatureKeys {
+    pub weak: Vec<NamedKey>,
+    pub strong: Vec<NamedKey>,
+}
+
+impl SignatureKeys {
+    /// Blizzard's published weak key and strong keys (game archives and Warcraft III maps).
+    pub fn blizzard() -> Self {
+        let named = |name: &str, pem: &str| NamedKey { name: name.to_string(), key: RsaPublicKey::from_pem(pem).expect("built-in key") };
+        Self {
+            weak: vec![named("Blizzard weak", BLIZZARD_WEAK_PUBLIC_KEY)],
+            strong: vec![named("Blizzard strong", BLIZZARD_STRONG_PUBLIC_KEY), named("Warcraft III map", WARCRAFT3_MAP_PUBLIC_KEY)],
+        }
+    }
+
+    /// Adds a custom key; 512-bit keys check weak signatures, larger ones strong signatures.
+    pub fn add(&mut self, name: impl Into<String>, key: RsaPublicKey) {
+        let named = NamedKey { name: name.into(), key };
+        if named.key.size() <= WEAK_SIGNATURE_SIZE {
+            self.weak.push(named);
+        } else {
+            self.strong.push(named);
+        }
+    }
+
+    /// Adds the PEM key stored at `path`, named after the file.
+    pub fn add_pem_file(&mut self, path: impl AsRef<Path>) -> Result<(), MpqArchiveError> {
+        let path = path.as_ref();
+        let pem = fs::read_to_string(path)?;
+        let key = RsaPublicKey::from_pem(&pem)?;
+        self.add(path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(), key);
+        Ok(())
+    }
+
+    /// Appends the keys of `other` after these.
+    pub fn extend(&mut self, other: &SignatureKeys) {
+        self.weak.extend(other.weak.iter().cloned());
+        self.strong.extend(other.strong.iter().cloned());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Absent,
+    /// Verified; holds the name of the matching key.
+    Valid(String),
+    /// Present but matching none of the keys.
+    Invalid,
+    Error(String),
+}
+
+impl SignatureStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignatureStatus::Absent => "absent",
+            SignatureStatus::Valid(_) => "valid",
+            SignatureStatus::Invalid => "invalid",
+            SignatureStatus::Error(_) => "error",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            SignatureStatus::Absent => "absent".to_string(),
+            SignatureStatus::Valid(key) => format!("valid ({key})"),
+            SignatureStatus::Invalid => "invalid (no matching key)".to_string(),
+            SignatureStatus::Error(error) => format!("error ({error})"),
+        }
+    }
+
+    pub fn is_present(&self) -> bool {
+        *self != SignatureStatus::Absent
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureReport {
+    pub weak: SignatureStatus,
+    pub strong: SignatureStatus,
+}
+
+impl SignatureReport {
+    /// False when a signature is present but does not verify.
+    pub fn is_ok(&self) -> bool {
+        [&self.weak, &self.strong]
+            .iter()
+            .all(|status| matches!(status, SignatureStatus::Absent | SignatureStatus::Valid(_)))
+    }
+}
+
+impl MpqArchive {
+    /// Absolute offset where the archive ends and a strong signature would start.
+    pub fn archive_end(&self) -> u64 {
+        let header = self.header();
+        let size = if header.archive_size_64 != 0 { header.archive_size_64 } else { header.archive_size as u64 };
+        self.location().archive_offset + size
+    }
+
+    /// Absolute offset of the strong signature marker, if the archive carries one.
+    pub fn strong_signature_offset(&self) -> Option<u64> {
+        let has_marker = |offset: u64| self.read_absolute(offset, 4).is_ok_and(|marker| marker == STRONG_SIGNATURE_ID);
+        let len = self.source_len();
+        // Normally right after the archive; some tools append it at the end of a padded file instead.
+        [Some(self.archive_end()), len.checked_sub(STRONG_SIGNATURE_SIZE as u64)]
+            .into_iter()
+            .flatten()
+            .find(|&offset| offset + STRONG_SIGNATURE_SIZE as u64 <= len && has_marker(offset))
+    }
+
+    /// Verifies both signatures against `keys`. Hashes the whole archive once per signature present.
+    pub fn verify_signatures(&self, keys: &SignatureKeys) -> SignatureReport {
+        let error = |err: MpqArchiveError| SignatureStatus::Error(err.to_string());
+        SignatureReport {
+            weak: self.verify_weak_signature(keys).unwrap_or_else(error),
+            strong: self.verify_strong_signature(keys).unwrap_or_else(error),
+        }
+    }
+
+    fn verify_weak_signature(&self, keys: &SignatureKeys) -> Result<SignatureStatus, MpqArchiveError> {
+        let Some((_, block)) = self.find_file(SIGNATURE_FILE_NAME) else {
+            return Ok(SignatureStatus::Absent);
+        };
+        let data = self.read_block(block, Some(SIGNATURE_FILE_NAME))?;
+        let signature = data
+            .get(WEAK_SIGNATURE_OFFSET..WEAK_SIGNATURE_OFFSET + WEAK_SIGNATURE_SIZE)
+            .ok_or_else(|| MpqArchiveError::Corrupted("truncated (signature)".into()))?;
+
+        let exclude_start = self.location().archive_offset + block.file_pos;
+        let exclude = exclude_start..exclude_start + block.compressed_size as u64;
+        let mut md5 = Md5::new();
+        self.hash_range(self.signed_data_start(), self.archive_end(), Some(exclude), |chunk| md5.update(chunk))?;
+        let digest = md5.finalize();
+
+        let mut expected = vec![0x00, 0x01];
+        expected.resize(WEAK_SIGNATURE_SIZE - MD5_DIGEST_INFO.len() - digest.len() - 1, 0xFF);
+        expected.push(0x00);
+        expected.extend_from_slice(MD5_DIGEST_INFO);
+        expected.extend_from_slice(&digest);
+
+        Ok(keys
+            .weak
+            .iter()
+            .find(|named| named.key.recover(signature).is_some_and(|message| message == expected))
+            .map(|named| SignatureStatus::Valid(named.name.clone()))
+            .unwrap_or(SignatureStatus::Invalid))
+    }
+
+    fn verify_strong_signature(&self, keys: &SignatureKeys) -> Result<SignatureStatus, MpqArchiveError> {
+        let Some(offset) = self.strong_signature_offset() else {
+            return Ok(SignatureStatus::Absent);
+        };
+        let signature = self.read_absolute(offset + 4, STRONG_SIGNATURE_SIZE - 4)?;
+        let recovered: Vec<(&NamedKey, Vec<u8>)> = keys
+            .strong
+            .iter()
+            .filter_map(|named| named.key.recover(&signature).map(|message| (named, message)))
+            .collect();
+
+        let mut tails: Vec<Vec<u8>> = vec![Vec::new(), b"ARCHIVE".to_vec()];
+        if let Some(file_name) = self.path().and_then(|path| path.file_name()) {
+            tails.push(file_name.to_string_lossy().to_ascii_uppercase().into_bytes());
+        }
+
+        // Maps are signed either from the archive or from the start of the file (HM3W header included).
+        let mut starts = vec![self.signed_data_start()];
+        if starts[0] != 0 {
+            starts.push(0);
+        }
+        for start in starts {
+            let mut sha1 = sha1_smol::Sha1::new();
+            self.hash_range(start, offset, None, |chunk| sha1.update(chunk))?;
+            for tail in &tails {
+                let mut tailed = sha1.clone();
+                tailed.update(tail);
+                let expected = strong_signature_block(&tailed.digest().bytes());
+                if let Some((named, _)) = recovered.iter().find(|(_, message)| *message == expected) {
+                    return Ok(SignatureStatus::Valid(named.name.clone()));
+                }
+            }
+        }
+        Ok(SignatureStatus::Invalid)
+    }
+
+    /// Start of the signed data: the user-data header if there is one, else the archive header.
+    fn signed_data_start(&self) -> u64 {
+        let location = self.location();
+        location.user_data.map(|user_data| user_data.offset).unwrap_or(location.archive_offset)
+    }
+
+    /// Feeds `start..end` to `update` in chunks, with the bytes of `exclude` replaced by zeroes.
+    fn hash_range(&self, start: u64, end: u64, exclude: Option<Range<u64>>, mut update: impl FnMut(&[u8])) -> Result<(), MpqArchiveError> {
+        let end = end.min(self.source_len());
+        let mut pos = start;
+        while pos < end {
+            let len = ((end - pos) as usize).min(HASH_CHUNK_SIZE);
+            let mut chunk = self.read_absolute(pos, len)?;
+            if let Some(exclude) = &exclude {
+                let from = exclude.start.clamp(pos, pos + len as u64);
+                let to = exclude.end.clamp(pos, pos + len as u64);
+                chunk[(from - pos) as usize..(to - pos) as usize].fill(0);
+            }
+            update(&chunk);
+            pos += len as u64;
+        }
+        Ok(())
+    }
+}
+
+/// The block a valid strong signature decrypts to, compared whole: with e = 3 a check of the digest alone can be met
+/// by a cube root.
+fn strong_signature_block(digest: &[u8; SHA1_DIGEST_SIZE]) -> Vec<u8> {
+    let mut block = vec![0x0B];
+    block.resize(STRONG_SIGNATURE_SIZE - 4 - SHA1_DIGEST_SIZE, 0xBB);
+    block.extend(digest.iter().rev());
+    block
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|&c| c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Splits one DER element off `input`: (tag, content, rest).
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, mut rest) = rest.split_first()?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7F) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        rest = &rest[count..];
+        len
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_SEQUENCE: u8 = 0x30;
+
+fn parse_rsa_key(der: &[u8]) -> Option<RsaPublicKey> {
+    let (tag, outer, _) = der_element(der)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (first_tag, first, rest) = der_element(outer)?;
+    if first_tag == DER_INTEGER {
+        // PKCS#1 RSAPublicKey: modulus, exponent.
+        let (tag, exponent, _) = der_element(rest)?;
+        return (tag == DER_INTEGER).then(|| RsaPublicKey { modulus: BigUint::from_bytes_be(first), exponent: BigUint::from_bytes_be(exponent) });
+    }
+    // SubjectPublicKeyInfo: algorithm, then a BIT STRING (unused-bits byte first) wrapping RSAPublicKey.
+    let (tag, bits, _) = der_element(rest)?;
+    if first_tag != DER_SEQUENCE || tag != DER_BIT_STRING {
+        return None;
+    }
+    parse_rsa_key(bits.get(1..)?)
+}
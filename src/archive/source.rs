@@ -0,0 +1,201 @@
+//! Random-access sources archives are read from: memory-mapped files, positioned file reads and byte buffers.
+//!
+//! A mount keeps its archives open and decompresses each file from its source when the file is opened, so mapped
+//! archives serve concurrent reads from the page cache without a lock or a copy into a read buffer.
+//! Files that cannot be mapped (empty, too large for the address space, on file systems without mapping support)
+//! fall back to positioned reads, which do not share a file cursor either.
+
+use crate::log::log;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Byte source an archive is read from. Implementations must allow concurrent reads.
+pub trait ReadAt: Send + Sync {
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Reads as many bytes as available at `offset`, returning the count; fewer than `buf.len()` only at the end.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// How files are read by [`MpqArchive::open_with`](crate::archive::MpqArchive::open_with).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadMode {
+    /// Memory-map the file, falling back to positioned reads when it cannot be mapped.
+    #[default]
+    Mapped,
+    /// Positioned reads only.
+    Positioned,
+}
+
+fn copy_from(bytes: &[u8], offset: u64, buf: &mut [u8]) -> usize {
+    let start = offset.min(bytes.len() as u64) as usize;
+    let end = (start + buf.len()).min(bytes.len());
+    buf[..end - start].copy_from_slice(&bytes[start..end]);
+    end - start
+}
+
+impl ReadAt for Arc<[u8]> {
+    fn len(&self) -> u64 {
+        <[u8]>::len(self) as u64
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(copy_from(self, offset, buf))
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn len(&self) -> u64 {
+        <[u8]>::len(self) as u64
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(copy_from(self, offset, buf))
+    }
+}
+
+/// A file read with positioned reads (`pread` / `ReadFile` at an offset).
+pub struct FileSource {
+    file: File,
+    len: u64,
+}
+
+impl FileSource {
+    pub fn new(file: File) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        Ok(Self { file, len })
+    }
+}
+
+impl ReadAt for FileSource {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let mut total = 0;
+        while total < buf.len() {
+            match read_file_at(&self.file, offset + total as u64, &mut buf[total..]) {
+                Ok(0) => break,
+                Ok(n) => total += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(total)
+    }
+}
+
+#[cfg(unix)]
+fn read_file_at(file: &File, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+fn read_file_at(file: &File, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+    match std::os::windows::fs::FileExt::seek_read(file, buf, offset) {
+        // Reading at or past the end fails with ERROR_HANDLE_EOF instead of returning 0.
+        Err(err) if err.raw_os_error() == Some(38) => Ok(0),
+        result => result,
+    }
+}
+
+/// A read-only memory map of a whole file. The file must not be truncated while it is mapped.
+pub struct MmapSource {
+    ptr: *const u8,
+    len: usize,
+    #[cfg(windows)]
+    mapping: windows::Win32::Foundation::HANDLE,
+}
+
+// The mapping is read-only and owned by this value; any thread may read it.
+unsafe impl Send for MmapSource {}
+unsafe impl Sync for MmapSource {}
+
+impl MmapSource {
+    /// Maps `file`. Fails for empty files and files larger than the address space.
+    pub fn new(file: &File) -> io::Result<Self> {
+        let len = file.metadata()?.len();
+        if len == 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "cannot map an empty file"));
+        }
+        let len = usize::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "file is larger than the address space"))?;
+        Self::map(file, len)
+    }
+
+    #[cfg(unix)]
+    fn map(file: &File, len: usize) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+        let ptr = unsafe { libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_PRIVATE, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr: ptr as *const u8, len })
+    }
+
+    #[cfg(windows)]
+    fn map(file: &File, len: usize) -> io::Result<Self> {
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::{CloseHandle, HANDLE};
+        use windows::Win32::System::Memory::{CreateFileMappingW, FILE_MAP_READ, MapViewOfFile, PAGE_READONLY};
+        use windows::core::PCWSTR;
+
+        let mapping = unsafe { CreateFileMappingW(HANDLE(file.as_raw_handle()), None, PAGE_READONLY, 0, 0, PCWSTR::null()) }.map_err(io::Error::other)?;
+        let view = unsafe { MapViewOfFile(mapping, FILE_MAP_READ, 0, 0, len) };
+        if view.Value.is_null() {
+            let err = io::Error::last_os_error();
+            let _ = unsafe { CloseHandle(mapping) };
+            return Err(err);
+        }
+        Ok(Self { ptr: view.Value as *const u8, len, mapping })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for MmapSource {
+    #[cfg(unix)]
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+    }
+
+    #[cfg(windows)]
+    fn drop(&mut self) {
+        use windows::Win32::Foundation::CloseHandle;
+        use windows::Win32::System::Memory::{MEMORY_MAPPED_VIEW_ADDRESS, UnmapViewOfFile};
+        unsafe {
+            let _ = UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS { Value: self.ptr as *mut _ });
+            let _ = CloseHandle(self.mapping);
+        }
+    }
+}
+
+impl ReadAt for MmapSource {
+    fn len(&self) -> u64 {
+        self.len as u64
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        Ok(copy_from(self.bytes(), offset, buf))
+    }
+}
+
+/// Opens `path` for reading the way `mode` asks.
+pub fn open_file(path: &Path, mode: ReadMode) -> io::Result<Box<dyn ReadAt>> {
+    let file = File::open(path)?;
+    if mode == ReadMode::Mapped {
+        match MmapSource::new(&file) {
+            Ok(map) => return Ok(Box::new(map)),
+            Err(err) => log(format!("MpqArchive: cannot map {}, using positioned reads: {}", path.display(), err)),
+        }
+    }
+    Ok(Box::new(FileSource::new(file)?))
+}
@@ -0,0 +1,175 @@
+//! Free-space map: the byte ranges of an archive that neither the header, the tables nor a live file use.
+//!
+//! Deleting or replacing a file only frees its hash entry; the data stays where it was until the archive is
+//! compacted. Those leftovers are reported per block so forensic tools can read them back, and gaps nothing
+//! points at are reported as such. The total tells whether compacting is worth it.
+
+use crate::archive::het_bet::{EXT_TABLE_HEADER_SIZE, ext_table_len};
+use crate::archive::reader::MpqArchive;
+use crate::archive::tables::HashSlotState;
+use crate::utils::json::JsonValue;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnusedKind {
+    /// Data of a block no live hash entry references (a deleted or replaced file), by block index.
+    UnusedBlock(usize),
+    /// Bytes nothing points at.
+    Gap,
+}
+
+/// Unused bytes at `offset` (relative to the archive start).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnusedRange {
+    pub offset: u64,
+    pub len: u64,
+    pub kind: UnusedKind,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FreeSpaceMap {
+    /// Bytes mapped from the archive start: the declared size, or the end of the furthest table or block when they
+    /// lie beyond it, capped at the end of the file.
+    pub archive_size: u64,
+    /// Unused ranges in file order; adjacent ranges of the same kind are merged.
+    pub ranges: Vec<UnusedRange>,
+    /// Hash table slots marked deleted.
+    pub deleted_slots: Vec<usize>,
+    /// Block table entries no live hash entry references, with or without data.
+    pub unused_blocks: Vec<usize>,
+}
+
+impl FreeSpaceMap {
+    pub fn unused_bytes(&self) -> u64 {
+        self.ranges.iter().map(|range| range.len).sum()
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let ranges: Vec<JsonValue> = self
+            .ranges
+            .iter()
+            .map(|range| {
+                let (kind, block) = match range.kind {
+                    UnusedKind::UnusedBlock(index) => ("unused-block", Some(index)),
+                    UnusedKind::Gap => ("gap", None),
+                };
+                JsonValue::object()
+                    .with("offset", range.offset)
+                    .with("len", range.len)
+                    .with("kind", kind)
+                    .with("block_index", block)
+            })
+            .collect();
+        JsonValue::object()
+            .with("archive_size", self.archive_size)
+            .with("unused_bytes", self.unused_bytes())
+            .with("ranges", ranges)
+            .with("deleted_slots", self.deleted_slots.clone())
+            .with("unused_blocks", self.unused_blocks.clone())
+    }
+}
+
+impl MpqArchive {
+    /// Indices of the hash table slots whose file was deleted.
+    pub fn deleted_slots(&self) -> Vec<usize> {
+        self.hash_table()
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.state() == HashSlotState::Deleted)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Indices of the blocks no live hash entry or HET slot references.
+    pub fn unused_blocks(&self) -> Vec<usize> {
+        self.live_blocks()
+            .iter()
+            .enumerate()
+            .filter(|(_, live)| !**live)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Maps the bytes of the archive that the header, the tables and live files leave unused.
+    pub fn free_space(&self) -> FreeSpaceMap {
+        let live = self.live_blocks();
+        // (offset, len, None for used bytes or the unused block they belong to)
+        let mut extents: Vec<(u64, u64, Option<usize>)> = self.table_extents().into_iter().map(|(offset, len)| (offset, len, None)).collect();
+        for (index, block) in self.block_table().iter().enumerate() {
+            if block.compressed_size > 0 {
+                extents.push((block.file_pos, block.compressed_size as u64, (!live[index]).then_some(index)));
+            }
+        }
+
+        let header = self.header();
+        let declared = if header.archive_size_64 != 0 { header.archive_size_64 } else { header.archive_size as u64 };
+        let furthest = extents.iter().map(|&(offset, len, _)| offset.saturating_add(len)).max().unwrap_or(0);
+        let available = self.source_len().saturating_sub(self.location().archive_offset);
+        let archive_size = declared.max(furthest).min(available);
+
+        // Sweep the extent boundaries: a segment is unused when no used extent covers it, and belongs to the
+        // lowest unused block covering it, if any.
+        let mut events: Vec<(u64, bool, Option<usize>)> = Vec::new();
+        for (offset, len, block) in extents {
+            let end = offset.saturating_add(len).min(archive_size);
+            if offset < end {
+                events.push((offset, true, block));
+                events.push((end, false, block));
+            }
+        }
+        events.sort_unstable_by_key(|&(at, ..)| at);
+
+        let mut ranges: Vec<UnusedRange> = Vec::new();
+        let mut used = 0usize;
+        let mut open = BTreeSet::new();
+        let mut at = 0;
+        let mut events = events.into_iter().peekable();
+        loop {
+            let next = events.peek().map_or(archive_size, |&(next, ..)| next);
+            if next > at && used == 0 {
+                let kind = open.first().map_or(UnusedKind::Gap, |&index| UnusedKind::UnusedBlock(index));
+                match ranges.last_mut() {
+                    Some(last) if last.kind == kind && last.offset + last.len == at => last.len += next - at,
+                    _ => ranges.push(UnusedRange { offset: at, len: next - at, kind }),
+                }
+            }
+            at = at.max(next);
+            let Some((_, start, block)) = events.next() else { break };
+            match (start, block) {
+                (true, None) => used += 1,
+                (false, None) => used -= 1,
+                (true, Some(index)) => {
+                    open.insert(index);
+                }
+                (false, Some(index)) => {
+                    open.remove(&index);
+                }
+            }
+        }
+
+        FreeSpaceMap { archive_size, ranges, deleted_slots: self.deleted_slots(), unused_blocks: self.unused_blocks() }
+    }
+
+    /// `(offset, len)` of the header and of every table the header points to.
+    fn table_extents(&self) -> Vec<(u64, u64)> {
+        let header = self.header();
+        let mut extents = vec![(0, header.header_size as u64)];
+        if header.hash_table_size != 0 {
+            extents.push((header.hash_table_offset(), header.hash_table_size as u64 * 16));
+        }
+        if header.block_table_size != 0 {
+            extents.push((header.block_table_offset(), header.block_table_size as u64 * 16));
+            if header.hi_block_table_pos != 0 {
+                extents.push((header.hi_block_table_pos, header.block_table_size as u64 * 2));
+            }
+        }
+        for (pos, size) in [(header.het_table_pos, header.het_table_size_64), (header.bet_table_pos, header.bet_table_size_64)] {
+            if pos == 0 {
+                continue;
+            }
+            let len = if size != 0 { Some(size) } else { self.read_exact(pos, EXT_TABLE_HEADER_SIZE).ok().map(|raw| ext_table_len(&raw) as u64) };
+            extents.extend(len.map(|len| (pos, len)));
+        }
+        extents
+    }
+}
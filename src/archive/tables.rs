@@ -0,0 +1,218 @@
+//! Classic hash and block tables.
+
+use crate::archive::crypto::{HASH_FILE_KEY, HASH_NAME_A, HASH_NAME_B, HASH_TABLE_OFFSET, decrypt_bytes, encrypt_bytes, hash_string};
+use crate::archive::header::{read_u16, read_u32};
+
+/// Block index of a hash entry that has never been used; terminates lookups.
+pub const HASH_ENTRY_EMPTY: u32 = 0xFFFF_FFFF;
+/// Block index of a hash entry whose file was deleted; lookups continue past it.
+pub const HASH_ENTRY_DELETED: u32 = 0xFFFF_FFFE;
+
+pub const MPQ_FILE_IMPLODE: u32 = 0x0000_0100;
+pub const MPQ_FILE_COMPRESS: u32 = 0x0000_0200;
+pub const MPQ_FILE_ENCRYPTED: u32 = 0x0001_0000;
+pub const MPQ_FILE_FIX_KEY: u32 = 0x0002_0000;
+pub const MPQ_FILE_PATCH_FILE: u32 = 0x0010_0000;
+pub const MPQ_FILE_SINGLE_UNIT: u32 = 0x0100_0000;
+pub const MPQ_FILE_DELETE_MARKER: u32 = 0x0200_0000;
+pub const MPQ_FILE_SECTOR_CRC: u32 = 0x0400_0000;
+pub const MPQ_FILE_EXISTS: u32 = 0x8000_0000;
+
+const HASH_ENTRY_SIZE: usize = 16;
+const BLOCK_ENTRY_SIZE: usize = 16;
+
+/// What a hash table slot holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashSlotState {
+    /// Never used.
+    Empty,
+    /// Held a file that was deleted; the slot can be reused.
+    Deleted,
+    /// Points at a block.
+    Used,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashEntry {
+    pub hash_a: u32,
+    pub hash_b: u32,
+    pub locale: u16,
+    pub platform: u16,
+    pub block_index: u32,
+}
+
+impl HashEntry {
+    pub fn is_empty(&self) -> bool {
+        self.block_index == HASH_ENTRY_EMPTY
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.block_index == HASH_ENTRY_DELETED
+    }
+
+    pub fn state(&self) -> HashSlotState {
+        match self.block_index {
+            HASH_ENTRY_EMPTY => HashSlotState::Empty,
+            HASH_ENTRY_DELETED => HashSlotState::Deleted,
+            _ => HashSlotState::Used,
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.hash_a == hash_string(name, HASH_NAME_A) && self.hash_b == hash_string(name, HASH_NAME_B)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockEntry {
+    /// Offset of the file data relative to the archive start (includes the hi-block bits).
+    pub file_pos: u64,
+    pub compressed_size: u32,
+    pub file_size: u32,
+    pub flags: u32,
+}
+
+impl BlockEntry {
+    pub fn exists(&self) -> bool {
+        self.flags & MPQ_FILE_EXISTS != 0
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & (MPQ_FILE_COMPRESS | MPQ_FILE_IMPLODE) != 0
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & MPQ_FILE_ENCRYPTED != 0
+    }
+
+    pub fn is_single_unit(&self) -> bool {
+        self.flags & MPQ_FILE_SINGLE_UNIT != 0
+    }
+
+    pub fn is_patch_file(&self) -> bool {
+        self.flags & MPQ_FILE_PATCH_FILE != 0
+    }
+
+    /// A patch archive's marker hiding the file of the same name in the archives below it.
+    pub fn is_delete_marker(&self) -> bool {
+        self.flags & MPQ_FILE_DELETE_MARKER != 0
+    }
+}
+
+/// Names of the set block flags, for diagnostics.
+pub fn describe_flags(flags: u32) -> Vec<&'static str> {
+    const NAMES: &[(u32, &str)] = &[
+        (MPQ_FILE_EXISTS, "exists"),
+        (MPQ_FILE_IMPLODE, "imploded"),
+        (MPQ_FILE_COMPRESS, "compressed"),
+        (MPQ_FILE_ENCRYPTED, "encrypted"),
+        (MPQ_FILE_FIX_KEY, "fix-key"),
+        (MPQ_FILE_PATCH_FILE, "patch"),
+        (MPQ_FILE_SINGLE_UNIT, "single-unit"),
+        (MPQ_FILE_DELETE_MARKER, "deleted"),
+        (MPQ_FILE_SECTOR_CRC, "sector-crc"),
+    ];
+    NAMES
+        .iter()
+        .filter(|(bit, _)| flags & bit != 0)
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Decrypts and parses a raw hash table of `count` entries.
+pub fn parse_hash_table(raw: &[u8], count: usize) -> Vec<HashEntry> {
+    let mut bytes = raw[..(count * HASH_ENTRY_SIZE).min(raw.len())].to_vec();
+    decrypt_bytes(&mut bytes, hash_table_key());
+    bytes
+        .chunks_exact(HASH_ENTRY_SIZE)
+        .map(|e| HashEntry { hash_a: read_u32(e, 0), hash_b: read_u32(e, 4), locale: read_u16(e, 8), platform: read_u16(e, 10), block_index: read_u32(e, 12) })
+        .collect()
+}
+
+/// Decrypts and parses a raw block table of `count` entries, merging the optional hi-block table.
+pub fn parse_block_table(raw: &[u8], count: usize, hi_block_table: Option<&[u8]>) -> Vec<BlockEntry> {
+    let mut bytes = raw[..(count * BLOCK_ENTRY_SIZE).min(raw.len())].to_vec();
+    decrypt_bytes(&mut bytes, block_table_key());
+    bytes
+        .chunks_exact(BLOCK_ENTRY_SIZE)
+        .enumerate()
+        .map(|(i, e)| {
+            let hi = hi_block_table
+                .filter(|hi| hi.len() >= (i + 1) * 2)
+                .map(|hi| read_u16(hi, i * 2) as u64)
+                .unwrap_or(0);
+            BlockEntry { file_pos: read_u32(e, 0) as u64 | (hi << 32), compressed_size: read_u32(e, 4), file_size: read_u32(e, 8), flags: read_u32(e, 12) }
+        })
+        .collect()
+}
+
+/// Serializes and encrypts a hash table.
+pub fn write_hash_table(entries: &[HashEntry]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(entries.len() * HASH_ENTRY_SIZE);
+    for e in entries {
+        out.extend_from_slice(&e.hash_a.to_le_bytes());
+        out.extend_from_slice(&e.hash_b.to_le_bytes());
+        out.extend_from_slice(&e.locale.to_le_bytes());
+        out.extend_from_slice(&e.platform.to_le_bytes());
+        out.extend_from_slice(&e.block_index.to_le_bytes());
+    }
+    encrypt_bytes(&mut out, hash_table_key());
+    out
+}
+
+/// Serializes and encrypts a block table. Only the low 32 bits of each offset are stored.
+pub fn write_block_table(entries: &[BlockEntry]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(entries.len() * BLOCK_ENTRY_SIZE);
+    for e in entries {
+        out.extend_from_slice(&(e.file_pos as u32).to_le_bytes());
+        out.extend_from_slice(&e.compressed_size.to_le_bytes());
+        out.extend_from_slice(&e.file_size.to_le_bytes());
+        out.extend_from_slice(&e.flags.to_le_bytes());
+    }
+    encrypt_bytes(&mut out, block_table_key());
+    out
+}
+
+/// Serializes the (unencrypted) hi-block table holding the upper 16 bits of each offset.
+pub fn write_hi_block_table(entries: &[BlockEntry]) -> Vec<u8> {
+    entries
+        .iter()
+        .flat_map(|e| ((e.file_pos >> 32) as u16).to_le_bytes())
+        .collect()
+}
+
+/// Finds the first live hash entry of `name`, probing from its home slot until an empty entry.
+pub fn find_hash_entry<'a>(table: &'a [HashEntry], name: &str) -> Option<(usize, &'a HashEntry)> {
+    find_hash_entries(table, name).into_iter().next()
+}
+
+/// Every live hash entry of `name` (one per locale/platform variant), in probe order. The home slot is masked with
+/// `size - 1` as Storm does, which differs from a remainder when the table size is not a power of two.
+pub fn find_hash_entries<'a>(table: &'a [HashEntry], name: &str) -> Vec<(usize, &'a HashEntry)> {
+    let mut found = Vec::new();
+    if table.is_empty() {
+        return found;
+    }
+    let hash_a = hash_string(name, HASH_NAME_A);
+    let hash_b = hash_string(name, HASH_NAME_B);
+    let start = hash_string(name, HASH_TABLE_OFFSET) as usize & (table.len() - 1);
+    for step in 0..table.len() {
+        let index = (start + step) % table.len();
+        let entry = &table[index];
+        if entry.is_empty() {
+            break;
+        }
+        if !entry.is_deleted() && entry.hash_a == hash_a && entry.hash_b == hash_b {
+            found.push((index, entry));
+        }
+    }
+    found
+}
+
+fn hash_table_key() -> u32 {
+    hash_string("(hash table)", HASH_FILE_KEY)
+}
+
+fn block_table_key() -> u32 {
+    hash_string("(block table)", HASH_FILE_KEY)
+}
@@ -0,0 +1,275 @@
+//! Integrity checks of an archive: `(attributes)` CRC32/MD5, per-sector Adler-32 checksums, the MD5s of
+//! MPQ v4 headers and raw chunks, signatures, and the `war3map.imp` manifest of maps.
+
+use crate::archive::MpqArchiveError;
+use crate::archive::attributes::{ATTRIBUTES_FILE_NAME, Attributes};
+use crate::archive::compression::decompress;
+use crate::archive::crypto::decrypt_bytes;
+use crate::archive::header::{MD5_HEADER_OFFSET, read_u32};
+use crate::archive::reader::{MpqArchive, block_key, sector_offsets};
+use crate::archive::signature::{SignatureKeys, SignatureReport};
+use crate::archive::tables::{BlockEntry, MPQ_FILE_PATCH_FILE, MPQ_FILE_SECTOR_CRC};
+use crate::log::log;
+use crate::utils::md5::md5;
+use crate::war3::imports::ImportReport;
+
+/// How the loader treats MD5 mismatches of MPQ v4 archives (header, tables and raw chunks).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntegrityMode {
+    /// Log the mismatch and carry on.
+    #[default]
+    Lenient,
+    /// Reject the archive or file with [`MpqArchiveError::Corrupted`].
+    Strict,
+}
+
+/// Outcome of one checksum comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    Ok,
+    Mismatch,
+    /// The archive does not store this checksum for the file.
+    NotStored,
+}
+
+impl Check {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Check::Ok => "ok",
+            Check::Mismatch => "mismatch",
+            Check::NotStored => "not-stored",
+        }
+    }
+
+    fn compare<T: PartialEq>(stored: Option<T>, actual: impl FnOnce() -> T) -> Self {
+        match stored {
+            Some(stored) if stored == actual() => Check::Ok,
+            Some(_) => Check::Mismatch,
+            None => Check::NotStored,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVerification {
+    pub name: String,
+    pub block_index: usize,
+    pub crc32: Check,
+    pub md5: Check,
+    pub sector_checksums: Check,
+    /// MD5s of the stored data in `raw_chunk_size` pieces (v4).
+    pub raw_chunks: Check,
+    /// Set when the file could not be read at all.
+    pub error: Option<String>,
+}
+
+impl FileVerification {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && ![self.crc32, self.md5, self.sector_checksums, self.raw_chunks].contains(&Check::Mismatch)
+    }
+}
+
+/// One of the MD5s a v4 header stores for itself and the tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PieceCheck {
+    pub piece: &'static str,
+    pub check: Check,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Header and table MD5s; empty before format v4.
+    pub pieces: Vec<PieceCheck>,
+    pub files: Vec<FileVerification>,
+    /// Live blocks no known name resolves to; they cannot be checked.
+    pub unnamed_blocks: usize,
+    pub has_attributes: bool,
+    pub signature: SignatureReport,
+    /// `war3map.imp` reconciled with the files, for maps that have one; the error when it cannot be parsed.
+    /// Import problems are reported but do not fail verification.
+    pub imports: Option<Result<ImportReport, String>>,
+}
+
+impl VerifyReport {
+    /// False when a file fails its checks or a signature is present but does not verify.
+    pub fn is_ok(&self) -> bool {
+        self.pieces.iter().all(|piece| piece.check != Check::Mismatch) && self.files.iter().all(FileVerification::is_ok) && self.signature.is_ok()
+    }
+}
+
+impl MpqArchive {
+    /// The parsed `(attributes)` file, if present and readable.
+    pub fn attributes(&self) -> Option<Attributes> {
+        let bytes = self.read_file(ATTRIBUTES_FILE_NAME).ok()?;
+        Attributes::parse(&bytes, self.block_table().len()).ok()
+    }
+
+    /// Checks every named file against `(attributes)` and its sector checksums, and the signatures against `keys`.
+    pub fn verify(&self, keys: &SignatureKeys) -> VerifyReport {
+        let attributes = self.attributes();
+        let mut files = Vec::new();
+        let mut checked_blocks = vec![false; self.block_table().len()];
+
+        for name in self.file_names() {
+            let Some((block_index, block)) = self.find_file(&name) else { continue };
+            checked_blocks[block_index] = true;
+            files.push(self.verify_block(&name, block_index, block, attributes.as_ref()));
+        }
+
+        let unnamed_blocks = self
+            .block_table()
+            .iter()
+            .zip(&checked_blocks)
+            .filter(|(block, checked)| block.exists() && !**checked)
+            .count();
+        VerifyReport {
+            pieces: self.verify_md5_pieces(),
+            files,
+            unnamed_blocks,
+            has_attributes: attributes.is_some(),
+            signature: self.verify_signatures(keys),
+            imports: self.check_imports().map(|imports| imports.map_err(|err| err.to_string())),
+        }
+    }
+
+    fn verify_block(&self, name: &str, block_index: usize, block: &BlockEntry, attributes: Option<&Attributes>) -> FileVerification {
+        let mut result = FileVerification { name: name.to_string(), block_index, crc32: Check::NotStored, md5: Check::NotStored, sector_checksums: Check::NotStored, raw_chunks: Check::NotStored, error: None };
+
+        match self.read_exact(block.file_pos, block.compressed_size as usize) {
+            Ok(raw) => result.raw_chunks = self.verify_raw_chunks(block, &raw),
+            Err(err) => result.error = Some(err.to_string()),
+        }
+
+        match self.verify_sector_checksums(block, name) {
+            Ok(check) => result.sector_checksums = check,
+            Err(err) => result.error = Some(err.to_string()),
+        }
+
+        // Attributes of patch files describe the patched result, which a single archive cannot produce.
+        if block.flags & MPQ_FILE_PATCH_FILE != 0 {
+            return result;
+        }
+        if let Some(attributes) = attributes {
+            match self.read_block(block, Some(name)) {
+                Ok(data) => {
+                    result.crc32 = Check::compare(attributes.crc32(block_index), || crc32fast::hash(&data));
+                    result.md5 = Check::compare(attributes.md5(block_index), || md5(&data));
+                }
+                Err(err) => result.error = Some(err.to_string()),
+            }
+        }
+        result
+    }
+
+    /// Checks the MD5s a v4 header stores for itself, the hash, block and hi-block tables and the HET/BET tables.
+    /// Empty for older formats. A piece reaching past the end of the file is a mismatch.
+    pub fn verify_md5_pieces(&self) -> Vec<PieceCheck> {
+        let header = self.header();
+        let available = self.source_len().saturating_sub(self.location().archive_offset);
+        if header.format_version < 3 {
+            return Vec::new();
+        }
+        // Tables are hashed as stored, still encrypted and compressed.
+        let pieces = [
+            ("header", 0, MD5_HEADER_OFFSET as u64, header.md5_header),
+            ("hash table", header.hash_table_offset(), header.hash_table_size_64, header.md5_hash_table),
+            ("block table", header.block_table_offset(), header.block_table_size_64, header.md5_block_table),
+            ("hi-block table", header.hi_block_table_pos, header.hi_block_table_size_64, header.md5_hi_block_table),
+            ("HET table", header.het_table_pos, header.het_table_size_64, header.md5_het_table),
+            ("BET table", header.bet_table_pos, header.bet_table_size_64, header.md5_bet_table),
+        ];
+        pieces
+            .into_iter()
+            .map(|(piece, offset, size, stored)| {
+                let check = if stored == [0; 16] || size == 0 {
+                    Check::NotStored
+                } else {
+                    let len = offset.checked_add(size).filter(|&end| end <= available).and_then(|_| usize::try_from(size).ok());
+                    match len.map(|len| self.read_exact(offset, len)) {
+                        Some(Ok(bytes)) => Check::compare(Some(stored), || md5(&bytes)),
+                        _ => Check::Mismatch,
+                    }
+                };
+                PieceCheck { piece, check }
+            })
+            .collect()
+    }
+
+    /// Compares the MD5 of each `raw_chunk_size` piece of a block's stored data (`raw`) with the array that
+    /// follows the data in v4 archives. An unreadable array counts as a mismatch. Data cut short (tolerant reads of a
+    /// block past the end of the file) is not checked: the array would be looked for in the wrong place.
+    pub fn verify_raw_chunks(&self, block: &BlockEntry, raw: &[u8]) -> Check {
+        let chunk_size = self.header().raw_chunk_size as usize;
+        if self.header().format_version < 3 || chunk_size == 0 || raw.is_empty() || raw.len() < block.compressed_size as usize {
+            return Check::NotStored;
+        }
+        let chunk_count = raw.len().div_ceil(chunk_size);
+        let Ok(stored) = self.read_exact(block.file_pos + raw.len() as u64, chunk_count * 16) else {
+            return Check::Mismatch;
+        };
+        if raw.chunks(chunk_size).zip(stored.chunks(16)).all(|(chunk, stored)| md5(chunk) == stored) { Check::Ok } else { Check::Mismatch }
+    }
+
+    /// Reports a failed MD5 check of `what`: an error in strict mode, a log line in lenient mode.
+    pub(crate) fn integrity_failure(&self, what: &str) -> Result<(), MpqArchiveError> {
+        match self.integrity_mode() {
+            IntegrityMode::Strict => Err(MpqArchiveError::Corrupted(format!("{what} MD5 mismatch"))),
+            IntegrityMode::Lenient => {
+                log(format!("MpqArchive: {} MD5 mismatch in {} (ignored)", what, self.display_name()));
+                Ok(())
+            }
+        }
+    }
+
+    /// Compares the Adler-32 of each stored (decrypted, still compressed) sector with the block's checksum table.
+    pub fn verify_sector_checksums(&self, block: &BlockEntry, name: &str) -> Result<Check, MpqArchiveError> {
+        if block.flags & MPQ_FILE_SECTOR_CRC == 0 || block.is_single_unit() || !block.is_compressed() || block.file_size == 0 {
+            return Ok(Check::NotStored);
+        }
+
+        let key = block_key(block, Some(name))?;
+        let raw = self.read_exact(block.file_pos, block.compressed_size as usize)?;
+        let sector_size = self.header().sector_size() as usize;
+        let sector_count = (block.file_size as usize).div_ceil(sector_size);
+        let offsets = sector_offsets(&raw, sector_count, block.flags, key)?;
+
+        let (start, end) = (offsets[sector_count] as usize, offsets[sector_count + 1] as usize);
+        if start > end || end > raw.len() {
+            return Err(MpqArchiveError::Corrupted("sector checksum table out of bounds".into()));
+        }
+        let table_size = sector_count * 4;
+        // The checksum table is compressed like a sector unless that would not save space.
+        let table = if end - start < table_size { decompress(&raw[start..end], table_size)? } else { raw[start..end].to_vec() };
+        if table.len() < table_size {
+            return Err(MpqArchiveError::Corrupted("truncated sector checksum table".into()));
+        }
+
+        let mut any_stored = false;
+        for i in 0..sector_count {
+            let stored = read_u32(&table, i * 4);
+            if stored == 0 {
+                continue;
+            }
+            any_stored = true;
+            let (start, end) = (offsets[i] as usize, offsets[i + 1] as usize);
+            if start > end || end > raw.len() {
+                return Err(MpqArchiveError::Corrupted(format!("sector {i} out of bounds ({start}..{end})")));
+            }
+            let mut sector = raw[start..end].to_vec();
+            if let Some(key) = key {
+                decrypt_bytes(&mut sector, key.wrapping_add(i as u32));
+            }
+            if storm_adler32(&sector) != stored {
+                return Ok(Check::Mismatch);
+            }
+        }
+        Ok(if any_stored { Check::Ok } else { Check::NotStored })
+    }
+}
+
+/// Adler-32 as Storm computes sector checksums: `adler32(0, sector, len)`, seeded with 0 rather than the standard 1.
+fn storm_adler32(bytes: &[u8]) -> u32 {
+    let mut adler = adler2::Adler32::from_checksum(0);
+    adler.write_slice(bytes);
+    adler.checksum()
+}
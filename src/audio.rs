@@ -0,0 +1,223 @@
+//! Metadata of WAV and MP3 sounds: format, channels, rate, duration and ID3v2 tags.
+
+use crate::archive::MpqArchiveError;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioInfo {
+    pub format: String,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits_per_sample: Option<u16>,
+    /// Bits per second.
+    pub bitrate: Option<u32>,
+    /// Seconds; estimated from the bitrate for MP3s without a Xing/Info frame.
+    pub duration: Option<f64>,
+    /// ID3v2 title, artist, album and year, when present.
+    pub tags: Vec<(&'static str, String)>,
+}
+
+impl AudioInfo {
+    /// `key: value` lines, like the `:mpq` metadata stream.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("format: {}\r\n", self.format);
+        text.push_str(&format!("channels: {}\r\n", self.channels));
+        text.push_str(&format!("sample_rate: {}\r\n", self.sample_rate));
+        if let Some(bits) = self.bits_per_sample {
+            text.push_str(&format!("bits_per_sample: {}\r\n", bits));
+        }
+        if let Some(bitrate) = self.bitrate {
+            text.push_str(&format!("bitrate: {} kbps\r\n", bitrate / 1000));
+        }
+        if let Some(duration) = self.duration {
+            text.push_str(&format!("duration: {:.3} s\r\n", duration));
+        }
+        for (key, value) in &self.tags {
+            text.push_str(&format!("{}: {}\r\n", key, value));
+        }
+        text
+    }
+}
+
+/// Reads the `fmt ` and `data` chunks of a RIFF/WAVE file.
+pub fn wav_info(data: &[u8]) -> Result<AudioInfo, MpqArchiveError> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(MpqArchiveError::Corrupted("not a RIFF/WAVE file".into()));
+    }
+    let mut info = None;
+    let mut data_size = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let (id, size) = (&data[pos..pos + 4], u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize);
+        let body = &data[pos + 8..(pos + 8 + size).min(data.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let u16_at = |at: usize| u16::from_le_bytes([body[at], body[at + 1]]);
+                let tag = u16_at(0);
+                let byte_rate = u32::from_le_bytes(body[8..12].try_into().unwrap());
+                info = Some(AudioInfo {
+                    format: format!("{} (0x{:04X})", wav_format_name(tag), tag),
+                    channels: u16_at(2),
+                    sample_rate: u32::from_le_bytes(body[4..8].try_into().unwrap()),
+                    bits_per_sample: Some(u16_at(14)).filter(|&bits| bits != 0),
+                    bitrate: Some(byte_rate * 8),
+                    ..AudioInfo::default()
+                });
+            }
+            b"data" => data_size = Some(size.min(data.len() - pos - 8)),
+            _ => {}
+        }
+        // Chunks are padded to an even size.
+        pos += 8 + size + (size & 1);
+    }
+    let mut info = info.ok_or_else(|| MpqArchiveError::Corrupted("WAV without a fmt chunk".into()))?;
+    if let (Some(size), Some(bitrate)) = (data_size, info.bitrate.filter(|&rate| rate > 0)) {
+        info.duration = Some(size as f64 * 8.0 / bitrate as f64);
+    }
+    Ok(info)
+}
+
+fn wav_format_name(tag: u16) -> &'static str {
+    match tag {
+        0x0001 => "PCM",
+        0x0002 => "Microsoft ADPCM",
+        0x0003 => "IEEE float",
+        0x0011 => "IMA ADPCM",
+        0x0055 => "MPEG Layer III",
+        0xFFFE => "extensible",
+        _ => "unknown",
+    }
+}
+
+const MP3_BITRATES: [[u32; 15]; 5] = [
+    [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384],
+    [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320],
+    [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+/// Reads the ID3v2 tag and the first MPEG audio frame (plus its Xing/Info header, if any).
+pub fn mp3_info(data: &[u8]) -> Result<AudioInfo, MpqArchiveError> {
+    let (tags, audio_start) = id3v2_tags(data);
+    let not_mp3 = || MpqArchiveError::Corrupted("no MPEG audio frame found".into());
+    let frame = (audio_start..data.len().saturating_sub(4))
+        .find(|&at| data[at] == 0xFF && data[at + 1] & 0xE0 == 0xE0 && frame_fields(&data[at..at + 4]).is_some())
+        .ok_or_else(not_mp3)?;
+    let (version, layer, bitrate, sample_rate, mono) = frame_fields(&data[frame..frame + 4]).ok_or_else(not_mp3)?;
+
+    let samples_per_frame = match (layer, version) {
+        (1, _) => 384,
+        (3, v) if v != 1 => 576,
+        _ => 1152,
+    };
+    // The Xing/Info frame follows the side information of the first frame.
+    let side_info = match (version == 1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let xing = frame + 4 + side_info;
+    let frames = data
+        .get(xing..xing + 12)
+        .filter(|header| &header[..4] == b"Xing" || &header[..4] == b"Info")
+        .filter(|header| header[7] & 1 != 0)
+        .map(|header| u32::from_be_bytes(header[8..12].try_into().unwrap()));
+    let duration = match frames {
+        Some(frames) => Some(frames as f64 * samples_per_frame as f64 / sample_rate as f64),
+        None if bitrate > 0 => Some((data.len() - frame) as f64 * 8.0 / bitrate as f64),
+        None => None,
+    };
+
+    let version_name = match version {
+        1 => "MPEG-1",
+        2 => "MPEG-2",
+        _ => "MPEG-2.5",
+    };
+    Ok(AudioInfo {
+        format: format!("{} Layer {}", version_name, ["I", "II", "III"][layer as usize - 1]),
+        channels: if mono { 1 } else { 2 },
+        sample_rate,
+        bits_per_sample: None,
+        bitrate: Some(bitrate).filter(|&rate| rate > 0),
+        duration,
+        tags,
+    })
+}
+
+/// Version (1, 2 or 25), layer (1..=3), bitrate in bits per second, sample rate and whether the frame is mono.
+fn frame_fields(header: &[u8]) -> Option<(u8, u8, u32, u32, bool)> {
+    let version = match (header[1] >> 3) & 3 {
+        3 => 1,
+        2 => 2,
+        0 => 25,
+        _ => return None,
+    };
+    let layer = match (header[1] >> 1) & 3 {
+        3 => 1,
+        2 => 2,
+        1 => 3,
+        _ => return None,
+    };
+    let bitrate_index = (header[2] >> 4) as usize;
+    let rate_index = ((header[2] >> 2) & 3) as usize;
+    if bitrate_index == 15 || rate_index == 3 {
+        return None;
+    }
+    let table = match (version, layer) {
+        (1, layer) => layer as usize - 1,
+        (_, 1) => 3,
+        _ => 4,
+    };
+    let sample_rate = [44100, 48000, 32000][rate_index] / match version {
+        1 => 1,
+        2 => 2,
+        _ => 4,
+    };
+    Some((version, layer, MP3_BITRATES[table][bitrate_index] * 1000, sample_rate, header[3] >> 6 == 3))
+}
+
+/// Text frames of an ID3v2.3/2.4 tag and the offset where audio starts.
+fn id3v2_tags(data: &[u8]) -> (Vec<(&'static str, String)>, usize) {
+    if data.len() < 10 || &data[..3] != b"ID3" {
+        return (Vec::new(), 0);
+    }
+    let syncsafe = |bytes: &[u8]| bytes.iter().fold(0usize, |acc, &b| (acc << 7) | (b & 0x7F) as usize);
+    let major = data[3];
+    let end = (10 + syncsafe(&data[6..10])).min(data.len());
+    let mut tags = Vec::new();
+    let mut pos = 10;
+    while major >= 3 && pos + 10 <= end && data[pos] != 0 {
+        let id = &data[pos..pos + 4];
+        let size = if major >= 4 { syncsafe(&data[pos + 4..pos + 8]) } else { u32::from_be_bytes(data[pos + 4..pos + 8].try_into().unwrap()) as usize };
+        let body = &data[pos + 10..(pos + 10 + size).min(end)];
+        let key = match id {
+            b"TIT2" => Some("title"),
+            b"TPE1" => Some("artist"),
+            b"TALB" => Some("album"),
+            b"TYER" | b"TDRC" => Some("year"),
+            _ => None,
+        };
+        if let (Some(key), Some((&encoding, text))) = (key, body.split_first()) {
+            tags.push((key, decode_id3_text(encoding, text)));
+        }
+        pos += 10 + size;
+    }
+    (tags, end)
+}
+
+fn decode_id3_text(encoding: u8, text: &[u8]) -> String {
+    let text = match encoding {
+        1 | 2 => {
+            let (big_endian, body) = match text {
+                [0xFE, 0xFF, rest @ ..] => (true, rest),
+                [0xFF, 0xFE, rest @ ..] => (false, rest),
+                _ => (encoding == 2, text),
+            };
+            let units = body.chunks_exact(2).map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) });
+            char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect()
+        }
+        3 => String::from_utf8_lossy(text).into_owned(),
+        _ => text.iter().map(|&b| b as char).collect(),
+    };
+    text.trim_end_matches('\0').to_string()
+}
@@ -2,7 +2,7 @@ use crate::EXE_BYTES;
 use crate::utils::notify_shell_assoc::notify_shell_assoc;
 use crate::utils::regedit::Rk;
 use mpq_folder_win::log::log;
-use mpq_folder_win::{DEFAULT_PROGID, SUPPORTED_EXTENSIONS};
+use mpq_folder_win::{DEFAULT_PROGID, REPLAY_EXTENSIONS, REPLAY_PROGID, SUPPORTED_EXTENSIONS};
 use std::path::PathBuf;
 use std::{fs, io};
 use winreg::RegKey;
@@ -56,7 +56,8 @@ pub fn install() -> io::Result<()> {
     
     println!("\n✓ Installation completed successfully!");
     println!("  → MPQ Viewer installed to C:\\Program Files\\mpq-folder-win\\");
-    println!("  → File associations registered for .mpq, .w3m, .w3x");
+    println!("  → File associations registered for {}", SUPPORTED_EXTENSIONS.join(", "));
+    println!("  → Replay associations registered for {}", REPLAY_EXTENSIONS.join(", "));
     println!("\nYou can now double-click on MPQ files to mount and browse them!\n");
     Ok(())
 }
@@ -109,12 +110,16 @@ fn install_inner() -> io::Result<()> {
     let exe_path_str = exe_path.to_string_lossy().to_string();
     log(format!("EXE installed: {}", exe_path_str));
 
-    // Register ProgID
-    register_progid(&exe_path_str)?;
+    // Register ProgIDs
+    register_progid(&exe_path_str, DEFAULT_PROGID, "MPQ Archive")?;
+    register_progid(&exe_path_str, REPLAY_PROGID, "Warcraft III Replay")?;
 
     // Register file associations
     for ext in SUPPORTED_EXTENSIONS {
-        register_extension(ext)?;
+        register_extension(ext, DEFAULT_PROGID)?;
+    }
+    for ext in REPLAY_EXTENSIONS {
+        register_extension(ext, REPLAY_PROGID)?;
     }
 
     // Notify shell of changes
@@ -136,9 +141,10 @@ fn pre_clean_registry() -> io::Result<()> {
     
     // Clean up old ProgID structure
     let _ = hkcr.delete_subkey_all(DEFAULT_PROGID);
+    let _ = hkcr.delete_subkey_all(REPLAY_PROGID);
     
     // Clean up extensions
-    for ext in SUPPORTED_EXTENSIONS {
+    for ext in SUPPORTED_EXTENSIONS.iter().chain(REPLAY_EXTENSIONS) {
         let _ = hkcr.delete_subkey_all(ext);
     }
     
@@ -146,15 +152,15 @@ fn pre_clean_registry() -> io::Result<()> {
     Ok(())
 }
 
-fn register_progid(exe_path: &str) -> io::Result<()> {
-    log(format!("Registering ProgID: {}", DEFAULT_PROGID));
+fn register_progid(exe_path: &str, name: &str, description: &str) -> io::Result<()> {
+    log(format!("Registering ProgID: {}", name));
     
     let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
     let classes = hklm.open_subkey(r"SOFTWARE\Classes")?;
     
     // Create ProgID
-    let progid = classes.create_subkey(DEFAULT_PROGID)?.0;
-    progid.set_value("", &"MPQ Archive")?;
+    let progid = classes.create_subkey(name)?.0;
+    progid.set_value("", &description)?;
     
     // DefaultIcon
     let default_icon = progid.create_subkey("DefaultIcon")?.0;
@@ -168,7 +174,7 @@ fn register_progid(exe_path: &str) -> io::Result<()> {
     Ok(())
 }
 
-fn register_extension(ext: &str) -> io::Result<()> {
+fn register_extension(ext: &str, progid: &str) -> io::Result<()> {
     log(format!("Registering extension: {}", ext));
     
     let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
@@ -176,8 +182,8 @@ fn register_extension(ext: &str) -> io::Result<()> {
     
     // Create extension key
     let ext_key = classes.create_subkey(ext)?.0;
-    ext_key.set_value("", &DEFAULT_PROGID)?;
+    ext_key.set_value("", &progid)?;
     
-    log(format!("Extension {} -> {}", ext, DEFAULT_PROGID));
+    log(format!("Extension {} -> {}", ext, progid));
     Ok(())
 }
@@ -1,6 +1,6 @@
 use crate::utils::notify_shell_assoc::notify_shell_assoc;
 use mpq_folder_win::log::log;
-use mpq_folder_win::{DEFAULT_PROGID, SUPPORTED_EXTENSIONS};
+use mpq_folder_win::{DEFAULT_PROGID, REPLAY_EXTENSIONS, REPLAY_PROGID, SUPPORTED_EXTENSIONS};
 use std::{fs, io};
 use winreg::RegKey;
 use winreg::enums::HKEY_LOCAL_MACHINE;
@@ -37,12 +37,14 @@ fn uninstall_inner() -> io::Result<()> {
     let root = RegKey::predef(HKEY_LOCAL_MACHINE);
     let classes = root.open_subkey(r"SOFTWARE\Classes")?;
 
-    // Remove ProgID
-    log(format!("Removing ProgID: {}", DEFAULT_PROGID));
-    let _ = classes.delete_subkey_all(DEFAULT_PROGID);
+    // Remove ProgIDs
+    for progid in [DEFAULT_PROGID, REPLAY_PROGID] {
+        log(format!("Removing ProgID: {}", progid));
+        let _ = classes.delete_subkey_all(progid);
+    }
 
     // Remove file associations
-    for ext in SUPPORTED_EXTENSIONS {
+    for ext in SUPPORTED_EXTENSIONS.iter().chain(REPLAY_EXTENSIONS) {
         log(format!("Removing extension: {}", ext));
         let _ = classes.delete_subkey_all(ext);
     }
@@ -0,0 +1,29 @@
+use mpq_folder_win::archive::{CompactProgress, compact_archive};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive: Option<PathBuf> = None;
+    let mut output: Option<PathBuf> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(iter.next().ok_or("--output requires a path")?.into()),
+            _ if archive.is_none() => archive = Some(arg.into()),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive = archive.ok_or("compact requires an archive path")?;
+
+    let report = compact_archive(&archive, output.as_deref(), |p: &CompactProgress| {
+        print!("\rCompacting: {}/{} files, {}/{} bytes", p.files_done, p.files_total, p.bytes_done, p.bytes_total);
+        let _ = io::stdout().flush();
+    })?;
+    println!();
+
+    println!("Live blocks:  {}", report.live_blocks);
+    println!("Freed blocks: {}", report.freed_blocks);
+    println!("Size:         {} -> {} bytes ({} reclaimed)", report.original_size, report.compacted_size, report.reclaimed_bytes());
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::compression::describe_compression;
+use mpq_folder_win::archive::diff::{ChangeKind, FileChange, FileSummary, diff_archives};
+use mpq_folder_win::archive::tables::describe_flags;
+use mpq_folder_win::utils::json::JsonValue;
+use mpq_folder_win::utils::md5::to_hex;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut paths: Vec<&str> = Vec::new();
+    let mut json = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ if paths.len() < 2 => paths.push(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let [old_path, new_path] = paths[..] else {
+        return Err("diff requires two archive paths: <old> <new>".into());
+    };
+    let diff = diff_archives(&MpqArchive::open(old_path)?, &MpqArchive::open(new_path)?);
+
+    if json {
+        let changes: Vec<JsonValue> = diff.changes.iter().map(change_json).collect();
+        let summary = JsonValue::object()
+            .with("old", old_path)
+            .with("new", new_path)
+            .with("added", diff.count(ChangeKind::Added))
+            .with("removed", diff.count(ChangeKind::Removed))
+            .with("modified", diff.count(ChangeKind::Modified))
+            .with("unchanged", diff.unchanged)
+            .with("changes", changes);
+        println!("{}", summary.to_pretty());
+        return Ok(());
+    }
+
+    for change in &diff.changes {
+        print_change(change);
+    }
+    println!("{} added, {} removed, {} modified, {} unchanged", diff.count(ChangeKind::Added), diff.count(ChangeKind::Removed), diff.count(ChangeKind::Modified), diff.unchanged);
+    Ok(())
+}
+
+fn print_change(change: &FileChange) {
+    let marker = match change.kind {
+        ChangeKind::Added => 'A',
+        ChangeKind::Removed => 'D',
+        ChangeKind::Modified => 'M',
+    };
+    let size = |summary: &Option<FileSummary>| summary.as_ref().map(|s| s.size.to_string()).unwrap_or_else(|| "?".into());
+    match change.kind {
+        ChangeKind::Added => println!("{marker}  {}  ({} bytes)", change.path, size(&change.new)),
+        ChangeKind::Removed => println!("{marker}  {}  ({} bytes)", change.path, size(&change.old)),
+        ChangeKind::Modified => println!("{marker}  {}", change.path),
+    }
+    if let Some(error) = &change.error {
+        println!("     error: {error}");
+    }
+    let (Some(old), Some(new)) = (&change.old, &change.new) else { return };
+    if change.content_changed() {
+        println!("     content: {} -> {} bytes, crc32 {:08x} -> {:08x}", old.size, new.size, old.crc32, new.crc32);
+    }
+    if change.flags_changed() {
+        println!("     flags: {} -> {}", describe_flags(old.flags).join(","), describe_flags(new.flags).join(","));
+    }
+    if change.compression_changed() {
+        println!("     compression: {} -> {}", compression_text(old.compression_mask), compression_text(new.compression_mask));
+    }
+}
+
+fn compression_text(mask: u8) -> String {
+    let methods = describe_compression(mask);
+    if methods.is_empty() { "none".to_string() } else { methods.join(",") }
+}
+
+fn change_json(change: &FileChange) -> JsonValue {
+    let summary = |summary: &Option<FileSummary>| {
+        summary.as_ref().map(|s| {
+            JsonValue::object()
+                .with("size", s.size)
+                .with("compressed_size", s.compressed_size)
+                .with("flags", describe_flags(s.flags))
+                .with("compression", describe_compression(s.compression_mask))
+                .with("crc32", format!("{:08x}", s.crc32))
+                .with("md5", to_hex(&s.md5))
+        })
+    };
+    JsonValue::object()
+        .with("path", change.path.as_str())
+        .with("kind", change.kind.as_str())
+        .with("content_changed", change.content_changed())
+        .with("flags_changed", change.flags_changed())
+        .with("compression_changed", change.compression_changed())
+        .with("old", summary(&change.old))
+        .with("new", summary(&change.new))
+        .with("error", change.error.clone())
+}
@@ -0,0 +1,106 @@
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::locale::parse_locale;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::verify::IntegrityMode;
+use mpq_folder_win::utils::json::JsonValue;
+use mpq_folder_win::utils::wildcard::{has_wildcards, wildcard_match};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut patterns: Vec<&str> = Vec::new();
+    let mut output = PathBuf::from(".");
+    let mut flat = false;
+    let mut json = false;
+    let mut locale = None;
+    let mut options = OpenOptions::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = iter.next().ok_or("--output requires a directory")?.into(),
+            "--locale" => {
+                let code = iter.next().ok_or("--locale requires a locale code")?;
+                locale = Some(parse_locale(code).ok_or_else(|| format!("unknown locale: {code}"))?);
+            }
+            "--flat" => flat = true,
+            "--strict" => options.integrity = IntegrityMode::Strict,
+            "--tolerant" => options.tolerant = true,
+            "--recover-names" => options.recover_names = true,
+            "--json" => json = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => patterns.push(arg),
+        }
+    }
+    let archive_path = archive_path.ok_or("extract requires an archive path")?;
+    let mut archive = MpqArchive::open_with(archive_path, options)?;
+    if let Some(locale) = locale {
+        archive.set_preferred_locale(locale);
+    }
+
+    let mut names: Vec<String> = archive
+        .file_names()
+        .into_iter()
+        .filter(|name| patterns.is_empty() || patterns.iter().any(|pattern| wildcard_match(pattern, name)))
+        .collect();
+    // Plain names can be extracted even when no listfile mentions them; missing ones are reported as failures.
+    for pattern in patterns.iter().filter(|p| !has_wildcards(p)) {
+        if !names.iter().any(|name| name.eq_ignore_ascii_case(pattern)) {
+            names.push(pattern.to_string());
+        }
+    }
+
+    let mut extracted = Vec::new();
+    let mut failed = Vec::new();
+    for name in names {
+        let target = output.join(target_path(&name, flat));
+        let result = archive
+            .read_file(&name)
+            .map_err(|e| e.to_string())
+            .and_then(|data| write_file(&target, &data).map(|_| data.len()).map_err(|e| e.to_string()));
+        match result {
+            Ok(size) => {
+                if !json {
+                    println!("{}", target.display());
+                }
+                extracted.push(JsonValue::object().with("name", name).with("path", target.display().to_string()).with("size", size));
+            }
+            Err(error) => {
+                if !json {
+                    eprintln!("failed: {name}: {error}");
+                }
+                failed.push(JsonValue::object().with("name", name).with("error", error));
+            }
+        }
+    }
+
+    let failures = failed.len();
+    if json {
+        println!("{}", JsonValue::object().with("archive", archive_path).with("extracted", extracted).with("failed", failed).to_pretty());
+    } else {
+        println!("{} file(s) extracted, {} failed", extracted.len(), failures);
+    }
+    if failures > 0 {
+        return Err(format!("{failures} file(s) could not be extracted").into());
+    }
+    Ok(())
+}
+
+/// Relative output path of an archive name. Components that could escape the output directory are dropped.
+fn target_path(name: &str, flat: bool) -> PathBuf {
+    let mut components = name
+        .split(['\\', '/'])
+        .filter(|part| !part.is_empty() && *part != "." && *part != ".." && !part.contains(':'));
+    if flat {
+        return components.next_back().map(PathBuf::from).unwrap_or_default();
+    }
+    components.collect()
+}
+
+fn write_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)
+}
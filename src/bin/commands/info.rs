@@ -0,0 +1,90 @@
+use super::{print_text, signature_json};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::signature::SignatureKeys;
+use mpq_folder_win::utils::json::JsonValue;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut json = false;
+    let mut keys = SignatureKeys::blizzard();
+    let mut options = OpenOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--tolerant" => options.tolerant = true,
+            "--key" => keys.add_pem_file(iter.next().ok_or("--key requires a PEM file")?)?,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or("info requires an archive path")?;
+    let archive = MpqArchive::open_with(archive_path, options)?;
+    let info = describe(archive_path, &archive, &keys);
+
+    if json {
+        println!("{}", info.to_pretty());
+    } else {
+        print_text(&info, "");
+    }
+    Ok(())
+}
+
+/// Everything `info` reports, as a JSON tree; the text output renders the same tree.
+fn describe(archive_path: &str, archive: &MpqArchive, keys: &SignatureKeys) -> JsonValue {
+    let header = archive.header();
+    let location = archive.location();
+
+    let hash_used = archive.hash_table().iter().filter(|h| !h.is_empty() && !h.is_deleted()).count();
+    let live_blocks = archive.block_table().iter().filter(|b| b.exists()).count();
+
+    let mut info = JsonValue::object()
+        .with("archive", archive_path)
+        .with("file_size", archive.source_len())
+        .with("archive_offset", location.archive_offset)
+        .with("format_version", header.format_version as u32 + 1)
+        .with("header_size", header.header_size)
+        .with("archive_size", if header.archive_size_64 != 0 { header.archive_size_64 } else { header.archive_size as u64 })
+        .with("sector_size", header.sector_size())
+        .with("raw_chunk_size", (header.format_version >= 3).then_some(header.raw_chunk_size))
+        .with("hash_table", JsonValue::object().with("offset", header.hash_table_offset()).with("entries", header.hash_table_size).with("used", hash_used))
+        .with("block_table", JsonValue::object().with("offset", header.block_table_offset()).with("entries", header.block_table_size).with("live", live_blocks))
+        .with("hi_block_table_offset", (header.hi_block_table_pos != 0).then_some(header.hi_block_table_pos))
+        .with("het_table", archive.het_index().map(|index| {
+            let used = index.entries().iter().filter(|h| !h.is_empty()).count();
+            JsonValue::object().with("offset", header.het_table_pos).with("entries", index.het.hashes.len()).with("used", used)
+        }))
+        .with("bet_table", archive.het_index().map(|index| JsonValue::object().with("offset", header.bet_table_pos).with("entries", index.bet.blocks.len())))
+        .with("named_files", archive.file_names().len())
+        .with("free_space", {
+            let space = archive.free_space();
+            JsonValue::object()
+                .with("unused_bytes", space.unused_bytes())
+                .with("unused_blocks", space.unused_blocks.len())
+                .with("deleted_slots", space.deleted_slots.len())
+        })
+        .with("attributes", archive.attributes().is_some())
+        .with("signature", signature_json(&archive.verify_signatures(keys)));
+
+    info.insert("user_data", location.user_data.map(|user_data| {
+        JsonValue::object()
+            .with("offset", user_data.offset)
+            .with("size", user_data.user_data_size)
+            .with("header_offset", user_data.header_offset)
+    }));
+    info.insert("map_header", archive.map_header().map(|map| {
+        JsonValue::object()
+            .with("name", map.name)
+            .with("flags", map.flags)
+            .with("max_players", map.max_players)
+    }));
+    info.insert("map_info", match archive.map_info() {
+        Some(Ok(map_info)) => map_info.to_json(),
+        Some(Err(err)) => JsonValue::object().with("error", err.to_string()),
+        None => JsonValue::Null,
+    });
+    let anomalies: Vec<String> = archive.anomalies().iter().map(ToString::to_string).collect();
+    info.insert("anomalies", anomalies);
+    info
+}
@@ -0,0 +1,60 @@
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::locale::locale_name;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::tables::describe_flags;
+use mpq_folder_win::utils::json::JsonValue;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut json = false;
+    let mut options = OpenOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--tolerant" => options.tolerant = true,
+            "--recover-names" => options.recover_names = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or("list requires an archive path")?;
+    let archive = MpqArchive::open_with(archive_path, options)?;
+
+    // One row per locale/platform variant of every known name; delete markers are listed with the `deleted` flag.
+    let mut files = Vec::new();
+    for name in archive.file_names().into_iter().chain(archive.deleted_names()) {
+        for hash in archive.file_variants(&name) {
+            let block_index = hash.block_index as usize;
+            let block = &archive.block_table()[block_index];
+            files.push((name.clone(), hash.locale, hash.platform, block_index, *block));
+        }
+    }
+
+    if json {
+        let files: Vec<JsonValue> = files
+            .iter()
+            .map(|(name, locale, platform, block_index, block)| {
+                JsonValue::object()
+                    .with("name", name.as_str())
+                    .with("size", block.file_size)
+                    .with("compressed_size", block.compressed_size)
+                    .with("flags", describe_flags(block.flags))
+                    .with("flags_raw", block.flags)
+                    .with("locale", locale_name(*locale))
+                    .with("locale_id", *locale)
+                    .with("platform", *platform)
+                    .with("block_index", *block_index)
+            })
+            .collect();
+        println!("{}", JsonValue::object().with("archive", archive_path).with("files", files).to_pretty());
+        return Ok(());
+    }
+
+    println!("{:>10} {:>10}  {:<7}  {:<40} Name", "Size", "Packed", "Locale", "Flags");
+    for (name, locale, _, _, block) in &files {
+        println!("{:>10} {:>10}  {:<7}  {:<40} {}", block.file_size, block.compressed_size, locale_name(*locale), describe_flags(block.flags).join(","), name);
+    }
+    let total: u64 = files.iter().map(|(.., block)| block.file_size as u64).sum();
+    println!("{} file(s), {} bytes", files.len(), total);
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::{OpenOptions, parse_listfile};
+use std::fs;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut output: Option<&str> = None;
+    let mut extra: Vec<String> = Vec::new();
+    let mut options = OpenOptions::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(iter.next().ok_or("--output requires a file")?),
+            "--names" => extra.extend(parse_listfile(&fs::read(iter.next().ok_or("--names requires a listfile")?)?)),
+            "--tolerant" => options.tolerant = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or("listfile requires an archive path")?;
+    let mut archive = MpqArchive::open_with(archive_path, options)?;
+
+    let recovery = archive.recover_names(&extra);
+    let recovered = recovery.recovered.len();
+    archive.add_names(recovery.recovered);
+    let mut listfile = String::new();
+    for name in archive.file_names() {
+        listfile.push_str(&name);
+        listfile.push_str("\r\n");
+    }
+
+    match output {
+        Some(path) => fs::write(path, listfile)?,
+        None => print!("{listfile}"),
+    }
+    eprintln!("{} name(s) recovered, {} file(s) still unnamed", recovered, recovery.unresolved);
+    Ok(())
+}
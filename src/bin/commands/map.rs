@@ -0,0 +1,105 @@
+use super::{imports_json, print_imports, print_text};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::texture::png::encode_png;
+use mpq_folder_win::utils::json::JsonValue;
+use std::fs;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some((subcommand, rest)) = args.split_first() else {
+        return Err("map requires a subcommand: terrain, imports, preview".into());
+    };
+    match subcommand.as_str() {
+        "terrain" => terrain(rest),
+        "imports" => imports(rest),
+        "preview" => preview(rest),
+        other => Err(format!("unknown map subcommand: {other}").into()),
+    }
+}
+
+/// Options shared by the `map` subcommands: the archive path, `--json` and `--tolerant`.
+fn parse_args<'a>(args: &'a [String], command: &str) -> Result<(&'a str, bool, MpqArchive), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut json = false;
+    let mut options = OpenOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--tolerant" => options.tolerant = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or_else(|| format!("map {command} requires an archive path"))?;
+    let archive = MpqArchive::open_with(archive_path, options)?;
+    Ok((archive_path, json, archive))
+}
+
+fn terrain(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (archive_path, json, archive) = parse_args(args, "terrain")?;
+    let terrain = archive.terrain().ok_or("the archive has no war3map.w3e")??;
+    let pathing = match archive.pathing_map() {
+        Some(Ok(pathing)) => pathing.to_json(),
+        Some(Err(err)) => JsonValue::object().with("error", err.to_string()),
+        None => JsonValue::Null,
+    };
+    let report = JsonValue::object()
+        .with("archive", archive_path)
+        .with("terrain", terrain.to_json())
+        .with("pathing", pathing);
+
+    if json {
+        println!("{}", report.to_pretty());
+    } else {
+        print_text(&report, "");
+    }
+    Ok(())
+}
+
+fn imports(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (archive_path, json, archive) = parse_args(args, "imports")?;
+    let imports = archive.check_imports().ok_or("the archive has no war3map.imp")?.map_err(|err| err.to_string());
+
+    if json {
+        let report = JsonValue::object().with("archive", archive_path).with("imports", imports_json(&Some(imports)));
+        println!("{}", report.to_pretty());
+    } else {
+        print_imports(&imports?);
+    }
+    Ok(())
+}
+
+fn preview(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut output: Option<String> = None;
+    let mut minimap = false;
+    let mut icons = true;
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(iter.next().ok_or("--output requires a file")?.clone()),
+            "--minimap" => minimap = true,
+            "--no-icons" => icons = false,
+            _ => rest.push(arg.clone()),
+        }
+    }
+    let (archive_path, json, archive) = parse_args(&rest, "preview")?;
+    let preview = if minimap || !icons { archive.minimap(icons) } else { archive.map_preview() };
+    let preview = preview.ok_or("the archive has no preview or minimap image")??;
+    let output = output.unwrap_or_else(|| format!("{}.png", archive_path.rsplit_once('.').map_or(archive_path, |(stem, _)| stem)));
+    fs::write(&output, encode_png(&preview.image))?;
+
+    let report = JsonValue::object()
+        .with("archive", archive_path)
+        .with("source", preview.source)
+        .with("width", preview.image.width)
+        .with("height", preview.image.height)
+        .with("icons", preview.icons)
+        .with("output", output.as_str());
+    if json {
+        println!("{}", report.to_pretty());
+    } else {
+        println!("{} ({}x{}, {} icon(s)) written to {}", preview.source, preview.image.width, preview.image.height, preview.icons, output);
+    }
+    Ok(())
+}
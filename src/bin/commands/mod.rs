@@ -0,0 +1,88 @@
+// Subcommands of mpq-tool
+pub mod compact;
+pub mod diff;
+pub mod extract;
+pub mod info;
+pub mod list;
+pub mod listfile;
+pub mod map;
+pub mod replay;
+pub mod script;
+pub mod space;
+pub mod verify;
+
+use mpq_folder_win::archive::signature::{SignatureReport, SignatureStatus};
+use mpq_folder_win::utils::json::JsonValue;
+use mpq_folder_win::war3::imports::ImportReport;
+
+/// Signature statuses as `{ "weak": { "status", "key", "error" }, "strong": ... }`.
+fn signature_json(report: &SignatureReport) -> JsonValue {
+    let status = |status: &SignatureStatus| {
+        let (key, error) = match status {
+            SignatureStatus::Valid(key) => (Some(key.clone()), None),
+            SignatureStatus::Error(error) => (None, Some(error.clone())),
+            _ => (None, None),
+        };
+        JsonValue::object()
+            .with("status", status.as_str())
+            .with("key", key)
+            .with("error", error)
+    };
+    JsonValue::object()
+        .with("weak", status(&report.weak))
+        .with("strong", status(&report.strong))
+}
+
+/// `war3map.imp` reconciliation: the report, `{ "error" }` or null for maps without a manifest.
+fn imports_json(imports: &Option<Result<ImportReport, String>>) -> JsonValue {
+    match imports {
+        Some(Ok(report)) => report.to_json(),
+        Some(Err(err)) => JsonValue::object().with("error", err.as_str()),
+        None => JsonValue::Null,
+    }
+}
+
+fn print_imports(report: &ImportReport) {
+    for path in &report.missing {
+        println!("MISSING   {path}");
+    }
+    for (listed, actual) in &report.mismatched {
+        println!("MISPLACED {listed} (found as {actual})");
+    }
+    for path in &report.orphaned {
+        println!("ORPHANED  {path}");
+    }
+    println!(
+        "war3map.imp: {} import(s) listed, {} missing, {} misplaced, {} orphaned file(s)",
+        report.listed,
+        report.missing.len(),
+        report.mismatched.len(),
+        report.orphaned.len()
+    );
+}
+
+/// Renders a JSON tree as indented `key: value` lines, for the text output of commands.
+fn print_text(value: &JsonValue, indent: &str) {
+    let JsonValue::Object(fields) = value else { return };
+    for (key, value) in fields {
+        match value {
+            JsonValue::Object(_) => {
+                println!("{indent}{key}:");
+                print_text(value, &format!("{indent}  "));
+            }
+            JsonValue::Array(items) if items.is_empty() => println!("{indent}{key}: -"),
+            JsonValue::Array(items) => {
+                println!("{indent}{key}:");
+                for item in items {
+                    match item {
+                        JsonValue::String(text) => println!("{indent}  - {text}"),
+                        other => println!("{indent}  - {other}"),
+                    }
+                }
+            }
+            JsonValue::Null => println!("{indent}{key}: -"),
+            JsonValue::String(text) => println!("{indent}{key}: {text}"),
+            other => println!("{indent}{key}: {other}"),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use super::print_text;
+use mpq_folder_win::archive::MpqArchiveDescriptor;
+use mpq_folder_win::utils::json::JsonValue;
+use mpq_folder_win::war3::replay::{Replay, SLOT_USED};
+use std::fs;
+use std::path::Path;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut replay_path: Option<&str> = None;
+    let mut output: Option<&str> = None;
+    let mut json = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-o" | "--output" => output = Some(iter.next().ok_or("--output requires a directory")?),
+            _ if replay_path.is_none() => replay_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let replay_path = replay_path.ok_or("replay requires a .w3g path")?;
+    let replay = Replay::parse(&fs::read(replay_path)?)?;
+
+    if let Some(dir) = output {
+        // The same files the mount presents.
+        fs::create_dir_all(dir)?;
+        for entry in MpqArchiveDescriptor::from_replay(&replay).entries() {
+            fs::write(Path::new(dir).join(&entry.path), entry.read()?)?;
+        }
+    }
+
+    if json {
+        let report = JsonValue::object()
+            .with("replay", replay_path)
+            .with("header", replay.header_json())
+            .with("players", replay.players_json())
+            .with("actions_size", replay.actions().len());
+        println!("{}", report.to_pretty());
+        return Ok(());
+    }
+    print_text(&replay.header_json(), "");
+    println!("players:");
+    for (index, slot) in replay.game.slots.iter().enumerate().filter(|(_, slot)| slot.status == SLOT_USED) {
+        println!("  {index:>2}: {} ({}, team {}, color {}, handicap {}%)", replay.game.slot_name(slot), slot.race_name(), slot.team, slot.color, slot.handicap);
+    }
+    println!("actions: {} bytes", replay.actions().len());
+    Ok(())
+}
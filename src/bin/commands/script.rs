@@ -0,0 +1,48 @@
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::utils::json::JsonValue;
+use mpq_folder_win::war3::jass::prettify;
+use mpq_folder_win::war3::script::RawcodeReport;
+use mpq_folder_win::war3::w3i::ScriptLanguage;
+use std::fs;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut pretty = false;
+    let mut output: Option<&str> = None;
+    let mut options = OpenOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--pretty" => pretty = true,
+            "-o" | "--output" => output = Some(iter.next().ok_or("--output requires a file")?),
+            "--tolerant" => options.tolerant = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or("script requires an archive path")?;
+    let archive = MpqArchive::open_with(archive_path, options)?;
+    let script = archive.map_script().ok_or("the archive has no war3map.j or war3map.lua")?;
+
+    let text = if pretty {
+        if script.language != ScriptLanguage::Jass {
+            return Err("--pretty only supports JASS scripts".into());
+        }
+        prettify(&script.source)
+    } else {
+        let rawcodes = RawcodeReport::new(&script.rawcodes(), &archive.all_object_data());
+        let json = JsonValue::object()
+            .with("archive", archive_path)
+            .with("script", script.path)
+            .with("language", script.language.as_str())
+            .with("declarations", script.declarations_json())
+            .with("rawcodes", rawcodes.to_json());
+        json.to_pretty() + "\n"
+    };
+    match output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{text}"),
+    }
+    Ok(())
+}
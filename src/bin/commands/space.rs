@@ -0,0 +1,56 @@
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::space::UnusedKind;
+use mpq_folder_win::utils::json::JsonValue;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut json = false;
+    let mut options = OpenOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--tolerant" => options.tolerant = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or("space requires an archive path")?;
+    let archive = MpqArchive::open_with(archive_path, options)?;
+    let space = archive.free_space();
+    let deleted = archive.deleted_names();
+
+    if json {
+        let report = JsonValue::object()
+            .with("archive", archive_path)
+            .with("free_space", space.to_json())
+            .with("delete_markers", deleted);
+        println!("{}", report.to_pretty());
+        return Ok(());
+    }
+
+    println!("{:>12} {:>10}  What", "Offset", "Length");
+    for range in &space.ranges {
+        let what = match range.kind {
+            UnusedKind::UnusedBlock(index) => {
+                let block = &archive.block_table()[index];
+                format!("unused block {} ({} bytes unpacked)", index, block.file_size)
+            }
+            UnusedKind::Gap => "gap".to_string(),
+        };
+        println!("{:>12} {:>10}  {}", format!("0x{:X}", range.offset), range.len, what);
+    }
+    for name in &deleted {
+        println!("delete marker: {name}");
+    }
+    let percent = if space.archive_size == 0 { 0.0 } else { space.unused_bytes() as f64 * 100.0 / space.archive_size as f64 };
+    println!(
+        "{} of {} bytes unused ({:.1}%), {} unused block(s), {} deleted hash slot(s)",
+        space.unused_bytes(),
+        space.archive_size,
+        percent,
+        space.unused_blocks.len(),
+        space.deleted_slots.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use super::{imports_json, print_imports, signature_json};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::signature::SignatureKeys;
+use mpq_folder_win::archive::verify::Check;
+use mpq_folder_win::utils::json::JsonValue;
+
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archive_path: Option<&str> = None;
+    let mut json = false;
+    let mut verbose = false;
+    let mut keys = SignatureKeys::blizzard();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--key" => keys.add_pem_file(iter.next().ok_or("--key requires a PEM file")?)?,
+            "--json" => json = true,
+            "-v" | "--verbose" => verbose = true,
+            _ if archive_path.is_none() => archive_path = Some(arg),
+            _ => return Err(format!("unexpected argument: {arg}").into()),
+        }
+    }
+    let archive_path = archive_path.ok_or("verify requires an archive path")?;
+    let archive = MpqArchive::open(archive_path)?;
+    let report = archive.verify(&keys);
+    let failures = report.files.iter().filter(|file| !file.is_ok()).count();
+
+    if json {
+        let files: Vec<JsonValue> = report
+            .files
+            .iter()
+            .map(|file| {
+                JsonValue::object()
+                    .with("name", file.name.as_str())
+                    .with("block_index", file.block_index)
+                    .with("ok", file.is_ok())
+                    .with("crc32", file.crc32.as_str())
+                    .with("md5", file.md5.as_str())
+                    .with("sector_checksums", file.sector_checksums.as_str())
+                    .with("raw_chunks", file.raw_chunks.as_str())
+                    .with("error", file.error.clone())
+            })
+            .collect();
+        let mut pieces = JsonValue::object();
+        for piece in &report.pieces {
+            pieces.insert(piece.piece, piece.check.as_str());
+        }
+        let summary = JsonValue::object()
+            .with("archive", archive_path)
+            .with("ok", report.is_ok())
+            .with("md5_pieces", pieces)
+            .with("attributes", report.has_attributes)
+            .with("signature", signature_json(&report.signature))
+            .with("unnamed_blocks", report.unnamed_blocks)
+            .with("imports", imports_json(&report.imports))
+            .with("files", files);
+        println!("{}", summary.to_pretty());
+    } else {
+        for file in report.files.iter().filter(|file| verbose || !file.is_ok()) {
+            let status = if file.is_ok() { "OK  " } else { "FAIL" };
+            print!("{status} {}  crc32={} md5={} sectors={} raw_chunks={}", file.name, file.crc32.as_str(), file.md5.as_str(), file.sector_checksums.as_str(), file.raw_chunks.as_str());
+            match &file.error {
+                Some(error) => println!("  ({error})"),
+                None => println!(),
+            }
+        }
+        for piece in &report.pieces {
+            println!("{} MD5: {}", piece.piece, piece.check.as_str());
+        }
+        if !report.has_attributes {
+            println!("No (attributes): CRC32/MD5 checks skipped");
+        }
+        println!("Weak signature: {}", report.signature.weak.describe());
+        println!("Strong signature: {}", report.signature.strong.describe());
+        match &report.imports {
+            Some(Ok(imports)) => print_imports(imports),
+            Some(Err(err)) => println!("war3map.imp: {err}"),
+            None => {}
+        }
+        if report.unnamed_blocks > 0 {
+            println!("{} block(s) have no known name and were not checked", report.unnamed_blocks);
+        }
+        println!("{} file(s) checked, {} failed", report.files.len(), failures);
+    }
+
+    if failures > 0 {
+        return Err(format!("{failures} file(s) failed verification").into());
+    }
+    if report.pieces.iter().any(|piece| piece.check == Check::Mismatch) {
+        return Err("header or table MD5 mismatch".into());
+    }
+    if !report.signature.is_ok() {
+        return Err("signature verification failed".into());
+    }
+    Ok(())
+}
@@ -1,20 +1,32 @@
-#![cfg(windows)]
-
+// The installer registers WinFsp and shell associations, so everything but a stub main is Windows-only.
+#[cfg(windows)]
 use mpq_folder_win::log::log;
+#[cfg(windows)]
 use std::{env, io, io::Write};
 
 // Embedded viewer EXE that you copy into ./bin/ at build time.
+#[cfg(windows)]
 static EXE_BYTES: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/bin/mpq-viewer.exe"));
 
 // Single source of truth from the library (your keys module)
+#[cfg(windows)]
 use crate::actiions::dialog::{Action, action_choose, action_execute};
 
+#[cfg(windows)]
 #[path = "actions/mod.rs"]
 mod actiions;
 
+#[cfg(windows)]
 #[path = "utils/mod.rs"]
 mod utils;
 
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("mpq-folder-win-installer only runs on Windows.");
+    std::process::exit(1);
+}
+
+#[cfg(windows)]
 fn main() -> io::Result<()> {
     log("Installer started");
     loop {
@@ -46,6 +58,7 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+#[cfg(windows)]
 fn pause(msg: &str) {
     print!("{msg}");
     let _ = io::stdout().flush();
@@ -0,0 +1,73 @@
+use std::env;
+
+#[path = "commands/mod.rs"]
+mod commands;
+
+const USAGE: &str = "\
+Usage: mpq-tool <command> [options]
+
+Commands:
+  list <archive> [--tolerant] [--recover-names] [--json]
+                                        List files with sizes, flags and locales
+  listfile <archive> [--names <listfile>]... [--output <file>] [--tolerant]
+                                        Recover names missing from (listfile) and print a full listfile
+  extract <archive> [<pattern>...] [--output <dir>] [--flat] [--locale <code>] [--strict] [--tolerant]
+          [--recover-names] [--json]
+                                        Extract files (all, or those matching * ? ** patterns)
+  info <archive> [--key <pem>]... [--tolerant] [--json]
+                                        Show header, tables, user data, map header, signatures and
+                                        (with --tolerant) the malformations of protected maps
+  verify <archive> [--key <pem>]... [--verbose] [--json]
+                                        Check (attributes) CRC32/MD5, sector checksums, signatures and
+                                        (for maps) war3map.imp
+  script <archive> [--pretty] [--output <file>] [--tolerant]
+                                        Index the declarations of war3map.j or war3map.lua and the object
+                                        ids they use as JSON, or (with --pretty) print a JASS script
+                                        re-indented
+  map terrain <archive> [--tolerant] [--json]
+                                        Terrain dimensions, tilesets, heights and pathing statistics
+  map imports <archive> [--tolerant] [--json]
+                                        Imports listed in war3map.imp that are missing or misplaced,
+                                        and files nothing lists
+  map preview <archive> [--output <png>] [--minimap] [--no-icons] [--tolerant] [--json]
+                                        Write the map's preview image, or its minimap with start
+                                        locations, gold mines and neutral buildings, as PNG
+  replay <replay.w3g> [--output <dir>] [--json]
+                                        Show a Warcraft III replay's version, game settings and players;
+                                        --output writes header.json, players.json and actions.bin
+  space <archive> [--tolerant] [--json]
+                                        Unused byte ranges (deleted or replaced file data, gaps),
+                                        deleted hash slots and delete markers
+  diff <old> <new> [--json]             Added/removed/modified files, flag and compression changes
+  compact <archive> [--output <path>]   Rewrite the archive without dead blocks";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let Some((command, rest)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        std::process::exit(1);
+    };
+
+    match command.as_str() {
+        "list" => commands::list::run(rest),
+        "listfile" => commands::listfile::run(rest),
+        "extract" => commands::extract::run(rest),
+        "info" => commands::info::run(rest),
+        "verify" => commands::verify::run(rest),
+        "script" => commands::script::run(rest),
+        "map" => commands::map::run(rest),
+        "replay" => commands::replay::run(rest),
+        "space" => commands::space::run(rest),
+        "diff" => commands::diff::run(rest),
+        "compact" => commands::compact::run(rest),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => {
+            eprintln!("Unknown command: {other}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Converters provided by this crate.
+
+use crate::archive::MpqArchiveError;
+use crate::audio::{mp3_info, wav_info};
+use crate::convert::Converter;
+use crate::texture::blp::decode_blp;
+use crate::texture::dds::decode_dds;
+use crate::texture::png::encode_png;
+use crate::texture::tga::decode_tga;
+use crate::war3::slk::SlkTable;
+use crate::war3::terrain::{PathingMap, Terrain};
+use crate::war3::wts::decode_text;
+
+/// BLP1/BLP2 textures to PNG.
+pub struct BlpPngConverter;
+
+impl Converter for BlpPngConverter {
+    fn name(&self) -> &'static str {
+        "blp-png"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["blp"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(encode_png(&decode_blp(data)?))
+    }
+}
+
+/// DXT and uncompressed DDS textures to PNG.
+pub struct DdsPngConverter;
+
+impl Converter for DdsPngConverter {
+    fn name(&self) -> &'static str {
+        "dds-png"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["dds"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(encode_png(&decode_dds(data)?))
+    }
+}
+
+/// TGA images to PNG, which Explorer can preview.
+pub struct TgaPngConverter;
+
+impl Converter for TgaPngConverter {
+    fn name(&self) -> &'static str {
+        "tga-png"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["tga"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(encode_png(&decode_tga(data)?))
+    }
+}
+
+/// SYLK data tables to CSV.
+pub struct SlkCsvConverter;
+
+impl Converter for SlkCsvConverter {
+    fn name(&self) -> &'static str {
+        "slk-csv"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["slk"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(SlkTable::parse(&String::from_utf8_lossy(data)).to_csv().into_bytes())
+    }
+}
+
+/// String tables (UTF-8 with a BOM or UTF-16) to plain UTF-8 text.
+pub struct WtsTextConverter;
+
+impl Converter for WtsTextConverter {
+    fn name(&self) -> &'static str {
+        "wts-txt"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["wts"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(decode_text(data).into_owned().into_bytes())
+    }
+}
+
+/// Format, channels, rate, duration and tags of WAV and MP3 sounds, as text.
+pub struct AudioInfoConverter;
+
+impl Converter for AudioInfoConverter {
+    fn name(&self) -> &'static str {
+        "audio-info"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["wav", "mp3"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        let info = if data.starts_with(b"RIFF") { wav_info(data)? } else { mp3_info(data)? };
+        Ok(info.to_text().into_bytes())
+    }
+}
+
+/// Terrain heightmaps (`war3map.w3e`) to PNG, water in blue.
+pub struct TerrainPngConverter;
+
+impl Converter for TerrainPngConverter {
+    fn name(&self) -> &'static str {
+        "w3e-png"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["w3e"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(encode_png(&Terrain::parse(data)?.heightmap_image()?))
+    }
+}
+
+/// Pathing maps (`war3map.wpm`) to PNG, colored by what may walk, fly or build.
+pub struct PathingPngConverter;
+
+impl Converter for PathingPngConverter {
+    fn name(&self) -> &'static str {
+        "wpm-png"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["wpm"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        Ok(encode_png(&PathingMap::parse(data)?.image()?))
+    }
+}
@@ -0,0 +1,203 @@
+//! Converted views: virtual siblings offered next to mounted files (`x.blp` gets `x.blp.png`).
+//!
+//! A [`Converter`] maps files of some extensions to bytes in another format. The [`ConverterRegistry`]
+//! lists the siblings, converts each one the first time it is opened and keeps the most recently used
+//! results up to a byte limit.
+
+mod builtin;
+
+pub use builtin::{
+    AudioInfoConverter, BlpPngConverter, DdsPngConverter, PathingPngConverter, SlkCsvConverter, TerrainPngConverter, TgaPngConverter,
+    WtsTextConverter,
+};
+
+use crate::archive::compression::is_supported;
+use crate::archive::{MpqArchiveDescriptor, MpqArchiveError, MpqEntry};
+use crate::log::log;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Bytes of converted siblings kept by default.
+pub const DEFAULT_CACHE_LIMIT: u64 = 64 << 20;
+
+pub trait Converter: Send + Sync {
+    /// Name the converter is switched on by, e.g. `blp-png`.
+    fn name(&self) -> &'static str;
+    /// Lowercase extensions, without the dot, of the files converted.
+    fn source_extensions(&self) -> &'static [&'static str];
+    /// Extension appended to the source name: `x.blp` is offered as `x.blp.png`.
+    fn target_extension(&self) -> &'static str;
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError>;
+}
+
+/// Converters enabled for a mount, with the conversions done so far.
+pub struct ConverterRegistry {
+    converters: Vec<Box<dyn Converter>>,
+    cache: Mutex<ConversionCache>,
+}
+
+impl Default for ConverterRegistry {
+    fn default() -> Self {
+        Self { converters: Vec::new(), cache: Mutex::new(ConversionCache { limit: DEFAULT_CACHE_LIMIT, ..ConversionCache::default() }) }
+    }
+}
+
+/// Converted siblings, least recently used dropped first once they hold more than `limit` bytes. Keyed by
+/// lowercase sibling path with `\` separators.
+#[derive(Default)]
+struct ConversionCache {
+    /// The entry (`None` when the conversion failed) and when it was last used.
+    entries: HashMap<String, (Option<Arc<MpqEntry>>, u64)>,
+    /// Size of every sibling converted so far, kept when its bytes are dropped so listings stay the same.
+    sizes: HashMap<String, u64>,
+    bytes: u64,
+    limit: u64,
+    clock: u64,
+}
+
+impl ConversionCache {
+    fn get(&mut self, key: &str) -> Option<Option<Arc<MpqEntry>>> {
+        self.clock += 1;
+        let (entry, used) = self.entries.get_mut(key)?;
+        *used = self.clock;
+        Some(entry.clone())
+    }
+
+    fn insert(&mut self, key: String, entry: Option<Arc<MpqEntry>>) {
+        if let Some(entry) = &entry {
+            self.sizes.insert(key.clone(), entry.uncompressed_size);
+        }
+        let bytes = cost(&key, &entry);
+        if bytes > self.limit {
+            return;
+        }
+        self.clock += 1;
+        if let Some((old, _)) = self.entries.insert(key.clone(), (entry, self.clock)) {
+            self.bytes -= cost(&key, &old);
+        }
+        self.bytes += bytes;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.bytes > self.limit {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(key, _)| key.clone()) else { break };
+            let (old, _) = self.entries.remove(&oldest).expect("cached");
+            self.bytes -= cost(&oldest, &old);
+        }
+    }
+}
+
+/// Bytes a cache entry is counted as: the converted data, or only its key for a failed conversion.
+fn cost(key: &str, entry: &Option<Arc<MpqEntry>>) -> u64 {
+    key.len() as u64 + entry.as_ref().map_or(0, |entry| entry.uncompressed_size)
+}
+
+impl ConverterRegistry {
+    /// Every converter this crate provides.
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(Box::new(BlpPngConverter));
+        registry.register(Box::new(DdsPngConverter));
+        registry.register(Box::new(TgaPngConverter));
+        registry.register(Box::new(SlkCsvConverter));
+        registry.register(Box::new(WtsTextConverter));
+        registry.register(Box::new(AudioInfoConverter));
+        registry.register(Box::new(TerrainPngConverter));
+        registry.register(Box::new(PathingPngConverter));
+        registry
+    }
+
+    /// The built-in converters named in `names`; `all` enables every one. Unknown names are an error.
+    pub fn with_names<S: AsRef<str>>(names: &[S]) -> Result<Self, String> {
+        let mut registry = Self::builtin();
+        if names.iter().any(|name| name.as_ref() == "all") {
+            return Ok(registry);
+        }
+        if let Some(unknown) = names.iter().find(|name| !registry.names().contains(&name.as_ref())) {
+            return Err(format!("unknown converter {} (known: {})", unknown.as_ref(), registry.names().join(", ")));
+        }
+        registry.converters.retain(|converter| names.iter().any(|name| name.as_ref() == converter.name()));
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, converter: Box<dyn Converter>) {
+        self.converters.push(converter);
+    }
+
+    /// Bytes of converted siblings kept for reuse, [`DEFAULT_CACHE_LIMIT`] unless set.
+    pub fn set_cache_limit(&mut self, bytes: u64) {
+        if let Ok(cache) = self.cache.get_mut() {
+            cache.limit = bytes;
+            cache.trim();
+        }
+    }
+
+    /// Bytes of converted siblings currently kept.
+    pub fn cached_bytes(&self) -> u64 {
+        self.cache.lock().map_or(0, |cache| cache.bytes)
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.converters.iter().map(|converter| converter.name()).collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.converters.is_empty()
+    }
+
+    /// Names of the siblings offered for `path`, the name `source` is listed under. None are offered for files
+    /// stored with a compression this crate cannot undo, since they could not be read to convert them.
+    pub fn siblings(&self, path: &str, source: &MpqEntry) -> Vec<String> {
+        let Some(extension) = extension(path) else { return Vec::new() };
+        let siblings: Vec<String> = self
+            .converters
+            .iter()
+            .filter(|converter| converter.source_extensions().contains(&extension.as_str()))
+            .map(|converter| format!("{}.{}", path, converter.target_extension()))
+            .collect();
+        if !siblings.is_empty() && !is_supported(source.compression_mask()) {
+            return Vec::new();
+        }
+        siblings
+    }
+
+    /// The sibling at `path`, converted from its source in `descriptor` unless it is still cached.
+    pub fn converted(&self, descriptor: &MpqArchiveDescriptor, path: &str) -> Option<Arc<MpqEntry>> {
+        let key = cache_key(path);
+        if let Some(cached) = self.cache.lock().ok()?.get(&key) {
+            return cached;
+        }
+
+        let (source_path, target) = path.rsplit_once('.')?;
+        let source_extension = extension(source_path)?;
+        let converter = self.converters.iter().find(|converter| {
+            converter.target_extension().eq_ignore_ascii_case(target) && converter.source_extensions().contains(&source_extension.as_str())
+        })?;
+        let source = descriptor.find_entry(source_path).filter(|source| is_supported(source.compression_mask()))?;
+        let converted = match source.read().and_then(|data| converter.convert(&data)) {
+            Ok(data) => Some(Arc::new(MpqEntry { source: source.source.clone(), ..MpqEntry::from_bytes(path.to_string(), data) })),
+            Err(err) => {
+                log(format!("ConverterRegistry: {} cannot convert {}: {}", converter.name(), source_path, err));
+                None
+            }
+        };
+        self.cache.lock().ok()?.insert(key, converted.clone());
+        converted
+    }
+
+    /// Size of a sibling converted before, also once its bytes have left the cache; `None` until it is first
+    /// converted, and for siblings that failed to convert.
+    pub fn known_size(&self, path: &str) -> Option<u64> {
+        self.cache.lock().ok()?.sizes.get(&cache_key(path)).copied()
+    }
+}
+
+fn cache_key(path: &str) -> String {
+    path.to_ascii_lowercase().replace('/', "\\")
+}
+
+fn extension(path: &str) -> Option<String> {
+    let name = path.rsplit(['\\', '/']).next()?;
+    name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase())
+}
@@ -1,8 +1,12 @@
 // MPQ Archive Viewer with WinFsp
 // Core modules
 pub mod archive;
+pub mod audio;
+pub mod convert;
 pub mod log;
+pub mod texture;
 pub mod utils;
+pub mod war3;
 
 // WinFsp filesystem implementation
 #[cfg(windows)]
@@ -12,8 +16,26 @@ pub mod mpq_filesystem;
 /// ProgID bound to `.mpq` family (HKCR\WarRaft.MPQArchive; HKCR\.mpq -> WarRaft.MPQArchive).
 pub const DEFAULT_PROGID: &str = "WarRaft.MPQArchive";
 
-/// File extensions supported by the handler.
-pub const SUPPORTED_EXTENSIONS: &[&str] = &[".mpq", ".w3m", ".w3x"];
+/// File extensions supported by the handler: plain MPQs, Warcraft III maps, and the StarCraft II and Heroes of the
+/// Storm maps, mods and replays, which are MPQs too.
+pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    ".mpq",
+    ".w3m",
+    ".w3x",
+    ".sc2map",
+    ".sc2mod",
+    ".sc2replay",
+    ".s2ma",
+    ".stormmap",
+    ".stormmod",
+    ".stormreplay",
+];
+
+/// ProgID bound to Warcraft III replays, which are not MPQs (HKCR\WarRaft.W3GReplay; HKCR\.w3g -> WarRaft.W3GReplay).
+pub const REPLAY_PROGID: &str = "WarRaft.W3GReplay";
+
+/// Warcraft III replay extensions, mounted as parsed views.
+pub const REPLAY_EXTENSIONS: &[&str] = &[".w3g"];
 
 /// Human-friendly application name
 pub const APP_NAME: &str = "MPQ Archive Viewer";
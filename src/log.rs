@@ -5,24 +5,34 @@
 //! - State then lives in-process (no further registry reads).
 //! - toggle_logging()/log_toggle() flips the state AND persists it to registry.
 //! - Public API: log_enabled(), toggle_logging(), log_toggle(), log(...), logf!().
+//! - Off Windows (the CLI tool): enabled by env MPQ_FOLDER_LOG=1, messages go to STDERR.
 
 use core::fmt::Write as _;
 use core::sync::atomic::{AtomicBool, Ordering};
 use std::{io, io::Write, sync::Once};
 
+#[cfg(windows)]
 use windows::{Win32::System::Console::GetConsoleWindow, Win32::System::Diagnostics::Debug::OutputDebugStringW, Win32::System::Threading::GetCurrentThreadId, core::PCWSTR};
 // <— добавили
 
+#[cfg(windows)]
 use winreg::RegKey;
+#[cfg(windows)]
 use winreg::enums::HKEY_CURRENT_USER;
 
 // Build timestamp (set at compile time)
 const BUILD_TIMESTAMP: &str = env!("BUILD_TIMESTAMP");
 
 // Registry location (per-user)
+#[cfg(windows)]
 const REG_SUBKEY: &str = r"Software\mpq-folder-win";
+#[cfg(windows)]
 const REG_VALUE: &str = "LogEnabled";
 
+// Environment switch used instead of the registry off Windows
+#[cfg(not(windows))]
+const ENV_VALUE: &str = "MPQ_FOLDER_LOG";
+
 // Process-local on/off flag
 static LOG_ON: AtomicBool = AtomicBool::new(false);
 
@@ -55,12 +65,32 @@ pub fn toggle_logging() {
     }
 }
 
+#[cfg(windows)]
 #[inline]
 fn console_attached() -> bool {
     let hwnd = unsafe { GetConsoleWindow() };
     !hwnd.0.is_null()
 }
 
+// Off Windows STDOUT belongs to the tool's own output (e.g. JSON), so only STDERR is used.
+#[cfg(not(windows))]
+#[inline]
+fn console_attached() -> bool {
+    false
+}
+
+#[cfg(windows)]
+#[inline]
+fn thread_tag() -> String {
+    unsafe { GetCurrentThreadId() }.to_string()
+}
+
+#[cfg(not(windows))]
+#[inline]
+fn thread_tag() -> String {
+    format!("{:?}", std::thread::current().id())
+}
+
 /// Logs a message to STDOUT (if a console is attached) and to DebugView (if enabled).
 #[inline]
 pub fn log(message: impl AsRef<str>) {
@@ -77,7 +107,7 @@ pub fn log(message: impl AsRef<str>) {
         return;
     }
 
-    let tid = unsafe { GetCurrentThreadId() };
+    let tid = thread_tag();
 
     // Префикс для DebugView
     let mut line = String::with_capacity(32 + msg.len());
@@ -93,7 +123,7 @@ pub fn __log_format(args: core::fmt::Arguments<'_>) {
         return;
     }
     let pid = std::process::id();
-    let tid = unsafe { GetCurrentThreadId() };
+    let tid = thread_tag();
 
     let mut line = String::with_capacity(64);
     let _ = write!(line, "[{}:{}] [mpq-folder] ", pid, tid);
@@ -125,6 +155,7 @@ fn ensure_init() {
 
 /// Internal: read HKCU\Software\mpq-folder-win\LogEnabled (DWORD 0/1).
 /// Returns Ok(bool) if successfully read, Err otherwise (treat as OFF).
+#[cfg(windows)]
 fn read_registry_flag_once() -> Result<bool, ()> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let key = match hkcu.open_subkey(REG_SUBKEY) {
@@ -139,6 +170,7 @@ fn read_registry_flag_once() -> Result<bool, ()> {
 
 /// Internal: write HKCU\Software\mpq-folder-win\LogEnabled (DWORD 0/1).
 /// Creates the subkey if missing. Best-effort: returns Err on failure.
+#[cfg(windows)]
 fn write_registry_flag(on: bool) -> Result<(), ()> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
     let (key, _disp) = hkcu.create_subkey(REG_SUBKEY).map_err(|_| ())?;
@@ -147,6 +179,7 @@ fn write_registry_flag(on: bool) -> Result<(), ()> {
 }
 
 /// Internal: emit a NUL-terminated UTF-16 string to OutputDebugStringW.
+#[cfg(windows)]
 #[inline]
 fn ods_immediate(s: &str) {
     let mut wide = Vec::with_capacity(s.len() + 1);
@@ -156,3 +189,26 @@ fn ods_immediate(s: &str) {
         OutputDebugStringW(PCWSTR(wide.as_ptr()));
     }
 }
+
+/// Internal: read env MPQ_FOLDER_LOG (anything but empty or "0" enables logging).
+#[cfg(not(windows))]
+fn read_registry_flag_once() -> Result<bool, ()> {
+    std::env::var(ENV_VALUE)
+        .map(|v| !v.is_empty() && v != "0")
+        .map_err(|_| ())
+}
+
+/// Internal: there is no persistent switch off Windows.
+#[cfg(not(windows))]
+fn write_registry_flag(_on: bool) -> Result<(), ()> {
+    Err(())
+}
+
+/// Internal: debug output goes to STDERR, and only while logging is on.
+#[cfg(not(windows))]
+#[inline]
+fn ods_immediate(s: &str) {
+    if LOG_ON.load(Ordering::Relaxed) {
+        eprintln!("{}", s);
+    }
+}
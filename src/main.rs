@@ -1,25 +1,137 @@
+// Mounting needs WinFsp; elsewhere only the stub main below is built (see mpq-tool for a portable CLI).
+#[cfg(windows)]
+use mpq_folder_win::archive::locale::{locale_name, parse_locale};
+#[cfg(windows)]
+use mpq_folder_win::archive::source::ReadMode;
+#[cfg(windows)]
+use mpq_folder_win::archive::verify::IntegrityMode;
+#[cfg(windows)]
+use mpq_folder_win::archive::{LoadOptions, ANOMALIES_INFO_FILE_NAME, LOCALES_DIR_NAME, OVERLAY_INFO_FILE_NAME, REPLAY_ACTIONS_FILE_NAME, REPLAY_HEADER_FILE_NAME, REPLAY_PLAYERS_FILE_NAME, SIGNATURES_INFO_FILE_NAME, STRINGS_DIR_NAME};
+#[cfg(windows)]
+use mpq_folder_win::convert::ConverterRegistry;
+#[cfg(windows)]
 use mpq_folder_win::log::log;
+#[cfg(windows)]
 use mpq_folder_win::mpq_filesystem::MpqFileSystem;
+#[cfg(windows)]
 use std::io::{self, Write};
+#[cfg(windows)]
 use winfsp::host::{FileSystemHost, VolumeParams};
+#[cfg(windows)]
 use winfsp::{winfsp_init_or_die, FspError};
 
+#[cfg(not(windows))]
+fn main() {
+    eprintln!("mpq-viewer mounts archives with WinFsp and only runs on Windows; use mpq-tool instead.");
+    std::process::exit(1);
+}
+
+#[cfg(windows)]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Get MPQ path from command line
+    // Get MPQ paths from command line. Later archives shadow earlier ones; --patch archives shadow them all.
     let args: Vec<String> = std::env::args().collect();
-    
-    if args.len() < 2 {
-        eprintln!("Usage: {} <path-to-mpq-file>", args[0]);
+
+    let mut archive_paths: Vec<String> = Vec::new();
+    let mut patch_paths: Vec<String> = Vec::new();
+    let mut options = LoadOptions::default();
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--patch" => match rest.next() {
+                Some(path) => patch_paths.push(path.clone()),
+                None => {
+                    eprintln!("--patch requires a path");
+                    std::process::exit(1);
+                }
+            },
+            "--locale" => match rest.next().map(|code| (code, parse_locale(code))) {
+                Some((_, Some(locale))) => options.preferred_locale = locale,
+                Some((code, None)) => {
+                    eprintln!("Unknown locale: {} (use a code like enUS, ruRU or 0x0419)", code);
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--locale requires a locale code");
+                    std::process::exit(1);
+                }
+            },
+            "--locale-view" => options.locale_view = true,
+            "--strict" => options.integrity = IntegrityMode::Strict,
+            "--tolerant" => options.tolerant = true,
+            "--recover-names" => options.recover_names = true,
+            "--inline-strings" => options.inline_strings = true,
+            "--no-mmap" => options.read_mode = ReadMode::Positioned,
+            "--blp-png" => options.converters.push("blp-png".to_string()),
+            "--convert" => match rest.next() {
+                Some(names) => options.converters.extend(names.split(',').map(|name| name.trim().to_string())),
+                None => {
+                    eprintln!("--convert requires converter names (e.g. blp-png,slk-csv or all)");
+                    std::process::exit(1);
+                }
+            },
+            "--key" => match rest.next().map(|path| options.signature_keys.add_pem_file(path)) {
+                Some(Ok(())) => {}
+                Some(Err(err)) => {
+                    eprintln!("Failed to load signature key: {}", err);
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--key requires a PEM file");
+                    std::process::exit(1);
+                }
+            },
+            _ => archive_paths.push(arg.clone()),
+        }
+    }
+    // Patches apply on top of every positional archive, wherever they appear on the command line.
+    archive_paths.extend(patch_paths);
+
+    let Some((mpq_path, layer_paths)) = archive_paths.split_first() else {
+        eprintln!("Usage: {} <path-to-mpq-file> [<overlay-mpq>...] [--patch <patch-mpq>]... [--locale <code>] [--locale-view] [--key <pem>]... [--strict] [--tolerant] [--recover-names] [--inline-strings] [--no-mmap] [--convert <names>] [--blp-png]", args[0]);
         eprintln!("\nThis program mounts MPQ archives as virtual drives using WinFsp.");
         eprintln!("Double-click on .mpq files to automatically mount and browse.");
+        eprintln!("Several archives are merged in the order given; later ones shadow earlier ones,");
+        eprintln!("like war3.mpq, War3x.mpq, War3xlocal.mpq, War3Patch.mpq and finally a map.");
+        eprintln!("--patch archives are applied after all of them, in the order given.");
+        eprintln!("--locale picks the language served for files stored in several locales (default: neutral);");
+        eprintln!("--locale-view also lists every variant under {}\\<locale>\\.", LOCALES_DIR_NAME);
+        eprintln!("--strict refuses MPQ v4 archives and files whose MD5s do not match instead of logging a warning.");
+        eprintln!("--tolerant accepts malformed (protected) maps like the game does and lists what was wrong in {}.", ANOMALIES_INFO_FILE_NAME);
+        eprintln!("--recover-names finds files missing from (listfile) by hashing the paths other files reference.");
+        eprintln!("--inline-strings lists map files with their war3map.wts strings inlined under {}\\.", STRINGS_DIR_NAME);
+        eprintln!("--no-mmap reads archives with positioned reads instead of memory-mapping them.");
+        eprintln!("--convert lists converted copies next to files, made when first opened: {} or all;", ConverterRegistry::builtin().names().join(", "));
+        eprintln!("--blp-png is short for --convert blp-png (a name.blp.png next to every BLP texture).");
+        eprintln!("Signatures are checked against Blizzard's keys and any --key; see {} in the mounted root.", SIGNATURES_INFO_FILE_NAME);
+        eprintln!("A .w3g replay mounts as {}, {} and {}.", REPLAY_HEADER_FILE_NAME, REPLAY_PLAYERS_FILE_NAME, REPLAY_ACTIONS_FILE_NAME);
         std::process::exit(1);
-    }
-    
-    let mpq_path = &args[1];
+    };
     
     println!("MPQ Archive Viewer");
     println!("==================");
     println!("Archive: {}", mpq_path);
+    for layer in layer_paths {
+        println!("Overlay: {}", layer);
+    }
+    if !layer_paths.is_empty() {
+        println!("File origins: see {} in the mounted root", OVERLAY_INFO_FILE_NAME);
+    }
+    println!("Locale: {}", locale_name(options.preferred_locale));
+    if options.locale_view {
+        println!("Locale variants: see {} in the mounted root", LOCALES_DIR_NAME);
+    }
+    if options.inline_strings {
+        println!("Inlined strings: see {} in the mounted root", STRINGS_DIR_NAME);
+    }
+    if !options.converters.is_empty() {
+        match ConverterRegistry::with_names(&options.converters) {
+            Ok(converters) => println!("Converted views: {}", converters.names().join(", ")),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
     println!();
     
     // Initialize WinFsp
@@ -28,7 +140,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Create MPQ filesystem
     log(format!("Loading MPQ archive: {}", mpq_path));
-    let mpq_fs = MpqFileSystem::new(mpq_path.clone())
+    let mpq_fs = MpqFileSystem::with_options(mpq_path.clone(), layer_paths, &options)
         .map_err(|e| format!("Failed to load MPQ archive: {:?}", e))?;
     
     // Configure volume parameters
@@ -47,6 +159,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .case_sensitive_search(false)
         .case_preserved_names(true)
         .unicode_on_disk(true)
+        .named_streams(true) // per-file metadata via `file:mpq`
         .persistent_acls(false)
         .post_cleanup_when_modified_only(true)
         .um_file_context_is_user_context2(true)
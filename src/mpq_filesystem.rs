@@ -1,12 +1,17 @@
-use crate::archive::{MpqArchiveDescriptor, MpqEntry};
+use crate::archive::{LoadOptions, MpqArchiveDescriptor, MpqEntry, STRINGS_DIR_NAME};
+use crate::war3::replay::is_replay_file;
 use crate::log::log;
+use crate::convert::ConverterRegistry;
 use std::ffi::c_void;
-use std::sync::Arc;
-use winfsp::filesystem::{DirInfo, DirMarker, FileInfo, FileSecurity, FileSystemContext, OpenFileInfo, VolumeInfo};
+use std::sync::{Arc, OnceLock};
+use winfsp::filesystem::{DirInfo, DirMarker, FileInfo, FileSecurity, FileSystemContext, OpenFileInfo, StreamInfo, VolumeInfo, WideNameInfo};
 use winfsp::{FspError, Result, U16CStr};
 use windows::Win32::Foundation::STATUS_SUCCESS;
 use winfsp_sys::{FILE_ACCESS_RIGHTS, FILE_FLAGS_AND_ATTRIBUTES};
 
+/// Name of the alternate data stream carrying per-file archive metadata (e.g. `file.blp:mpq`).
+const METADATA_STREAM_NAME: &str = "mpq";
+
 /// File context representing an open file or directory in the MPQ archive
 pub struct MpqFileContext {
     /// Full path relative to archive root (e.g., "folder/file.txt" or "folder/")
@@ -15,6 +20,10 @@ pub struct MpqFileContext {
     is_directory: bool,
     /// For files: reference to the entry data
     entry: Option<Arc<MpqEntry>>,
+    /// Contents of the `:mpq` metadata stream when the context was opened on it
+    stream: Option<Arc<[u8]>>,
+    /// File contents, decompressed from the archive on the first read and kept until the handle is closed
+    data: OnceLock<Arc<[u8]>>,
 }
 
 impl MpqFileContext {
@@ -23,7 +32,44 @@ impl MpqFileContext {
             path,
             is_directory: false,
             entry: Some(entry),
+            stream: None,
+            data: OnceLock::new(),
+        }
+    }
+
+    fn new_metadata_stream(path: String, entry: Arc<MpqEntry>) -> Self {
+        let stream = Arc::from(entry.metadata_text().into_bytes().into_boxed_slice());
+        Self {
+            path,
+            is_directory: false,
+            entry: Some(entry),
+            stream: Some(stream),
+            data: OnceLock::new(),
+        }
+    }
+
+    /// Size of the data served by `read`: the metadata stream or the file itself
+    fn data_size(&self) -> u64 {
+        match (&self.stream, &self.entry) {
+            (Some(stream), _) => stream.len() as u64,
+            (None, Some(entry)) => entry.uncompressed_size,
+            (None, None) => 0,
+        }
+    }
+
+    /// Bytes served by `read`: the metadata stream, or the file read from its archive
+    fn contents(&self, entry: &MpqEntry) -> Result<&Arc<[u8]>> {
+        if let Some(stream) = &self.stream {
+            return Ok(stream);
         }
+        if let Some(data) = self.data.get() {
+            return Ok(data);
+        }
+        let data = entry.read().map_err(|e| {
+            log(format!("read: cannot read {}: {}", self.path, e));
+            FspError::from_ntstatus(0xC0000102) // STATUS_FILE_CORRUPT_ERROR
+        })?;
+        Ok(self.data.get_or_init(|| data))
     }
 
     fn new_directory(path: String) -> Self {
@@ -31,6 +77,8 @@ impl MpqFileContext {
             path,
             is_directory: true,
             entry: None,
+            stream: None,
+            data: OnceLock::new(),
         }
     }
 }
@@ -41,6 +89,8 @@ pub struct MpqFileSystem {
     descriptor: Arc<MpqArchiveDescriptor>,
     /// Source archive path for logging
     archive_path: String,
+    /// Converted views listed next to the archive's files
+    converters: ConverterRegistry,
 }
 
 impl MpqFileSystem {
@@ -58,9 +108,53 @@ impl MpqFileSystem {
         Ok(Self {
             descriptor: Arc::new(descriptor),
             archive_path,
+            converters: ConverterRegistry::default(),
         })
     }
 
+    /// Mount `archive_path` with patch/overlay archives layered on top (oldest first), presenting the merged view.
+    pub fn with_patches(archive_path: String, patch_paths: &[String]) -> Result<Self> {
+        Self::with_options(archive_path, patch_paths, &LoadOptions::default())
+    }
+
+    /// Like [`with_patches`](Self::with_patches), with a preferred locale and optional `(locales)` view.
+    pub fn with_options(archive_path: String, patch_paths: &[String], options: &LoadOptions) -> Result<Self> {
+        log(format!("MpqFileSystem::with_options: loading {} with {} patch(es)", archive_path, patch_paths.len()));
+
+        // Replays are not MPQs; they mount as a folder of parsed views and take no overlays.
+        let descriptor = if patch_paths.is_empty() && is_replay_file(&archive_path) {
+            MpqArchiveDescriptor::load_replay(&archive_path)
+        } else {
+            MpqArchiveDescriptor::load_chain_with(&archive_path, patch_paths, options)
+        };
+        let descriptor = descriptor
+            .map_err(|e| {
+                log(format!("Failed to load {}: {}", archive_path, e));
+                FspError::from_ntstatus(0xC0000001) // STATUS_UNSUCCESSFUL
+            })?;
+
+        log(format!("MpqFileSystem: loaded {} entries", descriptor.entries().len()));
+
+        let converters = ConverterRegistry::with_names(&options.converters).map_err(|e| {
+            log(format!("Failed to set up converters: {}", e));
+            FspError::from_ntstatus(0xC000000D) // STATUS_INVALID_PARAMETER
+        })?;
+
+        Ok(Self {
+            descriptor: Arc::new(descriptor),
+            archive_path,
+            converters,
+        })
+    }
+
+    /// Size of a file as listed in directories; converted siblings are listed as empty until first opened
+    fn listed_size(&self, path: &str) -> u64 {
+        match self.descriptor.find_entry(path) {
+            Some(entry) => entry.uncompressed_size,
+            None => self.converters.known_size(path).unwrap_or(0),
+        }
+    }
+
     /// Check if a path is a directory in the archive
     fn is_directory(&self, path: &str) -> bool {
         // Root is always a directory
@@ -69,9 +163,13 @@ impl MpqFileSystem {
         }
 
         let normalized = self.normalize_path(path);
+        // Listed before its files are made, which only happens once something inside it is opened or listed
+        if self.descriptor.has_strings_view() && normalized.eq_ignore_ascii_case(&format!("{}/", STRINGS_DIR_NAME)) {
+            return true;
+        }
         
         // Check if any entry starts with this prefix
-        self.descriptor.entries().iter().any(|entry| {
+        self.descriptor.entries_for(&normalized).iter().any(|entry| {
             let entry_path = entry.path.replace('\\', "/");
             entry_path.starts_with(&normalized) && entry_path.len() > normalized.len()
         })
@@ -95,8 +193,11 @@ impl MpqFileSystem {
         let prefix_len = prefix.len();
         
         let mut children: std::collections::HashSet<String> = std::collections::HashSet::new();
+        if prefix.is_empty() && self.descriptor.has_strings_view() {
+            children.insert(STRINGS_DIR_NAME.to_string());
+        }
         
-        for entry in self.descriptor.entries() {
+        for entry in self.descriptor.entries_for(&prefix) {
             let entry_path = entry.path.replace('\\', "/");
             
             if !entry_path.starts_with(&prefix) {
@@ -116,6 +217,7 @@ impl MpqFileSystem {
             } else {
                 // This is a file
                 children.insert(remainder.to_string());
+                children.extend(self.converters.siblings(remainder, entry));
             }
         }
         
@@ -159,7 +261,25 @@ impl FileSystemContext for MpqFileSystem {
         let path = file_name.to_string_lossy();
         log(format!("open: {}", path));
         
-        let normalized = path.trim_start_matches('\\').replace('\\', "/");
+        // Split off a named stream ("file.blp:mpq" or "file.blp:mpq:$DATA")
+        let (file_part, stream_name) = match path.split_once(':') {
+            Some((file, stream)) => (file.to_string(), stream.trim_end_matches(":$DATA").trim_end_matches("$DATA").to_string()),
+            None => (path.clone(), String::new()),
+        };
+        let normalized = file_part.trim_start_matches('\\').replace('\\', "/");
+
+        if !stream_name.is_empty() {
+            if !stream_name.eq_ignore_ascii_case(METADATA_STREAM_NAME) {
+                log(format!("open: unknown stream: {}", path));
+                return Err(FspError::from_ntstatus(0xC0000034)); // STATUS_OBJECT_NAME_NOT_FOUND
+            }
+            let entry = self.descriptor.find_entry(&normalized)
+                .ok_or_else(|| FspError::from_ntstatus(0xC0000034))?; // STATUS_OBJECT_NAME_NOT_FOUND
+            let context = MpqFileContext::new_metadata_stream(normalized, Arc::new(entry.clone()));
+            file_info.set_file_attributes(0x00000080); // FILE_ATTRIBUTE_NORMAL
+            file_info.set_file_size(context.data_size());
+            return Ok(context);
+        }
         
         // Check if root
         if normalized.is_empty() {
@@ -172,7 +292,12 @@ impl FileSystemContext for MpqFileSystem {
         if let Some(entry) = self.descriptor.find_entry(&normalized) {
             file_info.set_file_attributes(0x00000080); // FILE_ATTRIBUTE_NORMAL
             file_info.set_file_size(entry.uncompressed_size);
-            return Ok(MpqFileContext::new_file(normalized, Arc::clone(&entry.data)));
+            return Ok(MpqFileContext::new_file(normalized, Arc::new(entry.clone())));
+        }
+        if let Some(entry) = self.converters.converted(&self.descriptor, &normalized) {
+            file_info.set_file_attributes(0x00000080); // FILE_ATTRIBUTE_NORMAL
+            file_info.set_file_size(entry.uncompressed_size);
+            return Ok(MpqFileContext::new_file(normalized, entry));
         }
         
         // Check if directory
@@ -194,9 +319,9 @@ impl FileSystemContext for MpqFileSystem {
         if context.is_directory {
             file_info.set_file_attributes(0x00000010); // FILE_ATTRIBUTE_DIRECTORY
             file_info.set_file_size(0);
-        } else if let Some(entry) = &context.entry {
+        } else if context.entry.is_some() {
             file_info.set_file_attributes(0x00000080); // FILE_ATTRIBUTE_NORMAL
-            file_info.set_file_size(entry.uncompressed_size);
+            file_info.set_file_size(context.data_size());
         }
         Ok(())
     }
@@ -209,7 +334,7 @@ impl FileSystemContext for MpqFileSystem {
         let entry = context.entry.as_ref()
             .ok_or_else(|| FspError::from_ntstatus(0xC0000001))?; // STATUS_UNSUCCESSFUL
         
-        let data = &entry.data;
+        let data = context.contents(entry)?;
         let start = offset as usize;
         
         if start >= data.len() {
@@ -252,9 +377,7 @@ impl FileSystemContext for MpqFileSystem {
             
             let file_attrs = if *is_dir { 0x00000010 } else { 0x00000080 };
             let file_size = if *is_dir { 0 } else {
-                self.descriptor.find_entry(&format!("{}{}", context.path, name))
-                    .map(|e| e.uncompressed_size)
-                    .unwrap_or(0)
+                self.listed_size(&format!("{}{}", context.path, name))
             };
             
             if !dir_info.write(file_attrs, file_size, 0, 0, 0, 0, name_u16cstr) {
@@ -265,6 +388,28 @@ impl FileSystemContext for MpqFileSystem {
         Ok(dir_info.bytes_written())
     }
 
+    fn get_stream_info(&self, context: &Self::FileContext, buffer: &mut [u8]) -> Result<u32> {
+        // Directories carry no streams; files have the default stream plus `:mpq`
+        let Some(entry) = &context.entry else {
+            return Ok(0);
+        };
+
+        let metadata_len = entry.metadata_text().len() as u64;
+        let mut cursor = 0u32;
+        for (name, size) in [("", entry.uncompressed_size), (METADATA_STREAM_NAME, metadata_len)] {
+            let mut stream_info: StreamInfo = StreamInfo::new();
+            stream_info.stream_size = size;
+            stream_info.stream_alloc_size = size;
+            stream_info.set_name(name)?;
+            if !stream_info.append_to_buffer(buffer, &mut cursor) {
+                return Ok(cursor);
+            }
+        }
+        StreamInfo::<255>::finalize_buffer(buffer, &mut cursor);
+
+        Ok(cursor)
+    }
+
     fn get_volume_info(&self, volume_info: &mut VolumeInfo) -> Result<()> {
         volume_info.set_total_size(self.descriptor.total_uncompressed_size());
         volume_info.set_free_size(0); // Read-only
@@ -0,0 +1,187 @@
+//! BLP textures: BLP1 (Warcraft III; JPEG or paletted) and BLP2 (paletted, DXT1/3/5 or raw BGRA),
+//! each with up to 16 mipmaps.
+
+use crate::archive::MpqArchiveError;
+use crate::texture::{RgbaImage, check_dimensions};
+use crate::texture::dxt::{DxtFormat, decode_dxt};
+
+pub const BLP1_ID: &[u8; 4] = b"BLP1";
+pub const BLP2_ID: &[u8; 4] = b"BLP2";
+pub const MAX_MIPMAPS: usize = 16;
+
+const BLP1_HEADER_SIZE: usize = 28;
+const BLP2_HEADER_SIZE: usize = 20;
+const PALETTE_SIZE: usize = 256 * 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlpVersion {
+    Blp1,
+    Blp2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlpEncoding {
+    /// Four-channel JPEG (BGRA, no color transform) sharing one header across mipmaps.
+    Jpeg,
+    /// 8-bit palette indices followed by a separate alpha plane of `alpha_bits`.
+    Palette,
+    Dxt(DxtFormat),
+    /// 32-bit BGRA pixels.
+    Raw,
+}
+
+/// Parsed BLP header; pixel data is decoded per mipmap by [`decode`](Self::decode).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlpTexture<'a> {
+    pub version: BlpVersion,
+    pub encoding: BlpEncoding,
+    /// 0, 1, 4 or 8.
+    pub alpha_bits: u8,
+    pub width: u32,
+    pub height: u32,
+    /// `(offset, size)` of each stored mipmap, largest first.
+    pub mipmaps: Vec<(u32, u32)>,
+    palette: &'a [u8],
+    jpeg_header: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> BlpTexture<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, MpqArchiveError> {
+        let truncated = || MpqArchiveError::Corrupted("truncated BLP header".into());
+        let u32_at = |at: usize| data.get(at..at + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap())).ok_or_else(truncated);
+        let version = match data.get(..4) {
+            Some(id) if id == BLP1_ID => BlpVersion::Blp1,
+            Some(id) if id == BLP2_ID => BlpVersion::Blp2,
+            _ => return Err(MpqArchiveError::Corrupted("not a BLP texture".into())),
+        };
+
+        let (encoding, alpha_bits, width, height, has_mipmaps, tables) = match version {
+            BlpVersion::Blp1 => {
+                let encoding = if u32_at(4)? == 0 { BlpEncoding::Jpeg } else { BlpEncoding::Palette };
+                (encoding, u32_at(8)? as u8, u32_at(12)?, u32_at(16)?, u32_at(24)? != 0, BLP1_HEADER_SIZE)
+            }
+            BlpVersion::Blp2 => {
+                let fields = data.get(8..12).ok_or_else(truncated)?;
+                let (encoding, alpha_bits, alpha_encoding, has_mipmaps) = (fields[0], fields[1], fields[2], fields[3]);
+                let encoding = match (u32_at(4)?, encoding) {
+                    (0, _) => BlpEncoding::Jpeg,
+                    (_, 1) => BlpEncoding::Palette,
+                    (_, 2) => BlpEncoding::Dxt(match alpha_encoding {
+                        1 => DxtFormat::Dxt3,
+                        7 => DxtFormat::Dxt5,
+                        _ => DxtFormat::Dxt1,
+                    }),
+                    (_, 3) => BlpEncoding::Raw,
+                    (_, other) => return Err(MpqArchiveError::Corrupted(format!("unknown BLP2 encoding {}", other))),
+                };
+                (encoding, alpha_bits, u32_at(12)?, u32_at(16)?, has_mipmaps != 0, BLP2_HEADER_SIZE)
+            }
+        };
+        if width == 0 || height == 0 {
+            return Err(MpqArchiveError::Corrupted(format!("BLP of {}x{} pixels", width, height)));
+        }
+        check_dimensions(width, height)?;
+
+        let sizes_at = tables + MAX_MIPMAPS * 4;
+        let after_tables = sizes_at + MAX_MIPMAPS * 4;
+        let mut mipmaps = Vec::new();
+        for level in 0..if has_mipmaps { MAX_MIPMAPS } else { 1 } {
+            let (offset, size) = (u32_at(tables + level * 4)?, u32_at(sizes_at + level * 4)?);
+            if offset == 0 || size == 0 || (width >> level == 0 && height >> level == 0) {
+                break;
+            }
+            mipmaps.push((offset, size));
+        }
+
+        // JPEG textures carry a shared header instead of a palette (BLP2 always reserves the palette).
+        let (mut palette, mut jpeg_header): (&[u8], &[u8]) = (&[], &[]);
+        if encoding == BlpEncoding::Jpeg {
+            let header_size = u32_at(after_tables)? as usize;
+            jpeg_header = data.get(after_tables + 4..after_tables + 4 + header_size).ok_or_else(truncated)?;
+        } else {
+            palette = data.get(after_tables..after_tables + PALETTE_SIZE).ok_or_else(truncated)?;
+        }
+        Ok(Self { version, encoding, alpha_bits, width, height, mipmaps, palette, jpeg_header, data })
+    }
+
+    /// Dimensions of mipmap `level`; each level halves the previous one, down to 1.
+    pub fn mipmap_size(&self, level: usize) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// Decodes mipmap `level` (0 is the full-size image).
+    pub fn decode(&self, level: usize) -> Result<RgbaImage, MpqArchiveError> {
+        let &(offset, size) = self
+            .mipmaps
+            .get(level)
+            .ok_or_else(|| MpqArchiveError::Corrupted(format!("BLP has no mipmap {}", level)))?;
+        let (width, height) = self.mipmap_size(level);
+        // The last mipmap is often cut short; decode what is there.
+        let start = (offset as usize).min(self.data.len());
+        let block = &self.data[start..(start + size as usize).min(self.data.len())];
+
+        let mut image = match self.encoding {
+            BlpEncoding::Jpeg => self.decode_jpeg(block, width, height)?,
+            BlpEncoding::Palette => self.decode_palette(block, width, height)?,
+            BlpEncoding::Dxt(format) => decode_dxt(block, width, height, format)?,
+            BlpEncoding::Raw => {
+                let mut pixels = RgbaImage::new(width, height)?.pixels;
+                let len = block.len().min(pixels.len());
+                pixels[..len].copy_from_slice(&block[..len]);
+                RgbaImage::from_bgra(width, height, pixels)
+            }
+        };
+        if self.alpha_bits == 0 {
+            image.pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+        }
+        Ok(image)
+    }
+
+    fn decode_jpeg(&self, block: &[u8], width: u32, height: u32) -> Result<RgbaImage, MpqArchiveError> {
+        let mut jpeg = self.jpeg_header.to_vec();
+        jpeg.extend_from_slice(block);
+        let mut decoder = jpeg_decoder::Decoder::new(jpeg.as_slice());
+        // Channels are stored as B, G, R, A. The decoder's CMYK conversion only inverts each channel.
+        decoder.set_color_transform(jpeg_decoder::ColorTransform::CMYK);
+        let decoded = decoder.decode().map_err(|err| MpqArchiveError::Corrupted(format!("BLP JPEG: {}", err)))?;
+        let info = decoder.info().ok_or_else(|| MpqArchiveError::Corrupted("BLP JPEG without a frame".into()))?;
+        if info.pixel_format != jpeg_decoder::PixelFormat::CMYK32 {
+            return Err(MpqArchiveError::Corrupted(format!("BLP JPEG has {:?} pixels, 4 channels expected", info.pixel_format)));
+        }
+        // The frame may be padded beyond the mipmap size.
+        let mut pixels = RgbaImage::new(width, height)?.pixels;
+        let (frame_width, frame_height) = (info.width as usize, info.height as usize);
+        let row = width.min(info.width as u32) as usize * 4;
+        for y in 0..(height as usize).min(frame_height) {
+            let from = y * frame_width * 4;
+            for (to, channel) in pixels[y * width as usize * 4..][..row].iter_mut().zip(&decoded[from..from + row]) {
+                *to = 255 - channel;
+            }
+        }
+        Ok(RgbaImage::from_bgra(width, height, pixels))
+    }
+
+    fn decode_palette(&self, block: &[u8], width: u32, height: u32) -> Result<RgbaImage, MpqArchiveError> {
+        let mut image = RgbaImage::new(width, height)?;
+        let pixel_count = image.pixels.len() / 4;
+        let (indices, alpha) = block.split_at(pixel_count.min(block.len()));
+        for (pixel, out) in image.pixels.chunks_exact_mut(4).enumerate() {
+            let index = indices.get(pixel).copied().unwrap_or(0) as usize * 4;
+            let color = &self.palette[index..index + 4];
+            let alpha = match self.alpha_bits {
+                1 => alpha.get(pixel / 8).map_or(255, |bits| if bits >> (pixel % 8) & 1 != 0 { 255 } else { 0 }),
+                4 => alpha.get(pixel / 2).map_or(255, |bits| (bits >> ((pixel % 2) * 4) & 0x0F) * 17),
+                8 => alpha.get(pixel).copied().unwrap_or(255),
+                _ => 255,
+            };
+            out.copy_from_slice(&[color[2], color[1], color[0], alpha]);
+        }
+        Ok(image)
+    }
+}
+
+/// Decodes the full-size image of a BLP texture.
+pub fn decode_blp(data: &[u8]) -> Result<RgbaImage, MpqArchiveError> {
+    BlpTexture::parse(data)?.decode(0)
+}
@@ -0,0 +1,56 @@
+//! DirectDraw Surface textures: DXT1/3/5 and uncompressed RGB(A) with channel masks. DX10 headers are not supported.
+
+use crate::archive::MpqArchiveError;
+use crate::texture::{RgbaImage, check_dimensions};
+use crate::texture::dxt::{DxtFormat, decode_dxt};
+
+pub const DDS_ID: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 128;
+const DDPF_ALPHAPIXELS: u32 = 0x1;
+const DDPF_FOURCC: u32 = 0x4;
+
+/// Decodes the top-level surface of a DDS texture.
+pub fn decode_dds(data: &[u8]) -> Result<RgbaImage, MpqArchiveError> {
+    if data.len() < DDS_HEADER_SIZE || &data[..4] != DDS_ID {
+        return Err(MpqArchiveError::Corrupted("not a DDS texture".into()));
+    }
+    let u32_at = |at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap());
+    let (height, width) = (u32_at(12), u32_at(16));
+    let (format_flags, four_cc) = (u32_at(80), &data[84..88]);
+    let pixels = &data[DDS_HEADER_SIZE..];
+
+    if format_flags & DDPF_FOURCC != 0 {
+        let format = match four_cc {
+            b"DXT1" => DxtFormat::Dxt1,
+            b"DXT2" | b"DXT3" => DxtFormat::Dxt3,
+            b"DXT4" | b"DXT5" => DxtFormat::Dxt5,
+            other => return Err(MpqArchiveError::Corrupted(format!("unsupported DDS format {}", String::from_utf8_lossy(other)))),
+        };
+        return decode_dxt(pixels, width, height, format);
+    }
+
+    let bit_count = u32_at(88);
+    let masks = [u32_at(92), u32_at(96), u32_at(100), if format_flags & DDPF_ALPHAPIXELS != 0 { u32_at(104) } else { 0 }];
+    let bytes_per_pixel = match bit_count {
+        16 | 24 | 32 => bit_count as usize / 8,
+        other => return Err(MpqArchiveError::Corrupted(format!("unsupported DDS pixel size of {} bits", other))),
+    };
+    let pixel_count = check_dimensions(width, height)?;
+    if pixels.len() < pixel_count * bytes_per_pixel {
+        return Err(MpqArchiveError::Corrupted("truncated DDS pixel data".into()));
+    }
+    let mut image = RgbaImage::new(width, height)?;
+    for (pixel, out) in pixels.chunks_exact(bytes_per_pixel).zip(image.pixels.chunks_exact_mut(4)) {
+        let value = pixel.iter().rev().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+        for (channel, &mask) in masks.iter().enumerate() {
+            out[channel] = if mask == 0 { 255 } else { scale_masked(value, mask) };
+        }
+    }
+    Ok(image)
+}
+
+/// The bits of `value` under `mask`, scaled to 0..=255.
+fn scale_masked(value: u32, mask: u32) -> u8 {
+    let max = mask >> mask.trailing_zeros();
+    (((value & mask) >> mask.trailing_zeros()) as u64 * 255 / max as u64) as u8
+}
@@ -0,0 +1,109 @@
+//! S3TC block decompression (DXT1, DXT3, DXT5), as used by BLP2 and DDS textures.
+
+use crate::archive::MpqArchiveError;
+use crate::texture::{RgbaImage, check_dimensions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DxtFormat {
+    /// 8-byte blocks; 1-bit alpha through the three-color mode.
+    Dxt1,
+    /// 16-byte blocks with explicit 4-bit alpha.
+    Dxt3,
+    /// 16-byte blocks with interpolated alpha.
+    Dxt5,
+}
+
+impl DxtFormat {
+    pub fn block_size(self) -> usize {
+        match self {
+            DxtFormat::Dxt1 => 8,
+            DxtFormat::Dxt3 | DxtFormat::Dxt5 => 16,
+        }
+    }
+
+    /// Bytes of a `width` x `height` image, in whole 4x4 blocks.
+    pub fn data_size(self, width: u32, height: u32) -> usize {
+        width.div_ceil(4) as usize * height.div_ceil(4) as usize * self.block_size()
+    }
+}
+
+/// Decodes a `width` x `height` image; partial edge blocks are cropped.
+pub fn decode_dxt(data: &[u8], width: u32, height: u32, format: DxtFormat) -> Result<RgbaImage, MpqArchiveError> {
+    check_dimensions(width, height)?;
+    let needed = format.data_size(width, height);
+    if data.len() < needed {
+        return Err(MpqArchiveError::Corrupted(format!("{:?} data holds {} bytes, {} needed", format, data.len(), needed)));
+    }
+    let mut image = RgbaImage::new(width, height)?;
+    let blocks_wide = width.div_ceil(4) as usize;
+    for (index, block) in data[..needed].chunks_exact(format.block_size()).enumerate() {
+        let (color, alpha) = match format {
+            DxtFormat::Dxt1 => (block, None),
+            DxtFormat::Dxt3 => (&block[8..], Some(explicit_alpha(&block[..8]))),
+            DxtFormat::Dxt5 => (&block[8..], Some(interpolated_alpha(&block[..8]))),
+        };
+        let texels = color_block(color, format == DxtFormat::Dxt1);
+        let (block_x, block_y) = ((index % blocks_wide) * 4, (index / blocks_wide) * 4);
+        for texel in 0..16 {
+            let (x, y) = (block_x + texel % 4, block_y + texel / 4);
+            if x >= width as usize || y >= height as usize {
+                continue;
+            }
+            let mut pixel = texels[texel];
+            if let Some(alpha) = &alpha {
+                pixel[3] = alpha[texel];
+            }
+            let at = (y * width as usize + x) * 4;
+            image.pixels[at..at + 4].copy_from_slice(&pixel);
+        }
+    }
+    Ok(image)
+}
+
+/// The 16 texels of a color block. DXT1 switches to three colors plus transparent black when `c0 <= c1`.
+fn color_block(block: &[u8], dxt1: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let mix = |wa: u32, wb: u32, total: u32| -> [u8; 4] {
+        let channel = |i: usize| ((a[i] as u32 * wa + b[i] as u32 * wb) / total) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    };
+    let palette = if c0 > c1 || !dxt1 {
+        [a, b, mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [a, b, mix(1, 1, 2), [0, 0, 0, 0]]
+    };
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|texel| palette[(indices >> (texel * 2)) as usize & 3])
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let (r, g, b) = ((color >> 11) & 0x1F, (color >> 5) & 0x3F, color & 0x1F);
+    [(r * 255 / 31) as u8, (g * 255 / 63) as u8, (b * 255 / 31) as u8, 255]
+}
+
+fn explicit_alpha(block: &[u8]) -> [u8; 16] {
+    std::array::from_fn(|texel| ((block[texel / 2] >> ((texel % 2) * 4)) & 0x0F) * 17)
+}
+
+/// Two endpoints, then 3-bit indices into either 8 interpolated values or 6 plus 0 and 255.
+fn interpolated_alpha(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let mut values = [0u8; 8];
+    values[0] = a0 as u8;
+    values[1] = a1 as u8;
+    if a0 > a1 {
+        for i in 1..7 {
+            values[i + 1] = (((7 - i) as u32 * a0 + i as u32 * a1) / 7) as u8;
+        }
+    } else {
+        for i in 1..5 {
+            values[i + 1] = (((5 - i) as u32 * a0 + i as u32 * a1) / 5) as u8;
+        }
+        values[6] = 0;
+        values[7] = 255;
+    }
+    let bits = block[2..8].iter().rev().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    std::array::from_fn(|texel| values[(bits >> (texel * 3)) as usize & 7])
+}
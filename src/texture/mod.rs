@@ -0,0 +1,53 @@
+//! Texture decoding (BLP, DDS, TGA) to RGBA and PNG encoding, for converted views of game textures.
+
+pub mod blp;
+pub mod dds;
+pub mod dxt;
+pub mod png;
+pub mod tga;
+
+use crate::archive::MpqArchiveError;
+
+/// Largest width or height decoded; anything bigger is taken for a corrupted header.
+pub const MAX_DIMENSION: u32 = 16384;
+
+/// Pixel count of a `width` x `height` image, or an error when a side exceeds [`MAX_DIMENSION`].
+pub fn check_dimensions(width: u32, height: u32) -> Result<usize, MpqArchiveError> {
+    if width > MAX_DIMENSION || height > MAX_DIMENSION {
+        return Err(MpqArchiveError::Corrupted(format!("image of {}x{} pixels exceeds {}x{}", width, height, MAX_DIMENSION, MAX_DIMENSION)));
+    }
+    (width as usize)
+        .checked_mul(height as usize)
+        .filter(|count| count.checked_mul(4).is_some())
+        .ok_or_else(|| MpqArchiveError::Corrupted(format!("image of {}x{} pixels is too large", width, height)))
+}
+
+/// 8-bit RGBA pixels, row by row from the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// A transparent black image; fails on dimensions [`check_dimensions`] rejects or when the pixels cannot be
+    /// allocated.
+    pub fn new(width: u32, height: u32) -> Result<Self, MpqArchiveError> {
+        let len = check_dimensions(width, height)? * 4;
+        let mut pixels = Vec::new();
+        pixels
+            .try_reserve_exact(len)
+            .map_err(|_| MpqArchiveError::Io(std::io::Error::new(std::io::ErrorKind::OutOfMemory, format!("cannot allocate a {}x{} image", width, height))))?;
+        pixels.resize(len, 0);
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Builds an image from BGRA pixels, the order Blizzard formats store them in.
+    pub fn from_bgra(width: u32, height: u32, mut pixels: Vec<u8>) -> Self {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        Self { width, height, pixels }
+    }
+}
@@ -0,0 +1,43 @@
+//! Minimal PNG writer: 8-bit RGBA, no filtering, zlib compressed.
+
+use crate::texture::RgbaImage;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use std::io::Write;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+const COLOR_TYPE_RGBA: u8 = 6;
+
+pub fn encode_png(image: &RgbaImage) -> Vec<u8> {
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend(image.width.to_be_bytes());
+    ihdr.extend(image.height.to_be_bytes());
+    // Bit depth, color type, compression, filter and interlace methods.
+    ihdr.extend([8, COLOR_TYPE_RGBA, 0, 0, 0]);
+
+    // Each row starts with its filter type (0, none).
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let row_len = image.width as usize * 4;
+    if row_len > 0 {
+        for row in image.pixels.chunks_exact(row_len) {
+            let _ = encoder.write_all(&[0]);
+            let _ = encoder.write_all(row);
+        }
+    }
+    let idat = encoder.finish().unwrap_or_default();
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &idat);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32fast::hash(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
@@ -0,0 +1,75 @@
+//! Truevision TGA images: true-color (24/32-bit) and grayscale, raw or run-length encoded.
+
+use crate::archive::MpqArchiveError;
+use crate::texture::{RgbaImage, check_dimensions};
+
+const TGA_HEADER_SIZE: usize = 18;
+const TGA_TRUE_COLOR: u8 = 2;
+const TGA_GRAYSCALE: u8 = 3;
+const TGA_RLE: u8 = 8;
+/// Descriptor bit set when rows are stored top to bottom.
+const TGA_TOP_ORIGIN: u8 = 0x20;
+/// Pixels one run-length packet expands to at most.
+const TGA_MAX_RUN: usize = 128;
+
+pub fn decode_tga(data: &[u8]) -> Result<RgbaImage, MpqArchiveError> {
+    if data.len() < TGA_HEADER_SIZE {
+        return Err(MpqArchiveError::Corrupted("truncated TGA header".into()));
+    }
+    let (id_length, color_map_type, image_type) = (data[0] as usize, data[1], data[2]);
+    let color_map_size = if color_map_type != 0 { u16::from_le_bytes([data[5], data[6]]) as usize * (data[7] as usize).div_ceil(8) } else { 0 };
+    let width = u16::from_le_bytes([data[12], data[13]]) as u32;
+    let height = u16::from_le_bytes([data[14], data[15]]) as u32;
+    let (bits, descriptor) = (data[16], data[17]);
+
+    let bytes_per_pixel = match (image_type & !TGA_RLE, bits) {
+        (TGA_TRUE_COLOR, 24 | 32) | (TGA_GRAYSCALE, 8) => bits as usize / 8,
+        _ => return Err(MpqArchiveError::Corrupted(format!("unsupported TGA image type {} with {} bits per pixel", image_type, bits))),
+    };
+    let start = TGA_HEADER_SIZE + id_length + color_map_size;
+    let stored = data.get(start..).unwrap_or(&[]);
+    let pixel_count = check_dimensions(width, height)?;
+    let raw = if image_type & TGA_RLE != 0 { unpack_rle(stored, bytes_per_pixel, pixel_count) } else { stored.to_vec() };
+    if raw.len() < pixel_count * bytes_per_pixel {
+        return Err(MpqArchiveError::Corrupted("truncated TGA pixel data".into()));
+    }
+
+    let mut image = RgbaImage::new(width, height)?;
+    for (index, pixel) in raw.chunks_exact(bytes_per_pixel).take(pixel_count).enumerate() {
+        let (x, y) = (index % width as usize, index / width as usize);
+        let row = if descriptor & TGA_TOP_ORIGIN != 0 { y } else { height as usize - 1 - y };
+        let rgba = match *pixel {
+            [gray] => [gray, gray, gray, 255],
+            [b, g, r] => [r, g, b, 255],
+            [b, g, r, a] => [r, g, b, a],
+            _ => unreachable!(),
+        };
+        let at = (row * width as usize + x) * 4;
+        image.pixels[at..at + 4].copy_from_slice(&rgba);
+    }
+    Ok(image)
+}
+
+/// Expands run-length packets: a count byte (high bit set for a repeated pixel) followed by pixel data.
+/// Reserves no more than the packets can expand to, whatever pixel count the header claims.
+fn unpack_rle(data: &[u8], bytes_per_pixel: usize, pixel_count: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity((pixel_count * bytes_per_pixel).min(data.len() * TGA_MAX_RUN));
+    let mut pos = 0;
+    while out.len() < pixel_count * bytes_per_pixel && pos < data.len() {
+        let header = data[pos];
+        let count = (header & 0x7F) as usize + 1;
+        pos += 1;
+        if header & 0x80 != 0 {
+            let Some(pixel) = data.get(pos..pos + bytes_per_pixel) else { break };
+            for _ in 0..count {
+                out.extend_from_slice(pixel);
+            }
+            pos += bytes_per_pixel;
+        } else {
+            let Some(pixels) = data.get(pos..pos + count * bytes_per_pixel) else { break };
+            out.extend_from_slice(pixels);
+            pos += count * bytes_per_pixel;
+        }
+    }
+    out
+}
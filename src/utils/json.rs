@@ -0,0 +1,182 @@
+//! Minimal JSON writer for machine-readable reports (no parsing).
+
+use std::fmt::{self, Display, Formatter, Write as _};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Keys keep their insertion order.
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn object() -> Self {
+        JsonValue::Object(Vec::new())
+    }
+
+    /// Builder-style insert for objects; ignored for other values.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<JsonValue>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<JsonValue>) {
+        if let JsonValue::Object(fields) = self {
+            fields.push((key.into(), value.into()));
+        }
+    }
+
+    /// Indented rendering (two spaces), for files meant to be read by people as well.
+    pub fn to_pretty(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out, Some(0));
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: Option<usize>) {
+        match self {
+            JsonValue::Null => out.push_str("null"),
+            JsonValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            JsonValue::Int(n) => {
+                let _ = write!(out, "{}", n);
+            }
+            JsonValue::UInt(n) => {
+                let _ = write!(out, "{}", n);
+            }
+            JsonValue::Float(f) if f.is_finite() => {
+                let _ = write!(out, "{}", f);
+            }
+            JsonValue::Float(_) => out.push_str("null"),
+            JsonValue::String(s) => write_string(out, s),
+            JsonValue::Array(items) => {
+                write_list(out, indent, '[', ']', items.iter(), |out, item, indent| item.write(out, indent));
+            }
+            JsonValue::Object(fields) => {
+                write_list(out, indent, '{', '}', fields.iter(), |out, (key, value), indent| {
+                    write_string(out, key);
+                    out.push_str(if indent.is_some() { ": " } else { ":" });
+                    value.write(out, indent);
+                });
+            }
+        }
+    }
+}
+
+fn write_list<T>(out: &mut String, indent: Option<usize>, open: char, close: char, items: impl ExactSizeIterator<Item = T>, mut write_item: impl FnMut(&mut String, T, Option<usize>)) {
+    out.push(open);
+    let empty = items.len() == 0;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(level) = indent {
+            out.push('\n');
+            out.push_str(&"  ".repeat(level + 1));
+        }
+        write_item(out, item, indent.map(|level| level + 1));
+    }
+    if let (Some(level), false) = (indent, empty) {
+        out.push('\n');
+        out.push_str(&"  ".repeat(level));
+    }
+    out.push(close);
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Compact rendering.
+impl Display for JsonValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out, None);
+        f.write_str(&out)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(value: bool) -> Self {
+        JsonValue::Bool(value)
+    }
+}
+
+macro_rules! json_from_unsigned {
+    ($($t:ty),*) => {$(
+        impl From<$t> for JsonValue {
+            fn from(value: $t) -> Self {
+                JsonValue::UInt(value as u64)
+            }
+        }
+    )*};
+}
+
+macro_rules! json_from_signed {
+    ($($t:ty),*) => {$(
+        impl From<$t> for JsonValue {
+            fn from(value: $t) -> Self {
+                JsonValue::Int(value as i64)
+            }
+        }
+    )*};
+}
+
+json_from_unsigned!(u8, u16, u32, u64, usize);
+json_from_signed!(i8, i16, i32, i64);
+
+impl From<f32> for JsonValue {
+    /// Keeps the shortest decimal form of the `f32` (0.1, not 0.10000000149011612).
+    fn from(value: f32) -> Self {
+        JsonValue::Float(value.to_string().parse().unwrap_or(value as f64))
+    }
+}
+
+impl From<f64> for JsonValue {
+    fn from(value: f64) -> Self {
+        JsonValue::Float(value)
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(value: &str) -> Self {
+        JsonValue::String(value.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(value: String) -> Self {
+        JsonValue::String(value)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Vec<T>> for JsonValue {
+    fn from(values: Vec<T>) -> Self {
+        JsonValue::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map(Into::into).unwrap_or(JsonValue::Null)
+    }
+}
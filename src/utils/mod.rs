@@ -1,2 +1,4 @@
 // Utility modules
-// (empty for now - add utilities as needed)
+pub mod json;
+pub mod md5;
+pub mod wildcard;
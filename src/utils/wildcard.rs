@@ -0,0 +1,46 @@
+//! Case-insensitive wildcard matching of archive paths.
+//!
+//! `?` matches one character and `*` any run within a path segment; `**` also crosses segments.
+//! `\` and `/` are interchangeable, as MPQ names use backslashes.
+
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().map(normalize).collect();
+    let name: Vec<char> = name.chars().map(normalize).collect();
+    matches_from(&pattern, &name)
+}
+
+/// True when `pattern` contains wildcard characters (a plain path otherwise).
+pub fn has_wildcards(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+fn normalize(c: char) -> char {
+    if c == '/' { '\\' } else { c.to_ascii_lowercase() }
+}
+
+fn matches_from(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => {
+            let (crosses, rest) = match rest.split_first() {
+                Some(('*', after)) => (true, after),
+                _ => (false, rest),
+            };
+            // `**\` may also match no directory at all.
+            if crosses && rest.first() == Some(&'\\') && matches_from(&rest[1..], name) {
+                return true;
+            }
+            for skip in 0..=name.len() {
+                if matches_from(rest, &name[skip..]) {
+                    return true;
+                }
+                if skip < name.len() && name[skip] == '\\' && !crosses {
+                    return false;
+                }
+            }
+            false
+        }
+        Some(('?', rest)) => name.first().is_some_and(|&c| c != '\\') && matches_from(rest, &name[1..]),
+        Some((&c, rest)) => name.first() == Some(&c) && matches_from(rest, &name[1..]),
+    }
+}
@@ -0,0 +1,139 @@
+//! `war3map.imp`: the manifest of files imported into a map, checked against what the archive holds.
+
+use crate::archive::recovery::KNOWN_MAP_FILES;
+use crate::archive::{MpqArchive, MpqArchiveError};
+use crate::utils::json::JsonValue;
+use crate::war3::BinaryReader;
+use std::collections::{HashMap, HashSet};
+
+pub const IMP_FILE_NAME: &str = "war3map.imp";
+/// Folder the World Editor stores imports in unless they are given a custom path.
+pub const IMPORTED_DIR: &str = "war3mapImported\\";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEntry {
+    /// 10 or 13 for a custom path; other values place the file under [`IMPORTED_DIR`].
+    pub flag: u8,
+    pub path: String,
+}
+
+impl ImportEntry {
+    pub fn has_custom_path(&self) -> bool {
+        matches!(self.flag, 10 | 13)
+    }
+
+    /// Name the file should have in the archive.
+    pub fn archive_path(&self) -> String {
+        let path = self.path.replace('/', "\\");
+        if self.has_custom_path() || path.to_ascii_lowercase().starts_with(&IMPORTED_DIR.to_ascii_lowercase()) {
+            path
+        } else {
+            format!("{}{}", IMPORTED_DIR, path)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportManifest {
+    pub version: u32,
+    pub entries: Vec<ImportEntry>,
+}
+
+impl ImportManifest {
+    /// Version 0 manifests have no path flag; their entries read as standard paths.
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let mut reader = BinaryReader::new(bytes, IMP_FILE_NAME);
+        let version = reader.u32()?;
+        let count = reader.u32()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let flag = if version >= 1 { reader.u8()? } else { 0 };
+            entries.push(ImportEntry { flag, path: reader.string()? });
+        }
+        Ok(Self { version, entries })
+    }
+}
+
+/// Differences between the manifest and the archive's named files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub listed: usize,
+    /// Listed paths with no file anywhere in the archive.
+    pub missing: Vec<String>,
+    /// Files that are neither listed nor standard map files.
+    pub orphaned: Vec<String>,
+    /// `(listed, actual)`: listed files found under another path, which the game will not look up.
+    pub mismatched: Vec<(String, String)>,
+}
+
+impl ImportReport {
+    /// Matches each entry to a file by its archive path (ignoring case and slash direction, as MPQ lookups do), then
+    /// by the same path with or without [`IMPORTED_DIR`], then by a unique file name elsewhere.
+    pub fn new(manifest: &ImportManifest, names: &[String]) -> Self {
+        let key = |path: &str| path.replace('/', "\\").to_ascii_lowercase();
+        let by_path: HashMap<String, &String> = names.iter().map(|name| (key(name), name)).collect();
+        let mut by_file_name: HashMap<String, Vec<&String>> = HashMap::new();
+        for name in names {
+            let file_name = key(name).rsplit('\\').next().unwrap_or_default().to_string();
+            by_file_name.entry(file_name).or_default().push(name);
+        }
+        let prefix = key(IMPORTED_DIR);
+
+        let mut report = Self { listed: manifest.entries.len(), ..Self::default() };
+        let mut accounted: HashSet<String> = HashSet::new();
+        for entry in &manifest.entries {
+            let expected = entry.archive_path();
+            let expected_key = key(&expected);
+            if by_path.contains_key(&expected_key) {
+                accounted.insert(expected_key);
+                continue;
+            }
+            let alternate = match expected_key.strip_prefix(&prefix) {
+                Some(bare) => bare.to_string(),
+                None => format!("{}{}", prefix, expected_key),
+            };
+            let file_name = expected_key.rsplit('\\').next().unwrap_or_default();
+            let actual = by_path.get(&alternate).copied().or_else(|| match by_file_name.get(file_name).map(Vec::as_slice) {
+                Some([only]) => Some(*only),
+                _ => None,
+            });
+            match actual {
+                Some(actual) => {
+                    accounted.insert(key(actual));
+                    report.mismatched.push((expected, actual.clone()));
+                }
+                None => report.missing.push(expected),
+            }
+        }
+
+        let standard: HashSet<String> = KNOWN_MAP_FILES.iter().map(|name| key(name)).collect();
+        report.orphaned = names
+            .iter()
+            .filter(|name| !accounted.contains(&key(name)) && !standard.contains(&key(name)) && !name.starts_with('('))
+            .cloned()
+            .collect();
+        report
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty() && self.mismatched.is_empty()
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mismatched = self.mismatched.iter().map(|(listed, actual)| JsonValue::object().with("listed", listed.as_str()).with("actual", actual.as_str()));
+        JsonValue::object()
+            .with("ok", self.is_ok())
+            .with("listed", self.listed)
+            .with("missing", self.missing.clone())
+            .with("orphaned", self.orphaned.clone())
+            .with("mismatched", JsonValue::Array(mismatched.collect()))
+    }
+}
+
+impl MpqArchive {
+    /// Reconciles `war3map.imp` with the named files; `None` when the map has no manifest.
+    pub fn check_imports(&self) -> Option<Result<ImportReport, MpqArchiveError>> {
+        let bytes = self.read_file(IMP_FILE_NAME).ok()?;
+        Some(ImportManifest::parse(&bytes).map(|manifest| ImportReport::new(&manifest, &self.file_names())))
+    }
+}
@@ -0,0 +1,365 @@
+//! `war3map.j`: the map's JASS script. A tokenizer, a pretty-printer for minified or obfuscated scripts and an
+//! index of the declared types, globals, natives and functions.
+
+use crate::utils::json::JsonValue;
+
+/// Where maps keep their script; the World Editor writes the first, some tools the second.
+pub const JASS_SCRIPT_PATHS: [&str; 2] = ["war3map.j", "scripts\\war3map.j"];
+/// Name of the pretty-printed copy listed next to the script.
+pub const PRETTY_JASS_FILE_NAME: &str = "war3map.pretty.j";
+
+const KEYWORDS: [&str; 31] = [
+    "and", "array", "call", "constant", "debug", "else", "elseif", "endfunction", "endglobals", "endif", "endloop", "exitwhen",
+    "extends", "false", "function", "globals", "if", "local", "loop", "native", "not", "nothing", "null", "or", "return",
+    "returns", "set", "takes", "then", "true", "type",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Identifier,
+    Keyword,
+    Integer,
+    Real,
+    /// `"..."`, with its quotes.
+    String,
+    /// `'hfoo'`, with its quotes.
+    RawCode,
+    Operator,
+    /// `// ...` up to the end of the line.
+    Comment,
+    Newline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// 1-based line of the token in the source.
+    pub line: u32,
+}
+
+/// Splits a script into tokens. Whitespace is dropped; characters that fit no token become one-character operators.
+pub fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let (mut pos, mut line) = (0, 1);
+    while pos < bytes.len() {
+        let start = pos;
+        let kind = match bytes[pos] {
+            b'\r' | b'\n' => {
+                pos += if bytes[pos] == b'\r' && bytes.get(pos + 1) == Some(&b'\n') { 2 } else { 1 };
+                tokens.push(Token { kind: TokenKind::Newline, text: &source[start..pos], line });
+                line += 1;
+                continue;
+            }
+            b' ' | b'\t' | 0x0B | 0x0C => {
+                pos += 1;
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                pos += bytes[pos..].iter().position(|&b| b == b'\r' || b == b'\n').unwrap_or(bytes.len() - pos);
+                TokenKind::Comment
+            }
+            quote @ (b'"' | b'\'') => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != quote {
+                    if bytes[pos] == b'\\' {
+                        pos += 1;
+                    }
+                    if bytes.get(pos) == Some(&b'\n') {
+                        line += 1;
+                    }
+                    pos += 1;
+                }
+                pos = (pos + 1).min(bytes.len());
+                if quote == b'"' { TokenKind::String } else { TokenKind::RawCode }
+            }
+            b'0'..=b'9' | b'$' | b'.' if bytes[pos] != b'.' || bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) => {
+                let hex = bytes[pos] == b'$' || (bytes[pos] == b'0' && matches!(bytes.get(pos + 1), Some(b'x' | b'X')));
+                pos += if bytes[pos] == b'$' { 1 } else if hex { 2 } else { 0 };
+                let mut real = false;
+                while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || (!hex && bytes[pos] == b'.')) {
+                    real |= bytes[pos] == b'.';
+                    pos += 1;
+                }
+                if real { TokenKind::Real } else { TokenKind::Integer }
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 => {
+                while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_' || bytes[pos] >= 0x80) {
+                    pos += 1;
+                }
+                if is_keyword(&source[start..pos]) { TokenKind::Keyword } else { TokenKind::Identifier }
+            }
+            b'=' | b'!' | b'<' | b'>' if bytes.get(pos + 1) == Some(&b'=') => {
+                pos += 2;
+                TokenKind::Operator
+            }
+            _ => {
+                pos += source[pos..].chars().next().map_or(1, char::len_utf8);
+                TokenKind::Operator
+            }
+        };
+        tokens.push(Token { kind, text: &source[start..pos], line });
+    }
+    tokens
+}
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.contains(&word)
+}
+
+/// Tokens of each source line, without the newlines.
+fn lines<'a>(tokens: &'a [Token<'a>]) -> impl Iterator<Item = &'a [Token<'a>]> {
+    tokens.split(|token| token.kind == TokenKind::Newline)
+}
+
+/// First keyword of a statement, skipping a leading `debug` or `constant`.
+fn statement_keyword<'a>(line: &[Token<'a>]) -> Option<&'a str> {
+    let mut words = line.iter().filter(|token| token.kind != TokenKind::Comment).map(|token| token.text);
+    let first = words.next()?;
+    match first {
+        "debug" | "constant" => words.next().or(Some(first)),
+        _ => Some(first),
+    }
+}
+
+/// Re-indents the script (four spaces per block), puts one space between tokens where JASS reads best, keeps single
+/// blank lines and separates top-level blocks with one. Lines end with CRLF, as the World Editor writes them.
+pub fn prettify(source: &str) -> String {
+    let tokens = tokenize(source.trim_start_matches('\u{feff}'));
+    let mut out = String::with_capacity(source.len() + source.len() / 4);
+    let mut depth = 0usize;
+    let mut blank = true;
+    // Comments right above a function stay attached to it.
+    let mut comment = false;
+    for line in lines(&tokens) {
+        if line.is_empty() {
+            if !blank {
+                out.push_str("\r\n");
+                blank = true;
+            }
+            continue;
+        }
+        let keyword = statement_keyword(line);
+        let opens_top_level = depth == 0 && matches!(keyword, Some("function" | "globals"));
+        if opens_top_level && !blank && !comment {
+            out.push_str("\r\n");
+        }
+        if matches!(keyword, Some("endfunction" | "endglobals" | "endif" | "endloop" | "else" | "elseif")) {
+            depth = depth.saturating_sub(1);
+        }
+        out.push_str(&"    ".repeat(depth));
+        out.push_str(&join_tokens(line));
+        out.push_str("\r\n");
+        blank = false;
+        comment = keyword.is_none();
+        match keyword {
+            Some("function" | "globals" | "if" | "loop" | "else" | "elseif") => depth += 1,
+            Some("endfunction" | "endglobals") if depth == 0 => {
+                out.push_str("\r\n");
+                blank = true;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// One line of tokens with JASS spacing: none inside brackets or before the `(` of a call, one after commas and
+/// around binary operators, none after a sign.
+fn join_tokens(line: &[Token]) -> String {
+    let mut text = String::new();
+    for (at, token) in line.iter().enumerate() {
+        if at > 0 {
+            let previous = &line[at - 1];
+            let space = match (previous.text, token.text) {
+                _ if token.kind == TokenKind::Comment => true,
+                (_, ")" | "]" | ",") | ("(" | "[", _) => false,
+                (_, "(" | "[") => previous.kind != TokenKind::Identifier,
+                ("-" | "+", _) => at >= 2 && !is_unary_position(&line[at - 2]),
+                _ => true,
+            };
+            if space {
+                text.push(' ');
+            }
+        }
+        text.push_str(token.text);
+    }
+    text
+}
+
+/// Whether a `-`/`+` after `token` is a sign rather than a binary operator.
+fn is_unary_position(token: &Token) -> bool {
+    match token.kind {
+        TokenKind::Operator => !matches!(token.text, ")" | "]"),
+        TokenKind::Keyword => !matches!(token.text, "true" | "false" | "null"),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JassParameter {
+    pub type_name: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JassType {
+    pub name: String,
+    pub parent: String,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JassGlobal {
+    pub name: String,
+    pub type_name: String,
+    pub constant: bool,
+    pub array: bool,
+    pub line: u32,
+}
+
+/// A native or function signature; natives have no body, so their `end_line` is their `line`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JassFunction {
+    pub name: String,
+    pub parameters: Vec<JassParameter>,
+    /// `nothing` for functions without a result.
+    pub returns: String,
+    pub constant: bool,
+    pub line: u32,
+    pub end_line: u32,
+}
+
+/// Declarations of a script, in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JassIndex {
+    pub types: Vec<JassType>,
+    pub globals: Vec<JassGlobal>,
+    pub natives: Vec<JassFunction>,
+    pub functions: Vec<JassFunction>,
+}
+
+impl JassIndex {
+    /// Reads the top-level declarations; statements inside functions are not checked, and malformed declarations
+    /// are skipped.
+    pub fn parse(source: &str) -> Self {
+        let tokens = tokenize(source);
+        let mut index = Self::default();
+        let mut in_globals = false;
+        let mut open_function: Option<JassFunction> = None;
+        for line in lines(&tokens) {
+            let code: Vec<&Token> = line.iter().filter(|token| token.kind != TokenKind::Comment).collect();
+            let Some(first) = code.first() else { continue };
+            let words: Vec<&str> = code.iter().map(|token| token.text).collect();
+            let constant = words[0] == "constant";
+            let rest = &words[constant as usize..];
+
+            if let Some(function) = open_function.as_mut() {
+                if rest.first() == Some(&"endfunction") {
+                    function.end_line = first.line;
+                    index.functions.extend(open_function.take());
+                }
+                continue;
+            }
+            if in_globals {
+                match rest {
+                    ["endglobals", ..] => in_globals = false,
+                    [type_name, "array", name, ..] | [type_name, name, ..] if is_name(name) => index.globals.push(JassGlobal {
+                        name: name.to_string(),
+                        type_name: type_name.to_string(),
+                        constant,
+                        array: words.contains(&"array"),
+                        line: first.line,
+                    }),
+                    _ => {}
+                }
+                continue;
+            }
+            match rest {
+                ["globals", ..] => in_globals = true,
+                ["type", name, "extends", parent, ..] => index.types.push(JassType { name: name.to_string(), parent: parent.to_string(), line: first.line }),
+                ["native" | "function", ..] => {
+                    let Some(signature) = signature(&rest[1..], constant, first.line) else { continue };
+                    if rest[0] == "native" {
+                        index.natives.push(signature);
+                    } else {
+                        open_function = Some(signature);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // A function cut off by the end of the script still lists.
+        index.functions.extend(open_function);
+        index
+    }
+
+    pub fn function(&self, name: &str) -> Option<&JassFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let functions = |functions: &[JassFunction]| {
+            JsonValue::Array(functions.iter().map(|function| {
+                let parameters = function.parameters.iter().map(|parameter| {
+                    JsonValue::object().with("type", parameter.type_name.as_str()).with("name", parameter.name.as_str())
+                });
+                JsonValue::object()
+                    .with("name", function.name.as_str())
+                    .with("parameters", JsonValue::Array(parameters.collect()))
+                    .with("returns", function.returns.as_str())
+                    .with("constant", function.constant)
+                    .with("line", function.line)
+                    .with("end_line", function.end_line)
+            }).collect())
+        };
+        JsonValue::object()
+            .with("types", JsonValue::Array(self.types.iter().map(|ty| {
+                JsonValue::object().with("name", ty.name.as_str()).with("extends", ty.parent.as_str()).with("line", ty.line)
+            }).collect()))
+            .with("globals", JsonValue::Array(self.globals.iter().map(|global| {
+                JsonValue::object()
+                    .with("name", global.name.as_str())
+                    .with("type", global.type_name.as_str())
+                    .with("constant", global.constant)
+                    .with("array", global.array)
+                    .with("line", global.line)
+            }).collect()))
+            .with("natives", functions(&self.natives))
+            .with("functions", functions(&self.functions))
+    }
+}
+
+/// `Name takes <nothing | type name, ...> returns <type>`.
+fn signature(words: &[&str], constant: bool, line: u32) -> Option<JassFunction> {
+    let [name, "takes", rest @ ..] = words else { return None };
+    let returns_at = rest.iter().position(|&word| word == "returns")?;
+    let parameters = rest[..returns_at]
+        .split(|&word| word == ",")
+        .filter_map(|parameter| match parameter {
+            [type_name, name] => Some(JassParameter { type_name: type_name.to_string(), name: name.to_string() }),
+            _ => None,
+        })
+        .collect();
+    Some(JassFunction {
+        name: name.to_string(),
+        parameters,
+        returns: rest.get(returns_at + 1)?.to_string(),
+        constant,
+        line,
+        end_line: line,
+    })
+}
+
+fn is_name(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_alphabetic() || c == '_') && !is_keyword(word)
+}
+
+/// Path of the pretty-printed copy of a script at `path`: `scripts\war3map.j` gives `scripts\war3map.pretty.j`.
+pub fn pretty_script_path(path: &str) -> String {
+    match path.rsplit_once(['\\', '/']) {
+        Some((directory, _)) => format!("{}\\{}", directory, PRETTY_JASS_FILE_NAME),
+        None => PRETTY_JASS_FILE_NAME.to_string(),
+    }
+}
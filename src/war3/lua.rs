@@ -0,0 +1,253 @@
+//! `war3map.lua`: the script of 1.31+ maps saved with Lua. An inventory of the functions and globals it declares and
+//! of the `FourCC` ids it uses.
+
+use crate::utils::json::JsonValue;
+
+/// Where maps keep a Lua script.
+pub const LUA_SCRIPT_PATHS: [&str; 2] = ["war3map.lua", "scripts\\war3map.lua"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Name,
+    Number,
+    /// Quoted or long string, with its delimiters.
+    String,
+    Operator,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    line: u32,
+}
+
+/// Splits a script into tokens, dropping whitespace and comments.
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let (mut pos, mut line) = (0, 1u32);
+    while pos < bytes.len() {
+        let start = pos;
+        let start_line = line;
+        let kind = match bytes[pos] {
+            b'\n' => {
+                line += 1;
+                pos += 1;
+                continue;
+            }
+            b if b.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'-' if bytes.get(pos + 1) == Some(&b'-') => {
+                pos += 2;
+                match long_bracket_end(&bytes[pos..]) {
+                    Some(len) => pos += len,
+                    None => pos += bytes[pos..].iter().position(|&b| b == b'\n').unwrap_or(bytes.len() - pos),
+                }
+                line += source[start..pos].matches('\n').count() as u32;
+                continue;
+            }
+            b'[' if long_bracket_end(&bytes[pos..]).is_some() => {
+                pos += long_bracket_end(&bytes[pos..]).unwrap_or_default();
+                TokenKind::String
+            }
+            quote @ (b'"' | b'\'') => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != quote && bytes[pos] != b'\n' {
+                    pos += if bytes[pos] == b'\\' { 2 } else { 1 };
+                }
+                pos = (pos + 1).min(bytes.len());
+                TokenKind::String
+            }
+            b'0'..=b'9' => {
+                while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'.') {
+                    pos += 1;
+                }
+                TokenKind::Number
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 => {
+                while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || bytes[pos] == b'_' || bytes[pos] >= 0x80) {
+                    pos += 1;
+                }
+                TokenKind::Name
+            }
+            _ => {
+                let two = bytes.get(pos..pos + 2).unwrap_or_default();
+                pos += match two {
+                    b"==" | b"~=" | b"<=" | b">=" | b"//" | b"::" | b"<<" | b">>" => 2,
+                    b".." if bytes.get(pos + 2) == Some(&b'.') => 3,
+                    b".." => 2,
+                    _ => source[pos..].chars().next().map_or(1, char::len_utf8),
+                };
+                TokenKind::Operator
+            }
+        };
+        line += source[start..pos].matches('\n').count() as u32;
+        tokens.push(Token { kind, text: &source[start..pos], line: start_line });
+    }
+    tokens
+}
+
+/// Length of a long bracket (`[[ ... ]]`, `[==[ ... ]==]`) at the start of `bytes`; unterminated ones run to the end.
+fn long_bracket_end(bytes: &[u8]) -> Option<usize> {
+    let level = bytes.strip_prefix(b"[")?.iter().position(|&b| b != b'=')?;
+    if bytes.get(level + 1) != Some(&b'[') {
+        return None;
+    }
+    let close = [b"]".as_slice(), &vec![b'='; level], b"]"].concat();
+    let body = level + 2;
+    Some(bytes[body..].windows(close.len()).position(|window| window == close).map_or(bytes.len(), |at| body + at + close.len()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuaFunction {
+    /// Dotted name (`Table.field`, `Class:method`); assigned anonymous functions take the name assigned to.
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub local: bool,
+    pub line: u32,
+    pub end_line: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LuaGlobal {
+    pub name: String,
+    /// Top-level `local` rather than a true global.
+    pub local: bool,
+    pub line: u32,
+}
+
+/// Functions and top-level variables of a script, in source order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LuaIndex {
+    pub functions: Vec<LuaFunction>,
+    pub globals: Vec<LuaGlobal>,
+    /// `FourCC('xxxx')` ids with the line they appear on.
+    pub fourcc: Vec<(String, u32)>,
+}
+
+impl LuaIndex {
+    /// Block nesting is followed through `function`/`if`/`do`/`repeat` ... `end`/`until`; the rest of the grammar is
+    /// not checked. Globals are variables assigned outside any block or bracket.
+    pub fn parse(source: &str) -> Self {
+        let tokens = tokenize(source.trim_start_matches('\u{feff}'));
+        let mut index = Self::default();
+        // Block depth and, for function blocks, the function's index.
+        let mut blocks: Vec<Option<usize>> = Vec::new();
+        let mut brackets = 0usize;
+        let name = |at: usize| tokens.get(at).filter(|token| token.kind == TokenKind::Name && !is_keyword(token.text));
+        let text = |at: usize| tokens.get(at).map_or("", |token| token.text);
+
+        for (at, token) in tokens.iter().enumerate() {
+            match (token.kind, token.text) {
+                (TokenKind::Operator, "(" | "[" | "{") => brackets += 1,
+                (TokenKind::Operator, ")" | "]" | "}") => brackets = brackets.saturating_sub(1),
+                (TokenKind::Name, "if" | "do" | "repeat") => blocks.push(None),
+                (TokenKind::Name, "end" | "until") => {
+                    if let Some(Some(function)) = blocks.pop() {
+                        index.functions[function].end_line = token.line;
+                    }
+                }
+                (TokenKind::Name, "function") => {
+                    let local = at > 0 && text(at - 1) == "local";
+                    let mut end = at + 1;
+                    let mut function_name = String::new();
+                    while let Some(part) = name(end) {
+                        function_name.push_str(part.text);
+                        if !matches!(text(end + 1), "." | ":") {
+                            end += 1;
+                            break;
+                        }
+                        function_name.push_str(text(end + 1));
+                        end += 2;
+                    }
+                    let (function_name, local) = if function_name.is_empty() {
+                        // `name = function(...)` or `local name = function(...)`.
+                        match at.checked_sub(2).filter(|&before| text(at - 1) == "=" && name(before).is_some()) {
+                            Some(before) => (assigned_name(&tokens, before), before > 0 && text(before - 1) == "local"),
+                            None => (String::new(), false),
+                        }
+                    } else {
+                        (function_name, local)
+                    };
+                    let parameters = if text(end) == "(" {
+                        tokens[end + 1..].iter().take_while(|token| token.text != ")").filter(|token| token.text != ",").map(|token| token.text.to_string()).collect()
+                    } else {
+                        Vec::new()
+                    };
+                    if function_name.is_empty() {
+                        blocks.push(None);
+                    } else {
+                        index.functions.push(LuaFunction { name: function_name, parameters, local, line: token.line, end_line: token.line });
+                        blocks.push(Some(index.functions.len() - 1));
+                    }
+                }
+                (TokenKind::Name, "FourCC") if text(at + 1) == "(" => {
+                    if let Some(id) = tokens.get(at + 2).filter(|token| token.kind == TokenKind::String).and_then(|token| unquote(token.text)) {
+                        index.fourcc.push((id.to_string(), token.line));
+                    }
+                }
+                (TokenKind::Name, _) if blocks.is_empty() && brackets == 0 && text(at + 1) == "=" && !is_keyword(token.text) => {
+                    // Back to the first name of `a, b, c = ...`.
+                    let mut first = at;
+                    while first >= 2 && text(first - 1) == "," && name(first - 2).is_some() {
+                        first -= 2;
+                    }
+                    let previous = first.checked_sub(1).map_or("", text);
+                    if !matches!(previous, "." | ":") {
+                        for target in tokens[first..=at].iter().step_by(2) {
+                            if !index.globals.iter().any(|global| global.name == target.text) {
+                                index.globals.push(LuaGlobal { name: target.text.to_string(), local: previous == "local", line: target.line });
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        index
+    }
+
+    pub fn function(&self, name: &str) -> Option<&LuaFunction> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::object()
+            .with("globals", JsonValue::Array(self.globals.iter().map(|global| {
+                JsonValue::object().with("name", global.name.as_str()).with("local", global.local).with("line", global.line)
+            }).collect()))
+            .with("functions", JsonValue::Array(self.functions.iter().map(|function| {
+                JsonValue::object()
+                    .with("name", function.name.as_str())
+                    .with("parameters", JsonValue::Array(function.parameters.iter().map(|parameter| parameter.as_str().into()).collect()))
+                    .with("local", function.local)
+                    .with("line", function.line)
+                    .with("end_line", function.end_line)
+            }).collect()))
+    }
+}
+
+/// Dotted name ending at the name token `at` (`a.b.c = function` gives `a.b.c`).
+fn assigned_name(tokens: &[Token], at: usize) -> String {
+    let mut start = at;
+    while start >= 2 && tokens[start - 1].text == "." && tokens[start - 2].kind == TokenKind::Name {
+        start -= 2;
+    }
+    tokens[start..=at].iter().map(|token| token.text).collect()
+}
+
+fn unquote(text: &str) -> Option<&str> {
+    let quote = text.chars().next()?;
+    text.strip_prefix(quote)?.strip_suffix(quote)
+}
+
+fn is_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "and" | "break" | "do" | "else" | "elseif" | "end" | "false" | "for" | "function" | "goto" | "if" | "in" | "local" | "nil"
+            | "not" | "or" | "repeat" | "return" | "then" | "true" | "until" | "while"
+    )
+}
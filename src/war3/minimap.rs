@@ -0,0 +1,137 @@
+//! Map thumbnails: the author's `war3mapPreview.tga`, or the minimap (`war3mapMap.blp`/`.tga`) with the icons of
+//! `war3map.mmp` (gold mines, neutral buildings, start locations) drawn on it.
+
+use crate::archive::{MpqArchive, MpqArchiveError};
+use crate::log::log;
+use crate::texture::RgbaImage;
+use crate::texture::blp::decode_blp;
+use crate::texture::tga::decode_tga;
+use crate::war3::BinaryReader;
+
+pub const MMP_FILE_NAME: &str = "war3map.mmp";
+pub const PREVIEW_FILE_NAMES: [&str; 2] = ["war3mapPreview.tga", "war3mapPreview.blp"];
+pub const MINIMAP_FILE_NAMES: [&str; 2] = ["war3mapMap.blp", "war3mapMap.tga"];
+
+/// Icon coordinates span 0..256 whatever the minimap's size.
+const MMP_SPACE: f32 = 256.0;
+const GOLD: [u8; 4] = [255, 210, 40, 255];
+const OUTLINE: [u8; 4] = [0, 0, 0, 255];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MinimapIconKind {
+    GoldMine,
+    NeutralBuilding,
+    StartLocation,
+    Other(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinimapIcon {
+    pub kind: MinimapIconKind,
+    pub x: u32,
+    pub y: u32,
+    /// RGBA; the file stores it as BGRA.
+    pub color: [u8; 4],
+}
+
+/// Reads the icons of `war3map.mmp`.
+pub fn parse_mmp(bytes: &[u8]) -> Result<Vec<MinimapIcon>, MpqArchiveError> {
+    let mut reader = BinaryReader::new(bytes, MMP_FILE_NAME);
+    reader.u32()?;
+    let count = reader.u32()?;
+    (0..count)
+        .map(|_| {
+            let kind = match reader.u32()? {
+                0 => MinimapIconKind::GoldMine,
+                1 => MinimapIconKind::NeutralBuilding,
+                2 => MinimapIconKind::StartLocation,
+                other => MinimapIconKind::Other(other),
+            };
+            let (x, y) = (reader.u32()?, reader.u32()?);
+            let bgra = reader.bytes(4)?;
+            Ok(MinimapIcon { kind, x, y, color: [bgra[2], bgra[1], bgra[0], bgra[3]] })
+        })
+        .collect()
+}
+
+/// Draws `icons` over `image`, scaled to its size: gold squares for mines, dots for neutral buildings and crosses for
+/// start locations, in their stored color and outlined in black.
+pub fn draw_icons(image: &mut RgbaImage, icons: &[MinimapIcon]) {
+    let scale = image.width.min(image.height) as f32 / MMP_SPACE;
+    let size = ((4.0 * scale).round() as i32).max(2);
+    for icon in icons {
+        let (cx, cy) = ((icon.x as f32 * image.width as f32 / MMP_SPACE) as i32, (icon.y as f32 * image.height as f32 / MMP_SPACE) as i32);
+        let inside = |dx: i32, dy: i32| icon_covers(icon.kind, size, dx, dy);
+        let color = if icon.kind == MinimapIconKind::GoldMine { GOLD } else { [icon.color[0], icon.color[1], icon.color[2], 255] };
+        let reach = size * 2 + 1;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let pixel = if inside(dx, dy) {
+                    color
+                } else if [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|&(ox, oy)| inside(dx + ox, dy + oy)) {
+                    OUTLINE
+                } else {
+                    continue;
+                };
+                let (x, y) = (cx + dx, cy + dy);
+                if x >= 0 && y >= 0 && (x as u32) < image.width && (y as u32) < image.height {
+                    let at = (y as usize * image.width as usize + x as usize) * 4;
+                    image.pixels[at..at + 4].copy_from_slice(&pixel);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the icon shape of `kind` covers the offset `(dx, dy)` from its center.
+fn icon_covers(kind: MinimapIconKind, size: i32, dx: i32, dy: i32) -> bool {
+    match kind {
+        MinimapIconKind::GoldMine => dx.abs() <= size && dy.abs() <= size,
+        MinimapIconKind::StartLocation => {
+            (dx.abs() <= size / 3 && dy.abs() <= size * 2) || (dy.abs() <= size / 3 && dx.abs() <= size * 2)
+        }
+        _ => dx * dx + dy * dy <= size * size,
+    }
+}
+
+/// A thumbnail and where it came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapPreview {
+    pub source: &'static str,
+    pub image: RgbaImage,
+    /// Icons drawn from `war3map.mmp`; always 0 for custom previews.
+    pub icons: usize,
+}
+
+fn decode_image(name: &str, data: &[u8]) -> Result<RgbaImage, MpqArchiveError> {
+    if name.ends_with(".tga") { decode_tga(data) } else { decode_blp(data) }
+}
+
+impl MpqArchive {
+    /// The minimap with `war3map.mmp` icons drawn on it (unless `icons` is false); `None` when there is no minimap.
+    pub fn minimap(&self, icons: bool) -> Option<Result<MapPreview, MpqArchiveError>> {
+        let (source, data) = MINIMAP_FILE_NAMES.iter().find_map(|&name| Some((name, self.read_file(name).ok()?)))?;
+        let render = || {
+            let mut image = decode_image(source, &data)?;
+            let icons = match self.read_file(MMP_FILE_NAME) {
+                Ok(mmp) if icons => parse_mmp(&mmp)?,
+                _ => Vec::new(),
+            };
+            draw_icons(&mut image, &icons);
+            Ok(MapPreview { source, image, icons: icons.len() })
+        };
+        Some(render())
+    }
+
+    /// The best thumbnail: the map's custom preview when it has one that decodes, else [`MpqArchive::minimap`].
+    pub fn map_preview(&self) -> Option<Result<MapPreview, MpqArchiveError>> {
+        for name in PREVIEW_FILE_NAMES {
+            let Ok(data) = self.read_file(name) else { continue };
+            match decode_image(name, &data) {
+                Ok(image) => return Some(Ok(MapPreview { source: name, image, icons: 0 })),
+                Err(err) => log(format!("MpqArchive: cannot decode {}: {}", name, err)),
+            }
+        }
+        self.minimap(true)
+    }
+}
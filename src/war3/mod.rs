@@ -0,0 +1,84 @@
+//! Warcraft III map content stored inside the archive: map info, string tables, imports, SYLK data tables, object data, JASS and Lua scripts, terrain, minimap; and `.w3g` replays.
+
+pub mod imports;
+pub mod jass;
+pub mod lua;
+pub mod minimap;
+pub mod objects;
+pub mod replay;
+pub mod script;
+pub mod slk;
+pub mod terrain;
+pub mod w3i;
+pub mod wts;
+
+use crate::archive::MpqArchiveError;
+
+/// Little-endian cursor over a Warcraft III binary file; running out of bytes is a corruption of `file`.
+pub(crate) struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    file: &'static str,
+}
+
+impl<'a> BinaryReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], file: &'static str) -> Self {
+        Self { bytes, pos: 0, file }
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], MpqArchiveError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(MpqArchiveError::Corrupted(format!("{} truncated at offset {}", self.file, self.pos)));
+        };
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// Offset of the next byte.
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The next byte, without consuming it.
+    pub(crate) fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, MpqArchiveError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Result<u16, MpqArchiveError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, MpqArchiveError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32, MpqArchiveError> {
+        Ok(self.u32()? as i32)
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32, MpqArchiveError> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    /// NUL-terminated UTF-8 string; invalid sequences are replaced.
+    pub(crate) fn string(&mut self) -> Result<String, MpqArchiveError> {
+        Ok(String::from_utf8_lossy(self.raw_string()?).into_owned())
+    }
+
+    /// Bytes of a NUL-terminated string, without the terminator.
+    pub(crate) fn raw_string(&mut self) -> Result<&'a [u8], MpqArchiveError> {
+        let len = self.bytes[self.pos.min(self.bytes.len())..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| MpqArchiveError::Corrupted(format!("{} has an unterminated string at offset {}", self.file, self.pos)))?;
+        let text = self.bytes(len)?;
+        self.pos += 1;
+        Ok(text)
+    }
+}
@@ -0,0 +1,248 @@
+//! Object editor data (`war3map.w3u`, `.w3t`, `.w3b`, `.w3d`, `.w3a`, `.w3h`, `.w3q`): changes to standard objects and
+//! new objects derived from them, as field modifications keyed by four-character ids ("rawcodes").
+
+use crate::archive::{MpqArchive, MpqArchiveError};
+use crate::utils::json::JsonValue;
+use crate::war3::BinaryReader;
+use crate::war3::wts::StringResolver;
+
+/// Version 3 (1.33+) groups each object's modifications into sets, one per graphics mode.
+pub const OBJECT_DATA_VERSION_SETS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ObjectKind {
+    Units,
+    Items,
+    Destructables,
+    Doodads,
+    Abilities,
+    Buffs,
+    Upgrades,
+}
+
+impl ObjectKind {
+    pub const ALL: [ObjectKind; 7] = [
+        ObjectKind::Units,
+        ObjectKind::Items,
+        ObjectKind::Destructables,
+        ObjectKind::Doodads,
+        ObjectKind::Abilities,
+        ObjectKind::Buffs,
+        ObjectKind::Upgrades,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ObjectKind::Units => "war3map.w3u",
+            ObjectKind::Items => "war3map.w3t",
+            ObjectKind::Destructables => "war3map.w3b",
+            ObjectKind::Doodads => "war3map.w3d",
+            ObjectKind::Abilities => "war3map.w3a",
+            ObjectKind::Buffs => "war3map.w3h",
+            ObjectKind::Upgrades => "war3map.w3q",
+        }
+    }
+
+    /// Kind of an object data file by its extension, so `war3mapSkin.w3u` is read like `war3map.w3u`.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let (_, extension) = path.rsplit_once('.')?;
+        ObjectKind::ALL.into_iter().find(|kind| kind.file_name().rsplit_once('.').is_some_and(|(_, own)| own.eq_ignore_ascii_case(extension)))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectKind::Units => "units",
+            ObjectKind::Items => "items",
+            ObjectKind::Destructables => "destructables",
+            ObjectKind::Doodads => "doodads",
+            ObjectKind::Abilities => "abilities",
+            ObjectKind::Buffs => "buffs",
+            ObjectKind::Upgrades => "upgrades",
+        }
+    }
+
+    /// Doodads, abilities and upgrades store a level (or variation) and data column with every modification.
+    pub fn has_levels(&self) -> bool {
+        matches!(self, ObjectKind::Doodads | ObjectKind::Abilities | ObjectKind::Upgrades)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModificationValue {
+    Int(i32),
+    Real(f32),
+    /// A real limited to 0..1.
+    Unreal(f32),
+    String(String),
+}
+
+impl ModificationValue {
+    /// Strings are resolved through `strings` when they are `TRIGSTR_<id>` references.
+    fn to_json(&self, strings: &impl StringResolver) -> JsonValue {
+        match self {
+            ModificationValue::Int(value) => (*value).into(),
+            ModificationValue::Real(value) | ModificationValue::Unreal(value) => (*value).into(),
+            ModificationValue::String(value) => strings.resolve(value).into(),
+        }
+    }
+
+    /// INI value: numbers as is, strings quoted with `\`, `"` and line breaks escaped.
+    fn to_ini(&self, strings: &impl StringResolver) -> String {
+        match self {
+            ModificationValue::Int(value) => value.to_string(),
+            ModificationValue::Real(value) | ModificationValue::Unreal(value) => value.to_string(),
+            ModificationValue::String(value) => {
+                let escaped = strings.resolve(value).replace('\\', "\\\\").replace('"', "\\\"").replace('\r', "\\r").replace('\n', "\\n");
+                format!("\"{}\"", escaped)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Modification {
+    /// Field rawcode, e.g. `umvs` (movement speed).
+    pub field: String,
+    /// Level of abilities and upgrades, variation of doodads; 0 for other kinds.
+    pub level: u32,
+    /// Data column (`A`..`I` as 1..9) of ability fields; 0 for other kinds.
+    pub data_pointer: u32,
+    pub value: ModificationValue,
+}
+
+impl Modification {
+    /// Field rawcode, with `:<level>` for levelled fields: `Hbz1:2`.
+    pub fn key(&self) -> String {
+        if self.level == 0 { self.field.clone() } else { format!("{}:{}", self.field, self.level) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectDefinition {
+    /// Standard object changed, or that a new object is derived from.
+    pub base_id: String,
+    /// Id of a new object; `None` for changed standard objects.
+    pub id: Option<String>,
+    pub modifications: Vec<Modification>,
+}
+
+impl ObjectDefinition {
+    /// Id the game knows the object by.
+    pub fn effective_id(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.base_id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectData {
+    pub kind: ObjectKind,
+    pub version: u32,
+    /// Standard objects with changed fields.
+    pub original: Vec<ObjectDefinition>,
+    /// New objects.
+    pub custom: Vec<ObjectDefinition>,
+}
+
+impl ObjectData {
+    pub fn parse(kind: ObjectKind, bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let mut reader = BinaryReader::new(bytes, kind.file_name());
+        let version = reader.u32()?;
+        let original = read_table(&mut reader, kind, version)?;
+        let custom = read_table(&mut reader, kind, version)?;
+        Ok(Self { kind, version, original, custom })
+    }
+
+    /// Changed standard objects, then new objects.
+    pub fn objects(&self) -> impl Iterator<Item = &ObjectDefinition> {
+        self.original.iter().chain(&self.custom)
+    }
+
+    /// `{ kind, version, original: [{ id, fields }], custom: [{ id, base, fields }] }`, fields keyed by
+    /// [`Modification::key`].
+    pub fn to_json(&self, strings: &impl StringResolver) -> JsonValue {
+        let objects = |objects: &[ObjectDefinition]| {
+            JsonValue::Array(objects.iter().map(|object| {
+                let mut fields = JsonValue::object();
+                for modification in &object.modifications {
+                    fields.insert(modification.key(), modification.value.to_json(strings));
+                }
+                JsonValue::object()
+                    .with("id", object.effective_id())
+                    .with("base", object.id.as_ref().map(|_| object.base_id.as_str()))
+                    .with("fields", fields)
+            }).collect())
+        };
+        JsonValue::object()
+            .with("kind", self.kind.as_str())
+            .with("version", self.version)
+            .with("original", objects(&self.original))
+            .with("custom", objects(&self.custom))
+    }
+
+    /// One `[id]` section per object; new objects name their base object in a `_base` key.
+    pub fn to_ini(&self, strings: &impl StringResolver) -> String {
+        let mut ini = format!("; {}: {} changed standard object(s), {} new object(s)\r\n", self.kind.as_str(), self.original.len(), self.custom.len());
+        for object in self.objects() {
+            ini.push_str(&format!("\r\n[{}]\r\n", object.effective_id()));
+            if object.id.is_some() {
+                ini.push_str(&format!("_base={}\r\n", object.base_id));
+            }
+            for modification in &object.modifications {
+                ini.push_str(&format!("{}={}\r\n", modification.key(), modification.value.to_ini(strings)));
+            }
+        }
+        ini
+    }
+}
+
+fn read_table(reader: &mut BinaryReader, kind: ObjectKind, version: u32) -> Result<Vec<ObjectDefinition>, MpqArchiveError> {
+    let count = reader.u32()?;
+    let mut objects = Vec::new();
+    for _ in 0..count {
+        let base_id = rawcode(reader.bytes(4)?);
+        let id = reader.bytes(4)?;
+        let id = (id != [0; 4]).then(|| rawcode(id));
+        let sets = if version >= OBJECT_DATA_VERSION_SETS { reader.u32()? } else { 1 };
+        let mut modifications = Vec::new();
+        for _ in 0..sets {
+            if version >= OBJECT_DATA_VERSION_SETS {
+                reader.u32()?;
+            }
+            let modification_count = reader.u32()?;
+            for _ in 0..modification_count {
+                modifications.push(read_modification(reader, kind)?);
+            }
+        }
+        objects.push(ObjectDefinition { base_id, id, modifications });
+    }
+    Ok(objects)
+}
+
+fn read_modification(reader: &mut BinaryReader, kind: ObjectKind) -> Result<Modification, MpqArchiveError> {
+    let field = rawcode(reader.bytes(4)?);
+    let value_type = reader.u32()?;
+    let (level, data_pointer) = if kind.has_levels() { (reader.u32()?, reader.u32()?) } else { (0, 0) };
+    let value = match value_type {
+        0 => ModificationValue::Int(reader.i32()?),
+        1 => ModificationValue::Real(reader.f32()?),
+        2 => ModificationValue::Unreal(reader.f32()?),
+        3 => ModificationValue::String(reader.string()?),
+        other => return Err(MpqArchiveError::Corrupted(format!("{} has an unknown value type {} for field {}", kind.file_name(), other, field))),
+    };
+    // End marker: zero or the object id.
+    reader.bytes(4)?;
+    Ok(Modification { field, level, data_pointer, value })
+}
+
+/// Four bytes as text, one character per byte.
+pub fn rawcode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+impl MpqArchive {
+    /// Parsed object data of one kind; `None` when the map does not change that kind of object.
+    pub fn object_data(&self, kind: ObjectKind) -> Option<Result<ObjectData, MpqArchiveError>> {
+        let bytes = self.read_file(kind.file_name()).ok()?;
+        Some(ObjectData::parse(kind, &bytes))
+    }
+}
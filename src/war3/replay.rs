@@ -0,0 +1,356 @@
+//! Warcraft III replays (`.w3g`): a header, then zlib-compressed blocks holding the game settings, the players and
+//! their slots, and the recorded actions. Replays are not MPQs; they are mounted as a folder of parsed views.
+
+use crate::archive::MpqArchiveError;
+use crate::utils::json::JsonValue;
+use crate::war3::BinaryReader;
+use std::io::Read;
+use std::path::Path;
+
+pub const REPLAY_SIGNATURE: &[u8; 28] = b"Warcraft III recorded game\x1A\0";
+const REPLAY_FILE: &str = "replay";
+const REPLAY_STREAM: &str = "replay stream";
+/// Reforged (1.32+) widens the block sizes to 32 bits.
+const REFORGED_VERSION: u32 = 10032;
+
+const RECORD_HOST: u8 = 0x00;
+const RECORD_PLAYER: u8 = 0x16;
+const RECORD_GAME_START: u8 = 0x19;
+/// Reforged player metadata (a length-prefixed protobuf) between the player list and the game start record.
+const RECORD_REFORGED_METADATA: u8 = 0x39;
+
+pub const SLOT_EMPTY: u8 = 0;
+pub const SLOT_CLOSED: u8 = 1;
+pub const SLOT_USED: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayHeader {
+    pub header_size: u32,
+    pub compressed_size: u32,
+    /// 0 up to 1.06, 1 since.
+    pub header_version: u32,
+    pub decompressed_size: u32,
+    pub block_count: u32,
+    /// `WAR3` (Reign of Chaos) or `W3XP` (The Frozen Throne); always `WAR3` in v0 headers.
+    pub product: String,
+    /// Minor game version: 26 for 1.26, 10032 for 1.32.
+    pub version: u32,
+    pub build: u16,
+    /// 0x8000 for multiplayer games.
+    pub flags: u16,
+    pub length_ms: u32,
+    pub checksum: u32,
+}
+
+impl ReplayHeader {
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        if !is_replay(bytes) {
+            return Err(MpqArchiveError::Corrupted("not a Warcraft III replay".into()));
+        }
+        let mut reader = BinaryReader::new(bytes, REPLAY_FILE);
+        reader.bytes(REPLAY_SIGNATURE.len())?;
+        let header_size = reader.u32()?;
+        let compressed_size = reader.u32()?;
+        let header_version = reader.u32()?;
+        let decompressed_size = reader.u32()?;
+        let block_count = reader.u32()?;
+        let (product, version) = match header_version {
+            0 => {
+                reader.u16()?;
+                ("WAR3".to_string(), reader.u16()? as u32)
+            }
+            1 => (reader.bytes(4)?.iter().rev().map(|&b| b as char).collect(), reader.u32()?),
+            other => return Err(MpqArchiveError::Corrupted(format!("unsupported replay header version {}", other))),
+        };
+        Ok(Self { header_size, compressed_size, header_version, decompressed_size, block_count, product, version, build: reader.u16()?, flags: reader.u16()?, length_ms: reader.u32()?, checksum: reader.u32()? })
+    }
+
+    /// `1.26`, `1.32`, ...
+    pub fn version_string(&self) -> String {
+        if self.version >= 10000 { format!("1.{}", self.version - 10000) } else { format!("1.{:02}", self.version) }
+    }
+
+    pub fn is_multiplayer(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    pub fn is_reforged(&self) -> bool {
+        self.header_version >= 1 && self.version >= REFORGED_VERSION
+    }
+}
+
+/// One slot of the lobby.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplaySlot {
+    /// 0 for computers and unused slots.
+    pub player_id: u8,
+    /// Map download progress, 100 when the player had the map.
+    pub download: u8,
+    /// `SLOT_*`.
+    pub status: u8,
+    pub computer: bool,
+    /// 12 (24 in Reforged) for observers.
+    pub team: u8,
+    pub color: u8,
+    /// 0x01 human, 0x02 orc, 0x04 night elf, 0x08 undead, 0x20 random; 0x40 when the race is fixed.
+    pub race: u8,
+    /// Of computers: 0 easy, 1 normal, 2 insane.
+    pub ai_strength: u8,
+    /// 50..100.
+    pub handicap: u8,
+}
+
+impl ReplaySlot {
+    pub fn race_name(&self) -> &'static str {
+        match self.race & 0x3F {
+            0x01 => "human",
+            0x02 => "orc",
+            0x04 => "nightelf",
+            0x08 => "undead",
+            0x20 => "random",
+            _ => "unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayPlayer {
+    pub id: u8,
+    pub name: String,
+    /// From the first player record, which the format documents as the game host.
+    pub host: bool,
+}
+
+/// Game settings and lobby, from the start of the decompressed stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayGame {
+    pub name: String,
+    /// Speed (low two bits: slow, normal, fast), visibility, teams and sharing options.
+    pub settings: u32,
+    pub map_width: u16,
+    pub map_height: u16,
+    pub map_checksum: u32,
+    pub map_path: String,
+    pub host_name: String,
+    pub player_count: u32,
+    /// 0x01 ladder, 0x09 custom, 0x0D local, 0x20 ladder team.
+    pub game_type: u8,
+    pub private: bool,
+    pub language: u32,
+    pub players: Vec<ReplayPlayer>,
+    pub slots: Vec<ReplaySlot>,
+    pub random_seed: u32,
+    pub select_mode: u8,
+    pub start_spots: u8,
+}
+
+impl ReplayGame {
+    pub fn speed_name(&self) -> &'static str {
+        match self.settings & 0x03 {
+            0 => "slow",
+            1 => "normal",
+            _ => "fast",
+        }
+    }
+
+    pub fn game_type_name(&self) -> &'static str {
+        match self.game_type {
+            0x01 => "ladder",
+            0x09 => "custom",
+            0x0D => "local",
+            0x20 => "ladder team",
+            _ => "unknown",
+        }
+    }
+
+    /// Name shown for a slot: its player's, or the difficulty of a computer.
+    pub fn slot_name(&self, slot: &ReplaySlot) -> String {
+        match self.players.iter().find(|player| !slot.computer && player.id == slot.player_id) {
+            Some(player) => player.name.clone(),
+            None => format!("Computer ({})", ["Easy", "Normal", "Insane"].get(slot.ai_strength as usize).unwrap_or(&"Unknown")),
+        }
+    }
+
+    /// Parses the game records at the start of `stream`; returns them with the offset the actions start at.
+    fn parse(stream: &[u8], header: &ReplayHeader) -> Result<(Self, usize), MpqArchiveError> {
+        let mut reader = BinaryReader::new(stream, REPLAY_STREAM);
+        reader.u32()?;
+        let mut players = vec![read_player(&mut reader, RECORD_HOST)?];
+        let name = reader.string()?;
+        reader.u8()?;
+        let settings = decode_stat_string(reader.raw_string()?);
+        let mut stat = BinaryReader::new(&settings, REPLAY_STREAM);
+        let settings = stat.u32()?;
+        stat.u8()?;
+        let (map_width, map_height, map_checksum) = (stat.u16()?, stat.u16()?, stat.u32()?);
+        let (map_path, host_name) = (stat.string()?, stat.string()?);
+
+        let player_count = reader.u32()?;
+        let game_type = reader.u8()?;
+        let private = reader.u8()? != 0;
+        reader.u16()?;
+        let language = reader.u32()?;
+        loop {
+            match reader.peek() {
+                Some(RECORD_PLAYER) => {
+                    players.push(read_player(&mut reader, RECORD_PLAYER)?);
+                    reader.u32()?;
+                }
+                Some(RECORD_REFORGED_METADATA) => {
+                    reader.bytes(2)?;
+                    let len = reader.u32()? as usize;
+                    reader.bytes(len)?;
+                }
+                _ => break,
+            }
+        }
+
+        if reader.u8()? != RECORD_GAME_START {
+            return Err(MpqArchiveError::Corrupted(format!("replay stream has no game start record at offset {}", reader.position() - 1)));
+        }
+        reader.u16()?;
+        let slot_count = reader.u8()?;
+        let mut slots = Vec::with_capacity(slot_count as usize);
+        for _ in 0..slot_count {
+            let (player_id, download, status, computer, team, color, race) = (reader.u8()?, reader.u8()?, reader.u8()?, reader.u8()? != 0, reader.u8()?, reader.u8()?, reader.u8()?);
+            // AI strength since 1.03, handicap since 1.07.
+            let ai_strength = if header.header_version >= 1 || header.version >= 3 { reader.u8()? } else { 1 };
+            let handicap = if header.header_version >= 1 || header.version >= 7 { reader.u8()? } else { 100 };
+            slots.push(ReplaySlot { player_id, download, status, computer, team, color, race, ai_strength, handicap });
+        }
+        let random_seed = reader.u32()?;
+        let select_mode = reader.u8()?;
+        let start_spots = reader.u8()?;
+
+        let game = Self { name, settings, map_width, map_height, map_checksum, map_path, host_name, player_count, game_type, private, language, players, slots, random_seed, select_mode, start_spots };
+        Ok((game, reader.position()))
+    }
+}
+
+/// A player record: record id, player id, name, then extra data (runtime and race in ladder games).
+fn read_player(reader: &mut BinaryReader, record: u8) -> Result<ReplayPlayer, MpqArchiveError> {
+    let id = reader.u8()?;
+    if id != record {
+        return Err(MpqArchiveError::Corrupted(format!("replay stream has record 0x{:02X} where a player record was expected", id)));
+    }
+    let id = reader.u8()?;
+    let name = reader.string()?;
+    let extra = reader.u8()? as usize;
+    reader.bytes(extra)?;
+    Ok(ReplayPlayer { id, name, host: record == RECORD_HOST })
+}
+
+/// Undoes the encoding of the map settings string: every eighth byte holds the low bits that the seven bytes after
+/// it had set, the others are stored incremented by one (unless that bit says otherwise) so none is zero.
+fn decode_stat_string(encoded: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut mask = 0u8;
+    for (index, &byte) in encoded.iter().enumerate() {
+        if index % 8 == 0 {
+            mask = byte;
+        } else if mask & (1 << (index % 8)) == 0 {
+            decoded.push(byte.wrapping_sub(1));
+        } else {
+            decoded.push(byte);
+        }
+    }
+    decoded
+}
+
+/// A parsed replay with its decompressed stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub game: ReplayGame,
+    /// All blocks decompressed: game records, then actions.
+    pub stream: Vec<u8>,
+    actions_offset: usize,
+}
+
+impl Replay {
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let header = ReplayHeader::parse(bytes)?;
+        let mut reader = BinaryReader::new(bytes, REPLAY_FILE);
+        reader.bytes(header.header_size as usize)?;
+        let mut stream = Vec::with_capacity(header.decompressed_size as usize);
+        for index in 0..header.block_count {
+            let (compressed, decompressed) = if header.is_reforged() { (reader.u32()? as usize, reader.u32()? as usize) } else { (reader.u16()? as usize, reader.u16()? as usize) };
+            reader.u32()?;
+            let start = stream.len();
+            flate2::read::ZlibDecoder::new(reader.bytes(compressed)?)
+                .read_to_end(&mut stream)
+                .map_err(|err| MpqArchiveError::Corrupted(format!("replay block {}: {}", index, err)))?;
+            if stream.len() - start != decompressed {
+                return Err(MpqArchiveError::Corrupted(format!("replay block {} inflates to {} bytes, not {}", index, stream.len() - start, decompressed)));
+            }
+        }
+        let (game, actions_offset) = ReplayGame::parse(&stream, &header)?;
+        Ok(Self { header, game, stream, actions_offset })
+    }
+
+    /// Action and chat records after the game start record, as stored (blocks are padded with zeros at the end).
+    pub fn actions(&self) -> &[u8] {
+        &self.stream[self.actions_offset..]
+    }
+
+    /// Header and game settings.
+    pub fn header_json(&self) -> JsonValue {
+        let header = &self.header;
+        let game = &self.game;
+        let seconds = header.length_ms / 1000;
+        JsonValue::object()
+            .with("header_version", header.header_version)
+            .with("product", header.product.as_str())
+            .with("version", header.version_string())
+            .with("build", header.build)
+            .with("multiplayer", header.is_multiplayer())
+            .with("length_ms", header.length_ms)
+            .with("length", format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60))
+            .with("compressed_size", header.compressed_size)
+            .with("decompressed_size", header.decompressed_size)
+            .with("blocks", header.block_count)
+            .with("game_name", game.name.as_str())
+            .with("game_type", game.game_type_name())
+            .with("private", game.private)
+            .with("speed", game.speed_name())
+            .with("settings", game.settings)
+            .with("map", game.map_path.as_str())
+            .with("map_size", format!("{}x{}", game.map_width, game.map_height))
+            .with("map_checksum", format!("{:08X}", game.map_checksum))
+            .with("host", game.host_name.as_str())
+            .with("player_count", game.player_count)
+            .with("language", game.language)
+            .with("random_seed", game.random_seed)
+            .with("select_mode", game.select_mode)
+            .with("start_spots", game.start_spots)
+    }
+
+    /// Used slots in lobby order, each with its player's name; computers are named by difficulty.
+    pub fn players_json(&self) -> JsonValue {
+        let slots = self.game.slots.iter().enumerate().filter(|(_, slot)| slot.status == SLOT_USED).map(|(index, slot)| {
+            let host = self.game.players.iter().any(|player| player.host && !slot.computer && player.id == slot.player_id);
+            JsonValue::object()
+                .with("slot", index)
+                .with("id", (!slot.computer).then_some(slot.player_id))
+                .with("name", self.game.slot_name(slot))
+                .with("host", host)
+                .with("computer", slot.computer)
+                .with("team", slot.team)
+                .with("color", slot.color)
+                .with("race", slot.race_name())
+                .with("handicap", slot.handicap)
+        });
+        JsonValue::Array(slots.collect())
+    }
+}
+
+/// Whether `bytes` start like a replay.
+pub fn is_replay(bytes: &[u8]) -> bool {
+    bytes.starts_with(REPLAY_SIGNATURE)
+}
+
+/// Whether the file at `path` is a replay, by its signature.
+pub fn is_replay_file(path: impl AsRef<Path>) -> bool {
+    let mut signature = [0u8; REPLAY_SIGNATURE.len()];
+    std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut signature)).is_ok() && is_replay(&signature)
+}
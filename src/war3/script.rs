@@ -0,0 +1,151 @@
+//! The map script in either language, and the object ids (rawcodes) it uses cross-referenced against object data.
+
+use crate::archive::MpqArchive;
+use crate::log::log;
+use crate::utils::json::JsonValue;
+use crate::war3::jass::{JASS_SCRIPT_PATHS, JassIndex, TokenKind, tokenize};
+use crate::war3::lua::{LUA_SCRIPT_PATHS, LuaIndex};
+use crate::war3::objects::{ObjectData, ObjectKind};
+use crate::war3::w3i::ScriptLanguage;
+use crate::war3::wts::decode_text;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapScript {
+    pub language: ScriptLanguage,
+    pub path: &'static str,
+    pub source: String,
+}
+
+impl MapScript {
+    /// Types, globals, natives and functions of a JASS script; globals and functions of a Lua one.
+    pub fn declarations_json(&self) -> JsonValue {
+        match self.language {
+            ScriptLanguage::Jass => JassIndex::parse(&self.source).to_json(),
+            ScriptLanguage::Lua => LuaIndex::parse(&self.source).to_json(),
+        }
+    }
+
+    /// Four-character ids the script uses, with the lines they appear on: `'hfoo'` literals in JASS, `FourCC('hfoo')`
+    /// calls in Lua.
+    pub fn rawcodes(&self) -> BTreeMap<String, Vec<u32>> {
+        let uses: Vec<(String, u32)> = match self.language {
+            ScriptLanguage::Jass => tokenize(&self.source)
+                .into_iter()
+                .filter(|token| token.kind == TokenKind::RawCode && token.text.len() == 6)
+                .map(|token| (token.text[1..5].to_string(), token.line))
+                .collect(),
+            ScriptLanguage::Lua => LuaIndex::parse(&self.source).fourcc,
+        };
+        let mut rawcodes: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for (id, line) in uses {
+            let lines = rawcodes.entry(id).or_default();
+            if lines.last() != Some(&line) {
+                lines.push(line);
+            }
+        }
+        rawcodes
+    }
+}
+
+/// Where an id the script uses comes from in the map's object data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawcodeOrigin {
+    /// A new object.
+    Custom(ObjectKind),
+    /// A standard object the map changes.
+    Modified(ObjectKind),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawcodeUse {
+    pub id: String,
+    pub lines: Vec<u32>,
+    /// Empty for standard objects left unchanged, and for ids that are not objects at all (orders, buffs in code...).
+    pub origins: Vec<RawcodeOrigin>,
+}
+
+/// Ids used in the script against the objects the map defines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RawcodeReport {
+    pub uses: Vec<RawcodeUse>,
+    /// New objects the script never mentions; they may still be placed on the map or referenced by other objects.
+    pub unreferenced: Vec<(ObjectKind, String)>,
+}
+
+impl RawcodeReport {
+    pub fn new(rawcodes: &BTreeMap<String, Vec<u32>>, objects: &[ObjectData]) -> Self {
+        let mut origins: BTreeMap<&str, Vec<RawcodeOrigin>> = BTreeMap::new();
+        for data in objects {
+            for object in &data.original {
+                origins.entry(object.effective_id()).or_default().push(RawcodeOrigin::Modified(data.kind));
+            }
+            for object in &data.custom {
+                origins.entry(object.effective_id()).or_default().push(RawcodeOrigin::Custom(data.kind));
+            }
+        }
+        let uses = rawcodes
+            .iter()
+            .map(|(id, lines)| RawcodeUse { id: id.clone(), lines: lines.clone(), origins: origins.get(id.as_str()).cloned().unwrap_or_default() })
+            .collect();
+        let unreferenced = objects
+            .iter()
+            .flat_map(|data| data.custom.iter().map(move |object| (data.kind, object.effective_id().to_string())))
+            .filter(|(_, id)| !rawcodes.contains_key(id))
+            .collect();
+        Self { uses, unreferenced }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let uses = self.uses.iter().map(|rawcode| {
+            let origins = rawcode.origins.iter().map(|origin| {
+                let (status, kind) = match origin {
+                    RawcodeOrigin::Custom(kind) => ("custom", kind),
+                    RawcodeOrigin::Modified(kind) => ("modified", kind),
+                };
+                JsonValue::object().with("kind", kind.as_str()).with("status", status)
+            });
+            JsonValue::object()
+                .with("id", rawcode.id.as_str())
+                .with("lines", JsonValue::Array(rawcode.lines.iter().map(|&line| line.into()).collect()))
+                .with("objects", JsonValue::Array(origins.collect()))
+        });
+        let unreferenced = self.unreferenced.iter().map(|(kind, id)| JsonValue::object().with("kind", kind.as_str()).with("id", id.as_str()));
+        JsonValue::object()
+            .with("used", JsonValue::Array(uses.collect()))
+            .with("unreferenced", JsonValue::Array(unreferenced.collect()))
+    }
+}
+
+impl MpqArchive {
+    /// The map script. Its language is the one `war3map.w3i` declares (JASS before 1.31), falling back to whichever
+    /// script the archive holds; `None` when there is none.
+    pub fn map_script(&self) -> Option<MapScript> {
+        let declared = match self.map_info() {
+            Some(Ok(info)) => info.script_language,
+            _ => ScriptLanguage::Jass,
+        };
+        let jass = JASS_SCRIPT_PATHS.map(|path| (ScriptLanguage::Jass, path));
+        let lua = LUA_SCRIPT_PATHS.map(|path| (ScriptLanguage::Lua, path));
+        let candidates = if declared == ScriptLanguage::Lua { [lua, jass].concat() } else { [jass, lua].concat() };
+        let (language, path, bytes) = candidates.into_iter().find_map(|(language, path)| Some((language, path, self.read_file(path).ok()?)))?;
+        if language != declared {
+            log(format!("MpqArchive: war3map.w3i declares {} but the map script is {}", declared.as_str(), path));
+        }
+        Some(MapScript { language, path, source: decode_text(&bytes).into_owned() })
+    }
+
+    /// Object data of every kind the map changes; files that cannot be parsed are logged and left out.
+    pub fn all_object_data(&self) -> Vec<ObjectData> {
+        ObjectKind::ALL
+            .iter()
+            .filter_map(|&kind| match self.object_data(kind)? {
+                Ok(data) => Some(data),
+                Err(err) => {
+                    log(format!("MpqArchive: cannot parse {}: {}", kind.file_name(), err));
+                    None
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,93 @@
+//! SYLK (`.slk`) spreadsheets, the format of Warcraft III's unit, ability and item data tables.
+
+use std::collections::BTreeMap;
+
+/// Cell values by `(row, column)`, both 1-based as in the file. Quoted strings keep their text, other values are kept verbatim.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SlkTable {
+    pub cells: BTreeMap<(u32, u32), String>,
+    pub rows: u32,
+    pub columns: u32,
+}
+
+impl SlkTable {
+    /// Reads `C` (cell) records; `F` (format) records only move the current position. Other records are ignored.
+    pub fn parse(text: &str) -> Self {
+        let mut table = Self::default();
+        let (mut row, mut column) = (1u32, 1u32);
+        for line in text.lines() {
+            let mut fields = split_fields(line.trim_end_matches('\r'));
+            let kind = fields.next().unwrap_or_default();
+            if kind != "C" && kind != "F" {
+                continue;
+            }
+            let mut value = None;
+            for field in fields {
+                let (key, rest) = field.split_at(field.len().min(1));
+                match key {
+                    "X" => column = rest.parse().unwrap_or(column),
+                    "Y" => row = rest.parse().unwrap_or(row),
+                    "K" if kind == "C" => value = Some(unquote(rest)),
+                    _ => {}
+                }
+            }
+            if let Some(value) = value {
+                table.rows = table.rows.max(row);
+                table.columns = table.columns.max(column);
+                table.cells.insert((row, column), value);
+            }
+        }
+        table
+    }
+
+    pub fn get(&self, row: u32, column: u32) -> Option<&str> {
+        self.cells.get(&(row, column)).map(String::as_str)
+    }
+
+    /// Comma-separated rendering, one line per row; values with commas, quotes or line breaks are quoted.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in 1..=self.rows {
+            let line: Vec<String> = (1..=self.columns).map(|column| csv_field(self.get(row, column).unwrap_or(""))).collect();
+            csv.push_str(&line.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+/// Splits a record on `;`, where `;;` is an escaped semicolon.
+fn split_fields(line: &str) -> impl Iterator<Item = String> + '_ {
+    let mut chars = line.chars().peekable();
+    std::iter::from_fn(move || {
+        chars.peek()?;
+        let mut field = String::new();
+        while let Some(c) = chars.next() {
+            if c == ';' {
+                if chars.peek() == Some(&';') {
+                    chars.next();
+                    field.push(';');
+                    continue;
+                }
+                break;
+            }
+            field.push(c);
+        }
+        Some(field)
+    })
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"') {
+        Some(inner) => inner.strip_suffix('"').unwrap_or(inner).to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,254 @@
+//! `war3map.w3e` (tilesets and a height, water, texture and cliff grid of tilepoints) and `war3map.wpm` (the pathing
+//! map, four cells per tile side).
+
+use crate::archive::{MpqArchive, MpqArchiveError};
+use crate::texture::RgbaImage;
+use crate::utils::json::JsonValue;
+use crate::war3::BinaryReader;
+use crate::war3::objects::rawcode;
+use crate::war3::w3i::tileset_name;
+
+pub const W3E_FILE_NAME: &str = "war3map.w3e";
+pub const WPM_FILE_NAME: &str = "war3map.wpm";
+
+const W3E_MAGIC: &[u8; 4] = b"W3E!";
+const W3E_VERSION: u32 = 11;
+const WPM_MAGIC: &[u8; 4] = b"MP3W";
+
+pub const TILE_FLAG_RAMP: u8 = 0x10;
+pub const TILE_FLAG_BLIGHT: u8 = 0x20;
+pub const TILE_FLAG_WATER: u8 = 0x40;
+pub const TILE_FLAG_BOUNDARY: u8 = 0x80;
+
+pub const PATHING_NO_WALK: u8 = 0x02;
+pub const PATHING_NO_FLY: u8 = 0x04;
+pub const PATHING_NO_BUILD: u8 = 0x08;
+pub const PATHING_BLIGHT: u8 = 0x20;
+/// Set where there is no water.
+pub const PATHING_NO_WATER: u8 = 0x40;
+
+/// One corner of the tile grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilePoint {
+    /// 0x2000 is ground level.
+    pub ground_height: i16,
+    pub water_level: u16,
+    /// Map edge, outside the playable area.
+    pub edge: bool,
+    /// `TILE_FLAG_*` bits.
+    pub flags: u8,
+    /// Index into [`Terrain::ground_tilesets`].
+    pub ground_texture: u8,
+    pub texture_variation: u8,
+    /// Index into [`Terrain::cliff_tilesets`].
+    pub cliff_texture: u8,
+    pub layer_height: u8,
+}
+
+impl TilePoint {
+    /// Height in world units, cliff level included.
+    pub fn height(&self) -> f32 {
+        (self.ground_height as f32 - 8192.0 + (self.layer_height as f32 - 2.0) * 512.0) / 4.0
+    }
+
+    /// Water surface in world units.
+    pub fn water_height(&self) -> f32 {
+        (self.water_level as f32 - 8192.0) / 4.0 - 89.6
+    }
+
+    pub fn is_water(&self) -> bool {
+        self.flags & TILE_FLAG_WATER != 0 && self.water_height() > self.height()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Terrain {
+    pub version: u32,
+    /// Main tileset letter, as in `war3map.w3i`.
+    pub tileset: char,
+    pub custom_tilesets: bool,
+    /// Ground tile ids, e.g. `Ldrt`.
+    pub ground_tilesets: Vec<String>,
+    /// Cliff tile ids, e.g. `CLdi`.
+    pub cliff_tilesets: Vec<String>,
+    /// Tilepoints per row and column: one more than the tiles.
+    pub width: u32,
+    pub height: u32,
+    /// World position of the bottom-left tilepoint.
+    pub offset: (f32, f32),
+    /// Row by row from the bottom (south) edge.
+    pub points: Vec<TilePoint>,
+}
+
+impl Terrain {
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let mut reader = BinaryReader::new(bytes, W3E_FILE_NAME);
+        if reader.bytes(4)? != W3E_MAGIC {
+            return Err(MpqArchiveError::Corrupted(format!("{} has no W3E! signature", W3E_FILE_NAME)));
+        }
+        let version = reader.u32()?;
+        if version != W3E_VERSION {
+            return Err(MpqArchiveError::Corrupted(format!("unsupported {} version {}", W3E_FILE_NAME, version)));
+        }
+        let tileset = reader.u8()? as char;
+        let custom_tilesets = reader.u32()? != 0;
+        let ground_tilesets = read_ids(&mut reader)?;
+        let cliff_tilesets = read_ids(&mut reader)?;
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let offset = (reader.f32()?, reader.f32()?);
+
+        let count = width as usize * height as usize;
+        let data = reader.bytes(count.checked_mul(7).ok_or_else(|| MpqArchiveError::Corrupted(format!("{} is too large", W3E_FILE_NAME)))?)?;
+        let points = data
+            .chunks_exact(7)
+            .map(|point| {
+                let water = u16::from_le_bytes([point[2], point[3]]);
+                TilePoint {
+                    ground_height: i16::from_le_bytes([point[0], point[1]]),
+                    water_level: water & 0x3FFF,
+                    edge: water & 0x4000 != 0,
+                    flags: point[4] & 0xF0,
+                    ground_texture: point[4] & 0x0F,
+                    texture_variation: point[5],
+                    cliff_texture: point[6] >> 4,
+                    layer_height: point[6] & 0x0F,
+                }
+            })
+            .collect();
+        Ok(Self { version, tileset, custom_tilesets, ground_tilesets, cliff_tilesets, width, height, offset, points })
+    }
+
+    /// Tilepoint `x` from the west edge, `y` from the south edge.
+    pub fn point(&self, x: u32, y: u32) -> Option<&TilePoint> {
+        (x < self.width && y < self.height).then(|| &self.points[(y * self.width + x) as usize])
+    }
+
+    /// Lowest and highest ground, in world units.
+    pub fn height_range(&self) -> Option<(f32, f32)> {
+        self.points.iter().map(TilePoint::height).fold(None, |range, height| match range {
+            None => Some((height, height)),
+            Some((low, high)) => Some((low.min(height), high.max(height))),
+        })
+    }
+
+    /// One pixel per tilepoint, north up: ground height in gray from lowest to highest, water tinted blue, map edges
+    /// darkened.
+    pub fn heightmap_image(&self) -> Result<RgbaImage, MpqArchiveError> {
+        let mut image = RgbaImage::new(self.width, self.height)?;
+        let (low, high) = self.height_range().unwrap_or((0.0, 0.0));
+        let scale = if high > low { 255.0 / (high - low) } else { 0.0 };
+        for (index, point) in self.points.iter().enumerate() {
+            let (x, y) = (index % self.width as usize, index / self.width as usize);
+            let gray = ((point.height() - low) * scale) as u8;
+            let mut rgb = if point.is_water() { [gray / 3, gray / 2 + 40, 160 + gray / 3] } else { [gray; 3] };
+            if point.edge {
+                rgb = rgb.map(|channel| channel / 2);
+            }
+            let at = ((self.height as usize - 1 - y) * self.width as usize + x) * 4;
+            image.pixels[at..at + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+        }
+        Ok(image)
+    }
+
+    /// Dimensions and tilesets; the tilepoints themselves are left out.
+    pub fn to_json(&self) -> JsonValue {
+        let (low, high) = self.height_range().unwrap_or((0.0, 0.0));
+        let ids = |ids: &[String]| JsonValue::Array(ids.iter().map(|id| id.as_str().into()).collect());
+        JsonValue::object()
+            .with("version", self.version)
+            .with("tileset", self.tileset.to_string())
+            .with("tileset_name", tileset_name(self.tileset))
+            .with("custom_tilesets", self.custom_tilesets)
+            .with("ground_tilesets", ids(&self.ground_tilesets))
+            .with("cliff_tilesets", ids(&self.cliff_tilesets))
+            .with("width", self.width)
+            .with("height", self.height)
+            .with("tiles", format!("{}x{}", self.width.saturating_sub(1), self.height.saturating_sub(1)))
+            .with("offset", JsonValue::Array(vec![self.offset.0.into(), self.offset.1.into()]))
+            .with("min_height", low)
+            .with("max_height", high)
+            .with("water_points", self.points.iter().filter(|point| point.is_water()).count())
+            .with("ramp_points", self.points.iter().filter(|point| point.flags & TILE_FLAG_RAMP != 0).count())
+            .with("blight_points", self.points.iter().filter(|point| point.flags & TILE_FLAG_BLIGHT != 0).count())
+    }
+}
+
+fn read_ids(reader: &mut BinaryReader) -> Result<Vec<String>, MpqArchiveError> {
+    let count = reader.u32()?;
+    (0..count).map(|_| reader.bytes(4).map(rawcode)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathingMap {
+    pub version: u32,
+    pub width: u32,
+    pub height: u32,
+    /// `PATHING_*` bits per cell, row by row from the south edge.
+    pub cells: Vec<u8>,
+}
+
+impl PathingMap {
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let mut reader = BinaryReader::new(bytes, WPM_FILE_NAME);
+        if reader.bytes(4)? != WPM_MAGIC {
+            return Err(MpqArchiveError::Corrupted(format!("{} has no MP3W signature", WPM_FILE_NAME)));
+        }
+        let version = reader.u32()?;
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let cells = reader.bytes(width as usize * height as usize)?.to_vec();
+        Ok(Self { version, width, height, cells })
+    }
+
+    /// Cells with all of `flags` set.
+    pub fn count(&self, flags: u8) -> usize {
+        self.cells.iter().filter(|&&cell| cell & flags == flags).count()
+    }
+
+    /// One pixel per cell, north up: white where units walk and build, yellow where they only walk, blue on water,
+    /// red where only air units pass and black where nothing does. Blight is tinted purple.
+    pub fn image(&self) -> Result<RgbaImage, MpqArchiveError> {
+        let mut image = RgbaImage::new(self.width, self.height)?;
+        for (index, &cell) in self.cells.iter().enumerate() {
+            let (x, y) = (index % self.width as usize, index / self.width as usize);
+            let mut rgb = match cell {
+                _ if cell & (PATHING_NO_WALK | PATHING_NO_FLY) == PATHING_NO_WALK | PATHING_NO_FLY => [0, 0, 0],
+                _ if cell & PATHING_NO_WALK != 0 => [200, 0, 0],
+                _ if cell & PATHING_NO_WATER == 0 => [0, 90, 255],
+                _ if cell & PATHING_NO_BUILD != 0 => [255, 210, 0],
+                _ => [255, 255, 255],
+            };
+            if cell & PATHING_BLIGHT != 0 {
+                rgb = [rgb[0] / 2 + 64, rgb[1] / 2, rgb[2] / 2 + 64];
+            }
+            let at = ((self.height as usize - 1 - y) * self.width as usize + x) * 4;
+            image.pixels[at..at + 4].copy_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+        }
+        Ok(image)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::object()
+            .with("version", self.version)
+            .with("width", self.width)
+            .with("height", self.height)
+            .with("unwalkable", self.count(PATHING_NO_WALK))
+            .with("unflyable", self.count(PATHING_NO_FLY))
+            .with("unbuildable", self.count(PATHING_NO_BUILD))
+            .with("blighted", self.count(PATHING_BLIGHT))
+            .with("water", self.cells.iter().filter(|&&cell| cell & PATHING_NO_WATER == 0).count())
+    }
+}
+
+impl MpqArchive {
+    /// Parsed `war3map.w3e`; `None` when the archive has none.
+    pub fn terrain(&self) -> Option<Result<Terrain, MpqArchiveError>> {
+        Some(Terrain::parse(&self.read_file(W3E_FILE_NAME).ok()?))
+    }
+
+    /// Parsed `war3map.wpm`; `None` when the archive has none.
+    pub fn pathing_map(&self) -> Option<Result<PathingMap, MpqArchiveError>> {
+        Some(PathingMap::parse(&self.read_file(WPM_FILE_NAME).ok()?))
+    }
+}
@@ -0,0 +1,375 @@
+//! `war3map.w3i`: map name, author, description, loading screens and player/force setup, in the
+//! Reign of Chaos (18), Frozen Throne (25) and 1.31+/Reforged (28, 31) layouts.
+
+use crate::archive::{MpqArchive, MpqArchiveError};
+use crate::utils::json::JsonValue;
+use crate::war3::BinaryReader;
+use crate::war3::wts::{StringResolver, StringTable, WTS_FILE_NAME};
+
+pub const W3I_FILE_NAME: &str = "war3map.w3i";
+
+pub const W3I_VERSION_ROC: u32 = 18;
+pub const W3I_VERSION_TFT: u32 = 25;
+/// 1.31: game version and script language.
+pub const W3I_VERSION_LUA: u32 = 28;
+/// 1.32 (Reforged): enemy priorities, supported graphics modes and game data version.
+pub const W3I_VERSION_REFORGED: u32 = 31;
+
+pub const MAP_FLAG_HIDE_MINIMAP: u32 = 0x0001;
+pub const MAP_FLAG_MELEE: u32 = 0x0004;
+pub const MAP_FLAG_FIXED_PLAYER_SETTINGS: u32 = 0x0020;
+pub const MAP_FLAG_CUSTOM_FORCES: u32 = 0x0040;
+pub const MAP_FLAG_CUSTOM_TECH_TREE: u32 = 0x0080;
+pub const MAP_FLAG_CUSTOM_ABILITIES: u32 = 0x0100;
+pub const MAP_FLAG_CUSTOM_UPGRADES: u32 = 0x0200;
+pub const MAP_FLAG_TERRAIN_FOG: u32 = 0x2000;
+pub const MAP_FLAG_ITEM_CLASSIFICATION: u32 = 0x8000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptLanguage {
+    Jass,
+    Lua,
+}
+
+impl ScriptLanguage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptLanguage::Jass => "jass",
+            ScriptLanguage::Lua => "lua",
+        }
+    }
+}
+
+/// Game version that saved the map (1.31+).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+    pub build: u32,
+}
+
+/// Loading or prologue screen. `preset` is the index of a built-in screen, -1 for none or a custom one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScreenInfo {
+    pub preset: i32,
+    /// Custom model (loading screen) or path (prologue); TFT and later only.
+    pub path: String,
+    pub text: String,
+    pub title: String,
+    pub subtitle: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerInfo {
+    pub id: u32,
+    /// 1 human, 2 computer, 3 neutral, 4 rescuable.
+    pub kind: u32,
+    /// 1 human, 2 orc, 3 undead, 4 night elf.
+    pub race: u32,
+    pub fixed_start: bool,
+    pub name: String,
+    pub start_x: f32,
+    pub start_y: f32,
+    pub ally_low_priorities: u32,
+    pub ally_high_priorities: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForceInfo {
+    /// 0x01 allied, 0x02 allied victory, 0x04 shared vision, 0x10 shared unit control, 0x20 shared advanced unit control.
+    pub flags: u32,
+    /// Bit `n` set when player `n` belongs to the force.
+    pub player_mask: u32,
+    pub name: String,
+}
+
+/// Parsed `war3map.w3i`. Text fields may be `TRIGSTR_<id>` references until [`resolve_strings`](Self::resolve_strings).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapInfo {
+    pub version: u32,
+    /// Number of times the map was saved.
+    pub map_version: u32,
+    pub editor_version: u32,
+    pub game_version: Option<GameVersion>,
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    pub recommended_players: String,
+    pub playable_width: u32,
+    pub playable_height: u32,
+    pub flags: u32,
+    pub tileset: char,
+    pub loading_screen: ScreenInfo,
+    pub prologue: ScreenInfo,
+    /// Game data preset (0 standard); TFT and later only.
+    pub game_data_set: Option<i32>,
+    pub script_language: ScriptLanguage,
+    /// 1 SD, 2 HD, 3 both; Reforged only.
+    pub supported_modes: Option<u32>,
+    /// 0 Reign of Chaos, 1 The Frozen Throne; Reforged only.
+    pub game_data_version: Option<u32>,
+    pub players: Vec<PlayerInfo>,
+    pub forces: Vec<ForceInfo>,
+}
+
+impl MapInfo {
+    /// Parses up to and including the force setup; upgrade, tech and random tables are not read.
+    pub fn parse(bytes: &[u8]) -> Result<Self, MpqArchiveError> {
+        let mut reader = BinaryReader::new(bytes, W3I_FILE_NAME);
+        let version = reader.u32()?;
+        if version != W3I_VERSION_ROC && version < W3I_VERSION_TFT {
+            return Err(MpqArchiveError::Corrupted(format!("unknown {} format version {}", W3I_FILE_NAME, version)));
+        }
+        let tft = version >= W3I_VERSION_TFT;
+        let map_version = reader.u32()?;
+        let editor_version = reader.u32()?;
+        let game_version = if version >= W3I_VERSION_LUA {
+            Some(GameVersion { major: reader.u32()?, minor: reader.u32()?, patch: reader.u32()?, build: reader.u32()? })
+        } else {
+            None
+        };
+        let name = reader.string()?;
+        let author = reader.string()?;
+        let description = reader.string()?;
+        let recommended_players = reader.string()?;
+        // Camera bounds (8 floats) and their complements (4 ints).
+        reader.bytes(8 * 4 + 4 * 4)?;
+        let playable_width = reader.u32()?;
+        let playable_height = reader.u32()?;
+        let flags = reader.u32()?;
+        let tileset = reader.u8()? as char;
+
+        let mut loading_screen = ScreenInfo { preset: reader.i32()?, ..ScreenInfo::default() };
+        if tft {
+            loading_screen.path = reader.string()?;
+        }
+        loading_screen.text = reader.string()?;
+        loading_screen.title = reader.string()?;
+        loading_screen.subtitle = reader.string()?;
+        // RoC stores the prologue preset here; TFT replaced it with the game data set.
+        let (game_data_set, mut prologue) = if tft {
+            (Some(reader.i32()?), ScreenInfo { preset: -1, path: reader.string()?, ..ScreenInfo::default() })
+        } else {
+            (None, ScreenInfo { preset: reader.i32()?, ..ScreenInfo::default() })
+        };
+        prologue.text = reader.string()?;
+        prologue.title = reader.string()?;
+        prologue.subtitle = reader.string()?;
+        if tft {
+            // Fog style, start/end z, density and RGBA color; weather id.
+            reader.bytes(4 + 3 * 4 + 4 + 4)?;
+            let _sound_environment = reader.string()?;
+            // Light environment tileset and water tint RGBA.
+            reader.bytes(1 + 4)?;
+        }
+        let script_language = if version >= W3I_VERSION_LUA && reader.u32()? == 1 { ScriptLanguage::Lua } else { ScriptLanguage::Jass };
+        let (supported_modes, game_data_version) = if version >= W3I_VERSION_REFORGED { (Some(reader.u32()?), Some(reader.u32()?)) } else { (None, None) };
+
+        let player_count = reader.u32()?;
+        let mut players = Vec::new();
+        for _ in 0..player_count {
+            let player = PlayerInfo {
+                id: reader.u32()?,
+                kind: reader.u32()?,
+                race: reader.u32()?,
+                fixed_start: reader.u32()? != 0,
+                name: reader.string()?,
+                start_x: reader.f32()?,
+                start_y: reader.f32()?,
+                ally_low_priorities: reader.u32()?,
+                ally_high_priorities: reader.u32()?,
+            };
+            if version >= W3I_VERSION_REFORGED {
+                // Enemy low/high priorities.
+                reader.bytes(8)?;
+            }
+            players.push(player);
+        }
+        let force_count = reader.u32()?;
+        let mut forces = Vec::new();
+        for _ in 0..force_count {
+            forces.push(ForceInfo { flags: reader.u32()?, player_mask: reader.u32()?, name: reader.string()? });
+        }
+
+        Ok(Self {
+            version,
+            map_version,
+            editor_version,
+            game_version,
+            name,
+            author,
+            description,
+            recommended_players,
+            playable_width,
+            playable_height,
+            flags,
+            tileset,
+            loading_screen,
+            prologue,
+            game_data_set,
+            script_language,
+            supported_modes,
+            game_data_version,
+            players,
+            forces,
+        })
+    }
+
+    /// Parses `w3i` and resolves its `TRIGSTR_<id>` references against the `wts` string table, when present.
+    pub fn load(w3i: &[u8], wts: Option<&[u8]>) -> Result<Self, MpqArchiveError> {
+        let mut info = Self::parse(w3i)?;
+        if let Some(wts) = wts {
+            info.resolve_strings(&StringTable::from_bytes(wts));
+        }
+        Ok(info)
+    }
+
+    /// Replaces `TRIGSTR_<id>` references with their text; unknown ids are kept.
+    pub fn resolve_strings(&mut self, strings: &impl StringResolver) {
+        let resolve = |text: &mut String| {
+            let resolved = strings.resolve(text).to_string();
+            *text = resolved;
+        };
+        for text in [&mut self.name, &mut self.author, &mut self.description, &mut self.recommended_players] {
+            resolve(text);
+        }
+        for screen in [&mut self.loading_screen, &mut self.prologue] {
+            for text in [&mut screen.text, &mut screen.title, &mut screen.subtitle] {
+                resolve(text);
+            }
+        }
+        for player in &mut self.players {
+            resolve(&mut player.name);
+        }
+        for force in &mut self.forces {
+            resolve(&mut force.name);
+        }
+    }
+
+    pub fn format_name(&self) -> &'static str {
+        match self.version {
+            W3I_VERSION_ROC => "Reign of Chaos",
+            version if version >= W3I_VERSION_REFORGED => "Reforged",
+            version if version >= W3I_VERSION_LUA => "1.31",
+            _ => "The Frozen Throne",
+        }
+    }
+
+    pub fn is_melee(&self) -> bool {
+        self.flags & MAP_FLAG_MELEE != 0
+    }
+
+    /// The report served as `map-info.json` and by `mpq-tool info`.
+    pub fn to_json(&self) -> JsonValue {
+        let screen = |screen: &ScreenInfo| {
+            JsonValue::object()
+                .with("preset", screen.preset)
+                .with("path", screen.path.as_str())
+                .with("text", screen.text.as_str())
+                .with("title", screen.title.as_str())
+                .with("subtitle", screen.subtitle.as_str())
+        };
+        let players: Vec<JsonValue> = self
+            .players
+            .iter()
+            .map(|player| {
+                JsonValue::object()
+                    .with("id", player.id)
+                    .with("name", player.name.as_str())
+                    .with("type", player_kind_name(player.kind))
+                    .with("race", race_name(player.race))
+                    .with("fixed_start", player.fixed_start)
+                    .with("start", vec![player.start_x, player.start_y])
+            })
+            .collect();
+        let forces: Vec<JsonValue> = self
+            .forces
+            .iter()
+            .map(|force| {
+                let members: Vec<u32> = (0..32).filter(|bit| force.player_mask & (1 << bit) != 0).collect();
+                JsonValue::object()
+                    .with("name", force.name.as_str())
+                    .with("flags", force.flags)
+                    .with("players", members)
+            })
+            .collect();
+
+        JsonValue::object()
+            .with("format_version", self.version)
+            .with("format", self.format_name())
+            .with("map_version", self.map_version)
+            .with("editor_version", self.editor_version)
+            .with("game_version", self.game_version.map(|v| format!("{}.{}.{}.{}", v.major, v.minor, v.patch, v.build)))
+            .with("name", self.name.as_str())
+            .with("author", self.author.as_str())
+            .with("description", self.description.as_str())
+            .with("recommended_players", self.recommended_players.as_str())
+            .with("playable_size", vec![self.playable_width, self.playable_height])
+            .with("flags", self.flags)
+            .with("melee", self.is_melee())
+            .with("tileset", self.tileset.to_string())
+            .with("tileset_name", tileset_name(self.tileset))
+            .with("game_data_set", self.game_data_set)
+            .with("game_data_version", self.game_data_version.map(|version| if version == 0 { "Reign of Chaos" } else { "The Frozen Throne" }))
+            .with("script_language", self.script_language.as_str())
+            .with("supported_modes", self.supported_modes)
+            .with("loading_screen", screen(&self.loading_screen))
+            .with("prologue", screen(&self.prologue))
+            .with("players", players)
+            .with("forces", forces)
+    }
+}
+
+impl MpqArchive {
+    /// Parsed `war3map.w3i` with strings resolved from `war3map.wts`; `None` when the archive has no map info.
+    pub fn map_info(&self) -> Option<Result<MapInfo, MpqArchiveError>> {
+        let w3i = self.read_file(W3I_FILE_NAME).ok()?;
+        let wts = self.read_file(WTS_FILE_NAME).ok();
+        Some(MapInfo::load(&w3i, wts.as_deref()))
+    }
+}
+
+pub fn tileset_name(tileset: char) -> Option<&'static str> {
+    Some(match tileset {
+        'A' => "Ashenvale",
+        'B' => "Barrens",
+        'C' => "Felwood",
+        'D' => "Dungeon",
+        'F' => "Lordaeron Fall",
+        'G' => "Underground",
+        'I' => "Icecrown Glacier",
+        'J' => "Dalaran Ruins",
+        'K' => "Black Citadel",
+        'L' => "Lordaeron Summer",
+        'N' => "Northrend",
+        'O' => "Outland",
+        'Q' => "Village Fall",
+        'V' => "Village",
+        'W' => "Lordaeron Winter",
+        'X' => "Dalaran",
+        'Y' => "Cityscape",
+        'Z' => "Sunken Ruins",
+        _ => return None,
+    })
+}
+
+fn player_kind_name(kind: u32) -> Option<&'static str> {
+    match kind {
+        1 => Some("human"),
+        2 => Some("computer"),
+        3 => Some("neutral"),
+        4 => Some("rescuable"),
+        _ => None,
+    }
+}
+
+fn race_name(race: u32) -> Option<&'static str> {
+    match race {
+        1 => Some("human"),
+        2 => Some("orc"),
+        3 => Some("undead"),
+        4 => Some("night elf"),
+        _ => None,
+    }
+}
@@ -0,0 +1,122 @@
+//! `war3map.wts`: the map's string table, referenced as `TRIGSTR_<id>` from `war3map.w3i`, object data and triggers.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub const WTS_FILE_NAME: &str = "war3map.wts";
+const TRIGGER_STRING_PREFIX: &str = "TRIGSTR_";
+
+/// Looks up `TRIGSTR_<id>` references; the Warcraft III parsers take any resolver.
+pub trait StringResolver {
+    fn lookup(&self, id: u32) -> Option<&str>;
+
+    /// Text of a value that is exactly a `TRIGSTR_<id>` reference; other values and unknown ids are returned unchanged.
+    fn resolve<'a>(&'a self, value: &'a str) -> &'a str {
+        trigger_string_id(value).and_then(|id| self.lookup(id)).unwrap_or(value)
+    }
+}
+
+/// Parsed string table: `STRING <id>` blocks whose text is enclosed in `{` and `}` lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StringTable {
+    strings: HashMap<u32, String>,
+}
+
+impl StringTable {
+    /// Decodes the file first: UTF-8 with or without a BOM (invalid sequences are replaced), or UTF-16 with a BOM.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Self::parse(&decode_text(bytes))
+    }
+
+    /// Blocks that are not well formed are skipped; an unterminated last block keeps the text read so far.
+    pub fn parse(text: &str) -> Self {
+        let mut strings = HashMap::new();
+        let mut lines = text.trim_start_matches('\u{feff}').lines();
+        while let Some(line) = lines.next() {
+            let Some(id) = line.trim().strip_prefix("STRING ").and_then(|id| id.trim().parse::<u32>().ok()) else { continue };
+            // Comment lines (`// ...`) may sit between the id and the opening brace.
+            if !lines.by_ref().any(|line| line.trim() == "{") {
+                break;
+            }
+            let body: Vec<&str> = lines.by_ref().take_while(|line| line.trim_end() != "}").collect();
+            strings.insert(id, body.join("\n"));
+        }
+        Self { strings }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&str> {
+        self.strings.get(&id).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl StringResolver for StringTable {
+    fn lookup(&self, id: u32) -> Option<&str> {
+        self.get(id)
+    }
+}
+
+/// Id of a `TRIGSTR_<id>` reference (`TRIGSTR_007` is id 7).
+pub fn trigger_string_id(value: &str) -> Option<u32> {
+    let digits = value.strip_prefix(TRIGGER_STRING_PREFIX)?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Copy of `data` with every `TRIGSTR_<id>` reference replaced by its text, or `None` when no reference
+/// resolves. Binary files stay well formed since their strings are NUL-terminated; `escape` keeps
+/// script string literals valid (see [`escape_script_string`]).
+pub fn inline_strings(data: &[u8], strings: &impl StringResolver, escape: fn(&str) -> Cow<'_, str>) -> Option<Vec<u8>> {
+    let prefix = TRIGGER_STRING_PREFIX.as_bytes();
+    let mut out = Vec::with_capacity(data.len());
+    let mut replaced = false;
+    let mut pos = 0;
+    while let Some(found) = data[pos..].windows(prefix.len()).position(|window| window == prefix) {
+        let start = pos + found;
+        let digits = data[start + prefix.len()..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let end = start + prefix.len() + digits;
+        out.extend_from_slice(&data[pos..start]);
+        let text = std::str::from_utf8(&data[start..end]).ok().and_then(trigger_string_id).and_then(|id| strings.lookup(id));
+        match text {
+            Some(text) => {
+                out.extend_from_slice(escape(text).as_bytes());
+                replaced = true;
+            }
+            None => out.extend_from_slice(&data[start..end]),
+        }
+        pos = end;
+    }
+    out.extend_from_slice(&data[pos..]);
+    replaced.then_some(out)
+}
+
+/// Escapes text for a JASS or Lua double-quoted string literal.
+pub fn escape_script_string(text: &str) -> Cow<'_, str> {
+    if !text.contains(['"', '\\', '\n', '\r']) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace('\\', "\\\\").replace('"', "\\\"").replace('\r', "\\r").replace('\n', "\\n"))
+}
+
+/// Text of a World Editor file: UTF-16 when it starts with a UTF-16 BOM, UTF-8 otherwise.
+pub(crate) fn decode_text(bytes: &[u8]) -> Cow<'_, str> {
+    let utf16 = |bytes: &[u8], read: fn([u8; 2]) -> u16| {
+        let units = bytes.chunks_exact(2).map(|pair| read([pair[0], pair[1]]));
+        Cow::Owned(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect())
+    };
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(bytes),
+    }
+}
@@ -0,0 +1,417 @@
+//! Archive fixtures shared by the integration tests: files are laid out, compressed, encrypted and indexed the way
+//! Storm writes them, so each test only states the shape it needs.
+
+// Every test crate compiles this module and uses a different part of it.
+#![allow(dead_code)]
+
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use mpq_folder_win::archive::crypto::{HASH_NAME_A, HASH_NAME_B, HASH_TABLE_OFFSET, encrypt_bytes, file_key, hash_string};
+use mpq_folder_win::archive::header::{MPQ_USER_DATA_ID, MpqHeader};
+use mpq_folder_win::archive::het_bet::{write_bet_table, write_het_table};
+use mpq_folder_win::archive::tables::{
+    BlockEntry, HASH_ENTRY_DELETED, HASH_ENTRY_EMPTY, HashEntry, MPQ_FILE_COMPRESS, MPQ_FILE_DELETE_MARKER, MPQ_FILE_ENCRYPTED, MPQ_FILE_EXISTS, MPQ_FILE_FIX_KEY,
+    MPQ_FILE_PATCH_FILE, MPQ_FILE_SINGLE_UNIT, write_block_table, write_hash_table,
+};
+use mpq_folder_win::utils::md5::md5;
+use mpq_folder_win::war3::objects::{ModificationValue, OBJECT_DATA_VERSION_SETS, ObjectDefinition, ObjectKind};
+use mpq_folder_win::war3::w3i::{W3I_VERSION_LUA, W3I_VERSION_REFORGED, W3I_VERSION_TFT};
+use std::io::Write;
+
+pub const USER_DATA_OFFSET: usize = 0x200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tables {
+    Classic,
+    HetBet,
+    Both,
+}
+
+#[derive(Debug, Clone)]
+enum Content {
+    /// File contents, compressed and encrypted as the flags ask when the archive is built.
+    Data(Vec<u8>),
+    /// Bytes stored as they are, with the file size the block table records.
+    Raw { raw: Vec<u8>, file_size: u32 },
+}
+
+/// One block of a test archive. Blocks without a name get no hash entry (deleted or replaced files).
+#[derive(Debug, Clone)]
+pub struct TestFile {
+    pub name: Option<String>,
+    pub locale: u16,
+    pub flags: u32,
+    content: Content,
+}
+
+impl TestFile {
+    pub fn plain(name: &str, data: &[u8]) -> Self {
+        Self { name: Some(name.to_string()), locale: 0, flags: MPQ_FILE_EXISTS, content: Content::Data(data.to_vec()) }
+    }
+
+    /// Split into sectors, each zlib-compressed when that saves space.
+    pub fn compressed(name: &str, data: &[u8]) -> Self {
+        Self { flags: MPQ_FILE_EXISTS | MPQ_FILE_COMPRESS, ..Self::plain(name, data) }
+    }
+
+    /// A block no hash entry points at.
+    pub fn dead(data: &[u8]) -> Self {
+        Self { name: None, ..Self::plain("", data) }
+    }
+
+    /// Bytes stored verbatim, recorded with `file_size`.
+    pub fn raw(name: &str, raw: &[u8], file_size: u32, flags: u32) -> Self {
+        Self { name: Some(name.to_string()), locale: 0, flags, content: Content::Raw { raw: raw.to_vec(), file_size } }
+    }
+
+    pub fn delete_marker(name: &str) -> Self {
+        Self::raw(name, &[], 0, MPQ_FILE_EXISTS | MPQ_FILE_DELETE_MARKER)
+    }
+
+    /// A `COPY` patch turning `old` into `new`, behind the patch info block patch files start with.
+    pub fn patch(name: &str, old: &[u8], new: &[u8]) -> Self {
+        let ptch = ptch(old, new, b"COPY", new);
+        let mut raw = Vec::new();
+        raw.extend_from_slice(&28u32.to_le_bytes());
+        raw.extend_from_slice(&0u32.to_le_bytes());
+        raw.extend_from_slice(&(ptch.len() as u32).to_le_bytes());
+        raw.extend_from_slice(&md5(&ptch));
+        raw.extend_from_slice(&ptch);
+        Self::raw(name, &raw, ptch.len() as u32, MPQ_FILE_EXISTS | MPQ_FILE_PATCH_FILE)
+    }
+
+    pub fn with_locale(self, locale: u16) -> Self {
+        Self { locale, ..self }
+    }
+
+    pub fn with_flags(self, flags: u32) -> Self {
+        Self { flags: self.flags | flags, ..self }
+    }
+
+    /// Encrypted with the key of its name, adjusted by position and size with `fix_key`.
+    pub fn encrypted(self, fix_key: bool) -> Self {
+        self.with_flags(MPQ_FILE_ENCRYPTED | if fix_key { MPQ_FILE_FIX_KEY } else { 0 })
+    }
+
+    /// Stored as one unit instead of sectors.
+    pub fn single_unit(self) -> Self {
+        self.with_flags(MPQ_FILE_SINGLE_UNIT)
+    }
+
+    /// The bytes stored at `file_pos` and the file size to record.
+    fn encode(&self, file_pos: u64, sector_size: usize) -> (Vec<u8>, u32) {
+        let data = match &self.content {
+            Content::Raw { raw, file_size } => return (raw.clone(), *file_size),
+            Content::Data(data) => data,
+        };
+        let compress = self.flags & MPQ_FILE_COMPRESS != 0;
+        let key = (self.flags & MPQ_FILE_ENCRYPTED != 0).then(|| file_key(self.name.as_deref().unwrap_or(""), file_pos, data.len() as u32, self.flags & MPQ_FILE_FIX_KEY != 0));
+        if self.flags & MPQ_FILE_SINGLE_UNIT != 0 || data.is_empty() {
+            let mut raw = if compress { zlib_unit(data) } else { data.clone() };
+            if let Some(key) = key {
+                encrypt_bytes(&mut raw, key);
+            }
+            return (raw, data.len() as u32);
+        }
+
+        let mut sectors: Vec<Vec<u8>> = data.chunks(sector_size).map(|sector| if compress { zlib_unit(sector) } else { sector.to_vec() }).collect();
+        if let Some(key) = key {
+            for (index, sector) in sectors.iter_mut().enumerate() {
+                encrypt_bytes(sector, key.wrapping_add(index as u32));
+            }
+        }
+        let mut raw = Vec::new();
+        if compress {
+            let mut offset = (sectors.len() as u32 + 1) * 4;
+            let mut table = offset.to_le_bytes().to_vec();
+            for sector in &sectors {
+                offset += sector.len() as u32;
+                table.extend_from_slice(&offset.to_le_bytes());
+            }
+            if let Some(key) = key {
+                encrypt_bytes(&mut table, key.wrapping_sub(1));
+            }
+            raw.extend_from_slice(&table);
+        }
+        sectors.iter().for_each(|sector| raw.extend_from_slice(sector));
+        (raw, data.len() as u32)
+    }
+}
+
+/// zlib-compressed behind its compression mask byte, or the data itself when that is not smaller.
+pub fn zlib_unit(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data).unwrap();
+    let mut packed = vec![0x02];
+    packed.extend_from_slice(&encoder.finish().unwrap());
+    if packed.len() < data.len() { packed } else { data.to_vec() }
+}
+
+/// A `PTCH` file of one `XFRM` block of `kind` (`COPY` or `BSD0`) carrying `payload`.
+pub fn ptch(old: &[u8], new: &[u8], kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut ptch = Vec::new();
+    ptch.extend_from_slice(b"PTCH");
+    ptch.extend_from_slice(&((0x44 + payload.len()) as u32).to_le_bytes());
+    ptch.extend_from_slice(&(old.len() as u32).to_le_bytes());
+    ptch.extend_from_slice(&(new.len() as u32).to_le_bytes());
+    ptch.extend_from_slice(b"MD5_");
+    ptch.extend_from_slice(&0x28u32.to_le_bytes());
+    ptch.extend_from_slice(&md5(old));
+    ptch.extend_from_slice(&md5(new));
+    ptch.extend_from_slice(b"XFRM");
+    ptch.extend_from_slice(&((12 + payload.len()) as u32).to_le_bytes());
+    ptch.extend_from_slice(kind);
+    ptch.extend_from_slice(payload);
+    ptch
+}
+
+/// Builds an archive: header, file data (each followed by its raw chunk MD5s in v4 archives), then the tables.
+#[derive(Debug, Clone)]
+pub struct ArchiveBuilder {
+    files: Vec<TestFile>,
+    format_version: u16,
+    sector_size_shift: u16,
+    tables: Tables,
+    hash_table_size: Option<usize>,
+    deleted_slots: usize,
+    listfile: bool,
+    user_data: bool,
+    tables_first: bool,
+    raw_chunk_size: u32,
+}
+
+impl Default for ArchiveBuilder {
+    /// A v1 archive with classic tables and a `(listfile)` of the named files.
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            format_version: 0,
+            sector_size_shift: 3,
+            tables: Tables::Classic,
+            hash_table_size: None,
+            deleted_slots: 0,
+            listfile: true,
+            user_data: false,
+            tables_first: false,
+            raw_chunk_size: 0,
+        }
+    }
+}
+
+impl ArchiveBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn file(mut self, file: TestFile) -> Self {
+        self.files.push(file);
+        self
+    }
+
+    pub fn files(mut self, files: impl IntoIterator<Item = TestFile>) -> Self {
+        self.files.extend(files);
+        self
+    }
+
+    /// 0 to 3 for MPQ v1 to v4.
+    pub fn format_version(self, format_version: u16) -> Self {
+        Self { format_version, ..self }
+    }
+
+    pub fn tables(self, tables: Tables) -> Self {
+        Self { tables, ..self }
+    }
+
+    /// Hash table slots; by default twice the named files, rounded up to a power of two.
+    pub fn hash_table_size(self, size: usize) -> Self {
+        Self { hash_table_size: Some(size), ..self }
+    }
+
+    /// Marks the first `count` free slots after the inserted entries deleted.
+    pub fn deleted_slots(self, count: usize) -> Self {
+        Self { deleted_slots: count, ..self }
+    }
+
+    pub fn without_listfile(self) -> Self {
+        Self { listfile: false, ..self }
+    }
+
+    /// Puts an `MPQ\x1B` user-data block before the archive, which then starts at [`USER_DATA_OFFSET`].
+    pub fn user_data(self) -> Self {
+        Self { user_data: true, ..self }
+    }
+
+    /// Writes the classic tables right after the header, before the file data.
+    pub fn tables_first(self) -> Self {
+        Self { tables_first: true, ..self }
+    }
+
+    /// v4 raw chunk size; every file is followed by the MD5 of each chunk of its stored data.
+    pub fn raw_chunk_size(self, raw_chunk_size: u32) -> Self {
+        Self { format_version: 3, raw_chunk_size, ..self }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut files = self.files.clone();
+        if self.listfile {
+            let mut names: Vec<&String> = files.iter().filter_map(|file| file.name.as_ref()).collect();
+            names.dedup();
+            let listfile: String = names.iter().map(|name| format!("{name}\r\n")).collect();
+            files.push(TestFile::plain("(listfile)", listfile.as_bytes()));
+        }
+        let named: Vec<(usize, &TestFile)> = files.iter().enumerate().filter(|(_, file)| file.name.is_some()).collect();
+        let header_size = MpqHeader::size_for_version(self.format_version) as usize;
+        let mut header = MpqHeader { header_size: header_size as u32, format_version: self.format_version, sector_size_shift: self.sector_size_shift, raw_chunk_size: self.raw_chunk_size, ..MpqHeader::default() };
+        let sector_size = header.sector_size() as usize;
+        let classic = self.tables != Tables::HetBet;
+        let hash_size = self.hash_table_size.unwrap_or_else(|| (named.len() * 2).next_power_of_two());
+
+        let mut body = vec![0u8; header_size];
+        let tables_len = if classic && self.tables_first { (hash_size + files.len()) * 16 } else { 0 };
+        body.resize(header_size + tables_len, 0);
+        let mut blocks = Vec::new();
+        for file in &files {
+            let file_pos = body.len() as u64;
+            let (raw, file_size) = file.encode(file_pos, sector_size);
+            blocks.push(BlockEntry { file_pos, compressed_size: raw.len() as u32, file_size, flags: file.flags });
+            body.extend_from_slice(&raw);
+            if self.raw_chunk_size != 0 {
+                raw.chunks(self.raw_chunk_size as usize).for_each(|chunk| body.extend_from_slice(&md5(chunk)));
+            }
+        }
+
+        if classic {
+            let mut hash_table = vec![HashEntry { hash_a: u32::MAX, hash_b: u32::MAX, locale: 0xFFFF, platform: 0xFFFF, block_index: HASH_ENTRY_EMPTY }; hash_size];
+            for &(index, file) in &named {
+                let name = file.name.as_deref().unwrap();
+                let mut slot = hash_string(name, HASH_TABLE_OFFSET) as usize & (hash_size - 1);
+                while !hash_table[slot].is_empty() {
+                    slot = (slot + 1) % hash_size;
+                }
+                hash_table[slot] = HashEntry { hash_a: hash_string(name, HASH_NAME_A), hash_b: hash_string(name, HASH_NAME_B), locale: file.locale, platform: 0, block_index: index as u32 };
+            }
+            for slot in hash_table.iter_mut().filter(|slot| slot.is_empty()).take(self.deleted_slots) {
+                slot.block_index = HASH_ENTRY_DELETED;
+            }
+            let tables = [write_hash_table(&hash_table), write_block_table(&blocks)].concat();
+            let at = if self.tables_first { header_size } else { body.len() };
+            header.hash_table_pos = at as u32;
+            header.hash_table_size = hash_size as u32;
+            header.block_table_pos = (at + hash_size * 16) as u32;
+            header.block_table_size = blocks.len() as u32;
+            if self.tables_first {
+                body[at..at + tables.len()].copy_from_slice(&tables);
+            } else {
+                body.extend_from_slice(&tables);
+            }
+        }
+        if self.tables != Tables::Classic {
+            let names: Vec<&str> = files.iter().map(|file| file.name.as_deref().unwrap_or("")).collect();
+            header.het_table_pos = body.len() as u64;
+            body.extend_from_slice(&write_het_table(&names));
+            header.bet_table_pos = body.len() as u64;
+            body.extend_from_slice(&write_bet_table(&blocks, &names));
+        }
+        header.archive_size = body.len() as u32;
+        header.archive_size_64 = body.len() as u64;
+        body[..header_size].copy_from_slice(&header.to_bytes());
+
+        if !self.user_data {
+            return body;
+        }
+        let mut out = Vec::new();
+        out.extend_from_slice(&MPQ_USER_DATA_ID.to_le_bytes());
+        out.extend_from_slice(&0x100u32.to_le_bytes());
+        out.extend_from_slice(&(USER_DATA_OFFSET as u32).to_le_bytes());
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.resize(USER_DATA_OFFSET, 0);
+        out.extend_from_slice(&body);
+        out
+    }
+}
+
+/// A `war3map.w3i` of `version` with two players in one force, text fields given as written. `lua` is only recorded
+/// from 1.31 on.
+pub fn w3i(version: u32, name: &str, author: &str, lua: bool) -> Vec<u8> {
+    let tft = version >= W3I_VERSION_TFT;
+    let mut bytes = Vec::new();
+    let u32s = |bytes: &mut Vec<u8>, values: &[u32]| values.iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    let strings = |bytes: &mut Vec<u8>, values: &[&str]| values.iter().for_each(|value| bytes.extend_from_slice(&[value.as_bytes(), &[0]].concat()));
+
+    u32s(&mut bytes, &[version, 7, 6072]);
+    if version >= W3I_VERSION_LUA {
+        u32s(&mut bytes, &[1, 36, 1, 20188]);
+    }
+    strings(&mut bytes, &[name, author, "Two players, one lake.", "1v1"]);
+    bytes.extend_from_slice(&[0; 48]);
+    u32s(&mut bytes, &[84, 84, 0x0004]);
+    bytes.push(b'N');
+    u32s(&mut bytes, &[u32::MAX]);
+    if tft {
+        strings(&mut bytes, &[""]);
+    }
+    strings(&mut bytes, &["Loading", "Frozen Lake", "a map"]);
+    u32s(&mut bytes, &[if tft { 0 } else { u32::MAX }]);
+    if tft {
+        strings(&mut bytes, &[""]);
+    }
+    strings(&mut bytes, &["", "", ""]);
+    if tft {
+        bytes.extend_from_slice(&[0; 24]);
+        strings(&mut bytes, &[""]);
+        bytes.extend_from_slice(&[0; 5]);
+    }
+    if version >= W3I_VERSION_LUA {
+        u32s(&mut bytes, &[lua as u32]);
+    }
+    if version >= W3I_VERSION_REFORGED {
+        u32s(&mut bytes, &[3, 1]);
+    }
+    u32s(&mut bytes, &[2]);
+    for (id, name) in [(0, "Red"), (1, "Blue")] {
+        u32s(&mut bytes, &[id, 1, id + 1, 1]);
+        strings(&mut bytes, &[name]);
+        u32s(&mut bytes, &[(-512.0f32).to_bits(), 512.0f32.to_bits(), 0, 0]);
+        if version >= W3I_VERSION_REFORGED {
+            u32s(&mut bytes, &[0, 0]);
+        }
+    }
+    u32s(&mut bytes, &[1, 0x01, 0b11]);
+    strings(&mut bytes, &["Everyone"]);
+    bytes
+}
+
+/// An object data file of `kind` in format `version`: `original` changes standard objects, `custom` adds new ones.
+pub fn object_data(kind: ObjectKind, version: u32, original: &[ObjectDefinition], custom: &[ObjectDefinition]) -> Vec<u8> {
+    let mut bytes = version.to_le_bytes().to_vec();
+    for table in [original, custom] {
+        bytes.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        for object in table {
+            bytes.extend_from_slice(object.base_id.as_bytes());
+            bytes.extend_from_slice(object.id.as_ref().map_or(&[0; 4][..], |id| id.as_bytes()));
+            if version >= OBJECT_DATA_VERSION_SETS {
+                // One set, for every graphics mode.
+                bytes.extend_from_slice(&1u32.to_le_bytes());
+                bytes.extend_from_slice(&0u32.to_le_bytes());
+            }
+            bytes.extend_from_slice(&(object.modifications.len() as u32).to_le_bytes());
+            for modification in &object.modifications {
+                bytes.extend_from_slice(modification.field.as_bytes());
+                let (value_type, value) = match &modification.value {
+                    ModificationValue::Int(value) => (0u32, value.to_le_bytes().to_vec()),
+                    ModificationValue::Real(value) => (1, value.to_le_bytes().to_vec()),
+                    ModificationValue::Unreal(value) => (2, value.to_le_bytes().to_vec()),
+                    ModificationValue::String(value) => (3, [value.as_bytes(), &[0]].concat()),
+                };
+                bytes.extend_from_slice(&value_type.to_le_bytes());
+                if kind.has_levels() {
+                    bytes.extend_from_slice(&modification.level.to_le_bytes());
+                    bytes.extend_from_slice(&modification.data_pointer.to_le_bytes());
+                }
+                bytes.extend_from_slice(&value);
+                bytes.extend_from_slice(object.effective_id().as_bytes());
+            }
+        }
+    }
+    bytes
+}
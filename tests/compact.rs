@@ -0,0 +1,43 @@
+//! Compaction of archives whose encrypted `FIX_KEY` files move: every locale variant has to be re-keyed under the
+//! shared name.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+
+const LOCALE_RU_RU: u16 = 0x419;
+
+/// A v1 archive: a dead block first (so compaction moves everything after it), `name` encrypted with `FIX_KEY` in
+/// the neutral locale and in ruRU, then a `(listfile)`.
+fn build(name: &str, neutral: &[u8], russian: &[u8]) -> Vec<u8> {
+    ArchiveBuilder::new()
+        .file(TestFile::dead(&[0xAA; 100]))
+        .file(TestFile::plain(name, neutral).encrypted(true))
+        .file(TestFile::plain(name, russian).with_locale(LOCALE_RU_RU).encrypted(true))
+        .build()
+}
+
+fn read_variant(bytes: &[u8], name: &str, locale: u16) -> Vec<u8> {
+    let mut archive = MpqArchive::from_bytes(bytes.to_vec().into()).unwrap();
+    archive.set_preferred_locale(locale);
+    archive.read_file(name).unwrap()
+}
+
+#[test]
+fn compaction_rekeys_every_locale_of_fix_key_files() {
+    let name = "scripts\\war3map.j";
+    let neutral = b"function main takes nothing returns nothing\r\nendfunction\r\n";
+    let russian = b"// ru\r\nfunction main takes nothing returns nothing\r\nendfunction\r\n";
+    let original = build(name, neutral, russian);
+    assert_eq!(read_variant(&original, name, 0), neutral);
+    assert_eq!(read_variant(&original, name, LOCALE_RU_RU), russian);
+
+    let archive = MpqArchive::from_bytes(original.into()).unwrap();
+    let mut compacted = Vec::new();
+    let report = archive.compact_to(&mut compacted, |_| {}).unwrap();
+    assert_eq!(report.freed_blocks, 1);
+
+    assert_eq!(read_variant(&compacted, name, 0), neutral);
+    assert_eq!(read_variant(&compacted, name, LOCALE_RU_RU), russian);
+}
@@ -0,0 +1,109 @@
+//! Converted siblings: made on first open, kept while they fit the cache, and listed with the size they converted to.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::tables::{MPQ_FILE_COMPRESS, MPQ_FILE_EXISTS, MPQ_FILE_SINGLE_UNIT};
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveChain, MpqArchiveDescriptor, MpqArchiveError};
+use mpq_folder_win::convert::{Converter, ConverterRegistry};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Upper-cases `.txt` files into `.txt.up` siblings, counting its conversions.
+struct Upper(Arc<AtomicUsize>);
+
+impl Converter for Upper {
+    fn name(&self) -> &'static str {
+        "upper"
+    }
+
+    fn source_extensions(&self) -> &'static [&'static str] {
+        &["txt"]
+    }
+
+    fn target_extension(&self) -> &'static str {
+        "up"
+    }
+
+    fn convert(&self, data: &[u8]) -> Result<Vec<u8>, MpqArchiveError> {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Ok(data.to_ascii_uppercase())
+    }
+}
+
+#[test]
+fn cache_drops_least_recently_used_conversions() {
+    let files = ["a", "b", "c"].map(|name| TestFile::plain(&format!("Docs\\{name}.txt"), &[b'x'; 100]));
+    let archive = MpqArchive::from_bytes(ArchiveBuilder::new().files(files).build().into()).unwrap();
+    let descriptor = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap();
+    let conversions = Arc::new(AtomicUsize::new(0));
+    let mut registry = ConverterRegistry::default();
+    registry.register(Box::new(Upper(Arc::clone(&conversions))));
+    registry.set_cache_limit(250);
+    let open = |registry: &ConverterRegistry, path: &str| registry.converted(&descriptor, path).unwrap();
+
+    assert_eq!(registry.known_size("Docs/a.txt.up"), None);
+    assert_eq!(&*open(&registry, "Docs/a.txt.up").read().unwrap(), &[b'X'; 100]);
+    open(&registry, "docs\\B.TXT.UP");
+    open(&registry, "Docs/a.txt.up");
+    assert_eq!(conversions.load(Ordering::Relaxed), 2);
+
+    // A third conversion goes past 250 bytes: `b` was used least recently and is dropped.
+    open(&registry, "Docs/c.txt.up");
+    assert!(registry.cached_bytes() <= 250);
+    open(&registry, "Docs/a.txt.up");
+    assert_eq!(conversions.load(Ordering::Relaxed), 3);
+    open(&registry, "Docs/b.txt.up");
+    assert_eq!(conversions.load(Ordering::Relaxed), 4);
+
+    // Sizes stay known after the bytes are dropped.
+    for name in ["a", "b", "c"] {
+        assert_eq!(registry.known_size(&format!("Docs/{name}.txt.up")), Some(100));
+    }
+
+    // Conversions larger than the whole cache are served but not kept.
+    registry.set_cache_limit(50);
+    assert_eq!(registry.cached_bytes(), 0);
+    open(&registry, "Docs/a.txt.up");
+    open(&registry, "Docs/a.txt.up");
+    assert_eq!(conversions.load(Ordering::Relaxed), 6);
+}
+
+/// A 16-bit mono PCM WAV holding `samples` of silence.
+fn wav(samples: usize) -> Vec<u8> {
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&(36 + 2 * samples as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    [1u16, 1].iter().for_each(|value| wav.extend_from_slice(&value.to_le_bytes()));
+    [22050u32, 44100].iter().for_each(|value| wav.extend_from_slice(&value.to_le_bytes()));
+    [2u16, 16].iter().for_each(|value| wav.extend_from_slice(&value.to_le_bytes()));
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(2 * samples as u32).to_le_bytes());
+    wav.resize(wav.len() + 2 * samples, 0);
+    wav
+}
+
+#[test]
+fn files_that_cannot_be_decompressed_get_no_siblings() {
+    // Huffman then ADPCM, as Blizzard stores sounds: the mask byte is all this crate needs to know it cannot read them.
+    let mut packed = vec![0x41];
+    packed.extend_from_slice(&wav(100)[..64]);
+    let flags = MPQ_FILE_EXISTS | MPQ_FILE_COMPRESS | MPQ_FILE_SINGLE_UNIT;
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::raw("Sound\\adpcm.wav", &packed, wav(100).len() as u32, flags))
+        .file(TestFile::compressed("Sound\\pcm.wav", &wav(100)));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    let descriptor = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap();
+    let registry = ConverterRegistry::builtin();
+
+    let adpcm = descriptor.find_entry("Sound/adpcm.wav").unwrap();
+    assert_eq!(adpcm.compression_mask(), 0x41);
+    assert!(registry.siblings("adpcm.wav", adpcm).is_empty());
+    assert!(registry.converted(&descriptor, "Sound/adpcm.wav.txt").is_none());
+
+    let pcm = descriptor.find_entry("Sound/pcm.wav").unwrap();
+    assert_eq!(registry.siblings("pcm.wav", pcm), ["pcm.wav.txt"]);
+    let info = registry.converted(&descriptor, "Sound/pcm.wav.txt").unwrap().read().unwrap();
+    assert!(String::from_utf8_lossy(&info).contains("22050"));
+}
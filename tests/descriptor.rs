@@ -0,0 +1,93 @@
+//! The mounted view of an archive: entries keep Storm's `\` paths while the file system looks them up with `/`.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::{LoadOptions, MpqArchive, MpqArchiveChain, MpqArchiveDescriptor, STRINGS_DIR_NAME};
+use mpq_folder_win::convert::ConverterRegistry;
+
+fn descriptor(builder: ArchiveBuilder) -> MpqArchiveDescriptor {
+    descriptor_of(builder.build())
+}
+
+fn descriptor_of(bytes: Vec<u8>) -> MpqArchiveDescriptor {
+    let archive = MpqArchive::from_bytes(bytes.into()).unwrap();
+    MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap()
+}
+
+#[test]
+fn subfolder_files_are_found_with_either_separator() {
+    let descriptor = descriptor(
+        ArchiveBuilder::new()
+            .file(TestFile::plain("war3mapImported\\foo.blp", b"BLP1"))
+            .file(TestFile::plain("war3mapImported\\strings.wts", b"STRING 1\r\n{\r\nHello\r\n}\r\n")),
+    );
+
+    for path in ["war3mapImported/foo.blp", "WAR3MAPIMPORTED\\FOO.BLP", "war3mapimported/Foo.blp"] {
+        let entry = descriptor.find_entry(path).unwrap_or_else(|| panic!("{path} not found"));
+        assert_eq!(entry.path, "war3mapImported\\foo.blp");
+        assert_eq!(entry.uncompressed_size, 4);
+    }
+    assert!(descriptor.find_entry("war3mapImported/foo.bl").is_none());
+    assert!(descriptor.find_entry("war3mapImported").is_none());
+
+    // Converted siblings find their source the same way.
+    let converters = ConverterRegistry::builtin();
+    assert!(converters.converted(&descriptor, "war3mapImported/strings.wts.txt").is_some());
+}
+
+/// Text that zlib shrinks; 400 lines span four 4096-byte sectors.
+fn text(lines: usize) -> Vec<u8> {
+    (0..lines).flat_map(|i| format!("line {i}: the quick brown fox\r\n").into_bytes()).collect()
+}
+
+#[test]
+fn compression_mask_comes_from_sector_headers() {
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::compressed("sectors.txt", &text(400)).encrypted(true))
+        .file(TestFile::compressed("unit.txt", &text(50)).single_unit())
+        .file(TestFile::plain("plain.txt", b"stored as is"));
+    let mut bytes = builder.build();
+    // Damage the deflate stream of `unit.txt` past its mask byte: reading fails, the mask is still known.
+    let archive = MpqArchive::from_bytes(bytes.clone().into()).unwrap();
+    let (_, unit) = archive.find_file("unit.txt").unwrap();
+    assert_eq!(archive.block_compression_mask(unit, None).unwrap(), 0x02);
+    let damaged = unit.file_pos as usize + 20;
+    bytes[damaged..damaged + 16].fill(0xFF);
+    let descriptor = descriptor_of(bytes);
+
+    let sectors = descriptor.find_entry("sectors.txt").unwrap();
+    assert_eq!(sectors.compression_mask(), 0x02);
+    assert!(sectors.metadata_text().contains("compression: zlib\r\n"));
+    assert_eq!(sectors.read().unwrap().len(), text(400).len());
+
+    let unit = descriptor.find_entry("unit.txt").unwrap();
+    assert!(unit.read().is_err());
+    assert_eq!(unit.compression_mask(), 0x02);
+
+    let plain = descriptor.find_entry("plain.txt").unwrap();
+    assert_eq!(plain.compression_mask(), 0);
+    assert!(plain.metadata_text().contains("compression: none\r\n"));
+}
+
+#[test]
+fn strings_view_is_made_on_first_use() {
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::plain("war3map.wts", b"STRING 1\r\n{\r\nSay \"hi\"\r\n}\r\n"))
+        .file(TestFile::plain("war3map.j", b"call DisplayText(\"TRIGSTR_001\")\r\n"))
+        .file(TestFile::plain("war3map.w3i", b"no strings here"));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    let options = LoadOptions { inline_strings: true, ..LoadOptions::default() };
+    let descriptor = MpqArchiveDescriptor::from_chain_with(MpqArchiveChain::new(archive), &options).unwrap();
+
+    assert!(descriptor.has_strings_view());
+    assert!(!descriptor.entries().iter().any(|entry| entry.path.starts_with(STRINGS_DIR_NAME)));
+    let script = descriptor.find_entry("(strings)/war3map.j").expect("inlined script");
+    assert_eq!(&*script.read().unwrap(), b"call DisplayText(\"Say \\\"hi\\\"\")\r\n");
+    assert_eq!(descriptor.strings_view().len(), 1);
+    assert!(descriptor.find_entry("(strings)\\war3map.w3i").is_none());
+    assert!(descriptor.find_entry("war3map.j").is_some());
+
+    // Off by default.
+    assert!(!descriptor_of(builder.build()).has_strings_view());
+}
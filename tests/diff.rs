@@ -0,0 +1,56 @@
+//! Archive diff: files are matched by name regardless of case and compared by their decompressed content, with
+//! storage changes reported apart from content changes.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::diff::{ChangeKind, diff_archives};
+
+fn open(builder: ArchiveBuilder) -> MpqArchive {
+    MpqArchive::from_bytes(builder.build().into()).unwrap()
+}
+
+#[test]
+fn diff_reports_added_removed_and_modified_files() {
+    let text = b"the same text, compressed or not, the same text".repeat(20);
+    let old = open(
+        ArchiveBuilder::new()
+            .file(TestFile::plain("war3map.j", b"old script"))
+            .file(TestFile::plain("Units\\Footman.mdx", b"MDLX"))
+            .file(TestFile::plain("readme.txt", &text))
+            .file(TestFile::plain("war3map.w3i", b"info"))
+            .file(TestFile::plain("war3map.wts", b"strings")),
+    );
+    let new = open(
+        ArchiveBuilder::new()
+            .file(TestFile::plain("war3map.j", b"new script"))
+            .file(TestFile::compressed("README.TXT", &text))
+            .file(TestFile::plain("war3map.w3i", b"info").encrypted(false))
+            .file(TestFile::plain("war3map.wts", b"strings"))
+            .file(TestFile::plain("war3map.shd", b"shadows")),
+    );
+    let diff = diff_archives(&old, &new);
+    let change = |path: &str| diff.changes.iter().find(|change| change.path.eq_ignore_ascii_case(path));
+
+    assert_eq!(change("war3map.shd").unwrap().kind, ChangeKind::Added);
+    assert_eq!(change("Units\\Footman.mdx").unwrap().kind, ChangeKind::Removed);
+
+    let script = change("war3map.j").unwrap();
+    assert_eq!(script.kind, ChangeKind::Modified);
+    assert!(script.content_changed() && !script.flags_changed() && !script.compression_changed());
+
+    // Compressing or encrypting a file changes how it is stored, not what it holds.
+    let readme = change("readme.txt").unwrap();
+    assert_eq!(readme.kind, ChangeKind::Modified);
+    assert!(!readme.content_changed() && readme.flags_changed() && readme.compression_changed());
+    assert_eq!((readme.old.as_ref().unwrap().compression_mask, readme.new.as_ref().unwrap().compression_mask), (0, 0x02));
+    let info = change("war3map.w3i").unwrap();
+    assert!(!info.content_changed() && info.flags_changed() && !info.compression_changed());
+
+    assert!(change("war3map.wts").is_none());
+    assert!(diff.changes.windows(2).all(|pair| pair[0].path.to_ascii_lowercase() <= pair[1].path.to_ascii_lowercase()));
+    assert_eq!(diff.count(ChangeKind::Added), 1);
+    assert_eq!(diff.count(ChangeKind::Removed), 1);
+    assert!(diff_archives(&old, &old).is_empty());
+}
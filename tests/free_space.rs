@@ -0,0 +1,53 @@
+//! Deleted hash slots, blocks nothing references and bytes nothing points at, as the free-space map reports them.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::space::{UnusedKind, UnusedRange};
+use mpq_folder_win::archive::tables::HashSlotState;
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveChain, MpqArchiveDescriptor};
+
+#[test]
+fn unused_blocks_and_trailing_bytes_are_mapped() {
+    let mut bytes = ArchiveBuilder::new()
+        .file(TestFile::dead(&[0xAA; 100]))
+        .file(TestFile::plain("kept.txt", b"kept"))
+        .hash_table_size(8)
+        .deleted_slots(2)
+        .build();
+    // Declare 64 more bytes than the tables end at: nothing points there.
+    let declared = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    bytes[8..12].copy_from_slice(&(declared + 64).to_le_bytes());
+    bytes.resize(bytes.len() + 64, 0);
+    let archive = MpqArchive::from_bytes(bytes.into()).unwrap();
+
+    let deleted = archive.deleted_slots();
+    assert_eq!(deleted.len(), 2);
+    assert!(deleted.iter().all(|&slot| archive.hash_table()[slot].state() == HashSlotState::Deleted));
+    assert_eq!(archive.unused_blocks(), [0]);
+
+    let space = archive.free_space();
+    assert_eq!(space.archive_size, declared as u64 + 64);
+    assert_eq!(space.ranges, [
+        UnusedRange { offset: archive.block_table()[0].file_pos, len: 100, kind: UnusedKind::UnusedBlock(0) },
+        UnusedRange { offset: declared as u64, len: 64, kind: UnusedKind::Gap },
+    ]);
+    assert_eq!(space.unused_bytes(), 164);
+    assert_eq!((space.deleted_slots.clone(), space.unused_blocks.clone()), (deleted, vec![0]));
+}
+
+#[test]
+fn delete_markers_are_not_mounted() {
+    let base = ArchiveBuilder::new().file(TestFile::plain("Obsolete.txt", b"old")).file(TestFile::plain("Kept.txt", b"kept")).build();
+    let update = ArchiveBuilder::new().file(TestFile::delete_marker("Obsolete.txt")).build();
+    let open = |bytes: &[u8]| MpqArchive::from_bytes(bytes.to_vec().into()).unwrap();
+
+    let alone = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(open(&update))).unwrap();
+    assert!(alone.find_entry("Obsolete.txt").is_none());
+
+    let mut chain = MpqArchiveChain::new(open(&base));
+    chain.add_patch(open(&update));
+    let overlay = MpqArchiveDescriptor::from_chain(chain).unwrap();
+    assert!(overlay.find_entry("Obsolete.txt").is_none());
+    assert_eq!(&*overlay.find_entry("Kept.txt").unwrap().read().unwrap(), b"kept");
+}
@@ -0,0 +1,42 @@
+//! Classic hash table lookups as Storm does them: the home slot is the name hash masked with `size - 1`, then entries
+//! are probed in order until an empty one.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::anomaly::Anomaly;
+use mpq_folder_win::archive::crypto::{HASH_TABLE_OFFSET, hash_string};
+use mpq_folder_win::archive::reader::OpenOptions;
+
+const NAMES: [&str; 4] = ["war3map.j", "war3map.w3e", "war3map.wts", "war3mapMap.blp"];
+
+#[test]
+fn non_power_of_two_table_is_probed_from_the_masked_slot() {
+    // Names whose masked home slot differs from the remainder, or the test would not tell them apart.
+    assert!(NAMES.iter().any(|name| hash_string(name, HASH_TABLE_OFFSET) as usize & 5 != hash_string(name, HASH_TABLE_OFFSET) as usize % 6));
+
+    let builder = ArchiveBuilder::new().files(NAMES.map(|name| TestFile::plain(name, name.as_bytes()))).hash_table_size(6);
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    assert_eq!(archive.hash_table().len(), 6);
+    for name in NAMES {
+        assert_eq!(archive.read_file(name).unwrap(), name.as_bytes(), "{name}");
+    }
+    assert!(!archive.contains("war3map.w3i"));
+
+    let options = OpenOptions { tolerant: true, ..OpenOptions::default() };
+    let archive = MpqArchive::from_bytes_with(builder.build().into(), options).unwrap();
+    assert!(archive.anomalies().iter().any(|anomaly| matches!(anomaly, Anomaly::HashTableSizeNotPowerOfTwo(6))));
+}
+
+#[test]
+fn probing_skips_deleted_entries_and_stops_at_empty_ones() {
+    let builder = ArchiveBuilder::new().files(NAMES.map(|name| TestFile::plain(name, name.as_bytes()))).hash_table_size(16).deleted_slots(16);
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    assert!(archive.hash_table().iter().all(|entry| !entry.is_empty()));
+    for name in NAMES {
+        assert_eq!(archive.read_file(name).unwrap(), name.as_bytes(), "{name}");
+    }
+    // A full table without empty entries still ends the probe after one pass.
+    assert!(!archive.contains("war3map.w3i"));
+}
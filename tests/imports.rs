@@ -0,0 +1,76 @@
+//! `war3map.imp` against the archive: imports found where the manifest says, elsewhere, or nowhere, and files nobody
+//! imported.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::signature::SignatureKeys;
+use mpq_folder_win::war3::imports::{ImportEntry, ImportManifest, ImportReport};
+
+/// A manifest of `version`; version 0 stores no path flags.
+fn imp(version: u32, entries: &[(u8, &str)]) -> Vec<u8> {
+    let mut bytes = version.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for (flag, path) in entries {
+        if version >= 1 {
+            bytes.push(*flag);
+        }
+        bytes.extend_from_slice(&[path.as_bytes(), &[0]].concat());
+    }
+    bytes
+}
+
+const ENTRIES: [(u8, &str); 5] = [(5, "icon.blp"), (13, "Custom\\model.mdx"), (13, "Sounds\\hit.wav"), (5, "missing.tga"), (5, "Textures/skin.blp")];
+
+fn archive() -> MpqArchive {
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::plain("war3map.imp", &imp(1, &ENTRIES)))
+        .file(TestFile::plain("war3map.j", b""))
+        .file(TestFile::plain("war3mapImported\\icon.blp", b"BLP1"))
+        .file(TestFile::plain("custom\\MODEL.mdx", b"MDLX"))
+        .file(TestFile::plain("war3mapImported\\Sounds\\hit.wav", b"RIFF"))
+        .file(TestFile::plain("Other\\skin.blp", b"BLP1"))
+        .file(TestFile::plain("stray.txt", b"?"));
+    MpqArchive::from_bytes(builder.build().into()).unwrap()
+}
+
+#[test]
+fn manifest_paths_follow_their_flags() {
+    let manifest = ImportManifest::parse(&imp(1, &ENTRIES)).unwrap();
+    let paths: Vec<String> = manifest.entries.iter().map(ImportEntry::archive_path).collect();
+    assert_eq!(paths, ["war3mapImported\\icon.blp", "Custom\\model.mdx", "Sounds\\hit.wav", "war3mapImported\\missing.tga", "war3mapImported\\Textures\\skin.blp"]);
+
+    let old = ImportManifest::parse(&imp(0, &[(0, "war3mapImported\\a.blp"), (0, "b.mdx")])).unwrap();
+    assert_eq!(old.entries.iter().map(ImportEntry::archive_path).collect::<Vec<_>>(), ["war3mapImported\\a.blp", "war3mapImported\\b.mdx"]);
+
+    let full = imp(1, &ENTRIES);
+    assert!(ImportManifest::parse(&full[..full.len() - 1]).is_err());
+}
+
+#[test]
+fn imports_are_reconciled_with_the_archive() {
+    let report = archive().check_imports().unwrap().unwrap();
+    assert_eq!(report.listed, 5);
+    assert_eq!(report.missing, ["war3mapImported\\missing.tga"]);
+    assert_eq!(report.mismatched, [
+        ("Sounds\\hit.wav".to_string(), "war3mapImported\\Sounds\\hit.wav".to_string()),
+        ("war3mapImported\\Textures\\skin.blp".to_string(), "Other\\skin.blp".to_string()),
+    ]);
+    assert_eq!(report.orphaned, ["stray.txt"]);
+    assert!(!report.is_ok());
+
+    let manifest = ImportManifest::parse(&imp(1, &[(5, "icon.blp")])).unwrap();
+    assert!(ImportReport::new(&manifest, &["war3mapImported/ICON.blp".to_string(), "war3map.w3i".to_string()]).is_ok());
+}
+
+#[test]
+fn verify_reports_imports_without_failing_on_them() {
+    let report = archive().verify(&SignatureKeys::blizzard());
+    let imports = report.imports.as_ref().unwrap().as_ref().unwrap();
+    assert_eq!(imports.missing.len(), 1);
+    assert!(report.is_ok());
+
+    let plain = MpqArchive::from_bytes(ArchiveBuilder::new().file(TestFile::plain("a.txt", b"")).build().into()).unwrap();
+    assert!(plain.verify(&SignatureKeys::blizzard()).imports.is_none());
+}
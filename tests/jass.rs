@@ -0,0 +1,85 @@
+//! JASS scripts: tokens, the re-indented copy mounted as `war3map.pretty.j` and the declaration index.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveChain, MpqArchiveDescriptor};
+use mpq_folder_win::war3::jass::{JassIndex, TokenKind, prettify, tokenize};
+
+const MINIFIED: &str = "type unit extends handle\nnative GetUnitX takes unit whichUnit returns real\nglobals\nconstant integer MAX=12\nunit array heroes\nendglobals\n// Adds two.\nfunction add takes integer a,integer b returns integer\nreturn a+b\nendfunction\nfunction main takes nothing returns nothing\nlocal integer i=-1\nif(i<MAX)then\ncall add(i,'hfoo')\nendif\nendfunction\n";
+
+#[test]
+fn tokens_keep_their_kind_and_line() {
+    let tokens = tokenize("set x = 0x1F + 1.5 // half\r\ncall Print(\"a \\\" b\", 'A000')");
+    let kinds: Vec<(TokenKind, &str, u32)> = tokens.iter().map(|token| (token.kind, token.text, token.line)).collect();
+    assert_eq!(kinds[..7], [
+        (TokenKind::Keyword, "set", 1),
+        (TokenKind::Identifier, "x", 1),
+        (TokenKind::Operator, "=", 1),
+        (TokenKind::Integer, "0x1F", 1),
+        (TokenKind::Operator, "+", 1),
+        (TokenKind::Real, "1.5", 1),
+        (TokenKind::Comment, "// half", 1),
+    ]);
+    assert_eq!(tokens[8].line, 2);
+    assert!(tokens.iter().any(|token| token.kind == TokenKind::String && token.text == "\"a \\\" b\""));
+    assert!(tokens.iter().any(|token| token.kind == TokenKind::RawCode && token.text == "'A000'"));
+}
+
+#[test]
+fn minified_scripts_are_reindented() {
+    let expected = [
+        "type unit extends handle",
+        "native GetUnitX takes unit whichUnit returns real",
+        "",
+        "globals",
+        "    constant integer MAX = 12",
+        "    unit array heroes",
+        "endglobals",
+        "",
+        "// Adds two.",
+        "function add takes integer a, integer b returns integer",
+        "    return a + b",
+        "endfunction",
+        "",
+        "function main takes nothing returns nothing",
+        "    local integer i = -1",
+        "    if (i < MAX) then",
+        "        call add(i, 'hfoo')",
+        "    endif",
+        "endfunction",
+        "",
+        "",
+    ];
+    let pretty = prettify(MINIFIED);
+    assert_eq!(pretty, expected.join("\r\n"));
+    assert_eq!(prettify(&pretty), pretty);
+}
+
+#[test]
+fn declarations_are_indexed() {
+    let index = JassIndex::parse(MINIFIED);
+    assert_eq!((index.types[0].name.as_str(), index.types[0].parent.as_str()), ("unit", "handle"));
+    assert_eq!(index.natives[0].name, "GetUnitX");
+    let globals: Vec<(&str, bool, bool)> = index.globals.iter().map(|global| (global.name.as_str(), global.constant, global.array)).collect();
+    assert_eq!(globals, [("MAX", true, false), ("heroes", false, true)]);
+
+    let add = index.function("add").unwrap();
+    assert_eq!(add.parameters.iter().map(|parameter| parameter.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+    assert_eq!((add.returns.as_str(), add.line, add.end_line), ("integer", 8, 10));
+    assert_eq!(index.function("main").unwrap().returns, "nothing");
+    assert!(index.function("GetUnitX").is_none());
+}
+
+#[test]
+fn pretty_copies_are_mounted_next_to_scripts() {
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::compressed("scripts\\war3map.j", MINIFIED.as_bytes()))
+        .file(TestFile::plain("war3map.lua", b"function main() end"));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    let descriptor = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap();
+
+    let pretty = descriptor.find_entry("scripts/war3map.pretty.j").unwrap().read().unwrap();
+    assert_eq!(&*pretty, prettify(MINIFIED).as_bytes());
+    assert!(descriptor.find_entry("war3map.pretty.j").is_none());
+}
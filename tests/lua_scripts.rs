@@ -0,0 +1,96 @@
+//! Lua map scripts: the language `war3map.w3i` declares, the function and global index, and `FourCC` ids
+//! cross-referenced against object data.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile, object_data, w3i};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::war3::lua::LuaIndex;
+use mpq_folder_win::war3::objects::{ObjectDefinition, ObjectKind};
+use mpq_folder_win::war3::script::{RawcodeOrigin, RawcodeReport};
+use mpq_folder_win::war3::w3i::{ScriptLanguage, W3I_VERSION_LUA};
+
+const LUA: &str = "\
+gg_trg_Init = nil
+local udg_count, udg_name = 0, \"x\"
+--[[ function Commented() end ]]
+function InitGlobals()
+    udg_count = 1
+end
+local function spawn(player, x, y)
+    if x > 0 then
+        return CreateUnit(player, FourCC(\"h000\"), x, y, 0)
+    end
+    return CreateUnit(player, FourCC('hpea'), x, y, 0)
+end
+Hero = {}
+function Hero.new(id) return { id = id } end
+function Hero:level() return self.lvl end
+Hero.onDeath = function(unit) end
+function main()
+    spawn(Player(0), 0, 0) spawn(Player(1), FourCC(\"Hpal\"), 1)
+end
+";
+
+fn object(base_id: &str, id: Option<&str>) -> ObjectDefinition {
+    ObjectDefinition { base_id: base_id.to_string(), id: id.map(str::to_string), modifications: Vec::new() }
+}
+
+#[test]
+fn lua_functions_and_globals_are_indexed() {
+    let index = LuaIndex::parse(LUA);
+    let functions: Vec<(&str, bool, u32, u32)> = index.functions.iter().map(|function| (function.name.as_str(), function.local, function.line, function.end_line)).collect();
+    assert_eq!(functions, [
+        ("InitGlobals", false, 4, 6),
+        ("spawn", true, 7, 12),
+        ("Hero.new", false, 14, 14),
+        ("Hero:level", false, 15, 15),
+        ("Hero.onDeath", false, 16, 16),
+        ("main", false, 17, 19),
+    ]);
+    assert_eq!(index.function("spawn").unwrap().parameters, ["player", "x", "y"]);
+    let globals: Vec<(&str, bool)> = index.globals.iter().map(|global| (global.name.as_str(), global.local)).collect();
+    assert_eq!(globals, [("gg_trg_Init", false), ("udg_count", true), ("udg_name", true), ("Hero", false)]);
+    assert_eq!(index.fourcc, [("h000".to_string(), 9), ("hpea".to_string(), 11), ("Hpal".to_string(), 18)]);
+}
+
+#[test]
+fn script_language_follows_war3map_w3i() {
+    let archive = |lua: bool, files: &[(&str, &[u8])]| {
+        let builder = ArchiveBuilder::new()
+            .file(TestFile::plain("war3map.w3i", &w3i(W3I_VERSION_LUA, "Map", "Me", lua)))
+            .files(files.iter().map(|(name, data)| TestFile::plain(name, data)));
+        MpqArchive::from_bytes(builder.build().into()).unwrap()
+    };
+    let jass: (&str, &[u8]) = ("war3map.j", b"function main takes nothing returns nothing\r\nendfunction\r\n");
+    let lua: (&str, &[u8]) = ("war3map.lua", LUA.as_bytes());
+
+    let script = archive(true, &[jass, lua]).map_script().unwrap();
+    assert_eq!((script.language, script.path), (ScriptLanguage::Lua, "war3map.lua"));
+    let script = archive(false, &[jass, lua]).map_script().unwrap();
+    assert_eq!((script.language, script.path), (ScriptLanguage::Jass, "war3map.j"));
+    // A declared language without its script falls back to the one present.
+    let script = archive(false, &[lua]).map_script().unwrap();
+    assert_eq!(script.language, ScriptLanguage::Lua);
+    assert!(archive(true, &[]).map_script().is_none());
+}
+
+#[test]
+fn fourcc_ids_are_matched_against_object_data() {
+    let units = object_data(ObjectKind::Units, 2, &[object("hpea", None)], &[object("hfoo", Some("h000")), object("hfoo", Some("h001"))]);
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::plain("war3map.w3i", &w3i(W3I_VERSION_LUA, "Map", "Me", true)))
+        .file(TestFile::plain("war3map.lua", LUA.as_bytes()))
+        .file(TestFile::plain("war3map.w3u", &units));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+
+    let rawcodes = archive.map_script().unwrap().rawcodes();
+    let report = RawcodeReport::new(&rawcodes, &archive.all_object_data());
+    let uses: Vec<(&str, &[RawcodeOrigin])> = report.uses.iter().map(|rawcode| (rawcode.id.as_str(), rawcode.origins.as_slice())).collect();
+    assert_eq!(uses, [
+        ("Hpal", &[][..]),
+        ("h000", &[RawcodeOrigin::Custom(ObjectKind::Units)][..]),
+        ("hpea", &[RawcodeOrigin::Modified(ObjectKind::Units)][..]),
+    ]);
+    assert_eq!(report.unreferenced, [(ObjectKind::Units, "h001".to_string())]);
+}
@@ -0,0 +1,64 @@
+//! Archives whose headers claim more than the file holds fail with an error instead of allocating what they claim.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::anomaly::Anomaly;
+use mpq_folder_win::archive::header::{HEADER_SIZE_V1, HEADER_SIZE_V4, MpqHeader};
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::verify::Check;
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveError};
+
+/// A 64-byte v1 archive whose hash table claims 2^32 - 1 entries (64 GiB).
+fn huge_hash_table() -> Vec<u8> {
+    let header = MpqHeader {
+        header_size: HEADER_SIZE_V1,
+        archive_size: 64,
+        sector_size_shift: 3,
+        hash_table_pos: HEADER_SIZE_V1,
+        block_table_pos: HEADER_SIZE_V1,
+        hash_table_size: u32::MAX,
+        ..MpqHeader::default()
+    };
+    let mut bytes = header.to_bytes();
+    bytes.resize(64, 0);
+    bytes
+}
+
+#[test]
+fn oversized_table_is_corrupted_not_allocated() {
+    let error = MpqArchive::from_bytes(huge_hash_table().into()).err();
+    assert!(matches!(error, Some(MpqArchiveError::Corrupted(_))), "{error:?}");
+
+    // Tolerant opens read what is there and report the cut.
+    let options = OpenOptions { tolerant: true, ..OpenOptions::default() };
+    let archive = MpqArchive::from_bytes_with(huge_hash_table().into(), options).unwrap();
+    assert_eq!(archive.hash_table().len(), 2);
+    assert!(archive.anomalies().iter().any(|anomaly| matches!(anomaly, Anomaly::TableCut { table: "hash table", available: 32, .. })));
+}
+
+#[test]
+fn reads_past_the_end_fail() {
+    let bytes = ArchiveBuilder::new().file(TestFile::plain("a.txt", b"abc")).build();
+    let archive = MpqArchive::from_bytes(bytes.clone().into()).unwrap();
+    assert_eq!(archive.read_absolute(0, 4).unwrap(), bytes[..4]);
+    assert!(archive.read_absolute(0, bytes.len() + 1).is_err());
+    assert!(archive.read_absolute(0, usize::MAX).is_err());
+    assert!(archive.read_absolute(u64::MAX, 1).is_err());
+    assert!(archive.read_exact(u64::MAX, 16).is_err());
+}
+
+#[test]
+fn md5_pieces_past_the_end_are_mismatches() {
+    let bytes = ArchiveBuilder::new().file(TestFile::plain("a.txt", b"abc")).format_version(3).build();
+    let mut header = MpqHeader::parse(&bytes).unwrap();
+    (header.het_table_pos, header.het_table_size_64, header.md5_het_table) = (0x10, u64::MAX, [1; 16]);
+    (header.bet_table_pos, header.bet_table_size_64, header.md5_bet_table) = (u64::MAX, 16, [1; 16]);
+    let mut bytes = bytes;
+    bytes[..HEADER_SIZE_V4 as usize].copy_from_slice(&header.to_bytes());
+
+    let archive = MpqArchive::from_bytes(bytes.into()).unwrap();
+    let checks: Vec<_> = archive.verify_md5_pieces().into_iter().map(|piece| (piece.piece, piece.check)).collect();
+    assert!(checks.contains(&("HET table", Check::Mismatch)));
+    assert!(checks.contains(&("BET table", Check::Mismatch)));
+}
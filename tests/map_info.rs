@@ -0,0 +1,61 @@
+//! `war3map.w3i` across its format versions, with `TRIGSTR_` references resolved from `war3map.wts`.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile, w3i};
+use mpq_folder_win::archive::{MAP_INFO_FILE_NAME, MpqArchive, MpqArchiveChain, MpqArchiveDescriptor};
+use mpq_folder_win::war3::w3i::{MapInfo, ScriptLanguage, W3I_VERSION_REFORGED, W3I_VERSION_ROC, W3I_VERSION_TFT};
+
+const WTS: &[u8] = b"\xEF\xBB\xBFSTRING 1\r\n{\r\nFrozen Lake\r\n}\r\n\r\nSTRING 2\r\n// author\r\n{\r\nA Mapper\r\n}\r\n";
+
+#[test]
+fn every_format_version_parses() {
+    for version in [W3I_VERSION_ROC, W3I_VERSION_TFT, W3I_VERSION_REFORGED] {
+        let info = MapInfo::parse(&w3i(version, "Lake", "Me", false)).unwrap_or_else(|err| panic!("version {version}: {err}"));
+        assert_eq!((info.version, info.map_version, info.editor_version), (version, 7, 6072));
+        assert_eq!((info.name.as_str(), info.author.as_str(), info.recommended_players.as_str()), ("Lake", "Me", "1v1"));
+        assert_eq!((info.playable_width, info.playable_height, info.tileset), (84, 84, 'N'));
+        assert!(info.is_melee());
+        assert_eq!(info.loading_screen.title, "Frozen Lake");
+        assert_eq!(info.game_data_set, (version >= W3I_VERSION_TFT).then_some(0));
+        assert_eq!(info.game_version.is_some(), version >= W3I_VERSION_REFORGED);
+        assert_eq!(info.game_data_version, (version >= W3I_VERSION_REFORGED).then_some(1));
+        assert_eq!(info.script_language, ScriptLanguage::Jass);
+        assert_eq!(info.players.iter().map(|player| (player.name.as_str(), player.race)).collect::<Vec<_>>(), [("Red", 1), ("Blue", 2)]);
+        assert_eq!(info.players[1].start_x, -512.0);
+        assert_eq!((info.forces.len(), info.forces[0].player_mask, info.forces[0].name.as_str()), (1, 0b11, "Everyone"));
+    }
+
+    assert!(MapInfo::parse(&w3i(20, "Lake", "Me", false)).is_err());
+    let full = w3i(W3I_VERSION_TFT, "Lake", "Me", false);
+    assert!(MapInfo::parse(&full[..full.len() - 4]).is_err());
+}
+
+#[test]
+fn trigger_strings_resolve_from_the_string_table() {
+    let info = MapInfo::load(&w3i(W3I_VERSION_TFT, "TRIGSTR_001", "TRIGSTR_002", false), Some(WTS)).unwrap();
+    assert_eq!((info.name.as_str(), info.author.as_str()), ("Frozen Lake", "A Mapper"));
+    // Unknown ids are kept as written.
+    let info = MapInfo::load(&w3i(W3I_VERSION_TFT, "TRIGSTR_009", "Me", false), Some(WTS)).unwrap();
+    assert_eq!(info.name, "TRIGSTR_009");
+}
+
+#[test]
+fn map_info_is_mounted_as_json() {
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::compressed("war3map.w3i", &w3i(W3I_VERSION_REFORGED, "TRIGSTR_001", "TRIGSTR_002", false)))
+        .file(TestFile::plain("war3map.wts", WTS));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    assert_eq!(archive.map_info().unwrap().unwrap().name, "Frozen Lake");
+
+    let descriptor = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap();
+    let json = descriptor.find_entry(MAP_INFO_FILE_NAME).unwrap().read().unwrap();
+    let json = String::from_utf8(json.to_vec()).unwrap();
+    for expected in ["\"Frozen Lake\"", "\"A Mapper\"", "\"Reforged\"", "\"Everyone\""] {
+        assert!(json.contains(expected), "{expected} missing from {json}");
+    }
+
+    let plain = MpqArchive::from_bytes(ArchiveBuilder::new().file(TestFile::plain("war3map.j", b"")).build().into()).unwrap();
+    assert!(plain.map_info().is_none());
+    assert!(MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(plain)).unwrap().find_entry(MAP_INFO_FILE_NAME).is_none());
+}
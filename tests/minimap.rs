@@ -0,0 +1,79 @@
+//! Map thumbnails: the custom preview when it decodes, else the minimap with the `war3map.mmp` icons drawn on it.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::texture::RgbaImage;
+use mpq_folder_win::war3::minimap::{MinimapIcon, MinimapIconKind, parse_mmp};
+
+/// An uncompressed 8-bit grayscale TGA stored top row first.
+fn tga(width: u16, height: u16, gray: u8) -> Vec<u8> {
+    let mut data = vec![0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(&[8, 0x20]);
+    data.resize(data.len() + width as usize * height as usize, gray);
+    data
+}
+
+/// Icons as `(kind, x, y, bgra)`.
+fn mmp(icons: &[(u32, u32, u32, [u8; 4])]) -> Vec<u8> {
+    let mut data = 0u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&(icons.len() as u32).to_le_bytes());
+    for (kind, x, y, bgra) in icons {
+        [kind, x, y].iter().for_each(|value| data.extend_from_slice(&value.to_le_bytes()));
+        data.extend_from_slice(bgra);
+    }
+    data
+}
+
+const ICONS: [(u32, u32, u32, [u8; 4]); 2] = [(0, 128, 128, [0, 0, 0, 255]), (2, 64, 64, [0, 0, 255, 255])];
+
+fn pixel(image: &RgbaImage, x: usize, y: usize) -> &[u8] {
+    let at = (y * image.width as usize + x) * 4;
+    &image.pixels[at..at + 4]
+}
+
+fn archive(files: Vec<TestFile>) -> MpqArchive {
+    MpqArchive::from_bytes(ArchiveBuilder::new().files(files).build().into()).unwrap()
+}
+
+#[test]
+fn mmp_icons_parse_with_rgba_colors() {
+    let icons = parse_mmp(&mmp(&ICONS)).unwrap();
+    assert_eq!(icons, [
+        MinimapIcon { kind: MinimapIconKind::GoldMine, x: 128, y: 128, color: [0, 0, 0, 255] },
+        MinimapIcon { kind: MinimapIconKind::StartLocation, x: 64, y: 64, color: [255, 0, 0, 255] },
+    ]);
+    let full = mmp(&ICONS);
+    assert!(parse_mmp(&full[..full.len() - 1]).is_err());
+}
+
+#[test]
+fn minimap_gets_icons_drawn_at_scale() {
+    let map = archive(vec![TestFile::plain("war3mapMap.tga", &tga(64, 64, 0x40)), TestFile::plain("war3map.mmp", &mmp(&ICONS))]);
+    let minimap = map.minimap(true).unwrap().unwrap();
+    assert_eq!((minimap.source, minimap.icons), ("war3mapMap.tga", 2));
+    // Icon space is 256 wide, so the mine lands in the middle of the 64-pixel minimap and the start location at 16.
+    assert_eq!(pixel(&minimap.image, 32, 32), [255, 210, 40, 255]);
+    assert_eq!(pixel(&minimap.image, 16, 16), [255, 0, 0, 255]);
+    assert_eq!(pixel(&minimap.image, 0, 63), [0x40, 0x40, 0x40, 255]);
+
+    let plain = map.minimap(false).unwrap().unwrap();
+    assert_eq!(plain.icons, 0);
+    assert_eq!(pixel(&plain.image, 32, 32), [0x40, 0x40, 0x40, 255]);
+    assert!(archive(vec![TestFile::plain("war3map.mmp", &mmp(&ICONS))]).minimap(true).is_none());
+}
+
+#[test]
+fn custom_preview_wins_when_it_decodes() {
+    let minimap = TestFile::plain("war3mapMap.tga", &tga(64, 64, 0x40));
+    let with_preview = archive(vec![minimap.clone(), TestFile::plain("war3mapPreview.tga", &tga(8, 8, 0x80))]);
+    let preview = with_preview.map_preview().unwrap().unwrap();
+    assert_eq!((preview.source, preview.image.width, preview.icons), ("war3mapPreview.tga", 8, 0));
+
+    let broken = archive(vec![minimap, TestFile::plain("war3mapPreview.tga", b"not a tga")]);
+    assert_eq!(broken.map_preview().unwrap().unwrap().source, "war3mapMap.tga");
+    assert!(archive(vec![TestFile::plain("war3map.j", b"")]).map_preview().is_none());
+}
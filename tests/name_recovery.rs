@@ -0,0 +1,51 @@
+//! Name recovery for maps without a `(listfile)`: standard map files first, then the paths the resolved files refer to.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::recovery::referenced_paths;
+
+fn stripped() -> Vec<u8> {
+    ArchiveBuilder::new()
+        .file(TestFile::plain("war3map.j", b"call AddSpecialEffect(\"Units\\\\Hero\\\\Hero.mdl\", 0, 0)\r\ncall SetIcon(\"icon.tga\")\r\n"))
+        // Models name their textures in NUL-padded fields.
+        .file(TestFile::compressed("Units\\Hero\\Hero.mdx", b"MDLXTEXS\0\0\0\0Textures\\Hero.blp\0\0\0\0"))
+        .file(TestFile::plain("Textures\\Hero.blp", b"BLP1"))
+        .file(TestFile::plain("war3mapImported\\icon.blp", b"BLP1"))
+        .file(TestFile::plain("nobody\\mentions.me", b"?"))
+        .without_listfile()
+        .build()
+}
+
+#[test]
+fn names_are_recovered_from_references() {
+    let archive = MpqArchive::from_bytes(stripped().into()).unwrap();
+    assert!(!archive.file_names().iter().any(|name| name == "war3map.j"));
+
+    let recovery = archive.recover_names::<&str>(&[]);
+    assert_eq!(recovery.recovered, ["war3map.j", "Units\\Hero\\Hero.mdx", "war3mapImported\\icon.blp", "Textures\\Hero.blp"]);
+    assert_eq!(recovery.unresolved, 1);
+
+    // Extra candidates, such as an external listfile, resolve the rest.
+    let recovery = archive.recover_names(&["NOBODY/mentions.me"]);
+    assert_eq!(recovery.unresolved, 0);
+}
+
+#[test]
+fn recovered_names_are_listed_when_opened_with_recovery() {
+    let archive = MpqArchive::from_bytes_with(stripped().into(), OpenOptions { recover_names: true, ..OpenOptions::default() }).unwrap();
+    let names = archive.file_names();
+    assert!(names.iter().any(|name| name == "Textures\\Hero.blp"));
+    assert_eq!(archive.read_file("war3mapImported\\icon.blp").unwrap(), b"BLP1");
+}
+
+#[test]
+fn references_are_normalized_with_the_variants_the_game_tries() {
+    let paths = referenced_paths(b"\"Doodads\\\\Tree.mdl\" x=Sounds/hit.wav;notapath");
+    for expected in ["Doodads\\Tree.mdl", "Doodads\\Tree.mdx", "war3mapImported\\Doodads\\Tree.mdx", "Sounds\\hit.wav"] {
+        assert!(paths.iter().any(|path| path == expected), "{expected} missing from {paths:?}");
+    }
+    assert!(!paths.iter().any(|path| path.contains("notapath")));
+}
@@ -0,0 +1,79 @@
+//! Object editor data: every kind parses, levelled kinds carry their level and data column, and the mount shows each
+//! file as JSON and INI with trigger strings resolved.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile, object_data};
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveChain, MpqArchiveDescriptor};
+use mpq_folder_win::war3::objects::{Modification, ModificationValue, OBJECT_DATA_VERSION_SETS, ObjectData, ObjectDefinition, ObjectKind};
+
+fn modification(field: &str, level: u32, data_pointer: u32, value: ModificationValue) -> Modification {
+    Modification { field: field.to_string(), level, data_pointer, value }
+}
+
+/// A changed standard object and a new one derived from it, with a value of every type.
+fn objects(kind: ObjectKind) -> (Vec<ObjectDefinition>, Vec<ObjectDefinition>) {
+    let level = if kind.has_levels() { 2 } else { 0 };
+    let data_pointer = if kind == ObjectKind::Abilities { 1 } else { 0 };
+    let original = ObjectDefinition {
+        base_id: "AHbz".to_string(),
+        id: None,
+        modifications: vec![modification("umvs", level, data_pointer, ModificationValue::Int(320))],
+    };
+    let custom = ObjectDefinition {
+        base_id: "AHbz".to_string(),
+        id: Some("A000".to_string()),
+        modifications: vec![
+            modification("unam", 0, 0, ModificationValue::String("TRIGSTR_001".to_string())),
+            modification("Hbz1", level, data_pointer, ModificationValue::Real(42.5)),
+            modification("ucol", level, 0, ModificationValue::Unreal(0.25)),
+        ],
+    };
+    (vec![original], vec![custom])
+}
+
+#[test]
+fn every_kind_parses_in_both_versions() {
+    for kind in ObjectKind::ALL {
+        for version in [2, OBJECT_DATA_VERSION_SETS] {
+            let (original, custom) = objects(kind);
+            let data = ObjectData::parse(kind, &object_data(kind, version, &original, &custom)).unwrap();
+            assert_eq!(data, ObjectData { kind, version, original, custom }, "{kind:?} v{version}");
+        }
+        assert_eq!(ObjectKind::from_path(&kind.file_name().replace("war3map", "war3mapSkin")), Some(kind));
+    }
+}
+
+#[test]
+fn unknown_value_types_and_truncation_are_corrupted() {
+    let (original, custom) = objects(ObjectKind::Units);
+    let bytes = object_data(ObjectKind::Units, 2, &original, &custom);
+    assert!(ObjectData::parse(ObjectKind::Units, &bytes[..bytes.len() - 1]).is_err());
+    // The value type of the first modification, after the version, count, two ids, modification count and field.
+    let mut unknown = bytes.clone();
+    unknown[24..28].copy_from_slice(&7u32.to_le_bytes());
+    assert!(ObjectData::parse(ObjectKind::Units, &unknown).is_err());
+}
+
+#[test]
+fn object_data_is_mounted_as_json_and_ini() {
+    let (original, custom) = objects(ObjectKind::Abilities);
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::compressed("war3map.w3a", &object_data(ObjectKind::Abilities, OBJECT_DATA_VERSION_SETS, &original, &custom)))
+        .file(TestFile::plain("war3map.wts", b"STRING 1\r\n{\r\nFrost \"Nova\"\r\n}\r\n"));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    assert_eq!(archive.object_data(ObjectKind::Abilities).unwrap().unwrap().custom.len(), 1);
+    assert!(archive.object_data(ObjectKind::Units).is_none());
+    let descriptor = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap();
+    let text = |path: &str| String::from_utf8(descriptor.find_entry(path).unwrap().read().unwrap().to_vec()).unwrap();
+
+    let json = text("war3map.w3a.json");
+    for expected in ["\"abilities\"", "\"A000\"", "\"AHbz\"", "\"Hbz1:2\"", "42.5", "\"Frost \\\"Nova\\\"\""] {
+        assert!(json.contains(expected), "{expected} missing from {json}");
+    }
+    let ini = text("war3map.w3a.ini");
+    for expected in ["\r\n[AHbz]\r\numvs:2=320\r\n", "\r\n[A000]\r\n_base=AHbz\r\nunam=\"Frost \\\"Nova\\\"\"\r\nHbz1:2=42.5\r\nucol:2=0.25\r\n"] {
+        assert!(ini.contains(expected), "{expected:?} missing from {ini}");
+    }
+    assert!(descriptor.find_entry("war3map.w3u.json").is_none());
+}
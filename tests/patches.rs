@@ -0,0 +1,96 @@
+//! `PTCH` files as patch archives carry them: `COPY` replaces the file, `BSD0` is a Blizzard bsdiff stream, raw or
+//! RLE-packed. Sizes come from the patch itself and must not be trusted past what it holds.
+
+mod common;
+
+use common::ptch;
+use mpq_folder_win::archive::MpqArchiveError;
+use mpq_folder_win::archive::patch::{PatchFile, PatchKind};
+
+const OLD: &[u8] = b"hello world";
+const NEW: &[u8] = b"hello there!";
+
+/// A `BSDIFF40` stream turning [`OLD`] into [`NEW`]: add 6 bytes to the old `hello `, then copy `there!`.
+fn bsdiff(ctrl_len: u64, data_len: u64, new_size: u64) -> Vec<u8> {
+    let mut stream = b"BSDIFF40".to_vec();
+    [ctrl_len, data_len, new_size].iter().for_each(|value| stream.extend_from_slice(&value.to_le_bytes()));
+    [6u32, 6, 0].iter().for_each(|value| stream.extend_from_slice(&value.to_le_bytes()));
+    stream.extend_from_slice(&[0; 6]);
+    stream.extend_from_slice(b"there!");
+    stream
+}
+
+/// Blizzard's RLE: a dword the unpacker skips, then zero runs (`n - 1`) and literal runs (`0x80 | n - 1`, bytes).
+fn rle(data: &[u8]) -> Vec<u8> {
+    let mut packed = vec![0; 4];
+    let mut rest = data;
+    while !rest.is_empty() {
+        let zeroes = rest.iter().take(128).take_while(|&&byte| byte == 0).count();
+        if zeroes > 0 {
+            packed.push(zeroes as u8 - 1);
+            rest = &rest[zeroes..];
+        } else {
+            let literal = rest.iter().take(128).take_while(|&&byte| byte != 0).count();
+            packed.push(0x80 | (literal as u8 - 1));
+            packed.extend_from_slice(&rest[..literal]);
+            rest = &rest[literal..];
+        }
+    }
+    packed
+}
+
+/// Sets the patch data size a packed `BSD0` payload unpacks to.
+fn with_data_size(mut ptch: Vec<u8>, unpacked_len: usize) -> Vec<u8> {
+    ptch[4..8].copy_from_slice(&((0x44 + unpacked_len) as u32).to_le_bytes());
+    ptch
+}
+
+#[test]
+fn copy_patch_replaces_the_file() {
+    let patch = PatchFile::parse(&ptch(OLD, NEW, b"COPY", NEW)).unwrap();
+    assert_eq!(patch.kind, PatchKind::Copy);
+    assert_eq!((patch.size_before, patch.size_after), (OLD.len() as u32, NEW.len() as u32));
+    assert_eq!(patch.apply(OLD).unwrap(), NEW);
+    assert!(matches!(patch.apply(b"hello world?"), Err(MpqArchiveError::Corrupted(_))));
+}
+
+#[test]
+fn bsd0_patch_applies_raw_and_rle_packed() {
+    let stream = bsdiff(12, 6, NEW.len() as u64);
+    let raw = PatchFile::parse(&ptch(OLD, NEW, b"BSD0", &stream)).unwrap();
+    assert_eq!(raw.kind, PatchKind::Bsd0);
+    assert_eq!(raw.apply(OLD).unwrap(), NEW);
+
+    let packed = rle(&stream);
+    assert!(packed.len() < stream.len());
+    let packed = PatchFile::parse(&with_data_size(ptch(OLD, NEW, b"BSD0", &packed), stream.len())).unwrap();
+    assert_eq!(packed.payload, stream);
+    assert_eq!(packed.apply(OLD).unwrap(), NEW);
+}
+
+#[test]
+fn truncated_and_oversized_patches_are_corrupted() {
+    let corrupted = |result: Result<Vec<u8>, MpqArchiveError>| matches!(result, Err(MpqArchiveError::Corrupted(_)));
+    let apply = |stream: &[u8]| PatchFile::parse(&ptch(OLD, NEW, b"BSD0", stream)).and_then(|patch| patch.apply(OLD));
+
+    // The XFRM block runs past the end of the file.
+    let full = ptch(OLD, NEW, b"COPY", NEW);
+    assert!(PatchFile::parse(&full[..full.len() - 1]).is_err());
+
+    // Block sizes whose sum overflows, or that reach past the stream.
+    assert!(corrupted(apply(&bsdiff(u64::MAX, 6, NEW.len() as u64))));
+    assert!(corrupted(apply(&bsdiff(12, u64::MAX - 20, NEW.len() as u64))));
+    assert!(corrupted(apply(&bsdiff(12, 1 << 40, NEW.len() as u64))));
+    // A new size other than the PTCH header's, or larger than the data and extra blocks.
+    assert!(corrupted(apply(&bsdiff(12, 6, u64::MAX))));
+    assert!(corrupted(apply(&bsdiff(12, 6, 1 << 32))));
+    let mut stream = bsdiff(12, 6, 13);
+    stream.truncate(stream.len() - 1);
+    let longer = [NEW, b"?"].concat();
+    assert!(corrupted(PatchFile::parse(&ptch(OLD, &longer, b"BSD0", &stream)).and_then(|patch| patch.apply(OLD))));
+
+    // An RLE payload claiming far more than its packets expand to fails before anything is allocated.
+    let packed = rle(&bsdiff(12, 6, NEW.len() as u64));
+    let error = PatchFile::parse(&with_data_size(ptch(OLD, NEW, b"BSD0", &packed), u32::MAX as usize - 0x44)).err();
+    assert!(matches!(error, Some(MpqArchiveError::Corrupted(_))), "{error:?}");
+}
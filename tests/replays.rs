@@ -0,0 +1,169 @@
+//! `.w3g` replays: v0 and v1 headers, zlib blocks with 16- or 32-bit sizes, the game records and the mounted views.
+
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use mpq_folder_win::{REPLAY_EXTENSIONS, SUPPORTED_EXTENSIONS};
+use mpq_folder_win::archive::{MpqArchiveDescriptor, REPLAY_ACTIONS_FILE_NAME, REPLAY_HEADER_FILE_NAME, REPLAY_PLAYERS_FILE_NAME};
+use mpq_folder_win::war3::replay::{REPLAY_SIGNATURE, Replay, ReplayHeader, SLOT_EMPTY, SLOT_USED};
+use std::io::Write;
+
+const ACTIONS: &[u8] = &[0x1F, 0x04, 0x00, 0xFA, 0x00, 0x20, 0x01];
+
+/// Map settings as stored: each run of seven bytes follows a mask byte whose bits mark the bytes stored as is, the
+/// others are stored plus one so the string holds no NUL.
+fn encode_stat_string(decoded: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    for chunk in decoded.chunks(7) {
+        let mut mask = 1u8;
+        let mut bytes = Vec::new();
+        for (index, &byte) in chunk.iter().enumerate() {
+            if byte == 0xFF {
+                mask |= 1 << (index + 1);
+                bytes.push(byte);
+            } else {
+                bytes.push(byte + 1);
+            }
+        }
+        encoded.push(mask);
+        encoded.extend_from_slice(&bytes);
+    }
+    encoded.push(0);
+    encoded
+}
+
+/// Game records for Alice (host) and Bob against an insane computer, with one empty slot, then [`ACTIONS`].
+fn stream() -> Vec<u8> {
+    let mut stream = vec![0; 4];
+    stream.extend_from_slice(&[0x00, 1]);
+    stream.extend_from_slice(b"Alice\0\x01\x00");
+    stream.extend_from_slice(b"Echo Isles 1v1\0\0");
+    let mut settings = 0x0000_4002u32.to_le_bytes().to_vec();
+    settings.push(0);
+    settings.extend_from_slice(&116u16.to_le_bytes());
+    settings.extend_from_slice(&84u16.to_le_bytes());
+    settings.extend_from_slice(&0xDEAD_BEEFu32.to_le_bytes());
+    settings.extend_from_slice(b"Maps/FrozenThrone/(2)EchoIsles.w3x\0Alice\0");
+    stream.extend_from_slice(&encode_stat_string(&settings));
+    stream.extend_from_slice(&3u32.to_le_bytes());
+    stream.extend_from_slice(&[0x09, 0, 0, 0]);
+    stream.extend_from_slice(&0x0409u32.to_le_bytes());
+    stream.extend_from_slice(&[0x16, 2]);
+    stream.extend_from_slice(b"Bob\0\x01\x00");
+    stream.extend_from_slice(&0u32.to_le_bytes());
+    stream.extend_from_slice(&[0x19, 0, 0, 4]);
+    // Player id, download, status, computer, team, color, race, AI strength, handicap.
+    stream.extend_from_slice(&[1, 100, SLOT_USED, 0, 0, 0, 0x01, 1, 100]);
+    stream.extend_from_slice(&[2, 100, SLOT_USED, 0, 1, 1, 0x42, 1, 90]);
+    stream.extend_from_slice(&[0, 100, SLOT_USED, 1, 1, 2, 0x08, 2, 100]);
+    stream.extend_from_slice(&[0, 0, SLOT_EMPTY, 0, 0, 3, 0x20, 1, 100]);
+    stream.extend_from_slice(&0x1234_5678u32.to_le_bytes());
+    stream.extend_from_slice(&[0, 4]);
+    stream.extend_from_slice(ACTIONS);
+    stream
+}
+
+/// A replay of `stream` in two blocks, with a v1 header of `version` (sizes widen to 32 bits from 1.32) or a v0 one.
+fn replay(header_version: u32, version: u32, stream: &[u8]) -> Vec<u8> {
+    let wide = header_version == 1 && version >= 10032;
+    let mut blocks = Vec::new();
+    for block in stream.chunks(stream.len().div_ceil(2)) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(block).unwrap();
+        let packed = encoder.finish().unwrap();
+        if wide {
+            blocks.extend_from_slice(&(packed.len() as u32).to_le_bytes());
+            blocks.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        } else {
+            blocks.extend_from_slice(&(packed.len() as u16).to_le_bytes());
+            blocks.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        }
+        blocks.extend_from_slice(&0u32.to_le_bytes());
+        blocks.extend_from_slice(&packed);
+    }
+
+    let header_size = if header_version == 1 { 0x44 } else { 0x40 };
+    let mut bytes = REPLAY_SIGNATURE.to_vec();
+    for value in [header_size, (header_size as usize + blocks.len()) as u32, header_version, stream.len() as u32, 2] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    if header_version == 1 {
+        bytes.extend_from_slice(b"PX3W");
+        bytes.extend_from_slice(&version.to_le_bytes());
+    } else {
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&(version as u16).to_le_bytes());
+    }
+    bytes.extend_from_slice(&6059u16.to_le_bytes());
+    bytes.extend_from_slice(&0x8000u16.to_le_bytes());
+    bytes.extend_from_slice(&754_000u32.to_le_bytes());
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&blocks);
+    bytes
+}
+
+#[test]
+fn headers_of_both_versions_parse() {
+    let header = ReplayHeader::parse(&replay(1, 26, &stream())).unwrap();
+    assert_eq!((header.product.as_str(), header.version_string(), header.build, header.block_count), ("W3XP", "1.26".to_string(), 6059, 2));
+    assert!(header.is_multiplayer() && !header.is_reforged());
+    assert!(ReplayHeader::parse(&replay(1, 10032, &stream())).unwrap().is_reforged());
+
+    let old = ReplayHeader::parse(&replay(0, 6, &stream())).unwrap();
+    assert_eq!((old.header_size, old.product.as_str(), old.version_string()), (0x40, "WAR3", "1.06".to_string()));
+
+    assert!(ReplayHeader::parse(b"Warcraft III recorded game").is_err());
+    let mut future = replay(1, 26, &stream());
+    future[36] = 2;
+    assert!(ReplayHeader::parse(&future).is_err());
+}
+
+#[test]
+fn game_records_and_actions_decode() {
+    for (header_version, version) in [(1, 26), (1, 10036), (0, 7)] {
+        let replay = Replay::parse(&replay(header_version, version, &stream())).unwrap_or_else(|err| panic!("v{header_version} {version}: {err}"));
+        let game = &replay.game;
+        assert_eq!(replay.stream, stream());
+        assert_eq!(replay.actions(), ACTIONS);
+        assert_eq!((game.name.as_str(), game.host_name.as_str(), game.map_path.as_str()), ("Echo Isles 1v1", "Alice", "Maps/FrozenThrone/(2)EchoIsles.w3x"));
+        assert_eq!((game.map_width, game.map_height, game.map_checksum), (116, 84, 0xDEAD_BEEF));
+        assert_eq!((game.speed_name(), game.game_type_name(), game.random_seed, game.start_spots), ("fast", "custom", 0x1234_5678, 4));
+        let players: Vec<(u8, &str, bool)> = game.players.iter().map(|player| (player.id, player.name.as_str(), player.host)).collect();
+        assert_eq!(players, [(1, "Alice", true), (2, "Bob", false)]);
+        let slots: Vec<String> = game.slots.iter().map(|slot| format!("{} {}", game.slot_name(slot), slot.race_name())).collect();
+        assert_eq!(slots, ["Alice human", "Bob orc", "Computer (Insane) undead", "Computer (Normal) random"]);
+    }
+}
+
+#[test]
+fn damaged_blocks_are_corrupted() {
+    let mut bytes = replay(1, 26, &stream());
+    // The first block's decompressed size.
+    bytes[0x44 + 2] ^= 1;
+    assert!(Replay::parse(&bytes).is_err());
+    let bytes = replay(1, 26, &stream());
+    assert!(Replay::parse(&bytes[..bytes.len() - 4]).is_err());
+    // Game records cut short of the game start record.
+    assert!(Replay::parse(&replay(1, 26, &stream()[..60])).is_err());
+}
+
+#[test]
+fn replays_mount_as_parsed_views() {
+    let replay = Replay::parse(&replay(1, 26, &stream())).unwrap();
+    let descriptor = MpqArchiveDescriptor::from_replay(&replay);
+    let text = |name: &str| String::from_utf8(descriptor.find_entry(name).unwrap().read().unwrap().to_vec()).unwrap();
+
+    let header = text(REPLAY_HEADER_FILE_NAME);
+    for expected in ["\"W3XP\"", "\"1.26\"", "\"0:12:34\"", "\"Echo Isles 1v1\"", "\"DEADBEEF\""] {
+        assert!(header.contains(expected), "{expected} missing from {header}");
+    }
+    let players = text(REPLAY_PLAYERS_FILE_NAME);
+    assert!(players.contains("\"Alice\"") && players.contains("\"Computer (Insane)\""), "{players}");
+    assert!(!players.contains("\"Computer (Normal)\""), "empty slots are left out: {players}");
+    assert_eq!(&*descriptor.find_entry(REPLAY_ACTIONS_FILE_NAME).unwrap().read().unwrap(), ACTIONS);
+}
+
+#[test]
+fn replays_are_not_associated_as_archives() {
+    assert!(REPLAY_EXTENSIONS.contains(&".w3g"));
+    assert!(!SUPPORTED_EXTENSIONS.contains(&".w3g"));
+}
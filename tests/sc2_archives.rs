@@ -0,0 +1,205 @@
+//! StarCraft II / Heroes of the Storm archive shapes, built in memory: `MPQ\x1B` user data before a v3 header (replays),
+//! HET/BET tables with or without classic ones (maps and mods), and patch archives with `PTCH` files and delete markers.
+//! One HET/BET archive is a byte fixture assembled outside this crate, to catch mistakes a round trip would hide.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile, Tables, USER_DATA_OFFSET};
+use mpq_folder_win::SUPPORTED_EXTENSIONS;
+use mpq_folder_win::archive::het_bet::jenkins_hash;
+use mpq_folder_win::archive::tables::{MPQ_FILE_COMPRESS, MPQ_FILE_EXISTS, MPQ_FILE_PATCH_FILE};
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveChain, MpqArchiveDescriptor, OVERLAY_INFO_FILE_NAME};
+use std::sync::Arc;
+
+/// A v3 archive of `files` plus a `(listfile)`, optionally behind an `MPQ\x1B` user-data block.
+fn build(files: Vec<TestFile>, tables: Tables, user_data: bool) -> Arc<[u8]> {
+    let builder = ArchiveBuilder::new().files(files).format_version(2).tables(tables);
+    if user_data { builder.user_data() } else { builder }.build().into()
+}
+
+#[test]
+fn extensions_cover_sc2_and_heroes_archives() {
+    for ext in [".mpq", ".w3x", ".sc2map", ".sc2mod", ".sc2replay", ".s2ma", ".stormmap", ".stormreplay"] {
+        assert!(SUPPORTED_EXTENSIONS.contains(&ext), "{ext} is not registered");
+    }
+}
+
+#[test]
+fn jenkins_hash_ignores_case_and_slash_direction() {
+    assert_eq!(jenkins_hash("Base.SC2Data/GameData/UnitData.xml"), jenkins_hash("base.sc2data\\gamedata\\unitdata.XML"));
+    assert_ne!(jenkins_hash("a"), jenkins_hash("b"));
+}
+
+/// Values of StormLib's `HashStringJenkins`, computed with a separate `lookup3.c` port checked against the test
+/// vectors published with it. The empty name skips the final mix, `(attributes)` is exactly one 12-byte block and the
+/// last name spans several.
+#[test]
+fn jenkins_hash_matches_storm() {
+    assert_eq!(jenkins_hash(""), 0xDEAD_BEF1_DEAD_BEF2);
+    assert_eq!(jenkins_hash("(listfile)"), 0x3BC4_3BB0_B2F3_866A);
+    assert_eq!(jenkins_hash("(attributes)"), 0x6955_E9E4_14C1_07A7);
+    assert_eq!(jenkins_hash("Units\\Human\\Footman.mdx"), 0x7641_C17C_F24A_0EDF);
+}
+
+/// A v3 archive indexed by HET/BET tables only: `DocumentHeader`, a zlib-compressed `UnitData.xml` and a
+/// `(listfile)`, followed by the HET and BET tables as StormLib's `SaveMPQTables` lays them out (4 HET slots with
+/// 2-bit indices, a BET table with two flag values and 56-bit name hash halves). Assembled outside this crate from
+/// the StormLib sources, with their own Jenkins and Storm crypt implementations.
+const STORM_HET_BET_ARCHIVE: [u8; 459] = [
+    0x4D, 0x50, 0x51, 0x1A, 0x44, 0x00, 0x00, 0x00, 0xCB, 0x01, 0x00, 0x00, 0x02, 0x00, 0x03, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xCB, 0x01, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x4B, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1A, 0x01, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x44, 0x6F, 0x63, 0x75, 0x6D, 0x65, 0x6E, 0x74, 0x48, 0x65, 0x61, 0x64,
+    0x65, 0x72, 0x20, 0x66, 0x6F, 0x72, 0x20, 0x61, 0x20, 0x74, 0x65, 0x73, 0x74, 0x20, 0x6D, 0x61,
+    0x70, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x82, 0x00, 0x00, 0x00, 0x02, 0x78, 0xDA, 0xB3,
+    0xB1, 0xAF, 0xC8, 0xCD, 0x51, 0x28, 0x4B, 0x2D, 0x2A, 0xCE, 0xCC, 0xCF, 0xB3, 0x55, 0x32, 0xD4,
+    0x33, 0x50, 0x52, 0x48, 0xCD, 0x4B, 0xCE, 0x4F, 0xC9, 0xCC, 0x4B, 0xB7, 0x55, 0x2A, 0x2D, 0x49,
+    0xD3, 0xB5, 0x50, 0xB2, 0xB7, 0xE3, 0xE5, 0xB2, 0x71, 0x4E, 0x2C, 0x49, 0xCC, 0xC9, 0x4F, 0x07,
+    0x32, 0x15, 0x80, 0xC0, 0xC6, 0x39, 0x34, 0x2F, 0xB3, 0x44, 0x21, 0x33, 0xC5, 0x56, 0xC9, 0x37,
+    0xB1, 0x28, 0x33, 0x2F, 0xD5, 0x40, 0xC9, 0xCE, 0xC6, 0x27, 0x33, 0x2D, 0xD5, 0x37, 0xB1, 0x42,
+    0xA1, 0x2C, 0x31, 0xA7, 0x34, 0xD5, 0x56, 0xC9, 0xC4, 0x54, 0x49, 0xDF, 0xCE, 0x46, 0x1F, 0xAC,
+    0x12, 0x97, 0x36, 0x43, 0xF2, 0xB4, 0x19, 0x91, 0xA7, 0xCD, 0x98, 0xA0, 0x36, 0x20, 0x0B, 0xEE,
+    0x4F, 0x00, 0x60, 0xAB, 0x52, 0xC0, 0x44, 0x6F, 0x63, 0x75, 0x6D, 0x65, 0x6E, 0x74, 0x48, 0x65,
+    0x61, 0x64, 0x65, 0x72, 0x0D, 0x0A, 0x42, 0x61, 0x73, 0x65, 0x2E, 0x53, 0x43, 0x32, 0x44, 0x61,
+    0x74, 0x61, 0x5C, 0x47, 0x61, 0x6D, 0x65, 0x44, 0x61, 0x74, 0x61, 0x5C, 0x55, 0x6E, 0x69, 0x74,
+    0x44, 0x61, 0x74, 0x61, 0x2E, 0x78, 0x6D, 0x6C, 0x0D, 0x0A, 0x48, 0x45, 0x54, 0x1A, 0x01, 0x00,
+    0x00, 0x00, 0x25, 0x00, 0x00, 0x00, 0xFD, 0xCF, 0x3C, 0x86, 0x0A, 0x27, 0xCD, 0x67, 0xBD, 0x92,
+    0x90, 0x60, 0xC8, 0x4C, 0xB2, 0x16, 0xC6, 0xDA, 0x16, 0x7C, 0x9A, 0xD7, 0xCB, 0x38, 0xDE, 0x64,
+    0x72, 0xDC, 0x37, 0xC5, 0x98, 0x94, 0x77, 0x47, 0xEF, 0xCA, 0x27, 0x42, 0x45, 0x54, 0x1A, 0x01,
+    0x00, 0x00, 0x00, 0x74, 0x00, 0x00, 0x00, 0x0B, 0x67, 0x48, 0x3D, 0x02, 0xD4, 0x08, 0xCA, 0x26,
+    0xC7, 0x35, 0xF8, 0x87, 0x0A, 0x35, 0x68, 0x2E, 0x46, 0xD7, 0x32, 0xE2, 0x28, 0x63, 0x74, 0x30,
+    0x0D, 0xD1, 0xE5, 0xC9, 0x4A, 0x9E, 0x29, 0xDA, 0x49, 0xC1, 0x97, 0xF6, 0x3B, 0x96, 0x09, 0x30,
+    0xA3, 0x66, 0x11, 0x56, 0x27, 0x5E, 0x35, 0x17, 0xDA, 0x2B, 0xC6, 0x35, 0xB6, 0x52, 0x15, 0x95,
+    0x21, 0x03, 0xFA, 0x87, 0x11, 0x13, 0xB4, 0xE2, 0x29, 0x7E, 0x0B, 0x5B, 0x82, 0x66, 0x72, 0x10,
+    0x93, 0x3F, 0xA4, 0xD2, 0x8E, 0xDD, 0x35, 0xEA, 0x0F, 0xE9, 0xAB, 0x68, 0xA7, 0xF6, 0x82, 0xB8,
+    0x5C, 0x48, 0xD7, 0x97, 0x5C, 0x9A, 0x76, 0x0F, 0x8A, 0x30, 0xFE, 0x83, 0x94, 0x27, 0x90, 0xC1,
+    0x61, 0xD1, 0xEA, 0xD7, 0x84, 0x09, 0xA9, 0x98, 0xB9, 0x26, 0xB1,
+];
+
+#[test]
+fn reads_storm_het_bet_archive() {
+    let archive = MpqArchive::from_bytes(Arc::from(&STORM_HET_BET_ARCHIVE[..])).unwrap();
+    assert_eq!(archive.header().format_version, 2);
+    assert!(archive.hash_table().is_empty());
+    let index = archive.het_index().expect("HET/BET tables");
+    assert_eq!(index.het.hashes.len(), 4);
+    assert_eq!(index.bet.blocks, archive.block_table());
+    assert_eq!(index.bet.blocks.iter().map(|block| block.flags).collect::<Vec<_>>(), [MPQ_FILE_EXISTS, MPQ_FILE_EXISTS | MPQ_FILE_COMPRESS, MPQ_FILE_EXISTS]);
+
+    let mut names = archive.file_names();
+    names.sort();
+    assert_eq!(names, ["(listfile)", "Base.SC2Data\\GameData\\UnitData.xml", "DocumentHeader"]);
+    for (name, block_index) in [("DocumentHeader", 0), ("Base.SC2Data\\GameData\\UnitData.xml", 1), ("(listfile)", 2)] {
+        assert_eq!(index.find(name).unwrap().1.block_index, block_index);
+    }
+    assert_eq!(archive.read_file("documentheader").unwrap(), b"DocumentHeader for a test map\0\0\0");
+    let unit_data = archive.read_file("Base.SC2Data/GameData/UnitData.xml").unwrap();
+    assert_eq!(unit_data.len(), 283);
+    assert!(unit_data.starts_with(b"<?xml") && unit_data.ends_with(b"</Catalog>\r\n"));
+    assert!(!archive.contains("Base.SC2Data\\GameData\\Abil.xml"));
+}
+
+#[test]
+fn sc2replay_with_user_data_and_classic_tables() {
+    let bytes = build(vec![TestFile::plain("replay.details", b"details"), TestFile::plain("replay.game.events", &[7; 5000])], Tables::Classic, true);
+    let archive = MpqArchive::from_bytes(bytes).unwrap();
+
+    let location = archive.location();
+    assert_eq!(location.archive_offset, USER_DATA_OFFSET as u64);
+    assert_eq!(location.user_data.map(|user_data| user_data.header_offset), Some(USER_DATA_OFFSET as u32));
+    assert_eq!(archive.header().format_version, 2);
+    assert!(archive.het_index().is_none());
+    assert_eq!(archive.read_file("replay.details").unwrap(), b"details");
+    assert_eq!(archive.read_file("replay.game.events").unwrap(), vec![7; 5000]);
+}
+
+#[test]
+fn sc2map_with_het_bet_tables_only() {
+    let bytes = build(vec![TestFile::plain("MapScript.galaxy", b"void InitMap() {}"), TestFile::plain("Base.SC2Data\\GameData\\UnitData.xml", b"<Catalog/>")], Tables::HetBet, false);
+    let archive = MpqArchive::from_bytes(bytes).unwrap();
+
+    assert!(archive.hash_table().is_empty());
+    assert_eq!(archive.block_table().len(), 3);
+    let index = archive.het_index().expect("HET/BET tables");
+    assert_eq!(index.bet.blocks, archive.block_table());
+    assert_eq!(archive.read_file("mapscript.galaxy").unwrap(), b"void InitMap() {}");
+    assert_eq!(archive.read_file("Base.SC2Data/GameData/UnitData.xml").unwrap(), b"<Catalog/>");
+    assert!(!archive.contains("MapScript.lua"));
+
+    let mut names = archive.file_names();
+    names.sort();
+    assert_eq!(names, ["(listfile)", "Base.SC2Data\\GameData\\UnitData.xml", "MapScript.galaxy"]);
+    assert_eq!(archive.live_blocks(), [true; 3]);
+}
+
+#[test]
+fn s2ma_het_index_agrees_with_classic_tables() {
+    let names = ["DocumentHeader", "Minimap.tga", "Triggers", "t3Terrain.xml"];
+    let files = names.iter().map(|&name| TestFile::plain(name, name.as_bytes())).collect();
+    let archive = MpqArchive::from_bytes(build(files, Tables::Both, false)).unwrap();
+
+    let index = archive.het_index().expect("HET/BET tables");
+    assert_eq!(index.bet.blocks, archive.block_table());
+    for name in names {
+        let (_, het_entry) = index.find(name).unwrap();
+        assert_eq!(het_entry.block_index, archive.find_hash_entry(name).unwrap().block_index);
+        assert_eq!(archive.read_file(name).unwrap(), name.as_bytes());
+    }
+    assert!(index.find("Objects").is_none());
+}
+
+#[test]
+fn sc2mod_patch_archive_applies_patches_and_delete_markers() {
+    let base = build(vec![TestFile::plain("GameData.xml", b"<old/>"), TestFile::plain("Obsolete.txt", b"gone soon"), TestFile::plain("Kept.txt", b"kept")], Tables::HetBet, false);
+    let update = build(vec![TestFile::patch("GameData.xml", b"<old/>", b"<new version=\"2\"/>"), TestFile::delete_marker("Obsolete.txt")], Tables::Both, false);
+    let update = MpqArchive::from_bytes(update).unwrap();
+    let (_, block) = update.find_file("GameData.xml").unwrap();
+    assert_ne!(block.flags & MPQ_FILE_PATCH_FILE, 0);
+
+    let mut chain = MpqArchiveChain::new(MpqArchive::from_bytes(base).unwrap());
+    chain.add_patch(update);
+    assert_eq!(chain.read_file("GameData.xml").unwrap(), b"<new version=\"2\"/>");
+    assert_eq!(chain.read_file("Kept.txt").unwrap(), b"kept");
+    assert!(!chain.contains("Obsolete.txt"));
+    assert!(chain.read_file("Obsolete.txt").is_err());
+    assert!(!chain.file_names().iter().any(|name| name == "Obsolete.txt"));
+}
+
+#[test]
+fn overlay_listing_does_not_hide_an_archive_file() {
+    let overlay = |base_files: Vec<TestFile>| {
+        let mut chain = MpqArchiveChain::new(MpqArchive::from_bytes(build(base_files, Tables::Classic, false)).unwrap());
+        chain.add_patch(MpqArchive::from_bytes(build(vec![TestFile::plain("Kept.txt", b"patched")], Tables::Classic, false)).unwrap());
+        MpqArchiveDescriptor::from_chain(chain).unwrap()
+    };
+    let listing = |descriptor: &MpqArchiveDescriptor| {
+        let entries: Vec<_> = descriptor.entries().iter().filter(|entry| entry.path == OVERLAY_INFO_FILE_NAME).collect();
+        assert_eq!(entries.len(), 1);
+        entries[0].read().unwrap()
+    };
+
+    let generated = overlay(vec![TestFile::plain("Kept.txt", b"kept")]);
+    assert!(std::str::from_utf8(&listing(&generated)).unwrap().starts_with("# Overlay of 2 archives"));
+
+    let shipped = overlay(vec![TestFile::plain("Kept.txt", b"kept"), TestFile::plain(OVERLAY_INFO_FILE_NAME, b"shipped with the archive")]);
+    assert_eq!(&*listing(&shipped), b"shipped with the archive");
+}
+
+#[test]
+fn patch_archive_alone_hides_delete_markers() {
+    let update = build(vec![TestFile::plain("Kept.txt", b"kept"), TestFile::delete_marker("Obsolete.txt")], Tables::Both, false);
+    let update = MpqArchive::from_bytes(update).unwrap();
+
+    assert!(update.find_file("Obsolete.txt").is_some_and(|(_, block)| block.is_delete_marker()));
+    assert!(!update.contains("Obsolete.txt"));
+    assert!(update.read_file("Obsolete.txt").is_err());
+    assert!(!update.file_names().iter().any(|name| name == "Obsolete.txt"));
+    assert_eq!(update.deleted_names(), ["Obsolete.txt"]);
+
+    // Tables and files are packed back to back: nothing is unused.
+    let space = update.free_space();
+    assert_eq!(space.archive_size, update.source_len());
+    assert_eq!(space.unused_bytes(), 0);
+    assert!(space.unused_blocks.is_empty());
+}
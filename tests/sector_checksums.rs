@@ -0,0 +1,79 @@
+//! Per-sector Adler-32 checksums (`MPQ_FILE_SECTOR_CRC`) as Storm writes them: `adler32(0, sector, len)` over each
+//! stored (compressed) sector, with 0 meaning "no checksum".
+
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::verify::Check;
+
+/// A v1 archive holding `sectors.txt` (4320 bytes of text, two zlib sectors of 4096 bytes) with a sector checksum
+/// table. Assembled outside this crate, with zlib's `adler32(0, ...)` computing the checksums as StormLib does.
+const SECTOR_CRC_ARCHIVE: [u8; 574] = [
+    0x4D, 0x50, 0x51, 0x1A, 0x20, 0x00, 0x00, 0x00, 0x3E, 0x02, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00,
+    0xEE, 0x01, 0x00, 0x00, 0x2E, 0x02, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+    0x10, 0x00, 0x00, 0x00, 0x87, 0x01, 0x00, 0x00, 0xC6, 0x01, 0x00, 0x00, 0xCE, 0x01, 0x00, 0x00,
+    0x02, 0x78, 0xDA, 0x75, 0xD6, 0xB9, 0x4D, 0x04, 0x41, 0x00, 0x44, 0x51, 0x1F, 0x89, 0x1C, 0x26,
+    0x04, 0xAA, 0xEF, 0x8E, 0x63, 0x43, 0x18, 0x8D, 0x04, 0x62, 0x17, 0x24, 0x8E, 0xFC, 0x21, 0x81,
+    0x57, 0x6E, 0x79, 0xCF, 0xFA, 0xB7, 0xEB, 0xFC, 0xF9, 0xFC, 0x3A, 0xCE, 0xD7, 0xEB, 0x7C, 0xFF,
+    0xFE, 0x7D, 0x1C, 0xF7, 0xB7, 0x8F, 0xEB, 0x78, 0xF9, 0xDF, 0xF3, 0xD3, 0x0D, 0x57, 0x7C, 0x15,
+    0x5F, 0xD5, 0x57, 0xF3, 0xD5, 0x7D, 0x0D, 0x5F, 0xD3, 0xD7, 0xF2, 0xB5, 0x79, 0xC5, 0x1A, 0xB1,
+    0x46, 0xAC, 0x11, 0x6B, 0xC4, 0x1A, 0xB1, 0x46, 0xAC, 0x11, 0x6B, 0xC4, 0x1A, 0xB1, 0x46, 0xB1,
+    0x46, 0xB1, 0x46, 0xB1, 0x46, 0xB1, 0x46, 0xB1, 0x46, 0xB1, 0x46, 0xB1, 0x46, 0xB1, 0x46, 0xB1,
+    0x46, 0xB1, 0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB5,
+    0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB5, 0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB3,
+    0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB3, 0x46, 0xB7, 0x46, 0xB7, 0x46, 0xB7,
+    0x46, 0xB7, 0x46, 0xB7, 0x46, 0xB7, 0x46, 0xB7, 0x46, 0xB7, 0x46, 0xB7, 0x46, 0xB7, 0xC6, 0xB0,
+    0xC6, 0xB0, 0xC6, 0xB0, 0xC6, 0xB0, 0xC6, 0xB0, 0xC6, 0xB0, 0xC6, 0xB0, 0xC6, 0xB0, 0xC6, 0xB0,
+    0xC6, 0xB0, 0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB4,
+    0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB4, 0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB2,
+    0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB2, 0xC6, 0xB6, 0xC6, 0xB6, 0xC6, 0xB6,
+    0xC6, 0xB6, 0xC6, 0xB6, 0xC6, 0xB6, 0xC6, 0xB6, 0xC6, 0xB6, 0xC6, 0xB6, 0xC6, 0xA6, 0x46, 0xDC,
+    0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7,
+    0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D,
+    0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B,
+    0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2,
+    0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68,
+    0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A,
+    0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0xC6, 0x2D, 0x1A, 0xB7, 0x68, 0xDC, 0xA2, 0x71, 0x8B, 0x06,
+    0x2D, 0xFA, 0x07, 0x99, 0xF9, 0x24, 0x21, 0x02, 0x78, 0xDA, 0x4B, 0x55, 0x30, 0x30, 0x34, 0x35,
+    0xE4, 0xE5, 0x0A, 0x4E, 0x4D, 0x2E, 0xC9, 0x2F, 0x52, 0x48, 0xCE, 0x48, 0x4D, 0xCE, 0x2E, 0x2E,
+    0xCD, 0x55, 0xC8, 0xC9, 0xCC, 0x4B, 0x05, 0x49, 0x19, 0xE1, 0x96, 0x32, 0xC6, 0x2D, 0x65, 0x82,
+    0x5B, 0xCA, 0x14, 0xB7, 0x94, 0x19, 0x6E, 0x29, 0x73, 0xDC, 0x52, 0x16, 0xB8, 0xA5, 0x2C, 0x79,
+    0xB9, 0x00, 0x23, 0xDD, 0x46, 0xD0, 0x60, 0xD0, 0x38, 0x1C, 0xE8, 0x1A, 0x0B, 0x3F, 0x98, 0x3D,
+    0xAC, 0xA8, 0x2D, 0x0D, 0x77, 0xAC, 0x3A, 0xDD, 0x68, 0x9B, 0x25, 0xE7, 0x93, 0xAC, 0x3E, 0x37,
+    0xD4, 0x31, 0xCB, 0x7A, 0x81, 0x32, 0x4A, 0x3A, 0x85, 0xF8, 0xD3, 0xBE, 0x52, 0xDF, 0xD4, 0x7B,
+    0x07, 0xD2, 0x86, 0x0F, 0x6E, 0x3E, 0x6B, 0x03, 0x8D, 0x04, 0x98, 0xCC, 0x53, 0x5C, 0xB7, 0xC5,
+    0xA1, 0x5C, 0xE0, 0xB7, 0x00, 0xC7, 0x81, 0x96, 0x74, 0xAF, 0xD3, 0x7F, 0x7E, 0xC9, 0xAB, 0x67,
+    0x48, 0x3D, 0x6F, 0xD2, 0x08, 0xCA, 0x41, 0xAC, 0x35, 0xF8, 0x37, 0xBC, 0x33, 0xEC,
+];
+
+/// Offsets into [`SECTOR_CRC_ARCHIVE`]: the first stored sector and the checksum table after the last one.
+const FIRST_SECTOR: usize = 48;
+const CHECKSUM_TABLE: usize = 486;
+
+fn sector_checksums(bytes: Vec<u8>) -> Check {
+    let archive = MpqArchive::from_bytes(bytes.into()).unwrap();
+    let (_, block) = archive.find_file("sectors.txt").unwrap();
+    archive.verify_sector_checksums(block, "sectors.txt").unwrap()
+}
+
+#[test]
+fn storm_sector_checksums_match() {
+    assert_eq!(sector_checksums(SECTOR_CRC_ARCHIVE.to_vec()), Check::Ok);
+    let archive = MpqArchive::from_bytes(SECTOR_CRC_ARCHIVE.to_vec().into()).unwrap();
+    assert_eq!(archive.read_file("sectors.txt").unwrap().len(), 4320);
+}
+
+#[test]
+fn zero_sector_checksums_are_not_stored() {
+    let mut bytes = SECTOR_CRC_ARCHIVE.to_vec();
+    bytes[CHECKSUM_TABLE..CHECKSUM_TABLE + 4].fill(0);
+    assert_eq!(sector_checksums(bytes.clone()), Check::Ok);
+    bytes[CHECKSUM_TABLE + 4..CHECKSUM_TABLE + 8].fill(0);
+    assert_eq!(sector_checksums(bytes), Check::NotStored);
+}
+
+#[test]
+fn damaged_sector_is_a_mismatch() {
+    let mut bytes = SECTOR_CRC_ARCHIVE.to_vec();
+    bytes[FIRST_SECTOR + 8] ^= 0x40;
+    assert_eq!(sector_checksums(bytes), Check::Mismatch);
+}
@@ -0,0 +1,114 @@
+//! Strong signatures (`NGIS` + RSA-2048 after the archive) must decrypt to Storm's whole padded block, not just end
+//! with the archive's SHA-1: Blizzard's keys use e = 3, so a digest alone can be matched by a cube root.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::signature::{RsaPublicKey, STRONG_SIGNATURE_ID, SignatureKeys, SignatureStatus};
+use num_bigint::BigUint;
+use sha1_smol::Sha1;
+
+/// Public half of an RSA-2048 test key with e = 3, generated for these tests.
+const TEST_PUBLIC_KEY: &str = "-----BEGIN RSA PUBLIC KEY-----
+MIIBCAKCAQEAvDnDqQj954RBlxr36llfR3BnBMxM8iEzPPe+q/2H0Wz1Yb1OE2U/
+nz8otft4tEWgQwjR/0UD2kLtmQQEs4YFVkDpZN7J7WxPU9XfPfJUnF7qdRh2PjsJ
+wQ/kTiJdckg7ldfzaVY09xOZKKcu0pshdXfzjGqxaWQF6GHqQZYK9doTmIgDWZJE
+7sSvkANdZA8GNVWmB9VdOEKGCP39GJk1YE5KXDZZAXZ9/i2A+bpAA0fgweaZL+0N
+EyIY228yAsSN1/tmeG5kVkDWM0zcP4MhRIxrjukpPUIqCKoSqhPXnqTgi/fkIHD3
+00qsKK+nydmg0dvLtCyaQALjO+KyhwHtwQIBAw==
+-----END RSA PUBLIC KEY-----";
+
+/// A v1 archive holding `readme.txt` and a `(listfile)`, followed by a strong signature made with the private half
+/// of [`TEST_PUBLIC_KEY`] over StormLib's block: `0x0B`, `0xBB` padding, the SHA-1 of the archive byte-reversed.
+/// Assembled and signed outside this crate.
+const SIGNED_ARCHIVE: [u8; 442] = [
+    0x4D, 0x50, 0x51, 0x1A, 0x20, 0x00, 0x00, 0x00, 0xB6, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00,
+    0x56, 0x00, 0x00, 0x00, 0x96, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+    0x53, 0x69, 0x67, 0x6E, 0x65, 0x64, 0x20, 0x77, 0x69, 0x74, 0x68, 0x20, 0x61, 0x20, 0x74, 0x65,
+    0x73, 0x74, 0x20, 0x6B, 0x65, 0x79, 0x2C, 0x20, 0x6E, 0x6F, 0x74, 0x20, 0x62, 0x79, 0x20, 0x42,
+    0x6C, 0x69, 0x7A, 0x7A, 0x61, 0x72, 0x64, 0x2E, 0x0D, 0x0A, 0x72, 0x65, 0x61, 0x64, 0x6D, 0x65,
+    0x2E, 0x74, 0x78, 0x74, 0x0D, 0x0A, 0x33, 0x30, 0xC3, 0x79, 0x28, 0xD9, 0x32, 0x98, 0xBC, 0x73,
+    0x6F, 0x9F, 0xB2, 0x88, 0x4E, 0xE9, 0xF8, 0x24, 0x9E, 0x86, 0xEB, 0xA0, 0x3D, 0x7C, 0x75, 0x74,
+    0x34, 0x60, 0xEC, 0xC7, 0x9C, 0x90, 0xAD, 0x20, 0x9A, 0x22, 0xE7, 0x14, 0xE9, 0x43, 0xE3, 0xD3,
+    0xAB, 0x2B, 0x97, 0xF4, 0xFF, 0xDA, 0x86, 0xDE, 0xD5, 0x3A, 0x8F, 0xEA, 0xB7, 0x6B, 0x10, 0x1F,
+    0x12, 0xEB, 0x42, 0x1A, 0xCD, 0x78, 0xAB, 0x67, 0x48, 0x3D, 0x8B, 0xD3, 0x08, 0xCA, 0xD7, 0xBA,
+    0x35, 0xF8, 0x5D, 0x77, 0x33, 0xE8, 0xBE, 0x4A, 0xA3, 0xB2, 0xB6, 0xC6, 0xAF, 0xED, 0xF9, 0x64,
+    0xB1, 0x88, 0x1F, 0x9D, 0x89, 0xA8, 0x4E, 0x47, 0x49, 0x53, 0xC8, 0x15, 0xE1, 0x09, 0xE0, 0x9F,
+    0xBA, 0x99, 0xBC, 0x76, 0x64, 0x61, 0xB1, 0x2F, 0x8C, 0x9E, 0x0E, 0x87, 0x6A, 0xDD, 0x86, 0x48,
+    0xF3, 0x60, 0x2E, 0xB3, 0x7A, 0x81, 0x96, 0x03, 0x8E, 0x6C, 0x89, 0x07, 0xC8, 0xC5, 0xF5, 0x39,
+    0xB1, 0xC2, 0x6E, 0xCE, 0xE1, 0xB3, 0xCA, 0x92, 0x57, 0xB4, 0x7D, 0x4B, 0xC1, 0x27, 0x6F, 0x5E,
+    0x76, 0xBF, 0xE8, 0xA7, 0x16, 0x0A, 0x92, 0xC6, 0xEB, 0x3B, 0x00, 0xDA, 0xF6, 0x5A, 0xF6, 0x00,
+    0x3C, 0x0F, 0x32, 0x0D, 0xE8, 0xF6, 0x39, 0xDA, 0x0F, 0x74, 0xA2, 0x90, 0xF4, 0xAA, 0x89, 0x0D,
+    0xDA, 0x4E, 0xC3, 0xC4, 0xA8, 0x0A, 0x8D, 0x21, 0x1D, 0x21, 0xE9, 0x42, 0xF5, 0x6F, 0x02, 0x41,
+    0x3B, 0x27, 0x2B, 0xC3, 0xA1, 0x84, 0x6F, 0x3A, 0xB1, 0xF9, 0x89, 0x34, 0x64, 0xCE, 0xBF, 0x97,
+    0x9D, 0x4B, 0xB1, 0x44, 0xF6, 0xD8, 0x8A, 0xC8, 0x6A, 0xC1, 0x28, 0x92, 0xC2, 0x71, 0xFE, 0x58,
+    0x8A, 0x57, 0x24, 0x7B, 0xBD, 0xBE, 0x66, 0x41, 0x8A, 0x89, 0x4E, 0xBC, 0x92, 0x05, 0x03, 0x07,
+    0x1A, 0xD1, 0x78, 0x1B, 0xED, 0xBA, 0x47, 0xC2, 0x51, 0x06, 0x13, 0xBC, 0x76, 0xE8, 0x9E, 0x6D,
+    0xF1, 0x60, 0x08, 0x66, 0x66, 0xBE, 0x40, 0x3E, 0x28, 0x17, 0xD9, 0x6D, 0xBC, 0x7C, 0x36, 0x65,
+    0x82, 0x2D, 0xC5, 0x4F, 0xAE, 0x06, 0x24, 0x6D, 0x8E, 0x67, 0x4F, 0xF0, 0x74, 0x31, 0x69, 0x22,
+    0xA7, 0x00, 0x59, 0x65, 0xD7, 0x06, 0xE1, 0x90, 0x61, 0x80, 0x30, 0x09, 0x35, 0x97, 0x82, 0xBA,
+    0x1A, 0x23, 0xF4, 0x5C, 0xBA, 0x39, 0x5F, 0x0F, 0x98, 0x2F, 0x8E, 0x1D, 0x10, 0x0B, 0xB2, 0xE9,
+    0xE4, 0x33, 0x59, 0x22, 0xC4, 0xD1, 0x78, 0x2D, 0xD4, 0x47, 0xCF, 0x91, 0xA2, 0xFD, 0xEA, 0xA4,
+    0x89, 0x72, 0x47, 0x55, 0x7A, 0x83, 0x26, 0x7A, 0xE9, 0x95,
+];
+
+fn test_keys() -> SignatureKeys {
+    let mut keys = SignatureKeys::default();
+    keys.add("test key", RsaPublicKey::from_pem(TEST_PUBLIC_KEY).unwrap());
+    keys
+}
+
+#[test]
+fn strong_signature_of_the_test_key_verifies() {
+    let archive = MpqArchive::from_bytes(SIGNED_ARCHIVE[..].into()).unwrap();
+    assert_eq!(archive.strong_signature_offset(), Some(182));
+    assert_eq!(archive.read_file("readme.txt").unwrap(), b"Signed with a test key, not by Blizzard.\r\n");
+    assert_eq!(archive.verify_signatures(&test_keys()).strong, SignatureStatus::Valid("test key".into()));
+    assert_eq!(archive.verify_signatures(&SignatureKeys::blizzard()).strong, SignatureStatus::Invalid);
+
+    let mut tampered = SIGNED_ARCHIVE.to_vec();
+    tampered[0x50] ^= 1;
+    let archive = MpqArchive::from_bytes(tampered.into()).unwrap();
+    assert_eq!(archive.verify_signatures(&test_keys()).strong, SignatureStatus::Invalid);
+}
+
+/// A signature whose cube is just `digest` (read big-endian): below every 2048-bit modulus, so with e = 3 it
+/// decrypts to zeroes followed by the digest. Needs an odd digest to have a cube root modulo 2^160.
+fn cube_root_forgery(digest: &[u8]) -> Vec<u8> {
+    let modulus = BigUint::from(1u8) << 160u32;
+    // Inverse of 3 modulo 2^158, the order of the odd residues modulo 2^160 divides it.
+    let inverse = ((BigUint::from(1u8) << 159u32) + 1u8) / 3u8;
+    let root = BigUint::from_bytes_be(digest).modpow(&inverse, &modulus);
+    assert_eq!(root.pow(3) % &modulus, BigUint::from_bytes_be(digest));
+    let mut signature = root.to_bytes_le();
+    signature.resize(256, 0);
+    signature
+}
+
+#[test]
+fn cube_root_forgery_is_rejected() {
+    for reversed in [false, true] {
+        // Vary the archive until the digest (in the order forged) is odd.
+        let (archive, digest) = (0..=255u8)
+            .map(|pad| {
+                let archive = ArchiveBuilder::new().file(TestFile::plain("forged.txt", &[pad])).build();
+                let mut digest = Sha1::from(&archive).digest().bytes();
+                if reversed {
+                    digest.reverse();
+                }
+                (archive, digest)
+            })
+            .find(|(_, digest)| digest[19] & 1 == 1)
+            .unwrap();
+        let mut forged = archive;
+        forged.extend_from_slice(STRONG_SIGNATURE_ID);
+        forged.extend_from_slice(&cube_root_forgery(&digest));
+
+        let archive = MpqArchive::from_bytes(forged.into()).unwrap();
+        assert!(archive.strong_signature_offset().is_some());
+        let report = archive.verify_signatures(&SignatureKeys::blizzard());
+        assert_eq!(report.strong, SignatureStatus::Invalid, "digest reversed: {reversed}");
+        assert!(!report.is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+//! Archives read the same from memory maps, positioned file reads, byte buffers and custom sources.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::MpqArchive;
+use mpq_folder_win::archive::reader::OpenOptions;
+use mpq_folder_win::archive::source::{FileSource, MmapSource, ReadAt, ReadMode, open_file};
+use std::fs::File;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A file in the temporary directory, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, bytes: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("mpq-folder-win-{}-{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn archive_bytes() -> Vec<u8> {
+    let text: Vec<u8> = (0..2000).flat_map(|i| format!("line {i}\r\n").into_bytes()).collect();
+    ArchiveBuilder::new()
+        .file(TestFile::compressed("war3map.j", &text).encrypted(true))
+        .file(TestFile::plain("readme.txt", b"hello"))
+        .user_data()
+        .build()
+}
+
+/// A byte buffer counting the reads made from it.
+struct Counted(Vec<u8>, Arc<AtomicUsize>);
+
+impl ReadAt for Counted {
+    fn len(&self) -> u64 {
+        self.0.len() as u64
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        self.0.read_at(offset, buf)
+    }
+}
+
+#[test]
+fn every_source_reads_the_same_bytes() {
+    let bytes = archive_bytes();
+    let file = TempFile::new("sources.mpq", &bytes);
+    let sources: Vec<Box<dyn ReadAt>> = vec![
+        Box::new(MmapSource::new(&File::open(&file.0).unwrap()).unwrap()),
+        Box::new(FileSource::new(File::open(&file.0).unwrap()).unwrap()),
+        Box::new(Arc::<[u8]>::from(bytes.clone())),
+    ];
+    for source in sources {
+        assert_eq!(source.len(), bytes.len() as u64);
+        let mut buf = [0u8; 16];
+        assert_eq!(source.read_at(100, &mut buf).unwrap(), 16);
+        assert_eq!(buf, bytes[100..116]);
+        // Short at the end, empty past it.
+        assert_eq!(source.read_at(bytes.len() as u64 - 4, &mut buf).unwrap(), 4);
+        assert_eq!(buf[..4], bytes[bytes.len() - 4..]);
+        assert_eq!(source.read_at(bytes.len() as u64 + 10, &mut buf).unwrap(), 0);
+    }
+}
+
+#[test]
+fn archives_open_through_either_read_mode() {
+    let bytes = archive_bytes();
+    let file = TempFile::new("modes.mpq", &bytes);
+    let expected = MpqArchive::from_bytes(bytes.clone().into()).unwrap().read_file("war3map.j").unwrap();
+    for read_mode in [ReadMode::Mapped, ReadMode::Positioned] {
+        let archive = MpqArchive::open_with(&file.0, OpenOptions { read_mode, ..OpenOptions::default() }).unwrap();
+        assert_eq!(archive.read_file("war3map.j").unwrap(), expected, "{read_mode:?}");
+        assert_eq!(archive.read_file("readme.txt").unwrap(), b"hello");
+    }
+
+    let reads = Arc::new(AtomicUsize::new(0));
+    let archive = MpqArchive::from_reader(Counted(bytes, Arc::clone(&reads)), OpenOptions::default()).unwrap();
+    assert_eq!(archive.read_file("war3map.j").unwrap(), expected);
+    assert!(reads.load(Ordering::Relaxed) > 0);
+}
+
+#[test]
+fn empty_files_fall_back_to_positioned_reads() {
+    let file = TempFile::new("empty.mpq", b"");
+    assert!(MmapSource::new(&File::open(&file.0).unwrap()).is_err());
+    let source = open_file(&file.0, ReadMode::Mapped).unwrap();
+    assert!(source.is_empty());
+    assert!(MpqArchive::open(&file.0).is_err());
+}
+
+#[test]
+fn concurrent_reads_share_one_mapped_archive() {
+    let file = TempFile::new("threads.mpq", &archive_bytes());
+    let archive = Arc::new(MpqArchive::open(&file.0).unwrap());
+    let expected = Arc::new(archive.read_file("war3map.j").unwrap());
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let (archive, expected) = (Arc::clone(&archive), Arc::clone(&expected));
+            std::thread::spawn(move || (0..20).all(|_| archive.read_file("war3map.j").is_ok_and(|data| data == *expected)))
+        })
+        .collect();
+    assert!(threads.into_iter().all(|thread| thread.join().unwrap()));
+}
@@ -0,0 +1,104 @@
+//! `war3map.w3e` tilepoints and `war3map.wpm` pathing cells, and the PNG previews made from them.
+
+mod common;
+
+use common::{ArchiveBuilder, TestFile};
+use mpq_folder_win::archive::{MpqArchive, MpqArchiveChain, MpqArchiveDescriptor};
+use mpq_folder_win::convert::ConverterRegistry;
+use mpq_folder_win::war3::terrain::{
+    PATHING_BLIGHT, PATHING_NO_BUILD, PATHING_NO_FLY, PATHING_NO_WALK, PATHING_NO_WATER, PathingMap, TILE_FLAG_BLIGHT, TILE_FLAG_RAMP,
+    TILE_FLAG_WATER, Terrain,
+};
+
+/// A 2x2 tilepoint grid, south row first: flat ground and a raised ramp, then a lower water cliff and a blighted edge.
+fn w3e() -> Vec<u8> {
+    let mut bytes = b"W3E!".to_vec();
+    bytes.extend_from_slice(&11u32.to_le_bytes());
+    bytes.push(b'L');
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+    bytes.extend_from_slice(b"LdrtLgrs");
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(b"CLdi");
+    [2u32, 2].iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    [-128.0f32, -128.0].iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    // Ground height, water level and edge bit, flags and ground texture, variation, cliff texture and layer.
+    let points: [(i16, u16, u8, u8, u8); 4] = [
+        (0x2000, 0, 0, 0, 0x02),
+        (0x2400, 0, TILE_FLAG_RAMP | 1, 3, 0x02),
+        (0x2000, 0x2000, TILE_FLAG_WATER, 0, 0x01),
+        (0x2000, 0x4000, TILE_FLAG_BLIGHT, 0, 0x02),
+    ];
+    for (height, water, flags, variation, cliff) in points {
+        bytes.extend_from_slice(&height.to_le_bytes());
+        bytes.extend_from_slice(&water.to_le_bytes());
+        bytes.extend_from_slice(&[flags, variation, cliff]);
+    }
+    bytes
+}
+
+/// One row: open ground, a wall, blighted ground units cannot build on, then water.
+fn wpm() -> Vec<u8> {
+    let mut bytes = b"MP3W".to_vec();
+    [0u32, 4, 1].iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+    bytes.extend_from_slice(&[PATHING_NO_WATER, PATHING_NO_WALK | PATHING_NO_FLY | PATHING_NO_WATER, PATHING_NO_BUILD | PATHING_BLIGHT | PATHING_NO_WATER, 0]);
+    bytes
+}
+
+#[test]
+fn terrain_tilepoints_decode() {
+    let terrain = Terrain::parse(&w3e()).unwrap();
+    assert_eq!((terrain.tileset, terrain.width, terrain.height, terrain.offset), ('L', 2, 2, (-128.0, -128.0)));
+    assert_eq!(terrain.ground_tilesets, ["Ldrt", "Lgrs"]);
+    assert_eq!(terrain.cliff_tilesets, ["CLdi"]);
+
+    let ramp = terrain.point(1, 0).unwrap();
+    assert_eq!((ramp.height(), ramp.flags, ramp.ground_texture, ramp.texture_variation), (256.0, TILE_FLAG_RAMP, 1, 3));
+    let water = terrain.point(0, 1).unwrap();
+    assert_eq!(water.height(), -128.0);
+    assert!(water.is_water() && !ramp.is_water());
+    assert!(terrain.point(1, 1).unwrap().edge);
+    assert!(terrain.point(2, 0).is_none());
+    assert_eq!(terrain.height_range(), Some((-128.0, 256.0)));
+
+    // North up: the water and edge row comes first.
+    let image = terrain.heightmap_image().unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.pixels, [0, 40, 160, 255, 42, 42, 42, 255, 85, 85, 85, 255, 255, 255, 255, 255]);
+
+    let full = w3e();
+    assert!(Terrain::parse(&full[..full.len() - 1]).is_err());
+    let mut version = full.clone();
+    version[4] = 12;
+    assert!(Terrain::parse(&version).is_err());
+}
+
+#[test]
+fn pathing_cells_decode() {
+    let pathing = PathingMap::parse(&wpm()).unwrap();
+    assert_eq!((pathing.width, pathing.height), (4, 1));
+    assert_eq!((pathing.count(PATHING_NO_WALK), pathing.count(PATHING_NO_BUILD), pathing.count(PATHING_BLIGHT)), (1, 1, 1));
+    let image = pathing.image().unwrap();
+    assert_eq!(image.pixels, [255, 255, 255, 255, 0, 0, 0, 255, 191, 105, 64, 255, 0, 90, 255, 255]);
+
+    let full = wpm();
+    assert!(PathingMap::parse(&full[..full.len() - 1]).is_err());
+    assert!(PathingMap::parse(&[b"MP3X", &full[4..]].concat()).is_err());
+}
+
+#[test]
+fn previews_are_mounted_as_png() {
+    let builder = ArchiveBuilder::new()
+        .file(TestFile::compressed("war3map.w3e", &w3e()))
+        .file(TestFile::plain("war3map.wpm", &wpm()));
+    let archive = MpqArchive::from_bytes(builder.build().into()).unwrap();
+    assert_eq!(archive.terrain().unwrap().unwrap().points.len(), 4);
+    assert_eq!(archive.pathing_map().unwrap().unwrap().cells.len(), 4);
+
+    let descriptor = MpqArchiveDescriptor::from_chain(MpqArchiveChain::new(archive)).unwrap();
+    let converters = ConverterRegistry::builtin();
+    for path in ["war3map.w3e.png", "war3map.wpm.png"] {
+        let png = converters.converted(&descriptor, path).unwrap_or_else(|| panic!("{path} missing")).read().unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1A\n"), "{path}");
+    }
+}
@@ -0,0 +1,69 @@
+//! Texture headers come from map files anyone can edit: dimensions past the limit must fail before anything is
+//! allocated for them.
+
+use mpq_folder_win::texture::blp::{BlpTexture, decode_blp};
+use mpq_folder_win::texture::tga::decode_tga;
+use mpq_folder_win::texture::{MAX_DIMENSION, RgbaImage};
+
+/// A BLP1 paletted texture without mipmaps: palette entry `i` is the BGRA color `(i, i, i, 0)`, `indices` the pixels.
+fn blp1_palette(width: u32, height: u32, indices: &[u8]) -> Vec<u8> {
+    let mut data = b"BLP1".to_vec();
+    for field in [1, 0, width, height, 5, 0] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    let mut offsets = [0u32; 16];
+    let mut sizes = [0u32; 16];
+    offsets[0] = 28 + 16 * 8 + 256 * 4;
+    sizes[0] = indices.len() as u32;
+    offsets.iter().chain(&sizes).for_each(|value| data.extend_from_slice(&value.to_le_bytes()));
+    (0..=255u8).for_each(|i| data.extend_from_slice(&[i, i, i, 0]));
+    data.extend_from_slice(indices);
+    data
+}
+
+#[test]
+fn paletted_blp_decodes() {
+    let image = decode_blp(&blp1_palette(2, 1, &[1, 2])).unwrap();
+    assert_eq!((image.width, image.height), (2, 1));
+    assert_eq!(image.pixels, [1, 1, 1, 255, 2, 2, 2, 255]);
+}
+
+#[test]
+fn oversized_blp_is_rejected() {
+    for (width, height) in [(MAX_DIMENSION + 1, 1), (1, u32::MAX), (u32::MAX, u32::MAX)] {
+        assert!(BlpTexture::parse(&blp1_palette(width, height, &[0])).is_err(), "{width}x{height}");
+    }
+    assert!(decode_blp(&blp1_palette(MAX_DIMENSION, 2, &[0])).is_ok());
+}
+
+#[test]
+fn oversized_image_is_an_error() {
+    assert!(RgbaImage::new(MAX_DIMENSION + 1, 1).is_err());
+    assert!(RgbaImage::new(u32::MAX, u32::MAX).is_err());
+    assert_eq!(RgbaImage::new(3, 2).unwrap().pixels.len(), 24);
+}
+
+/// An 8-bit grayscale run-length encoded TGA, rows from the top.
+fn tga_rle(width: u16, height: u16, packets: &[u8]) -> Vec<u8> {
+    let mut data = vec![0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(&[8, 0x20]);
+    data.extend_from_slice(packets);
+    data
+}
+
+#[test]
+fn run_length_tga_decodes() {
+    // A run of three 7s, then the literal pixels 1 and 2.
+    let image = decode_tga(&tga_rle(5, 1, &[0x82, 7, 0x01, 1, 2])).unwrap();
+    assert_eq!(image.pixels.chunks(4).map(|pixel| pixel[0]).collect::<Vec<_>>(), [7, 7, 7, 1, 2]);
+}
+
+#[test]
+fn oversized_tga_is_rejected() {
+    assert!(decode_tga(&tga_rle(MAX_DIMENSION as u16 + 1, 1, &[0xFF, 0])).is_err());
+    assert!(decode_tga(&tga_rle(u16::MAX, u16::MAX, &[0xFF, 0])).is_err());
+    // Within the limit, but the packets expand to 128 of the 2^28 pixels claimed.
+    assert!(decode_tga(&tga_rle(MAX_DIMENSION as u16, MAX_DIMENSION as u16, &[0xFF, 0])).is_err());
+}